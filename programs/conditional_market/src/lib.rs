@@ -1,16 +1,40 @@
 use anchor_lang::prelude::*;
+use simple_prediction_market::{
+    program::SimplePredictionMarket, MarketAccount as ParentMarketAccount,
+    MarketState as ParentMarketState, MarketStatus as ParentMarketStatus,
+    Outcome as ParentOutcome,
+};
 
 declare_id!("condMarkM3mP9vK8JqF2nH5xY7wD4bC6eA8g");
 
+pub const MAX_PARENT_CONDITIONS: usize = 4;
+pub const MAX_FEE_BPS: u16 = 2_000; // 20% cap on the market fee
+pub const MAX_QUESTION_LEN: usize = 1_000; // Ceiling for max_question_len
+
 #[program]
 pub mod conditional_market {
     use super::*;
 
-    pub fn initialize(ctx: Context<Initialize>, fee_percentage: u16) -> Result<()> {
+    pub fn initialize(
+        ctx: Context<Initialize>,
+        fee_percentage: u16,
+        min_bet: u64,
+        max_question_len: u16, // Longest question accepted, at most MAX_QUESTION_LEN
+    ) -> Result<()> {
+        require!(fee_percentage <= MAX_FEE_BPS, MarketError::FeeTooHigh);
+        require!(
+            max_question_len > 0 && max_question_len as usize <= MAX_QUESTION_LEN,
+            MarketError::InvalidMaxQuestionLen
+        );
+
         let market_state = &mut ctx.accounts.market_state;
         market_state.authority = ctx.accounts.authority.key();
         market_state.market_counter = 0;
         market_state.fee_percentage = fee_percentage;
+        market_state.accumulated_fees = 0;
+        market_state.min_bet = min_bet;
+        market_state.max_question_len = max_question_len;
+        market_state.treasury = ctx.accounts.authority.key();
         market_state.bump = ctx.bumps.market_state;
         Ok(())
     }
@@ -18,17 +42,28 @@ pub mod conditional_market {
     pub fn create_market(
         ctx: Context<CreateMarket>,
         question: String,
-        parent_market: Pubkey,
-        required_parent_outcome: u8,
+        conditions: Vec<ParentCondition>,
+        parlay: bool, // Winners also split the parent parlay shares routed to this market
     ) -> Result<u64> {
         require!(
-            question.len() > 0 && question.len() <= 500,
+            question.len() > 0
+                && question.len() <= ctx.accounts.market_state.max_question_len as usize,
             MarketError::InvalidQuestion
         );
         require!(
-            parent_market != Pubkey::default(),
-            MarketError::InvalidParentMarket
+            conditions.len() > 0 && conditions.len() <= MAX_PARENT_CONDITIONS,
+            MarketError::InvalidConditionCount
         );
+        for (i, condition) in conditions.iter().enumerate() {
+            require!(
+                condition.parent_market != Pubkey::default()
+                    && conditions[..i]
+                        .iter()
+                        .all(|c| c.parent_market != condition.parent_market),
+                MarketError::InvalidParentMarket
+            );
+            require!(condition.required_outcome <= 1, MarketError::InvalidRequiredOutcome);
+        }
 
         let market_state = &mut ctx.accounts.market_state;
         let market_id = market_state.market_counter;
@@ -36,8 +71,7 @@ pub mod conditional_market {
 
         market_account.market_id = market_id;
         market_account.question = question.clone();
-        market_account.parent_market = parent_market;
-        market_account.required_parent_outcome = required_parent_outcome;
+        market_account.conditions = conditions.clone();
         market_account.yes_pool = 0;
         market_account.no_pool = 0;
         market_account.total_fees = 0;
@@ -45,14 +79,16 @@ pub mod conditional_market {
         market_account.resolved_at = 0;
         market_account.status = MarketStatus::Active;
         market_account.final_outcome = false;
+        market_account.parlay = parlay;
+        market_account.inherited_pool = 0;
 
         market_state.market_counter = market_id.checked_add(1).unwrap();
 
         emit!(MarketCreated {
             market_id,
             question,
-            parent_market,
-            required_outcome: required_parent_outcome,
+            conditions,
+            parlay,
         });
 
         Ok(market_id)
@@ -71,6 +107,10 @@ pub mod conditional_market {
 
         let bet_amount = ctx.accounts.bettor.lamports();
         require!(bet_amount > 0, MarketError::ZeroBet);
+        require!(
+            bet_amount >= ctx.accounts.market_state.min_bet,
+            MarketError::BetTooSmall
+        );
 
         let market_state = &ctx.accounts.market_state;
         let fee = bet_amount
@@ -104,6 +144,10 @@ pub mod conditional_market {
                 .checked_add(bet_amount_net)
                 .ok_or(MarketError::Overflow)?;
         }
+        position.fees_paid = position
+            .fees_paid
+            .checked_add(fee)
+            .ok_or(MarketError::Overflow)?;
 
         **ctx.accounts.bettor.to_account_info().try_borrow_mut_lamports()? -= bet_amount;
         **ctx.accounts.market_account.to_account_info().try_borrow_mut_lamports()? += bet_amount;
@@ -125,32 +169,59 @@ pub mod conditional_market {
             MarketError::MarketNotActive
         );
 
-        // Check parent market outcome (simplified - would use CPI in production)
-        // For now, we assume parent market outcome is provided
-        let parent_outcome = ctx.accounts.parent_market_outcome.outcome;
-        let parent_resolved = ctx.accounts.parent_market_outcome.resolved;
-
-        require!(parent_resolved, MarketError::ParentNotResolved);
+        // Parents are simple_prediction_market accounts passed in condition order
+        let parents = ctx.remaining_accounts;
+        require!(
+            parents.len() == market_account.conditions.len(),
+            MarketError::InvalidParentMarket
+        );
 
-        let condition_met = (parent_outcome == 1 && market_account.required_parent_outcome == 1)
-            || (parent_outcome == 0 && market_account.required_parent_outcome == 0);
+        let mut conditions_met = Vec::with_capacity(parents.len());
+        for (condition, parent_info) in market_account.conditions.iter().zip(parents.iter()) {
+            require!(
+                parent_info.key() == condition.parent_market
+                    && *parent_info.owner == simple_prediction_market::ID,
+                MarketError::InvalidParentMarket
+            );
+            let parent = ParentMarketAccount::try_deserialize(&mut &parent_info.data.borrow()[..])?;
+            require!(
+                parent.status == ParentMarketStatus::Resolved,
+                MarketError::ParentNotResolved
+            );
+
+            // Required outcome: 1 = YES, 0 = NO; a void parent meets no condition
+            let parent_outcome: Option<u8> = match parent.outcome {
+                ParentOutcome::Yes => Some(1),
+                ParentOutcome::No => Some(0),
+                ParentOutcome::Void => None,
+                ParentOutcome::Unresolved => return Err(MarketError::ParentNotResolved.into()),
+            };
+            conditions_met.push(parent_outcome == Some(condition.required_outcome));
+        }
+        let condition_met = conditions_met.iter().all(|met| *met);
 
-        if condition_met {
-            market_account.status = MarketStatus::ParentUnresolved;
+        // Participants of an unmet condition reclaim their gross stake (including fees) via get_refund
+        let next_status = if condition_met {
+            MarketStatus::ParentUnresolved
         } else {
-            market_account.status = MarketStatus::ConditionNotMet;
-            // Refund all participants
-            // Note: In production, would need to track all participants for refunds
-        }
+            MarketStatus::ConditionNotMet
+        };
+        require!(
+            market_account.status.can_transition_to(next_status),
+            MarketError::InvalidStatusTransition
+        );
+        market_account.status = next_status;
 
         emit!(ParentResolved {
             market_id,
             condition_met,
+            conditions_met,
         });
 
         Ok(())
     }
 
+    /// Settle a market whose parent conditions were met (authority only)
     pub fn resolve_market(
         ctx: Context<ResolveMarket>,
         market_id: u64,
@@ -158,15 +229,27 @@ pub mod conditional_market {
     ) -> Result<()> {
         let market_account = &mut ctx.accounts.market_account;
         require!(
-            market_account.status == MarketStatus::ParentUnresolved
-                || market_account.status == MarketStatus::Active,
-            MarketError::CannotResolve
+            market_account.status.can_transition_to(MarketStatus::Resolved),
+            MarketError::InvalidStatusTransition
         );
 
         market_account.final_outcome = outcome;
         market_account.resolved_at = Clock::get()?.unix_timestamp;
         market_account.status = MarketStatus::Resolved;
 
+        // Sweep the entry fees out of the pool so they become withdrawable; a zero-fee market has nothing to move
+        let fees = market_account.total_fees;
+        if fees > 0 {
+            let market_state = &mut ctx.accounts.market_state;
+            market_state.accumulated_fees = market_state
+                .accumulated_fees
+                .checked_add(fees)
+                .ok_or(MarketError::Overflow)?;
+
+            **ctx.accounts.market_account.to_account_info().try_borrow_mut_lamports()? -= fees;
+            **ctx.accounts.market_state.to_account_info().try_borrow_mut_lamports()? += fees;
+        }
+
         emit!(MarketResolved {
             market_id,
             outcome,
@@ -175,8 +258,101 @@ pub mod conditional_market {
         Ok(())
     }
 
-    pub fn claim_winnings(ctx: Context<ClaimWinnings>, market_id: u64) -> Result<()> {
+    /// Pull a parent market's parlay share into this market's winnings, once its condition is met
+    pub fn inherit_parent_pool(ctx: Context<InheritParentPool>, market_id: u64) -> Result<u64> {
         let market_account = &ctx.accounts.market_account;
+        require!(market_account.parlay, MarketError::NotParlay);
+        require!(
+            market_account.status == MarketStatus::ParentUnresolved,
+            MarketError::InvalidStatusTransition
+        );
+        let parent_market = &ctx.accounts.parent_market;
+        require!(
+            market_account
+                .conditions
+                .iter()
+                .any(|c| c.parent_market == parent_market.key())
+                && parent_market.parlay_recipient == market_account.key(),
+            MarketError::InvalidParentMarket
+        );
+
+        // The parent program moves the share into this market account, gated on its authority
+        let amount = simple_prediction_market::cpi::release_parlay_share(
+            CpiContext::new(
+                ctx.accounts.parent_program.to_account_info(),
+                simple_prediction_market::cpi::accounts::ReleaseParlayShare {
+                    market_account: parent_market.to_account_info(),
+                    market_state: ctx.accounts.parent_market_state.to_account_info(),
+                    authority: ctx.accounts.authority.to_account_info(),
+                    recipient: ctx.accounts.market_account.to_account_info(),
+                },
+            ),
+            parent_market.market_id,
+        )?
+        .get();
+
+        let market_account = &mut ctx.accounts.market_account;
+        market_account.inherited_pool = market_account
+            .inherited_pool
+            .checked_add(amount)
+            .ok_or(MarketError::Overflow)?;
+
+        emit!(ParentPoolInherited {
+            market_id,
+            parent_market: ctx.accounts.parent_market.key(),
+            amount,
+        });
+
+        Ok(amount)
+    }
+
+    /// Hand a parent's parlay share back to the parent's winners once this market can no longer
+    /// inherit it (its condition failed, or it settled without inheriting)
+    pub fn release_parent_pool(ctx: Context<ReleaseParentPool>, market_id: u64) -> Result<u64> {
+        let market_account = &ctx.accounts.market_account;
+        require!(market_account.parlay, MarketError::NotParlay);
+        require!(
+            matches!(
+                market_account.status,
+                MarketStatus::ConditionNotMet | MarketStatus::Resolved | MarketStatus::Cancelled
+            ),
+            MarketError::InvalidStatusTransition
+        );
+        let parent_market = &ctx.accounts.parent_market;
+        require!(
+            market_account
+                .conditions
+                .iter()
+                .any(|c| c.parent_market == parent_market.key())
+                && parent_market.parlay_recipient == market_account.key(),
+            MarketError::InvalidParentMarket
+        );
+
+        let amount = simple_prediction_market::cpi::return_parlay_share(
+            CpiContext::new(
+                ctx.accounts.parent_program.to_account_info(),
+                simple_prediction_market::cpi::accounts::ReturnParlayShare {
+                    market_account: parent_market.to_account_info(),
+                    market_state: ctx.accounts.parent_market_state.to_account_info(),
+                    caller: ctx.accounts.authority.to_account_info(),
+                },
+            ),
+            parent_market.market_id,
+        )?
+        .get();
+
+        emit!(ParentPoolReleased {
+            market_id,
+            parent_market: ctx.accounts.parent_market.key(),
+            amount,
+        });
+
+        Ok(amount)
+    }
+
+    pub fn claim_winnings(ctx: Context<ClaimWinnings>, market_id: u64) -> Result<u64> {
+        let market_account = &ctx.accounts.market_account;
+        require!(market_account.market_id == market_id, MarketError::MarketMismatch);
         require!(
             market_account.status == MarketStatus::Resolved,
             MarketError::NotResolved
@@ -185,33 +361,9 @@ pub mod conditional_market {
         let position = &mut ctx.accounts.position;
         require!(!position.claimed, MarketError::AlreadyClaimed);
 
-        let winning_pool = if market_account.final_outcome {
-            market_account.yes_pool
-        } else {
-            market_account.no_pool
-        };
-        let losing_pool = if market_account.final_outcome {
-            market_account.no_pool
-        } else {
-            market_account.yes_pool
-        };
-        let user_winning_amount = if market_account.final_outcome {
-            position.yes_amount
-        } else {
-            position.no_amount
-        };
-
-        require!(user_winning_amount > 0, MarketError::NotWinner);
-        require!(winning_pool > 0, MarketError::NoWinnings);
-
-        let payout = user_winning_amount
-            .checked_add(
-                user_winning_amount
-                    .checked_mul(losing_pool)
-                    .and_then(|x| x.checked_div(winning_pool))
-                    .ok_or(MarketError::Overflow)?,
-            )
-            .ok_or(MarketError::Overflow)?;
+        let payout = market_account.winnings_for(position)?;
+        let payout = capped_payout(&market_account.to_account_info(), payout)?;
+        let cost_basis = market_account.winning_stake(position);
 
         position.claimed = true;
 
@@ -224,10 +376,29 @@ pub mod conditional_market {
             amount: payout,
         });
 
-        Ok(())
+        emit!(ClaimRecord {
+            market_id,
+            user: ctx.accounts.winner.key(),
+            cost_basis,
+            payout,
+        });
+
+        Ok(payout)
+    }
+
+    pub fn quote_payout(ctx: Context<QuotePayout>, market_id: u64) -> Result<u64> {
+        let market_account = &ctx.accounts.market_account;
+        require!(market_account.market_id == market_id, MarketError::MarketMismatch);
+        require!(
+            market_account.status == MarketStatus::Resolved,
+            MarketError::NotResolved
+        );
+        require!(!ctx.accounts.position.claimed, MarketError::AlreadyClaimed);
+
+        market_account.winnings_for(&ctx.accounts.position)
     }
 
-    pub fn get_refund(ctx: Context<GetRefund>, market_id: u64) -> Result<()> {
+    pub fn get_refund(ctx: Context<GetRefund>, market_id: u64) -> Result<u64> {
         let market_account = &mut ctx.accounts.market_account;
         require!(
             market_account.status == MarketStatus::ConditionNotMet
@@ -238,22 +409,95 @@ pub mod conditional_market {
         let position = &mut ctx.accounts.position;
         require!(!position.claimed, MarketError::AlreadyClaimed);
 
+        // Refund the gross stake: the fee was charged for a market that never ran
         let refund_amount = position
             .yes_amount
             .checked_add(position.no_amount)
+            .and_then(|x| x.checked_add(position.fees_paid))
             .ok_or(MarketError::Overflow)?;
 
         require!(refund_amount > 0, MarketError::NoPosition);
 
+        market_account.total_fees = market_account
+            .total_fees
+            .checked_sub(position.fees_paid)
+            .ok_or(MarketError::Overflow)?;
+        // Mark the position claimed before any lamports leave the market
         position.claimed = true;
 
         **ctx.accounts.market_account.to_account_info().try_borrow_mut_lamports()? -= refund_amount;
         **ctx.accounts.refundee.to_account_info().try_borrow_mut_lamports()? += refund_amount;
 
+        Ok(refund_amount)
+    }
+
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>) -> Result<()> {
+        let market_state = &mut ctx.accounts.market_state;
+        let amount = market_state.accumulated_fees;
+        require!(amount > 0, MarketError::NoFees);
+
+        market_state.accumulated_fees = 0;
+
+        **ctx.accounts.market_state.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.treasury.to_account_info().try_borrow_mut_lamports()? += amount;
+
+        Ok(())
+    }
+
+    pub fn set_min_bet(ctx: Context<SetMinBet>, min_bet: u64) -> Result<()> {
+        let old_min_bet = ctx.accounts.market_state.min_bet;
+        ctx.accounts.market_state.min_bet = min_bet;
+
+        emit!(MinBetUpdated {
+            old_min_bet,
+            new_min_bet: min_bet,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_fee_percentage(ctx: Context<SetFeePercentage>, fee_percentage: u16) -> Result<()> {
+        require!(fee_percentage <= MAX_FEE_BPS, MarketError::FeeTooHigh);
+
+        let old_fee_percentage = ctx.accounts.market_state.fee_percentage;
+        ctx.accounts.market_state.fee_percentage = fee_percentage;
+
+        emit!(FeePercentageUpdated {
+            old_fee_percentage,
+            new_fee_percentage: fee_percentage,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_treasury(ctx: Context<SetTreasury>, treasury: Pubkey) -> Result<()> {
+        let old_treasury = ctx.accounts.market_state.treasury;
+        ctx.accounts.market_state.treasury = treasury;
+
+        emit!(TreasuryUpdated {
+            old_treasury,
+            new_treasury: treasury,
+        });
+
         Ok(())
     }
 }
 
+/// Cap a payout at the lamports the market holds above rent exemption
+fn capped_payout(market_info: &AccountInfo, payout: u64) -> Result<u64> {
+    let rent_exempt = Rent::get()?.minimum_balance(market_info.data_len());
+    let available = market_info.lamports().saturating_sub(rent_exempt);
+    if payout > available {
+        msg!(
+            "Payout short by {} lamports, paying the remaining {}",
+            payout - available,
+            available
+        );
+        return Ok(available);
+    }
+    Ok(payout)
+}
+
 #[derive(Accounts)]
 pub struct Initialize<'info> {
     #[account(
@@ -274,7 +518,7 @@ pub struct CreateMarket<'info> {
     #[account(
         init,
         payer = creator,
-        space = 8 + MarketAccount::LEN,
+        space = 8 + MarketAccount::size(market_state.max_question_len),
         seeds = [b"market", market_state.market_counter.to_le_bytes().as_ref()],
         bump
     )]
@@ -311,8 +555,7 @@ pub struct TakePosition<'info> {
 pub struct CheckParentMarket<'info> {
     #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
     pub market_account: Account<'info, MarketAccount>,
-    /// CHECK: Parent market outcome account
-    pub parent_market_outcome: AccountInfo<'info>,
+    // Remaining accounts: one simple_prediction_market parent per condition, in order
 }
 
 #[derive(Accounts)]
@@ -320,9 +563,52 @@ pub struct CheckParentMarket<'info> {
 pub struct ResolveMarket<'info> {
     #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
     pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        mut,
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
     pub authority: Signer<'info>,
 }
 
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct InheritParentPool<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    #[account(mut)]
+    pub parent_market: Account<'info, ParentMarketAccount>,
+    pub parent_market_state: Account<'info, ParentMarketState>,
+    pub authority: Signer<'info>,
+    pub parent_program: Program<'info, SimplePredictionMarket>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct ReleaseParentPool<'info> {
+    #[account(seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    #[account(mut)]
+    pub parent_market: Account<'info, ParentMarketAccount>,
+    pub parent_market_state: Account<'info, ParentMarketState>,
+    pub authority: Signer<'info>,
+    pub parent_program: Program<'info, SimplePredictionMarket>,
+}
+
 #[derive(Accounts)]
 #[instruction(market_id: u64)]
 pub struct ClaimWinnings<'info> {
@@ -338,6 +624,19 @@ pub struct ClaimWinnings<'info> {
     pub winner: Signer<'info>,
 }
 
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct QuotePayout<'info> {
+    #[account(seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        seeds = [b"position", market_id.to_le_bytes().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+    pub owner: Signer<'info>,
+}
+
 #[derive(Accounts)]
 #[instruction(market_id: u64)]
 pub struct GetRefund<'info> {
@@ -353,24 +652,78 @@ pub struct GetRefund<'info> {
     pub refundee: Signer<'info>,
 }
 
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    #[account(
+        mut,
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
+    /// CHECK: Verified against the treasury stored on market state
+    #[account(mut, address = market_state.treasury @ MarketError::InvalidTreasury)]
+    pub treasury: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMinBet<'info> {
+    #[account(
+        mut,
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetTreasury<'info> {
+    #[account(
+        mut,
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetFeePercentage<'info> {
+    #[account(
+        mut,
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
+}
+
 #[account]
 pub struct MarketState {
     pub authority: Pubkey,
     pub market_counter: u64,
     pub fee_percentage: u16,
+    pub accumulated_fees: u64,
+    pub min_bet: u64,
+    pub max_question_len: u16,
+    pub treasury: Pubkey,
     pub bump: u8,
 }
 
 impl MarketState {
-    pub const LEN: usize = 32 + 8 + 2 + 1;
+    pub const LEN: usize = 32 + 8 + 2 + 8 + 8 + 2 + 32 + 1;
 }
 
 #[account]
 pub struct MarketAccount {
     pub market_id: u64,
     pub question: String,
-    pub parent_market: Pubkey,
-    pub required_parent_outcome: u8,
+    pub conditions: Vec<ParentCondition>,
     pub yes_pool: u64,
     pub no_pool: u64,
     pub total_fees: u64,
@@ -378,10 +731,67 @@ pub struct MarketAccount {
     pub resolved_at: i64,
     pub status: MarketStatus,
     pub final_outcome: bool,
+    pub parlay: bool,
+    pub inherited_pool: u64,
 }
 
 impl MarketAccount {
-    pub const LEN: usize = 8 + (4 + 500) + 32 + 1 + 8 + 8 + 8 + 8 + 8 + 1 + 1;
+    /// Account size with question space for the configured `max_question_len`
+    pub fn size(max_question_len: u16) -> usize {
+        8 + (4 + max_question_len as usize) + (4 + MAX_PARENT_CONDITIONS * ParentCondition::LEN) + 8 + 8 + 8 + 8 + 8 + 1 + 1 + 1 + 8
+    }
+
+    /// Net stake a position has on the winning side, the cost basis of its claim
+    pub fn winning_stake(&self, position: &Position) -> u64 {
+        if self.final_outcome {
+            position.yes_amount
+        } else {
+            position.no_amount
+        }
+    }
+
+    /// Payout owed to a position on this resolved market
+    pub fn winnings_for(&self, position: &Position) -> Result<u64> {
+        let winning_pool = if self.final_outcome {
+            self.yes_pool
+        } else {
+            self.no_pool
+        };
+        // Parlay shares inherited from parents are split like the losing pool
+        let losing_pool = if self.final_outcome {
+            self.no_pool
+        } else {
+            self.yes_pool
+        }
+        .checked_add(self.inherited_pool)
+        .ok_or(MarketError::Overflow)?;
+        let user_winning_amount = self.winning_stake(position);
+
+        require!(winning_pool > 0, MarketError::EmptyWinningPool);
+        require!(user_winning_amount > 0, MarketError::BetOnLosingSide);
+
+        // Widen before multiplying so large pools cannot overflow ahead of the division
+        let payout = user_winning_amount
+            .checked_add(
+                (user_winning_amount as u128)
+                    .checked_mul(losing_pool as u128)
+                    .and_then(|x| x.checked_div(winning_pool as u128))
+                    .and_then(|x| u64::try_from(x).ok())
+                    .ok_or(MarketError::Overflow)?,
+            )
+            .ok_or(MarketError::Overflow)?;
+        Ok(payout)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ParentCondition {
+    pub parent_market: Pubkey,
+    pub required_outcome: u8, // 1 = YES, 0 = NO
+}
+
+impl ParentCondition {
+    pub const LEN: usize = 32 + 1;
 }
 
 #[account]
@@ -389,10 +799,11 @@ pub struct Position {
     pub yes_amount: u64,
     pub no_amount: u64,
     pub claimed: bool,
+    pub fees_paid: u64,
 }
 
 impl Position {
-    pub const LEN: usize = 8 + 8 + 1;
+    pub const LEN: usize = 8 + 8 + 1 + 8;
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
@@ -404,12 +815,26 @@ pub enum MarketStatus {
     Cancelled,
 }
 
+impl MarketStatus {
+    /// Lifecycle transitions a market may make; only a market whose conditions were met resolves,
+    /// and resolved, condition-not-met and cancelled are terminal
+    pub fn can_transition_to(self, next: MarketStatus) -> bool {
+        matches!(
+            (self, next),
+            (MarketStatus::Active, MarketStatus::ParentUnresolved)
+                | (MarketStatus::Active, MarketStatus::ConditionNotMet)
+                | (MarketStatus::ParentUnresolved, MarketStatus::Resolved)
+                | (MarketStatus::Active | MarketStatus::ParentUnresolved, MarketStatus::Cancelled)
+        )
+    }
+}
+
 #[event]
 pub struct MarketCreated {
     pub market_id: u64,
     pub question: String,
-    pub parent_market: Pubkey,
-    pub required_outcome: u8,
+    pub conditions: Vec<ParentCondition>,
+    pub parlay: bool,
 }
 
 #[event]
@@ -424,6 +849,7 @@ pub struct PositionTaken {
 pub struct ParentResolved {
     pub market_id: u64,
     pub condition_met: bool,
+    pub conditions_met: Vec<bool>,
 }
 
 #[event]
@@ -432,6 +858,20 @@ pub struct MarketResolved {
     pub outcome: bool,
 }
 
+#[event]
+pub struct ParentPoolInherited {
+    pub market_id: u64,
+    pub parent_market: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct ParentPoolReleased {
+    pub market_id: u64,
+    pub parent_market: Pubkey,
+    pub amount: u64,
+}
+
 #[event]
 pub struct WinningsClaimed {
     pub market_id: u64,
@@ -439,18 +879,52 @@ pub struct WinningsClaimed {
     pub amount: u64,
 }
 
+#[event]
+pub struct ClaimRecord {
+    pub market_id: u64,
+    pub user: Pubkey,
+    pub cost_basis: u64, // Net stake on the paying side
+    pub payout: u64,
+}
+
+#[event]
+pub struct MinBetUpdated {
+    pub old_min_bet: u64,
+    pub new_min_bet: u64,
+}
+
+#[event]
+pub struct TreasuryUpdated {
+    pub old_treasury: Pubkey,
+    pub new_treasury: Pubkey,
+}
+
+#[event]
+pub struct FeePercentageUpdated {
+    pub old_fee_percentage: u16,
+    pub new_fee_percentage: u16,
+}
+
 #[error_code]
 pub enum MarketError {
     #[msg("Invalid question")]
     InvalidQuestion,
     #[msg("Invalid parent market")]
     InvalidParentMarket,
+    #[msg("Invalid condition count")]
+    InvalidConditionCount,
+    #[msg("Invalid required outcome")]
+    InvalidRequiredOutcome,
     #[msg("Market not active")]
     MarketNotActive,
     #[msg("Zero bet")]
     ZeroBet,
+    #[msg("Bet below minimum")]
+    BetTooSmall,
     #[msg("Overflow")]
     Overflow,
+    #[msg("Unauthorized")]
+    Unauthorized,
     #[msg("Parent not resolved")]
     ParentNotResolved,
     #[msg("Cannot resolve")]
@@ -459,13 +933,27 @@ pub enum MarketError {
     NotResolved,
     #[msg("Already claimed")]
     AlreadyClaimed,
-    #[msg("Not winner")]
-    NotWinner,
-    #[msg("No winnings")]
-    NoWinnings,
+    #[msg("Bet on the losing side")]
+    BetOnLosingSide,
+    #[msg("Nobody bet on the winning side")]
+    EmptyWinningPool,
     #[msg("Not cancelled")]
     NotCancelled,
     #[msg("No position")]
     NoPosition,
+    #[msg("Market account does not match market id")]
+    MarketMismatch,
+    #[msg("Fee exceeds maximum")]
+    FeeTooHigh,
+    #[msg("Market cannot move to that status")]
+    InvalidStatusTransition,
+    #[msg("No fees")]
+    NoFees,
+    #[msg("Market does not inherit parent pools")]
+    NotParlay,
+    #[msg("Max question length must be between 1 and MAX_QUESTION_LEN")]
+    InvalidMaxQuestionLen,
+    #[msg("Invalid treasury")]
+    InvalidTreasury,
 }
 
@@ -2,6 +2,13 @@ use anchor_lang::prelude::*;
 
 declare_id!("multOutcM3mP9vK8JqF2nH5xY7wD4bC6eA8g");
 
+pub const EMERGENCY_CANCEL_GRACE_PERIOD: i64 = 30 * 24 * 60 * 60; // 30 days in seconds
+pub const MAX_FEE_BPS: u16 = 2_000; // 20% cap on the market fee
+pub const CLAIM_PERIOD: i64 = 180 * 24 * 60 * 60; // 180 days in seconds
+pub const TIE_FLAG: u64 = 1 << 63; // Oracle answers with this bit set report a tie; the low bits mask the tied outcomes
+pub const MAX_QUESTION_LEN: usize = 1_000; // Ceiling for max_question_len
+pub const MAX_OUTCOMES: u8 = 63; // Ceiling for max_outcomes: a tie mask has 63 outcome bits, and 63 100-byte labels still fit one account
+
 #[program]
 pub mod multi_outcome_market {
     use super::*;
@@ -10,13 +17,32 @@ pub mod multi_outcome_market {
         ctx: Context<Initialize>,
         oracle_program: Pubkey,
         fee_percentage: u16,
+        min_bet: u64,
+        max_question_len: u16, // Longest question accepted, at most MAX_QUESTION_LEN
+        min_outcomes: u8, // Fewest outcomes a market may have, at least 2
+        max_outcomes: u8, // Most outcomes a market may have, at most MAX_OUTCOMES
     ) -> Result<()> {
+        require!(fee_percentage <= MAX_FEE_BPS, MarketError::FeeTooHigh);
+        require!(
+            max_question_len > 0 && max_question_len as usize <= MAX_QUESTION_LEN,
+            MarketError::InvalidMaxQuestionLen
+        );
+        require!(
+            min_outcomes >= 2 && min_outcomes <= max_outcomes && max_outcomes <= MAX_OUTCOMES,
+            MarketError::InvalidOutcomeBounds
+        );
+
         let market_state = &mut ctx.accounts.market_state;
         market_state.authority = ctx.accounts.authority.key();
         market_state.oracle_program = oracle_program;
         market_state.market_counter = 0;
         market_state.fee_percentage = fee_percentage;
         market_state.accumulated_fees = 0;
+        market_state.min_bet = min_bet;
+        market_state.max_question_len = max_question_len;
+        market_state.min_outcomes = min_outcomes;
+        market_state.max_outcomes = max_outcomes;
+        market_state.treasury = ctx.accounts.authority.key();
         market_state.bump = ctx.bumps.market_state;
         Ok(())
     }
@@ -26,19 +52,49 @@ pub mod multi_outcome_market {
         question: String,
         outcome_labels: Vec<String>,
         resolution_time: i64,
+        close_time: i64, // Betting cutoff, 0 = resolution time
+        min_confidence: u8, // Minimum oracle confidence (1-100) required to resolve
+        oracle_question_id: u64, // trace9 question that resolves this market
+        shared: bool, // Let other markets bind the same oracle question
+        min_pool_per_side: u64, // Liquidity the winning and losing sides need at resolution, otherwise the market cancels
+        oracle_program_override: Option<Pubkey>, // Oracle deployment answering this market, None = the state-level program
+        resolver: Option<Pubkey>, // Only this key may resolve, None = anyone
     ) -> Result<u64> {
         require!(
-            question.len() > 0 && question.len() <= 500,
+            question.len() > 0
+                && question.len() <= ctx.accounts.market_state.max_question_len as usize,
             MarketError::InvalidQuestion
         );
         require!(
-            outcome_labels.len() >= 2 && outcome_labels.len() <= 10,
+            min_confidence > 0 && min_confidence <= 100,
+            MarketError::InvalidMinConfidence
+        );
+        require!(
+            outcome_labels.len() >= ctx.accounts.market_state.min_outcomes as usize
+                && outcome_labels.len() <= ctx.accounts.market_state.max_outcomes as usize,
             MarketError::InvalidOutcomeCount
         );
         require!(
             resolution_time > Clock::get()?.unix_timestamp,
             MarketError::InvalidResolutionTime
         );
+        let close_time = if close_time == 0 { resolution_time } else { close_time };
+        require!(
+            close_time > Clock::get()?.unix_timestamp && close_time <= resolution_time,
+            MarketError::InvalidCloseTime
+        );
+
+        // Check every label before writing anything to the market
+        for (i, label) in outcome_labels.iter().enumerate() {
+            require!(
+                label.len() > 0 && label.len() <= 100,
+                MarketError::InvalidOutcomeLabel
+            );
+            require!(
+                !outcome_labels[..i].contains(label),
+                MarketError::DuplicateOutcomeLabel
+            );
+        }
 
         let market_state = &mut ctx.accounts.market_state;
         let market_id = market_state.market_counter;
@@ -47,21 +103,46 @@ pub mod multi_outcome_market {
         market_account.market_id = market_id;
         market_account.question = question.clone();
         market_account.resolution_time = resolution_time;
+        market_account.close_time = close_time;
         market_account.num_outcomes = outcome_labels.len() as u8;
         market_account.status = MarketStatus::Open;
         market_account.total_pool = 0;
         market_account.total_fees = 0;
         market_account.created_at = Clock::get()?.unix_timestamp;
+        market_account.creator = ctx.accounts.creator.key();
+        market_account.min_confidence = min_confidence;
+        market_account.oracle_question_id = oracle_question_id;
+        market_account.claim_deadline = 0;
+        market_account.min_pool_per_side = min_pool_per_side;
+        market_account.oracle_program_override = oracle_program_override;
+        market_account.resolver = resolver;
 
-        // Store outcome labels
-        for (i, label) in outcome_labels.iter().enumerate() {
+        // Store outcome labels, one empty pool per outcome
+        market_account.outcome_labels = outcome_labels.clone();
+        market_account.outcome_pools = vec![0; outcome_labels.len()];
+
+        // Bind the oracle question; an exclusive binding admits no further markets
+        let question_binding = &mut ctx.accounts.question_binding;
+        if question_binding.market_count == 0 {
+            question_binding.question_id = oracle_question_id;
+            question_binding.shared = shared;
+            question_binding.bump = ctx.bumps.question_binding;
+        } else {
             require!(
-                label.len() > 0 && label.len() <= 100,
-                MarketError::InvalidOutcomeLabel
+                question_binding.shared && shared,
+                MarketError::QuestionAlreadyBound
             );
-            market_account.outcome_labels.push(label.clone());
-            market_account.outcome_pools.push(0);
         }
+        question_binding.market_count = question_binding
+            .market_count
+            .checked_add(1)
+            .ok_or(MarketError::Overflow)?;
+
+        require!(
+            market_account.outcome_pools.len() == market_account.outcome_labels.len()
+                && market_account.outcome_labels.len() == market_account.num_outcomes as usize,
+            MarketError::InvalidOutcomeCount
+        );
 
         market_state.market_counter = market_id.checked_add(1).unwrap();
 
@@ -70,6 +151,11 @@ pub mod multi_outcome_market {
             question,
             num_outcomes: outcome_labels.len() as u8,
             resolution_time,
+            close_time,
+            min_confidence,
+            oracle_question_id,
+            oracle_program_override,
+            resolver,
         });
 
         Ok(market_id)
@@ -86,13 +172,17 @@ pub mod multi_outcome_market {
             MarketError::MarketNotOpen
         );
         require!(
-            Clock::get()?.unix_timestamp < market_account.resolution_time,
+            Clock::get()?.unix_timestamp < market_account.close_time,
             MarketError::MarketExpired
         );
         require!(outcome < market_account.num_outcomes, MarketError::InvalidOutcome);
 
         let bet_amount = ctx.accounts.bettor.lamports();
         require!(bet_amount > 0, MarketError::ZeroBet);
+        require!(
+            bet_amount >= ctx.accounts.market_state.min_bet,
+            MarketError::BetTooSmall
+        );
 
         let market_state = &ctx.accounts.market_state;
         let fee = bet_amount
@@ -135,70 +225,187 @@ pub mod multi_outcome_market {
         Ok(())
     }
 
-    pub fn resolve_market(ctx: Context<ResolveMarket>, market_id: u64) -> Result<()> {
+    pub fn close_market(ctx: Context<CloseMarket>, market_id: u64) -> Result<()> {
         let market_account = &mut ctx.accounts.market_account;
+        require!(
+            ctx.accounts.closer.key() == market_account.creator
+                || ctx.accounts.closer.key() == ctx.accounts.market_state.authority,
+            MarketError::Unauthorized
+        );
+        require!(
+            market_account.status.can_transition_to(MarketStatus::Closed),
+            MarketError::InvalidStatusTransition
+        );
+
+        market_account.status = MarketStatus::Closed;
+
+        emit!(MultiOutcomeMarketClosed { market_id });
+
+        Ok(())
+    }
+
+    pub fn delete_empty_market(ctx: Context<DeleteEmptyMarket>, market_id: u64) -> Result<()> {
+        let market_account = &ctx.accounts.market_account;
         require!(
             market_account.status == MarketStatus::Open,
             MarketError::MarketNotOpen
         );
+        require!(market_account.total_pool == 0, MarketError::MarketNotEmpty);
+
+        // Free the oracle question for another market
+        let question_binding = &mut ctx.accounts.question_binding;
+        question_binding.market_count = question_binding.market_count.saturating_sub(1);
+
+        emit!(MultiOutcomeMarketDeleted {
+            market_id,
+            creator: ctx.accounts.creator.key(),
+        });
+
+        Ok(())
+    }
+
+    pub fn resolve_market(ctx: Context<ResolveMarket>, market_id: u64) -> Result<()> {
+        let market_account = &mut ctx.accounts.market_account;
+        require!(
+            market_account.status.can_transition_to(MarketStatus::Resolved),
+            MarketError::InvalidStatusTransition
+        );
+        require!(
+            market_account.resolver.map_or(true, |resolver| resolver == ctx.accounts.resolver.key()),
+            MarketError::NotResolver
+        );
         require!(
             Clock::get()?.unix_timestamp >= market_account.resolution_time,
             MarketError::TooEarly
         );
 
-        // Oracle provides numeric answer as winning outcome index
-        let winning_outcome = ctx.accounts.oracle_answer.numeric_answer as u8;
+        let oracle_program = market_account.oracle_program(&ctx.accounts.market_state);
+        let question: QuestionAccount = load_oracle_account(&ctx.accounts.oracle_question, &oracle_program)?;
+        require!(
+            question.question_id == market_account.oracle_question_id,
+            MarketError::OracleQuestionMismatch
+        );
+        // The question must be settled on the oracle side, not still open or under dispute
         require!(
-            winning_outcome < market_account.num_outcomes,
-            MarketError::InvalidOutcome
+            matches!(question.status, AnswerStatus::Answered | AnswerStatus::Finalized),
+            MarketError::OracleQuestionNotFinal
         );
+        let answer: AnswerAccount = load_oracle_account(&ctx.accounts.oracle_answer, &oracle_program)?;
         require!(
-            ctx.accounts.oracle_answer.confidence_score > 0,
-            MarketError::OracleNotAnswered
+            answer.question_id == market_account.oracle_question_id,
+            MarketError::OracleQuestionMismatch
         );
 
+        // An inconclusive answer names no outcome: cancel so every position can be refunded
+        if answer.inconclusive {
+            market_account.status = MarketStatus::Canceled;
+
+            emit!(MultiOutcomeMarketCanceled { market_id });
+
+            return Ok(());
+        }
+
+        // Oracle provides numeric answer as winning outcome index, or a tie mask; reject rather than truncate
+        let numeric_answer = answer.numeric_answer;
+        let winning_outcomes = market_account.winners_from_answer(numeric_answer)?;
+        let confidence_score = answer.confidence_score;
+        require!(confidence_score > 0, MarketError::OracleNotAnswered);
+        require!(
+            confidence_score >= market_account.min_confidence,
+            MarketError::ConfidenceTooLow
+        );
+
+        // A winning or losing side too thin for sane odds cancels so every position can be refunded
+        let winning_pool = market_account.pool_of(&winning_outcomes)?;
+        let losing_pool = market_account
+            .total_pool
+            .checked_sub(winning_pool)
+            .ok_or(MarketError::Overflow)?;
+        if winning_pool < market_account.min_pool_per_side
+            || losing_pool < market_account.min_pool_per_side
+        {
+            market_account.status = MarketStatus::Canceled;
+
+            emit!(MultiOutcomeMarketCanceled { market_id });
+
+            return Ok(());
+        }
+
         market_account.status = MarketStatus::Resolved;
-        market_account.winning_outcome = winning_outcome;
+        market_account.winning_outcomes = winning_outcomes.clone();
+        market_account.claim_deadline = Clock::get()?
+            .unix_timestamp
+            .checked_add(CLAIM_PERIOD)
+            .ok_or(MarketError::Overflow)?;
 
+        let fees = market_account.total_fees;
         let market_state = &mut ctx.accounts.market_state;
         market_state.accumulated_fees = market_state
             .accumulated_fees
-            .checked_add(market_account.total_fees)
+            .checked_add(fees)
             .ok_or(MarketError::Overflow)?;
 
         emit!(MultiOutcomeMarketResolved {
             market_id,
-            winning_outcome,
+            winning_labels: winning_outcomes
+                .iter()
+                .map(|outcome| market_account.outcome_labels[*outcome as usize].clone())
+                .collect(),
+            winning_outcomes,
+            numeric_answer,
+            bool_answer: answer.bool_answer,
+            confidence_score,
         });
 
+        // Fees move to market state so withdraw_fees is funded and sweeps leave them alone
+        if fees > 0 {
+            **ctx.accounts.market_account.to_account_info().try_borrow_mut_lamports()? -= fees;
+            **ctx.accounts.market_state.to_account_info().try_borrow_mut_lamports()? += fees;
+        }
+
         Ok(())
     }
 
-    pub fn claim_winnings(ctx: Context<ClaimWinnings>, market_id: u64) -> Result<()> {
+    pub fn get_outcome_label(
+        ctx: Context<GetOutcomeLabel>,
+        market_id: u64,
+        index: u8,
+    ) -> Result<String> {
         let market_account = &ctx.accounts.market_account;
+        require!(index < market_account.num_outcomes, MarketError::InvalidOutcome);
+
+        Ok(market_account.outcome_labels[index as usize].clone())
+    }
+
+    pub fn claim_winnings(ctx: Context<ClaimWinnings>, market_id: u64) -> Result<u64> {
+        let market_account = &ctx.accounts.market_account;
+        require!(market_account.market_id == market_id, MarketError::MarketMismatch);
         require!(
             market_account.status == MarketStatus::Resolved,
             MarketError::NotResolved
         );
+        require!(
+            Clock::get()?.unix_timestamp < market_account.claim_deadline,
+            MarketError::ClaimDeadlinePassed
+        );
 
         let position = &mut ctx.accounts.position;
         require!(!position.claimed, MarketError::AlreadyClaimed);
 
-        let winning_amount = if position.amounts.len() > market_account.winning_outcome as usize {
-            position.amounts[market_account.winning_outcome as usize]
-        } else {
-            0
-        };
-
-        require!(winning_amount > 0, MarketError::NoWinnings);
+        let winning_outcomes = market_account.winning_outcomes.clone();
+        let winning_amount = position.stake_on_all(&winning_outcomes)?;
+        let payout = market_account.winnings_for(position)?;
+        let payout = capped_payout(&market_account.to_account_info(), payout)?;
 
-        let winning_pool = market_account.outcome_pools[market_account.winning_outcome as usize];
-        require!(winning_pool > 0, MarketError::NoWinnings);
-
-        let payout = winning_amount
-            .checked_mul(market_account.total_pool)
-            .and_then(|x| x.checked_div(winning_pool))
-            .ok_or(MarketError::Overflow)?;
+        // Stake on other outcomes stays on the position as the record of what was lost
+        let mut forfeited = 0u64;
+        for (outcome, amount) in position.amounts.iter_mut().enumerate() {
+            if winning_outcomes.contains(&(outcome as u8)) {
+                *amount = 0;
+            } else {
+                forfeited = forfeited.checked_add(*amount).ok_or(MarketError::Overflow)?;
+            }
+        }
 
         position.claimed = true;
 
@@ -208,29 +415,241 @@ pub mod multi_outcome_market {
         emit!(MultiOutcomeWinningsClaimed {
             market_id,
             user: ctx.accounts.winner.key(),
+            outcomes: winning_outcomes,
+            stake: winning_amount,
             amount: payout,
+            forfeited,
         });
 
+        emit!(ClaimRecord {
+            market_id,
+            user: ctx.accounts.winner.key(),
+            cost_basis: winning_amount,
+            payout,
+        });
+
+        Ok(payout)
+    }
+
+    pub fn quote_payout(ctx: Context<QuotePayout>, market_id: u64) -> Result<u64> {
+        let market_account = &ctx.accounts.market_account;
+        require!(market_account.market_id == market_id, MarketError::MarketMismatch);
+        require!(
+            market_account.status == MarketStatus::Resolved,
+            MarketError::NotResolved
+        );
+        require!(!ctx.accounts.position.claimed, MarketError::AlreadyClaimed);
+
+        market_account.winnings_for(&ctx.accounts.position)
+    }
+
+    pub fn cancel_market(ctx: Context<CancelMarket>, market_id: u64) -> Result<()> {
+        let market_account = &mut ctx.accounts.market_account;
+        require!(
+            market_account.status.can_transition_to(MarketStatus::Canceled),
+            MarketError::InvalidStatusTransition
+        );
+        let oracle_program = market_account.oracle_program(&ctx.accounts.market_state);
+        let question: QuestionAccount = load_oracle_account(&ctx.accounts.oracle_question, &oracle_program)?;
+        require!(
+            question.question_id == market_account.oracle_question_id,
+            MarketError::OracleQuestionMismatch
+        );
+
+        // Unanswered markets wait out the refund period; low-confidence answers cancel right away
+        if matches!(question.status, AnswerStatus::Pending | AnswerStatus::Expired) {
+            let refund_period: i64 = 7 * 24 * 60 * 60; // 7 days
+            require!(
+                Clock::get()?.unix_timestamp
+                    >= market_account.resolution_time + refund_period,
+                MarketError::TooEarlyToCancel
+            );
+        } else {
+            let answer: AnswerAccount = load_oracle_account(&ctx.accounts.oracle_answer, &oracle_program)?;
+            require!(
+                answer.confidence_score < market_account.min_confidence,
+                MarketError::AlreadyAnswered
+            );
+            require!(
+                Clock::get()?.unix_timestamp >= market_account.resolution_time,
+                MarketError::TooEarly
+            );
+        }
+
+        market_account.status = MarketStatus::Canceled;
+
+        emit!(MultiOutcomeMarketCanceled { market_id });
+
         Ok(())
     }
 
-    pub fn withdraw_fees(ctx: Context<WithdrawFees>) -> Result<()> {
-        let market_state = &mut ctx.accounts.market_state;
+    pub fn emergency_cancel(ctx: Context<EmergencyCancel>, market_id: u64) -> Result<()> {
+        let market_account = &mut ctx.accounts.market_account;
         require!(
-            ctx.accounts.authority.key() == market_state.authority,
-            MarketError::Unauthorized
+            market_account.status.can_transition_to(MarketStatus::Canceled),
+            MarketError::InvalidStatusTransition
+        );
+        require!(
+            Clock::get()?.unix_timestamp
+                >= market_account.resolution_time + EMERGENCY_CANCEL_GRACE_PERIOD,
+            MarketError::TooEarlyToCancel
         );
 
+        market_account.status = MarketStatus::Canceled;
+
+        emit!(MultiOutcomeMarketEmergencyCanceled { market_id });
+
+        Ok(())
+    }
+
+    pub fn claim_refund(ctx: Context<ClaimRefund>, market_id: u64) -> Result<u64> {
+        require!(
+            ctx.accounts.market_account.status == MarketStatus::Canceled,
+            MarketError::NotCanceled
+        );
+
+        let position = &mut ctx.accounts.position;
+        require!(!position.claimed, MarketError::AlreadyClaimed);
+
+        // A canceled market has no winner, so every outcome's stake is returned
+        let mut stake = 0u64;
+        for amount in position.amounts.iter_mut() {
+            stake = stake.checked_add(*amount).ok_or(MarketError::Overflow)?;
+            *amount = 0;
+        }
+        require!(stake > 0, MarketError::NoPosition);
+
+        // Fees are never collected on a canceled market, so refund the gross stake pro rata;
+        // widen before multiplying so large pools cannot overflow ahead of the division
+        let market_account = &ctx.accounts.market_account;
+        let refund_amount = (stake as u128)
+            .checked_mul(
+                (market_account.total_pool as u128)
+                    .checked_add(market_account.total_fees as u128)
+                    .ok_or(MarketError::Overflow)?,
+            )
+            .and_then(|x| x.checked_div(market_account.total_pool as u128))
+            .and_then(|x| u64::try_from(x).ok())
+            .ok_or(MarketError::Overflow)?;
+
+        // Mark the position claimed before any lamports leave the market
+        position.claimed = true;
+
+        **ctx.accounts.market_account.to_account_info().try_borrow_mut_lamports()? -= refund_amount;
+        **ctx.accounts.refundee.to_account_info().try_borrow_mut_lamports()? += refund_amount;
+
+        emit!(MultiOutcomeRefundClaimed {
+            market_id,
+            user: ctx.accounts.refundee.key(),
+            amount: refund_amount,
+        });
+
+        Ok(refund_amount)
+    }
+
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>) -> Result<()> {
+        let market_state = &mut ctx.accounts.market_state;
         let amount = market_state.accumulated_fees;
         require!(amount > 0, MarketError::NoFees);
 
         market_state.accumulated_fees = 0;
 
         **ctx.accounts.market_state.to_account_info().try_borrow_mut_lamports()? -= amount;
-        **ctx.accounts.authority.to_account_info().try_borrow_mut_lamports()? += amount;
+        **ctx.accounts.treasury.to_account_info().try_borrow_mut_lamports()? += amount;
 
         Ok(())
     }
+
+    pub fn sweep_unclaimed(ctx: Context<SweepUnclaimed>, market_id: u64) -> Result<()> {
+        let market_account = &ctx.accounts.market_account;
+        require!(
+            market_account.status == MarketStatus::Resolved,
+            MarketError::NotResolved
+        );
+        require!(
+            Clock::get()?.unix_timestamp >= market_account.claim_deadline,
+            MarketError::ClaimDeadlineNotPassed
+        );
+
+        // Leave enough behind to keep the market account rent exempt
+        let market_info = ctx.accounts.market_account.to_account_info();
+        let rent_exempt = Rent::get()?.minimum_balance(market_info.data_len());
+        let amount = market_info.lamports().saturating_sub(rent_exempt);
+        require!(amount > 0, MarketError::NothingToSweep);
+
+        **market_info.try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.treasury.to_account_info().try_borrow_mut_lamports()? += amount;
+
+        emit!(UnclaimedSwept { market_id, amount });
+
+        Ok(())
+    }
+
+    pub fn set_min_bet(ctx: Context<SetMinBet>, min_bet: u64) -> Result<()> {
+        let old_min_bet = ctx.accounts.market_state.min_bet;
+        ctx.accounts.market_state.min_bet = min_bet;
+
+        emit!(MinBetUpdated {
+            old_min_bet,
+            new_min_bet: min_bet,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_fee_percentage(ctx: Context<SetFeePercentage>, fee_percentage: u16) -> Result<()> {
+        require!(fee_percentage <= MAX_FEE_BPS, MarketError::FeeTooHigh);
+
+        let old_fee_percentage = ctx.accounts.market_state.fee_percentage;
+        ctx.accounts.market_state.fee_percentage = fee_percentage;
+
+        emit!(FeePercentageUpdated {
+            old_fee_percentage,
+            new_fee_percentage: fee_percentage,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_treasury(ctx: Context<SetTreasury>, treasury: Pubkey) -> Result<()> {
+        let old_treasury = ctx.accounts.market_state.treasury;
+        ctx.accounts.market_state.treasury = treasury;
+
+        emit!(TreasuryUpdated {
+            old_treasury,
+            new_treasury: treasury,
+        });
+
+        Ok(())
+    }
+}
+
+/// Cap a payout at the lamports the market holds above rent exemption, so rounding
+/// drift leaves the last winner with the remainder rather than a failed claim
+fn capped_payout(market_info: &AccountInfo, payout: u64) -> Result<u64> {
+    let rent_exempt = Rent::get()?.minimum_balance(market_info.data_len());
+    let available = market_info.lamports().saturating_sub(rent_exempt);
+    if payout > available {
+        msg!(
+            "Payout short by {} lamports, paying the remaining {}",
+            payout - available,
+            available
+        );
+        return Ok(available);
+    }
+    Ok(payout)
+}
+
+/// Read an oracle account as `T` once it is known to be owned by the oracle program and hold data,
+/// so a default or uninitialized account cannot pass; the discriminator check keeps a question
+/// account from standing in for an answer
+fn load_oracle_account<T: AccountDeserialize>(account: &AccountInfo, oracle_program: &Pubkey) -> Result<T> {
+    require!(
+        account.owner == oracle_program && !account.data_is_empty(),
+        MarketError::InvalidOracleAccount
+    );
+    let data = account.try_borrow_data()?;
+    T::try_deserialize(&mut &data[..]).map_err(|_| error!(MarketError::InvalidOracleAccount))
 }
 
 #[derive(Accounts)]
@@ -249,17 +668,33 @@ pub struct Initialize<'info> {
 }
 
 #[derive(Accounts)]
+#[instruction(
+    question: String,
+    outcome_labels: Vec<String>,
+    resolution_time: i64,
+    close_time: i64,
+    min_confidence: u8,
+    oracle_question_id: u64
+)]
 pub struct CreateMarket<'info> {
     #[account(
         init,
         payer = creator,
-        space = 8 + MarketAccount::LEN,
+        space = 8 + MarketAccount::size(&outcome_labels, market_state.max_question_len),
         seeds = [b"market", market_state.market_counter.to_le_bytes().as_ref()],
         bump
     )]
     pub market_account: Account<'info, MarketAccount>,
     #[account(mut, seeds = [b"market_state"], bump = market_state.bump)]
     pub market_state: Account<'info, MarketState>,
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = 8 + QuestionBinding::LEN,
+        seeds = [b"question_binding", oracle_question_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub question_binding: Account<'info, QuestionBinding>,
     #[account(mut)]
     pub creator: Signer<'info>,
     pub system_program: Program<'info, System>,
@@ -273,7 +708,7 @@ pub struct TakePosition<'info> {
     #[account(
         init_if_needed,
         payer = bettor,
-        space = 8 + Position::LEN,
+        space = 8 + Position::size(market_account.num_outcomes),
         seeds = [b"position", market_id.to_le_bytes().as_ref(), bettor.key().as_ref()],
         bump
     )]
@@ -285,6 +720,37 @@ pub struct TakePosition<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct CloseMarket<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(seeds = [b"market_state"], bump = market_state.bump)]
+    pub market_state: Account<'info, MarketState>,
+    pub closer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct DeleteEmptyMarket<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump,
+        has_one = creator @ MarketError::Unauthorized,
+        close = creator
+    )]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        mut,
+        seeds = [b"question_binding", market_account.oracle_question_id.to_le_bytes().as_ref()],
+        bump = question_binding.bump
+    )]
+    pub question_binding: Account<'info, QuestionBinding>,
+    #[account(mut)]
+    pub creator: Signer<'info>,
+}
+
 #[derive(Accounts)]
 #[instruction(market_id: u64)]
 pub struct ResolveMarket<'info> {
@@ -292,8 +758,23 @@ pub struct ResolveMarket<'info> {
     pub market_account: Account<'info, MarketAccount>,
     #[account(mut, seeds = [b"market_state"], bump = market_state.bump)]
     pub market_state: Account<'info, MarketState>,
-    /// CHECK: Oracle answer from trace9 program
+    /// CHECK: Oracle question from trace9 program; owner and discriminator are checked on resolution
+    pub oracle_question: AccountInfo<'info>,
+    /// CHECK: The market's answer PDA under its oracle program; contents are checked on resolution
+    #[account(
+        seeds = [b"answer", market_account.oracle_question_id.to_le_bytes().as_ref()],
+        bump,
+        seeds::program = market_account.oracle_program(&market_state)
+    )]
     pub oracle_answer: AccountInfo<'info>,
+    pub resolver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct GetOutcomeLabel<'info> {
+    #[account(seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
 }
 
 #[derive(Accounts)]
@@ -312,10 +793,135 @@ pub struct ClaimWinnings<'info> {
 }
 
 #[derive(Accounts)]
-pub struct WithdrawFees<'info> {
-    #[account(mut, seeds = [b"market_state"], bump = market_state.bump)]
+#[instruction(market_id: u64)]
+pub struct QuotePayout<'info> {
+    #[account(seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        seeds = [b"position", market_id.to_le_bytes().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct CancelMarket<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(seeds = [b"market_state"], bump = market_state.bump)]
     pub market_state: Account<'info, MarketState>,
+    /// CHECK: The market's question PDA under its oracle program; contents are checked on cancel
+    #[account(
+        seeds = [b"question", market_account.oracle_question_id.to_le_bytes().as_ref()],
+        bump,
+        seeds::program = market_account.oracle_program(&market_state)
+    )]
+    pub oracle_question: AccountInfo<'info>,
+    /// CHECK: The market's answer PDA under its oracle program; read only once the question is answered
+    #[account(
+        seeds = [b"answer", market_account.oracle_question_id.to_le_bytes().as_ref()],
+        bump,
+        seeds::program = market_account.oracle_program(&market_state)
+    )]
+    pub oracle_answer: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct EmergencyCancel<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct ClaimRefund<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        mut,
+        seeds = [b"position", market_id.to_le_bytes().as_ref(), refundee.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
     #[account(mut)]
+    pub refundee: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    #[account(
+        mut,
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
+    /// CHECK: Verified against the treasury stored on market state
+    #[account(mut, address = market_state.treasury @ MarketError::InvalidTreasury)]
+    pub treasury: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct SweepUnclaimed<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
+    /// CHECK: Verified against the treasury stored on market state
+    #[account(mut, address = market_state.treasury @ MarketError::InvalidTreasury)]
+    pub treasury: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMinBet<'info> {
+    #[account(
+        mut,
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetFeePercentage<'info> {
+    #[account(
+        mut,
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetTreasury<'info> {
+    #[account(
+        mut,
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
     pub authority: Signer<'info>,
 }
 
@@ -326,11 +932,16 @@ pub struct MarketState {
     pub market_counter: u64,
     pub fee_percentage: u16,
     pub accumulated_fees: u64,
+    pub min_bet: u64,
+    pub treasury: Pubkey,
+    pub max_question_len: u16,
+    pub min_outcomes: u8,
+    pub max_outcomes: u8,
     pub bump: u8,
 }
 
 impl MarketState {
-    pub const LEN: usize = 32 + 32 + 8 + 2 + 8 + 1;
+    pub const LEN: usize = 32 + 32 + 8 + 2 + 8 + 8 + 32 + 2 + 1 + 1 + 1;
 }
 
 #[account]
@@ -338,18 +949,86 @@ pub struct MarketAccount {
     pub market_id: u64,
     pub question: String,
     pub resolution_time: i64,
+    pub close_time: i64,
     pub num_outcomes: u8,
     pub outcome_labels: Vec<String>,
     pub outcome_pools: Vec<u64>,
     pub status: MarketStatus,
-    pub winning_outcome: u8,
+    pub winning_outcomes: Vec<u8>, // More than one on a tie
     pub total_pool: u64,
     pub total_fees: u64,
     pub created_at: i64,
+    pub creator: Pubkey,
+    pub min_confidence: u8,
+    pub oracle_question_id: u64,
+    pub claim_deadline: i64,
+    pub min_pool_per_side: u64,
+    pub oracle_program_override: Option<Pubkey>,
+    pub resolver: Option<Pubkey>,
 }
 
 impl MarketAccount {
-    pub const LEN: usize = 8 + (4 + 500) + 8 + 1 + (4 + 10 * (4 + 100)) + (4 + 10 * 8) + 1 + 1 + 8 + 8 + 8;
+    // Largest market: MAX_OUTCOMES outcomes with 100-byte labels
+    pub const LEN: usize = 8 + (4 + MAX_QUESTION_LEN) + 8 + 8 + 1 + (4 + MAX_OUTCOMES as usize * (4 + 100)) + (4 + MAX_OUTCOMES as usize * 8) + 1 + (4 + MAX_OUTCOMES as usize) + 8 + 8 + 8 + 32 + 1 + 8 + 8 + 8 + (1 + 32) + (1 + 32);
+
+    /// Space for a market with exactly these outcome labels: label, pool and winning outcome
+    /// vectors are sized to the outcomes instead of the MAX_OUTCOMES maximum, and the question
+    /// to the deployment's `max_question_len`
+    pub fn size(outcome_labels: &[String], max_question_len: u16) -> usize {
+        let num_outcomes = outcome_labels.len();
+        let labels: usize = outcome_labels.iter().map(|label| 4 + label.len()).sum();
+        8 + (4 + max_question_len as usize) + 8 + 8 + 1 + (4 + labels) + (4 + num_outcomes * 8) + 1 + (4 + num_outcomes) + 8 + 8 + 8 + 32 + 1 + 8 + 8 + 8 + (1 + 32) + (1 + 32)
+    }
+
+    /// Oracle program whose answer accounts may settle this market
+    pub fn oracle_program(&self, market_state: &MarketState) -> Pubkey {
+        self.oracle_program_override.unwrap_or(market_state.oracle_program)
+    }
+
+    /// Winning outcomes named by an oracle answer: a single index, or a `TIE_FLAG` answer whose
+    /// low bits mark two or more tied outcomes
+    pub fn winners_from_answer(&self, answer: u64) -> Result<Vec<u8>> {
+        if answer & TIE_FLAG != 0 {
+            let mask = answer & !TIE_FLAG;
+            require!(
+                mask.count_ones() >= 2 && mask >> self.num_outcomes == 0,
+                MarketError::InvalidOutcome
+            );
+            return Ok((0..self.num_outcomes).filter(|outcome| mask & (1 << outcome) != 0).collect());
+        }
+
+        let outcome = u8::try_from(answer)
+            .ok()
+            .filter(|outcome| *outcome < self.num_outcomes)
+            .ok_or(MarketError::InvalidOutcome)?;
+        Ok(vec![outcome])
+    }
+
+    /// Combined pool of `outcomes`
+    pub fn pool_of(&self, outcomes: &[u8]) -> Result<u64> {
+        outcomes.iter().try_fold(0u64, |pool, outcome| {
+            pool.checked_add(self.outcome_pools[*outcome as usize])
+                .ok_or(MarketError::Overflow.into())
+        })
+    }
+
+    /// Payout owed to a position on this resolved market; tied outcomes split the pool
+    /// in proportion to their combined stake
+    pub fn winnings_for(&self, position: &Position) -> Result<u64> {
+        let winning_pool = self.pool_of(&self.winning_outcomes)?;
+        require!(winning_pool > 0, MarketError::EmptyWinningPool);
+
+        let winning_amount = position.stake_on_all(&self.winning_outcomes)?;
+        require!(winning_amount > 0, MarketError::BetOnLosingSide);
+
+        // Widen before multiplying so large pools cannot overflow ahead of the division
+        let payout = (winning_amount as u128)
+            .checked_mul(self.total_pool as u128)
+            .and_then(|x| x.checked_div(winning_pool as u128))
+            .and_then(|x| u64::try_from(x).ok())
+            .ok_or(MarketError::Overflow)?;
+        Ok(payout)
+    }
 }
 
 #[account]
@@ -359,7 +1038,38 @@ pub struct Position {
 }
 
 impl Position {
-    pub const LEN: usize = 4 + (10 * 8) + 1;
+    pub const LEN: usize = 4 + (MAX_OUTCOMES as usize * 8) + 1;
+
+    /// Space for a position on a market with `num_outcomes` outcomes
+    pub fn size(num_outcomes: u8) -> usize {
+        4 + (num_outcomes as usize * 8) + 1
+    }
+
+    /// Stake on `outcome`, zero if the position never bet on it
+    pub fn stake_on(&self, outcome: u8) -> u64 {
+        self.amounts.get(outcome as usize).copied().unwrap_or(0)
+    }
+
+    /// Combined stake on `outcomes`
+    pub fn stake_on_all(&self, outcomes: &[u8]) -> Result<u64> {
+        outcomes.iter().try_fold(0u64, |stake, outcome| {
+            stake.checked_add(self.stake_on(*outcome))
+                .ok_or(MarketError::Overflow.into())
+        })
+    }
+}
+
+// Markets bound to a trace9 question
+#[account]
+pub struct QuestionBinding {
+    pub question_id: u64,
+    pub market_count: u32,
+    pub shared: bool,
+    pub bump: u8,
+}
+
+impl QuestionBinding {
+    pub const LEN: usize = 8 + 4 + 1 + 1;
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
@@ -370,12 +1080,83 @@ pub enum MarketStatus {
     Canceled,
 }
 
+impl MarketStatus {
+    /// Lifecycle transitions a market may make; resolved and canceled are terminal
+    pub fn can_transition_to(self, next: MarketStatus) -> bool {
+        matches!(
+            (self, next),
+            (MarketStatus::Open, MarketStatus::Closed)
+                | (MarketStatus::Open | MarketStatus::Closed, MarketStatus::Resolved)
+                | (MarketStatus::Open | MarketStatus::Closed, MarketStatus::Canceled)
+        )
+    }
+}
+
+// Oracle question structure (matches trace9 program); the name must match too, as it sets the discriminator
+#[account]
+pub struct QuestionAccount {
+    pub question_id: u64,
+    pub requester: Pubkey,
+    pub question_type: QuestionType,
+    pub question_hash: [u8; 32],
+    pub category: String,
+    pub bounty: u64,
+    pub timestamp: i64,
+    pub deadline: i64,
+    pub status: AnswerStatus,
+    pub refunded: bool,
+    pub revealed: bool,
+    pub incentive_opt_in: bool,
+    pub last_accrual_at: i64,
+    pub assigned_provider: Option<Pubkey>,
+}
+
+// Oracle answer structure (matches trace9 program)
+#[account]
+pub struct AnswerAccount {
+    pub question_id: u64,
+    pub provider: Pubkey,
+    pub confidence_score: u8,
+    pub bool_answer: bool,
+    pub numeric_answer: u64,
+    pub timestamp: i64,
+    pub challenge_deadline: i64,
+    pub revision: u8,
+    pub bond_locked: u64,
+    pub disputer: Pubkey,
+    pub inconclusive: bool,
+}
+
+// Oracle question type (matches trace9 program)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum QuestionType {
+    General,
+    Price,
+    YesNo,
+    Numeric,
+}
+
+// Oracle question status (matches trace9 program)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum AnswerStatus {
+    Pending,
+    Answered,
+    Disputed,
+    Finalized,
+    Expired,
+}
+
 #[event]
 pub struct MultiOutcomeMarketCreated {
     pub market_id: u64,
     pub question: String,
     pub num_outcomes: u8,
     pub resolution_time: i64,
+    pub close_time: i64,
+    pub min_confidence: u8,
+    pub oracle_question_id: u64,
+    pub oracle_program_override: Option<Pubkey>,
+    pub resolver: Option<Pubkey>,
 }
 
 #[event]
@@ -386,19 +1167,86 @@ pub struct OutcomePositionTaken {
     pub amount: u64,
 }
 
+#[event]
+pub struct MultiOutcomeMarketClosed {
+    pub market_id: u64,
+}
+
+#[event]
+pub struct MultiOutcomeMarketDeleted {
+    pub market_id: u64,
+    pub creator: Pubkey,
+}
+
 #[event]
 pub struct MultiOutcomeMarketResolved {
     pub market_id: u64,
-    pub winning_outcome: u8,
+    pub winning_outcomes: Vec<u8>,
+    pub winning_labels: Vec<String>,
+    pub numeric_answer: u64, // Oracle values the winners were read from
+    pub bool_answer: bool,
+    pub confidence_score: u8,
+}
+
+#[event]
+pub struct MultiOutcomeMarketCanceled {
+    pub market_id: u64,
+}
+
+#[event]
+pub struct MultiOutcomeMarketEmergencyCanceled {
+    pub market_id: u64,
+}
+
+#[event]
+pub struct MultiOutcomeRefundClaimed {
+    pub market_id: u64,
+    pub user: Pubkey,
+    pub amount: u64,
 }
 
 #[event]
 pub struct MultiOutcomeWinningsClaimed {
     pub market_id: u64,
     pub user: Pubkey,
+    pub outcomes: Vec<u8>,
+    pub stake: u64,
+    pub amount: u64,
+    pub forfeited: u64,
+}
+
+#[event]
+pub struct ClaimRecord {
+    pub market_id: u64,
+    pub user: Pubkey,
+    pub cost_basis: u64, // Net stake on the paying side
+    pub payout: u64,
+}
+
+#[event]
+pub struct TreasuryUpdated {
+    pub old_treasury: Pubkey,
+    pub new_treasury: Pubkey,
+}
+
+#[event]
+pub struct UnclaimedSwept {
+    pub market_id: u64,
     pub amount: u64,
 }
 
+#[event]
+pub struct MinBetUpdated {
+    pub old_min_bet: u64,
+    pub new_min_bet: u64,
+}
+
+#[event]
+pub struct FeePercentageUpdated {
+    pub old_fee_percentage: u16,
+    pub new_fee_percentage: u16,
+}
+
 #[error_code]
 pub enum MarketError {
     #[msg("Invalid question")]
@@ -407,8 +1255,12 @@ pub enum MarketError {
     InvalidOutcomeCount,
     #[msg("Invalid resolution time")]
     InvalidResolutionTime,
+    #[msg("Invalid close time")]
+    InvalidCloseTime,
     #[msg("Invalid outcome label")]
     InvalidOutcomeLabel,
+    #[msg("Duplicate outcome label")]
+    DuplicateOutcomeLabel,
     #[msg("Market not open")]
     MarketNotOpen,
     #[msg("Market expired")]
@@ -417,6 +1269,8 @@ pub enum MarketError {
     InvalidOutcome,
     #[msg("Zero bet")]
     ZeroBet,
+    #[msg("Bet below minimum")]
+    BetTooSmall,
     #[msg("Overflow")]
     Overflow,
     #[msg("Too early")]
@@ -427,11 +1281,55 @@ pub enum MarketError {
     NotResolved,
     #[msg("Already claimed")]
     AlreadyClaimed,
-    #[msg("No winnings")]
-    NoWinnings,
+    #[msg("Bet on the losing side")]
+    BetOnLosingSide,
     #[msg("Unauthorized")]
     Unauthorized,
     #[msg("No fees")]
     NoFees,
+    #[msg("Invalid minimum confidence")]
+    InvalidMinConfidence,
+    #[msg("Oracle confidence below market minimum")]
+    ConfidenceTooLow,
+    #[msg("Too early to cancel")]
+    TooEarlyToCancel,
+    #[msg("Already answered")]
+    AlreadyAnswered,
+    #[msg("Not canceled")]
+    NotCanceled,
+    #[msg("No position")]
+    NoPosition,
+    #[msg("Invalid treasury")]
+    InvalidTreasury,
+    #[msg("Market account does not match market id")]
+    MarketMismatch,
+    #[msg("Oracle answer is for a different question")]
+    OracleQuestionMismatch,
+    #[msg("Market has bets")]
+    MarketNotEmpty,
+    #[msg("Oracle question already bound to a market")]
+    QuestionAlreadyBound,
+    #[msg("Fee exceeds maximum")]
+    FeeTooHigh,
+    #[msg("Claim deadline has passed")]
+    ClaimDeadlinePassed,
+    #[msg("Claim deadline has not passed")]
+    ClaimDeadlineNotPassed,
+    #[msg("Nothing to sweep")]
+    NothingToSweep,
+    #[msg("Market cannot move to that status")]
+    InvalidStatusTransition,
+    #[msg("Oracle account is not an initialized account of the market's oracle program")]
+    InvalidOracleAccount,
+    #[msg("Max question length must be between 1 and MAX_QUESTION_LEN")]
+    InvalidMaxQuestionLen,
+    #[msg("Signer is not the market's resolver")]
+    NotResolver,
+    #[msg("Nobody bet on the winning outcomes")]
+    EmptyWinningPool,
+    #[msg("Oracle question is not answered yet, or its answer is disputed")]
+    OracleQuestionNotFinal,
+    #[msg("Outcome bounds must satisfy 2 <= min_outcomes <= max_outcomes <= MAX_OUTCOMES")]
+    InvalidOutcomeBounds,
 }
 
@@ -1,8 +1,16 @@
 use anchor_lang::prelude::*;
 use anchor_lang::system_program;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
 
 declare_id!("payFaciL3mP9vK8JqF2nH5xY7wD4bC6eA8g");
 
+pub const MAX_MEMO_LEN: usize = 64;
+pub const MAX_SPLIT_RECIPIENTS: usize = 10;
+pub const DEFAULT_REFUND_GRACE_PERIOD: i64 = 24 * 60 * 60; // 1 day in seconds
+pub const MAX_FEE_TIERS: usize = 5;
+pub const MAX_BATCH_SIZE: usize = 20; // Payments per batch settlement
+pub const MAX_MULTISIG_SIGNERS: usize = 10; // Keys that can approve authority actions
+
 #[program]
 pub mod payment_facilitator {
     use super::*;
@@ -15,6 +23,11 @@ pub mod payment_facilitator {
         facilitator.authority = ctx.accounts.authority.key();
         facilitator.platform_fee_bps = platform_fee_bps;
         facilitator.accumulated_fees = 0;
+        facilitator.refund_grace_period = DEFAULT_REFUND_GRACE_PERIOD;
+        facilitator.fee_tiers = Vec::new();
+        facilitator.treasury = ctx.accounts.authority.key();
+        facilitator.multisig_signers = Vec::new();
+        facilitator.multisig_threshold = 1;
         facilitator.bump = ctx.bumps.facilitator;
         
         Ok(())
@@ -25,8 +38,19 @@ pub mod payment_facilitator {
         ctx: Context<SettlePayment>,
         amount: u64,
         payment_id: [u8; 32],
+        memo: String, // Optional invoice reference, empty for none
+        expires_at: i64, // Last moment the payment may settle, 0 = never expires
     ) -> Result<()> {
         require!(amount > 0, PaymentFacilitatorError::InvalidAmount);
+        check_settlement_terms(&memo, expires_at)?;
+        require!(
+            ctx.accounts.recipient.key() != ctx.accounts.payer.key(),
+            PaymentFacilitatorError::RecipientIsPayer
+        );
+        require!(
+            ctx.accounts.recipient.key() != ctx.accounts.facilitator.key(),
+            PaymentFacilitatorError::RecipientIsFacilitator
+        );
         
         let facilitator = &mut ctx.accounts.facilitator;
         
@@ -40,7 +64,7 @@ pub mod payment_facilitator {
         
         // Calculate fee
         let fee = (amount as u128)
-            .checked_mul(facilitator.platform_fee_bps as u128)
+            .checked_mul(facilitator.fee_bps_for(amount) as u128)
             .and_then(|f| f.checked_div(10000))
             .ok_or(PaymentFacilitatorError::Overflow)? as u64;
         
@@ -80,6 +104,19 @@ pub mod payment_facilitator {
                 .checked_add(fee)
                 .ok_or(PaymentFacilitatorError::Overflow)?;
         }
+
+        // Record the settlement so it can be refunded during the grace period
+        let payment_record = &mut ctx.accounts.payment_record;
+        payment_record.payment_id = payment_id;
+        payment_record.payer = ctx.accounts.payer.key();
+        payment_record.recipient = ctx.accounts.recipient.key();
+        payment_record.amount = amount;
+        payment_record.fee = fee;
+        payment_record.settled_at = Clock::get()?.unix_timestamp;
+        payment_record.expires_at = expires_at;
+        payment_record.mint = Pubkey::default();
+        payment_record.refunded = false;
+        payment_record.bump = ctx.bumps.payment_record;
         
         emit!(PaymentSettled {
             payer: ctx.accounts.payer.key(),
@@ -87,11 +124,113 @@ pub mod payment_facilitator {
             amount,
             fee,
             payment_id,
+            memo,
         });
         
         Ok(())
     }
 
+    /// Settle a single payment in SPL tokens, taking the platform fee into the facilitator's vault
+    pub fn settle_payment_spl(
+        ctx: Context<SettlePaymentSpl>,
+        amount: u64,
+        payment_id: [u8; 32],
+        mint: Pubkey,
+        memo: String, // Optional invoice reference, empty for none
+        expires_at: i64, // Last moment the payment may settle, 0 = never expires
+    ) -> Result<()> {
+        require!(amount > 0, PaymentFacilitatorError::InvalidAmount);
+        check_settlement_terms(&memo, expires_at)?;
+
+        let recipient = ctx.accounts.recipient_token_account.owner;
+        require!(
+            recipient != ctx.accounts.payer.key(),
+            PaymentFacilitatorError::RecipientIsPayer
+        );
+        require!(
+            recipient != ctx.accounts.facilitator.key(),
+            PaymentFacilitatorError::RecipientIsFacilitator
+        );
+
+        let facilitator = &mut ctx.accounts.facilitator;
+
+        // Token payments share the anti-replay list with SOL payments
+        require!(
+            !facilitator.used_payments.contains(&payment_id),
+            PaymentFacilitatorError::PaymentUsed
+        );
+
+        facilitator.used_payments.push(payment_id);
+
+        // Calculate fee
+        let fee = (amount as u128)
+            .checked_mul(facilitator.fee_bps_for(amount) as u128)
+            .and_then(|f| f.checked_div(10000))
+            .ok_or(PaymentFacilitatorError::Overflow)? as u64;
+
+        let recipient_amount = amount.checked_sub(fee).ok_or(PaymentFacilitatorError::Overflow)?;
+
+        // Transfer to recipient
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.payer_token_account.to_account_info(),
+                    to: ctx.accounts.recipient_token_account.to_account_info(),
+                    authority: ctx.accounts.payer.to_account_info(),
+                },
+            ),
+            recipient_amount,
+        )?;
+
+        // Transfer fee to the vault; token fees are counted per mint, since accumulated_fees only counts lamports
+        let token_fee_balance = &mut ctx.accounts.token_fee_balance;
+        token_fee_balance.mint = mint;
+        token_fee_balance.bump = ctx.bumps.token_fee_balance;
+        if fee > 0 {
+            token::transfer(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.payer_token_account.to_account_info(),
+                        to: ctx.accounts.fee_vault.to_account_info(),
+                        authority: ctx.accounts.payer.to_account_info(),
+                    },
+                ),
+                fee,
+            )?;
+
+            token_fee_balance.accumulated_fees = token_fee_balance
+                .accumulated_fees
+                .checked_add(fee)
+                .ok_or(PaymentFacilitatorError::Overflow)?;
+        }
+
+        let payment_record = &mut ctx.accounts.payment_record;
+        payment_record.payment_id = payment_id;
+        payment_record.payer = ctx.accounts.payer.key();
+        payment_record.recipient = recipient;
+        payment_record.amount = amount;
+        payment_record.fee = fee;
+        payment_record.settled_at = Clock::get()?.unix_timestamp;
+        payment_record.expires_at = expires_at;
+        payment_record.mint = mint;
+        payment_record.refunded = false;
+        payment_record.bump = ctx.bumps.payment_record;
+
+        emit!(TokenPaymentSettled {
+            payer: ctx.accounts.payer.key(),
+            recipient,
+            mint,
+            amount,
+            fee,
+            payment_id,
+            memo,
+        });
+
+        Ok(())
+    }
+
     /// Batch settle multiple payments
     pub fn batch_settle_payments(
         ctx: Context<BatchSettlePayments>,
@@ -99,17 +238,39 @@ pub mod payment_facilitator {
         recipients: Vec<Pubkey>,
         payment_ids: Vec<[u8; 32]>,
     ) -> Result<()> {
+        check_batch_size(amounts.len())?;
+        check_batch_len("recipients", recipients.len(), amounts.len())?;
+        check_batch_len("payment_ids", payment_ids.len(), amounts.len())?;
         require!(
-            amounts.len() == recipients.len() && amounts.len() == payment_ids.len(),
-            PaymentFacilitatorError::InvalidBatch
+            payment_ids
+                .iter()
+                .enumerate()
+                .all(|(i, id)| !payment_ids[..i].contains(id)),
+            PaymentFacilitatorError::DuplicateBatchId
+        );
+
+        // Fail fast if the payer can't cover the whole batch
+        let total_amount = amounts
+            .iter()
+            .try_fold(0u64, |total, amount| total.checked_add(*amount))
+            .ok_or(PaymentFacilitatorError::Overflow)?;
+        require!(
+            ctx.accounts.payer.lamports() >= total_amount,
+            PaymentFacilitatorError::InsufficientFunds
         );
-        require!(amounts.len() > 0 && amounts.len() <= 20, PaymentFacilitatorError::InvalidBatchSize);
         
+        let payer_key = ctx.accounts.payer.key();
+        let facilitator_key = ctx.accounts.facilitator.key();
         let facilitator = &mut ctx.accounts.facilitator;
         let mut total_fee = 0u64;
         
         for i in 0..amounts.len() {
             require!(amounts[i] > 0, PaymentFacilitatorError::InvalidAmount);
+            require!(recipients[i] != payer_key, PaymentFacilitatorError::RecipientIsPayer);
+            require!(
+                recipients[i] != facilitator_key,
+                PaymentFacilitatorError::RecipientIsFacilitator
+            );
             require!(
                 !facilitator.used_payments.contains(&payment_ids[i]),
                 PaymentFacilitatorError::PaymentUsed
@@ -118,7 +279,7 @@ pub mod payment_facilitator {
             facilitator.used_payments.push(payment_ids[i]);
             
             let fee = (amounts[i] as u128)
-                .checked_mul(facilitator.platform_fee_bps as u128)
+                .checked_mul(facilitator.fee_bps_for(amounts[i]) as u128)
                 .and_then(|f| f.checked_div(10000))
                 .ok_or(PaymentFacilitatorError::Overflow)? as u64;
             
@@ -167,19 +328,199 @@ pub mod payment_facilitator {
         emit!(BatchPaymentsSettled {
             payer: ctx.accounts.payer.key(),
             count: amounts.len() as u8,
-            total_amount: amounts.iter().sum(),
+            total_amount,
             total_fee,
         });
         
         Ok(())
     }
 
-    /// Withdraw accumulated fees (authority only)
-    pub fn withdraw_fees(ctx: Context<WithdrawFees>) -> Result<()> {
+    /// Settle a single payment split across recipients by basis-point shares
+    /// Recipients are passed as writable remaining accounts, in the same order as `shares`
+    pub fn settle_split_payment<'info>(
+        ctx: Context<'_, '_, '_, 'info, SettleSplitPayment<'info>>,
+        amount: u64,
+        payment_id: [u8; 32],
+        shares: Vec<u16>,
+    ) -> Result<()> {
+        require!(amount > 0, PaymentFacilitatorError::InvalidAmount);
         require!(
-            ctx.accounts.authority.key() == ctx.accounts.facilitator.authority,
-            PaymentFacilitatorError::Unauthorized
+            shares.len() > 0 && shares.len() <= MAX_SPLIT_RECIPIENTS,
+            PaymentFacilitatorError::InvalidBatchSize
+        );
+        require!(
+            shares.len() == ctx.remaining_accounts.len(),
+            PaymentFacilitatorError::InvalidBatch
+        );
+        let total_shares: u64 = shares.iter().map(|s| *s as u64).sum();
+        require!(total_shares == 10000, PaymentFacilitatorError::InvalidShares);
+
+        let facilitator = &mut ctx.accounts.facilitator;
+
+        // Check if payment already used
+        require!(
+            !facilitator.used_payments.contains(&payment_id),
+            PaymentFacilitatorError::PaymentUsed
+        );
+
+        facilitator.used_payments.push(payment_id);
+
+        // Calculate fee
+        let fee = (amount as u128)
+            .checked_mul(facilitator.fee_bps_for(amount) as u128)
+            .and_then(|f| f.checked_div(10000))
+            .ok_or(PaymentFacilitatorError::Overflow)? as u64;
+
+        let net_amount = amount.checked_sub(fee).ok_or(PaymentFacilitatorError::Overflow)?;
+
+        // Compute each cut, giving the rounding remainder to the first recipient
+        let mut cuts = Vec::with_capacity(shares.len());
+        for share in shares.iter() {
+            let cut = (net_amount as u128)
+                .checked_mul(*share as u128)
+                .and_then(|c| c.checked_div(10000))
+                .ok_or(PaymentFacilitatorError::Overflow)? as u64;
+            cuts.push(cut);
+        }
+        let distributed: u64 = cuts.iter().sum();
+        cuts[0] = cuts[0]
+            .checked_add(net_amount - distributed)
+            .ok_or(PaymentFacilitatorError::Overflow)?;
+
+        for (recipient, cut) in ctx.remaining_accounts.iter().zip(cuts.iter()) {
+            if *cut == 0 {
+                continue;
+            }
+            anchor_lang::solana_program::program::invoke(
+                &anchor_lang::solana_program::system_instruction::transfer(
+                    ctx.accounts.payer.key,
+                    recipient.key,
+                    *cut,
+                ),
+                &[
+                    ctx.accounts.payer.to_account_info(),
+                    recipient.clone(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
+
+        // Transfer fee to facilitator
+        if fee > 0 {
+            anchor_lang::solana_program::program::invoke(
+                &anchor_lang::solana_program::system_instruction::transfer(
+                    ctx.accounts.payer.key,
+                    &ctx.accounts.facilitator.key(),
+                    fee,
+                ),
+                &[
+                    ctx.accounts.payer.to_account_info(),
+                    ctx.accounts.facilitator.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+
+            let facilitator = &mut ctx.accounts.facilitator;
+            facilitator.accumulated_fees = facilitator
+                .accumulated_fees
+                .checked_add(fee)
+                .ok_or(PaymentFacilitatorError::Overflow)?;
+        }
+
+        emit!(SplitPaymentSettled {
+            payer: ctx.accounts.payer.key(),
+            recipients: ctx.remaining_accounts.iter().map(|r| r.key()).collect(),
+            amounts: cuts,
+            fee,
+            payment_id,
+        });
+
+        Ok(())
+    }
+
+    /// Deposit a payment into the recipient's claimable balance instead of transferring it directly
+    pub fn deposit_for(
+        ctx: Context<DepositFor>,
+        recipient: Pubkey,
+        amount: u64,
+        payment_id: [u8; 32],
+    ) -> Result<()> {
+        require!(amount > 0, PaymentFacilitatorError::InvalidAmount);
+
+        let facilitator = &mut ctx.accounts.facilitator;
+
+        // Check if payment already used
+        require!(
+            !facilitator.used_payments.contains(&payment_id),
+            PaymentFacilitatorError::PaymentUsed
         );
+
+        facilitator.used_payments.push(payment_id);
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: ctx.accounts.claimable_balance.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let claimable_balance = &mut ctx.accounts.claimable_balance;
+        claimable_balance.recipient = recipient;
+        claimable_balance.balance = claimable_balance
+            .balance
+            .checked_add(amount)
+            .ok_or(PaymentFacilitatorError::Overflow)?;
+        claimable_balance.bump = ctx.bumps.claimable_balance;
+
+        emit!(PaymentDeposited {
+            payer: ctx.accounts.payer.key(),
+            recipient,
+            amount,
+            payment_id,
+            unclaimed: claimable_balance.balance,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraw the caller's claimable balance, taking the platform fee at claim time
+    pub fn claim_payment(ctx: Context<ClaimPayment>) -> Result<()> {
+        let amount = ctx.accounts.claimable_balance.balance;
+        require!(amount > 0, PaymentFacilitatorError::NothingToClaim);
+
+        let facilitator = &mut ctx.accounts.facilitator;
+        let fee = (amount as u128)
+            .checked_mul(facilitator.fee_bps_for(amount) as u128)
+            .and_then(|f| f.checked_div(10000))
+            .ok_or(PaymentFacilitatorError::Overflow)? as u64;
+        let recipient_amount = amount.checked_sub(fee).ok_or(PaymentFacilitatorError::Overflow)?;
+
+        facilitator.accumulated_fees = facilitator
+            .accumulated_fees
+            .checked_add(fee)
+            .ok_or(PaymentFacilitatorError::Overflow)?;
+        ctx.accounts.claimable_balance.balance = 0;
+
+        **ctx.accounts.claimable_balance.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.recipient.to_account_info().try_borrow_mut_lamports()? += recipient_amount;
+        **ctx.accounts.facilitator.to_account_info().try_borrow_mut_lamports()? += fee;
+
+        emit!(PaymentClaimed {
+            recipient: ctx.accounts.recipient.key(),
+            amount: recipient_amount,
+            fee,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraw accumulated fees (authority only, plus multisig approvals as signing remaining accounts)
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>) -> Result<()> {
+        ctx.accounts.facilitator.require_multisig(ctx.remaining_accounts)?;
         
         let facilitator = &mut ctx.accounts.facilitator;
         let amount = facilitator.accumulated_fees;
@@ -187,64 +528,485 @@ pub mod payment_facilitator {
         
         facilitator.accumulated_fees = 0;
         
-        // Transfer fees to authority
+        // Transfer fees to treasury
         **ctx.accounts.facilitator.to_account_info().try_borrow_mut_lamports()? -= amount;
-        **ctx.accounts.authority.to_account_info().try_borrow_mut_lamports()? += amount;
+        **ctx.accounts.treasury.to_account_info().try_borrow_mut_lamports()? += amount;
         
         emit!(FeesWithdrawn {
-            to: ctx.accounts.authority.key(),
+            to: ctx.accounts.treasury.key(),
             amount,
         });
         
         Ok(())
     }
 
-    /// Update platform fee (authority only)
-    pub fn update_platform_fee(ctx: Context<UpdatePlatformFee>, new_fee_bps: u16) -> Result<()> {
+    /// Withdraw the token fees accrued for `mint` from the fee vault to the treasury's token account
+    /// (authority only, plus multisig approvals as signing remaining accounts)
+    pub fn withdraw_token_fees(ctx: Context<WithdrawTokenFees>, mint: Pubkey) -> Result<()> {
+        ctx.accounts.facilitator.require_multisig(ctx.remaining_accounts)?;
+
+        let amount = ctx.accounts.token_fee_balance.accumulated_fees;
+        require!(amount > 0, PaymentFacilitatorError::NoFees);
+
+        ctx.accounts.token_fee_balance.accumulated_fees = 0;
+
+        // The vault belongs to the facilitator PDA, so the transfer is signed with its seeds
+        let bump = [ctx.accounts.facilitator.bump];
+        let signer_seeds: &[&[u8]] = &[b"payment_facilitator", &bump];
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.fee_vault.to_account_info(),
+                    to: ctx.accounts.treasury_token_account.to_account_info(),
+                    authority: ctx.accounts.facilitator.to_account_info(),
+                },
+                &[signer_seeds],
+            ),
+            amount,
+        )?;
+
+        emit!(TokenFeesWithdrawn {
+            mint,
+            to: ctx.accounts.treasury_token_account.key(),
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Reverse a settled payment within the grace period (recipient or authority)
+    pub fn refund_payment(ctx: Context<RefundPayment>, payment_id: [u8; 32]) -> Result<()> {
+        let refunder = ctx.accounts.refunder.key();
+        let payment_record = &ctx.accounts.payment_record;
         require!(
-            ctx.accounts.authority.key() == ctx.accounts.facilitator.authority,
+            refunder == payment_record.recipient || refunder == ctx.accounts.facilitator.authority,
             PaymentFacilitatorError::Unauthorized
         );
-        require!(new_fee_bps <= 1000, PaymentFacilitatorError::InvalidFee);
-        
-        let old_fee = ctx.accounts.facilitator.platform_fee_bps;
-        ctx.accounts.facilitator.platform_fee_bps = new_fee_bps;
-        
-        emit!(PlatformFeeUpdated {
-            old_fee,
-            new_fee: new_fee_bps,
+        require!(
+            ctx.accounts.payer.key() == payment_record.payer,
+            PaymentFacilitatorError::InvalidPayer
+        );
+        require!(!payment_record.refunded, PaymentFacilitatorError::AlreadyRefunded);
+        require!(
+            payment_record.mint == Pubkey::default(),
+            PaymentFacilitatorError::TokenPayment
+        );
+        require!(
+            Clock::get()?.unix_timestamp
+                <= payment_record.settled_at + ctx.accounts.facilitator.refund_grace_period,
+            PaymentFacilitatorError::RefundWindowClosed
+        );
+
+        let fee = payment_record.fee;
+        let net_amount = payment_record
+            .amount
+            .checked_sub(fee)
+            .ok_or(PaymentFacilitatorError::Overflow)?;
+
+        // Flag the record before moving lamports so a payment can never be refunded twice
+        ctx.accounts.payment_record.refunded = true;
+
+        // Return the net amount from the refunder to the payer
+        anchor_lang::solana_program::program::invoke(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                ctx.accounts.refunder.key,
+                ctx.accounts.payer.key,
+                net_amount,
+            ),
+            &[
+                ctx.accounts.refunder.to_account_info(),
+                ctx.accounts.payer.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        // Give back the platform fee accrued for this payment
+        if fee > 0 {
+            let facilitator = &mut ctx.accounts.facilitator;
+            facilitator.accumulated_fees = facilitator
+                .accumulated_fees
+                .checked_sub(fee)
+                .ok_or(PaymentFacilitatorError::NoFees)?;
+
+            **ctx.accounts.facilitator.to_account_info().try_borrow_mut_lamports()? -= fee;
+            **ctx.accounts.payer.to_account_info().try_borrow_mut_lamports()? += fee;
+        }
+
+        emit!(PaymentRefunded {
+            payment_id,
+            payer: ctx.accounts.payer.key(),
+            refunder,
+            amount: net_amount,
+            fee,
         });
-        
+
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + PaymentFacilitator::LEN,
-        seeds = [b"payment_facilitator"],
-        bump
-    )]
-    pub facilitator: Account<'info, PaymentFacilitator>,
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+    /// Update the fee treasury (authority only, plus multisig approvals as signing remaining accounts)
+    pub fn set_treasury(ctx: Context<SetTreasury>, treasury: Pubkey) -> Result<()> {
+        ctx.accounts.facilitator.require_multisig(ctx.remaining_accounts)?;
 
-#[derive(Accounts)]
-pub struct SettlePayment<'info> {
-    #[account(mut, seeds = [b"payment_facilitator"], bump = facilitator.bump)]
-    pub facilitator: Account<'info, PaymentFacilitator>,
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    /// CHECK: Recipient can be any account
-    #[account(mut)]
-    pub recipient: UncheckedAccount<'info>,
-    pub system_program: Program<'info, System>,
-}
+        let old_treasury = ctx.accounts.facilitator.treasury;
+        ctx.accounts.facilitator.treasury = treasury;
+
+        emit!(TreasuryUpdated {
+            old_treasury,
+            new_treasury: treasury,
+        });
+
+        Ok(())
+    }
+
+    /// Set volume-based fee tiers, sorted by ascending threshold (authority only)
+    /// Payments at or above a tier's threshold pay that tier's bps; smaller ones pay the base fee
+    pub fn set_fee_schedule(ctx: Context<SetFeeSchedule>, fee_tiers: Vec<FeeTier>) -> Result<()> {
+        require!(fee_tiers.len() <= MAX_FEE_TIERS, PaymentFacilitatorError::InvalidFeeSchedule);
+
+        for (i, tier) in fee_tiers.iter().enumerate() {
+            require!(tier.bps <= 1000, PaymentFacilitatorError::InvalidFee); // Max 10%
+            if i > 0 {
+                require!(
+                    tier.threshold > fee_tiers[i - 1].threshold,
+                    PaymentFacilitatorError::InvalidFeeSchedule
+                );
+            }
+        }
+
+        ctx.accounts.facilitator.fee_tiers = fee_tiers.clone();
+
+        emit!(FeeScheduleUpdated { fee_tiers });
+
+        Ok(())
+    }
+
+    /// Update the refund grace period (authority only)
+    pub fn set_refund_grace_period(
+        ctx: Context<SetRefundGracePeriod>,
+        refund_grace_period: i64,
+    ) -> Result<()> {
+        require!(refund_grace_period >= 0, PaymentFacilitatorError::InvalidGracePeriod);
+
+        ctx.accounts.facilitator.refund_grace_period = refund_grace_period;
+
+        Ok(())
+    }
+
+    /// Replace the M-of-N signer set gating fee withdrawals and treasury changes (authority only, plus current approvals)
+    /// A threshold of 1 keeps single-authority mode
+    pub fn set_multisig(ctx: Context<SetMultisig>, signers: Vec<Pubkey>, threshold: u8) -> Result<()> {
+        ctx.accounts.facilitator.require_multisig(ctx.remaining_accounts)?;
+
+        require!(
+            signers.len() <= MAX_MULTISIG_SIGNERS
+                && threshold >= 1
+                && (threshold == 1 || threshold as usize <= signers.len()),
+            PaymentFacilitatorError::InvalidMultisig
+        );
+        for (i, signer) in signers.iter().enumerate() {
+            require!(!signers[..i].contains(signer), PaymentFacilitatorError::InvalidMultisig);
+        }
+
+        let facilitator = &mut ctx.accounts.facilitator;
+        facilitator.multisig_signers = signers.clone();
+        facilitator.multisig_threshold = threshold;
+
+        emit!(MultisigUpdated {
+            signers,
+            threshold,
+        });
+
+        Ok(())
+    }
+
+    /// Create a recurring payment to a recipient, pre-funded by the payer
+    pub fn create_subscription(
+        ctx: Context<CreateSubscription>,
+        amount: u64,
+        interval_secs: i64,
+        start_time: i64, // First due time, 0 = now
+        deposit: u64,
+    ) -> Result<()> {
+        require!(amount > 0, PaymentFacilitatorError::InvalidAmount);
+        require!(interval_secs > 0, PaymentFacilitatorError::InvalidInterval);
+
+        let now = Clock::get()?.unix_timestamp;
+        let next_due = if start_time == 0 { now } else { start_time };
+        require!(next_due >= now, PaymentFacilitatorError::InvalidInterval);
+
+        let subscription = &mut ctx.accounts.subscription;
+        subscription.payer = ctx.accounts.payer.key();
+        subscription.recipient = ctx.accounts.recipient.key();
+        subscription.amount = amount;
+        subscription.interval_secs = interval_secs;
+        subscription.next_due = next_due;
+        subscription.balance = 0;
+        subscription.bump = ctx.bumps.subscription;
+
+        if deposit > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.payer.to_account_info(),
+                        to: ctx.accounts.subscription.to_account_info(),
+                    },
+                ),
+                deposit,
+            )?;
+            ctx.accounts.subscription.balance = deposit;
+        }
+
+        emit!(SubscriptionCreated {
+            payer: ctx.accounts.payer.key(),
+            recipient: ctx.accounts.recipient.key(),
+            amount,
+            interval_secs,
+            next_due,
+        });
+
+        Ok(())
+    }
+
+    /// Top up the pre-funded balance of a subscription
+    pub fn fund_subscription(ctx: Context<FundSubscription>, amount: u64) -> Result<()> {
+        require!(amount > 0, PaymentFacilitatorError::InvalidAmount);
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: ctx.accounts.subscription.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let subscription = &mut ctx.accounts.subscription;
+        subscription.balance = subscription
+            .balance
+            .checked_add(amount)
+            .ok_or(PaymentFacilitatorError::Overflow)?;
+
+        Ok(())
+    }
+
+    /// Pay out a subscription installment once it is due (permissionless)
+    pub fn execute_subscription(ctx: Context<ExecuteSubscription>) -> Result<()> {
+        require!(
+            ctx.accounts.recipient.key() == ctx.accounts.subscription.recipient,
+            PaymentFacilitatorError::InvalidRecipient
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let subscription = &mut ctx.accounts.subscription;
+        require!(now >= subscription.next_due, PaymentFacilitatorError::SubscriptionNotDue);
+
+        let amount = subscription.amount;
+        require!(
+            subscription.balance >= amount,
+            PaymentFacilitatorError::InsufficientSubscriptionBalance
+        );
+
+        let facilitator = &mut ctx.accounts.facilitator;
+        let fee = (amount as u128)
+            .checked_mul(facilitator.fee_bps_for(amount) as u128)
+            .and_then(|f| f.checked_div(10000))
+            .ok_or(PaymentFacilitatorError::Overflow)? as u64;
+        let recipient_amount = amount.checked_sub(fee).ok_or(PaymentFacilitatorError::Overflow)?;
+
+        subscription.balance -= amount;
+        subscription.next_due = subscription
+            .next_due
+            .checked_add(subscription.interval_secs)
+            .ok_or(PaymentFacilitatorError::Overflow)?;
+        facilitator.accumulated_fees = facilitator
+            .accumulated_fees
+            .checked_add(fee)
+            .ok_or(PaymentFacilitatorError::Overflow)?;
+
+        **ctx.accounts.subscription.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.recipient.to_account_info().try_borrow_mut_lamports()? += recipient_amount;
+        **ctx.accounts.facilitator.to_account_info().try_borrow_mut_lamports()? += fee;
+
+        emit!(SubscriptionExecuted {
+            payer: ctx.accounts.subscription.payer,
+            recipient: ctx.accounts.subscription.recipient,
+            amount,
+            fee,
+            next_due: ctx.accounts.subscription.next_due,
+        });
+
+        Ok(())
+    }
+
+    /// Cancel a subscription and return the remaining balance to the payer
+    pub fn cancel_subscription(ctx: Context<CancelSubscription>) -> Result<()> {
+        emit!(SubscriptionCanceled {
+            payer: ctx.accounts.subscription.payer,
+            recipient: ctx.accounts.subscription.recipient,
+            refunded: ctx.accounts.subscription.balance,
+        });
+
+        Ok(())
+    }
+
+    /// Update platform fee (authority only)
+    pub fn update_platform_fee(ctx: Context<UpdatePlatformFee>, new_fee_bps: u16) -> Result<()> {
+        require!(new_fee_bps <= 1000, PaymentFacilitatorError::InvalidFee);
+        
+        let old_fee = ctx.accounts.facilitator.platform_fee_bps;
+        ctx.accounts.facilitator.platform_fee_bps = new_fee_bps;
+        
+        emit!(PlatformFeeUpdated {
+            old_fee,
+            new_fee: new_fee_bps,
+        });
+        
+        Ok(())
+    }
+}
+
+/// Checks shared by the SOL and token settlement paths: the payment has not expired and the memo is
+/// a short single line
+fn check_settlement_terms(memo: &str, expires_at: i64) -> Result<()> {
+    require!(
+        expires_at == 0 || Clock::get()?.unix_timestamp <= expires_at,
+        PaymentFacilitatorError::PaymentExpired
+    );
+    require!(
+        memo.len() <= MAX_MEMO_LEN && !memo.chars().any(|c| c.is_control()),
+        PaymentFacilitatorError::InvalidMemo
+    );
+    Ok(())
+}
+
+/// Reject empty batches and batches over MAX_BATCH_SIZE
+fn check_batch_size(len: usize) -> Result<()> {
+    require!(len > 0, PaymentFacilitatorError::EmptyBatch);
+    require!(len <= MAX_BATCH_SIZE, PaymentFacilitatorError::BatchTooLarge);
+    Ok(())
+}
+
+/// Reject a batch vector whose length differs from the batch's, logging which field mismatched
+fn check_batch_len(field: &str, len: usize, expected: usize) -> Result<()> {
+    if len != expected {
+        msg!("{} has {} entries, expected {}", field, len, expected);
+        return err!(PaymentFacilitatorError::LengthMismatch);
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + PaymentFacilitator::LEN,
+        seeds = [b"payment_facilitator"],
+        bump
+    )]
+    pub facilitator: Account<'info, PaymentFacilitator>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, payment_id: [u8; 32])]
+pub struct SettlePayment<'info> {
+    #[account(mut, seeds = [b"payment_facilitator"], bump = facilitator.bump)]
+    pub facilitator: Account<'info, PaymentFacilitator>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PaymentRecord::LEN,
+        seeds = [b"payment", payment_id.as_ref()],
+        bump
+    )]
+    pub payment_record: Account<'info, PaymentRecord>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: Recipient can be any account
+    #[account(mut)]
+    pub recipient: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, payment_id: [u8; 32], mint: Pubkey)]
+pub struct SettlePaymentSpl<'info> {
+    #[account(mut, seeds = [b"payment_facilitator"], bump = facilitator.bump)]
+    pub facilitator: Account<'info, PaymentFacilitator>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PaymentRecord::LEN,
+        seeds = [b"payment", payment_id.as_ref()],
+        bump
+    )]
+    pub payment_record: Account<'info, PaymentRecord>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = payer
+    )]
+    pub payer_token_account: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = mint)]
+    pub recipient_token_account: Account<'info, TokenAccount>,
+    /// The mint's associated token account of the facilitator PDA, where token fees accrue
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = facilitator
+    )]
+    pub fee_vault: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + TokenFeeBalance::LEN,
+        seeds = [b"token_fees", mint.as_ref()],
+        bump
+    )]
+    pub token_fee_balance: Account<'info, TokenFeeBalance>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct WithdrawTokenFees<'info> {
+    #[account(
+        seeds = [b"payment_facilitator"],
+        bump = facilitator.bump,
+        has_one = authority @ PaymentFacilitatorError::Unauthorized
+    )]
+    pub facilitator: Account<'info, PaymentFacilitator>,
+    pub authority: Signer<'info>,
+    #[account(mut, seeds = [b"token_fees", mint.as_ref()], bump = token_fee_balance.bump)]
+    pub token_fee_balance: Account<'info, TokenFeeBalance>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = facilitator
+    )]
+    pub fee_vault: Account<'info, TokenAccount>,
+    /// CHECK: Only names the owner of the treasury token account
+    #[account(address = facilitator.treasury @ PaymentFacilitatorError::InvalidTreasury)]
+    pub treasury: UncheckedAccount<'info>,
+    /// The mint's associated token account of the treasury
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = treasury
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
 
 #[derive(Accounts)]
 pub struct BatchSettlePayments<'info> {
@@ -259,17 +1021,192 @@ pub struct BatchSettlePayments<'info> {
 }
 
 #[derive(Accounts)]
-pub struct WithdrawFees<'info> {
+pub struct SettleSplitPayment<'info> {
+    #[account(mut, seeds = [b"payment_facilitator"], bump = facilitator.bump)]
+    pub facilitator: Account<'info, PaymentFacilitator>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(recipient: Pubkey)]
+pub struct DepositFor<'info> {
+    #[account(mut, seeds = [b"payment_facilitator"], bump = facilitator.bump)]
+    pub facilitator: Account<'info, PaymentFacilitator>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + ClaimableBalance::LEN,
+        seeds = [b"claimable", recipient.as_ref()],
+        bump
+    )]
+    pub claimable_balance: Account<'info, ClaimableBalance>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimPayment<'info> {
     #[account(mut, seeds = [b"payment_facilitator"], bump = facilitator.bump)]
     pub facilitator: Account<'info, PaymentFacilitator>,
+    #[account(
+        mut,
+        seeds = [b"claimable", recipient.key().as_ref()],
+        bump = claimable_balance.bump,
+        has_one = recipient @ PaymentFacilitatorError::Unauthorized
+    )]
+    pub claimable_balance: Account<'info, ClaimableBalance>,
     #[account(mut)]
+    pub recipient: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    #[account(
+        mut,
+        seeds = [b"payment_facilitator"],
+        bump = facilitator.bump,
+        has_one = authority @ PaymentFacilitatorError::Unauthorized
+    )]
+    pub facilitator: Account<'info, PaymentFacilitator>,
     pub authority: Signer<'info>,
+    /// CHECK: Verified against the treasury stored on the facilitator
+    #[account(mut, address = facilitator.treasury @ PaymentFacilitatorError::InvalidTreasury)]
+    pub treasury: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
-pub struct UpdatePlatformFee<'info> {
+pub struct SetTreasury<'info> {
+    #[account(
+        mut,
+        seeds = [b"payment_facilitator"],
+        bump = facilitator.bump,
+        has_one = authority @ PaymentFacilitatorError::Unauthorized
+    )]
+    pub facilitator: Account<'info, PaymentFacilitator>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(payment_id: [u8; 32])]
+pub struct RefundPayment<'info> {
+    #[account(mut, seeds = [b"payment_facilitator"], bump = facilitator.bump)]
+    pub facilitator: Account<'info, PaymentFacilitator>,
+    #[account(mut, seeds = [b"payment", payment_id.as_ref()], bump = payment_record.bump)]
+    pub payment_record: Account<'info, PaymentRecord>,
+    #[account(mut)]
+    pub refunder: Signer<'info>,
+    /// CHECK: Verified against the payer stored on the payment record
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateSubscription<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Subscription::LEN,
+        seeds = [b"subscription", payer.key().as_ref(), recipient.key().as_ref()],
+        bump
+    )]
+    pub subscription: Account<'info, Subscription>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: Recipient can be any account
+    pub recipient: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FundSubscription<'info> {
+    #[account(
+        mut,
+        seeds = [b"subscription", payer.key().as_ref(), subscription.recipient.as_ref()],
+        bump = subscription.bump
+    )]
+    pub subscription: Account<'info, Subscription>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteSubscription<'info> {
+    #[account(
+        mut,
+        seeds = [b"subscription", subscription.payer.as_ref(), subscription.recipient.as_ref()],
+        bump = subscription.bump
+    )]
+    pub subscription: Account<'info, Subscription>,
     #[account(mut, seeds = [b"payment_facilitator"], bump = facilitator.bump)]
     pub facilitator: Account<'info, PaymentFacilitator>,
+    /// CHECK: Verified against the subscription recipient
+    #[account(mut)]
+    pub recipient: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelSubscription<'info> {
+    #[account(
+        mut,
+        close = payer,
+        seeds = [b"subscription", payer.key().as_ref(), subscription.recipient.as_ref()],
+        bump = subscription.bump
+    )]
+    pub subscription: Account<'info, Subscription>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePlatformFee<'info> {
+    #[account(
+        mut,
+        seeds = [b"payment_facilitator"],
+        bump = facilitator.bump,
+        has_one = authority @ PaymentFacilitatorError::Unauthorized
+    )]
+    pub facilitator: Account<'info, PaymentFacilitator>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetFeeSchedule<'info> {
+    #[account(
+        mut,
+        seeds = [b"payment_facilitator"],
+        bump = facilitator.bump,
+        has_one = authority @ PaymentFacilitatorError::Unauthorized
+    )]
+    pub facilitator: Account<'info, PaymentFacilitator>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMultisig<'info> {
+    #[account(
+        mut,
+        seeds = [b"payment_facilitator"],
+        bump = facilitator.bump,
+        has_one = authority @ PaymentFacilitatorError::Unauthorized
+    )]
+    pub facilitator: Account<'info, PaymentFacilitator>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRefundGracePeriod<'info> {
+    #[account(
+        mut,
+        seeds = [b"payment_facilitator"],
+        bump = facilitator.bump,
+        has_one = authority @ PaymentFacilitatorError::Unauthorized
+    )]
+    pub facilitator: Account<'info, PaymentFacilitator>,
     pub authority: Signer<'info>,
 }
 
@@ -279,11 +1216,109 @@ pub struct PaymentFacilitator {
     pub platform_fee_bps: u16,       // 2 bytes (basis points, e.g., 100 = 1%)
     pub accumulated_fees: u64,       // 8 bytes
     pub used_payments: Vec<[u8; 32]>, // Variable length
+    pub refund_grace_period: i64,    // 8 bytes (seconds)
+    pub fee_tiers: Vec<FeeTier>,     // 4 + MAX_FEE_TIERS * FeeTier::LEN bytes
+    pub treasury: Pubkey,            // 32 bytes
+    pub multisig_signers: Vec<Pubkey>, // 4 + MAX_MULTISIG_SIGNERS * 32 bytes
+    pub multisig_threshold: u8,      // 1 byte (1 = single authority)
     pub bump: u8,                     // 1 byte
 }
 
 impl PaymentFacilitator {
-    pub const LEN: usize = 32 + 2 + 8 + 4 + (32 * 100) + 1; // Space for up to 100 used payments
+    pub const LEN: usize = 32 + 2 + 8 + 4 + (32 * 100) + 8 + (4 + MAX_FEE_TIERS * FeeTier::LEN) + 32 + (4 + MAX_MULTISIG_SIGNERS * 32) + 1 + 1; // Space for up to 100 used payments
+
+    /// Fee in basis points for a payment of `amount`, using the highest tier it reaches
+    pub fn fee_bps_for(&self, amount: u64) -> u16 {
+        self.fee_tiers
+            .iter()
+            .rev()
+            .find(|tier| amount >= tier.threshold)
+            .map(|tier| tier.bps)
+            .unwrap_or(self.platform_fee_bps)
+    }
+
+    /// Require at least `multisig_threshold` of the configured signers among `approvals`
+    pub fn require_multisig(&self, approvals: &[AccountInfo]) -> Result<()> {
+        if self.multisig_threshold <= 1 {
+            return Ok(());
+        }
+
+        let approved = self
+            .multisig_signers
+            .iter()
+            .filter(|signer| approvals.iter().any(|a| a.is_signer && a.key == *signer))
+            .count();
+        require!(
+            approved >= self.multisig_threshold as usize,
+            PaymentFacilitatorError::MultisigThresholdNotMet
+        );
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct FeeTier {
+    pub threshold: u64,              // 8 bytes (minimum payment in lamports)
+    pub bps: u16,                    // 2 bytes
+}
+
+impl FeeTier {
+    pub const LEN: usize = 8 + 2;
+}
+
+#[account]
+pub struct PaymentRecord {
+    pub payment_id: [u8; 32],        // 32 bytes
+    pub payer: Pubkey,               // 32 bytes
+    pub recipient: Pubkey,           // 32 bytes
+    pub amount: u64,                 // 8 bytes (gross, including fee)
+    pub fee: u64,                    // 8 bytes
+    pub settled_at: i64,             // 8 bytes
+    pub expires_at: i64,             // 8 bytes (0 = never expires)
+    pub mint: Pubkey,                // 32 bytes (default = native SOL)
+    pub refunded: bool,              // 1 byte
+    pub bump: u8,                    // 1 byte
+}
+
+impl PaymentRecord {
+    pub const LEN: usize = 32 + 32 + 32 + 8 + 8 + 8 + 8 + 32 + 1 + 1;
+}
+
+#[account]
+pub struct Subscription {
+    pub payer: Pubkey,               // 32 bytes
+    pub recipient: Pubkey,           // 32 bytes
+    pub amount: u64,                 // 8 bytes (per installment)
+    pub interval_secs: i64,          // 8 bytes
+    pub next_due: i64,               // 8 bytes
+    pub balance: u64,                // 8 bytes (pre-funded lamports)
+    pub bump: u8,                    // 1 byte
+}
+
+impl Subscription {
+    pub const LEN: usize = 32 + 32 + 8 + 8 + 8 + 8 + 1;
+}
+
+#[account]
+pub struct TokenFeeBalance {
+    pub mint: Pubkey,                // 32 bytes
+    pub accumulated_fees: u64,       // 8 bytes (in the mint's base units)
+    pub bump: u8,                    // 1 byte
+}
+
+impl TokenFeeBalance {
+    pub const LEN: usize = 32 + 8 + 1;
+}
+
+#[account]
+pub struct ClaimableBalance {
+    pub recipient: Pubkey,           // 32 bytes
+    pub balance: u64,                // 8 bytes (unclaimed lamports, gross of fee)
+    pub bump: u8,                    // 1 byte
+}
+
+impl ClaimableBalance {
+    pub const LEN: usize = 32 + 8 + 1;
 }
 
 #[event]
@@ -293,6 +1328,52 @@ pub struct PaymentSettled {
     pub amount: u64,
     pub fee: u64,
     pub payment_id: [u8; 32],
+    pub memo: String,
+}
+
+#[event]
+pub struct TokenPaymentSettled {
+    pub payer: Pubkey,
+    pub recipient: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
+    pub payment_id: [u8; 32],
+    pub memo: String,
+}
+
+#[event]
+pub struct PaymentDeposited {
+    pub payer: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub payment_id: [u8; 32],
+    pub unclaimed: u64,
+}
+
+#[event]
+pub struct PaymentClaimed {
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
+}
+
+#[event]
+pub struct PaymentRefunded {
+    pub payment_id: [u8; 32],
+    pub payer: Pubkey,
+    pub refunder: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
+}
+
+#[event]
+pub struct SplitPaymentSettled {
+    pub payer: Pubkey,
+    pub recipients: Vec<Pubkey>,
+    pub amounts: Vec<u64>,
+    pub fee: u64,
+    pub payment_id: [u8; 32],
 }
 
 #[event]
@@ -303,12 +1384,61 @@ pub struct BatchPaymentsSettled {
     pub total_fee: u64,
 }
 
+#[event]
+pub struct SubscriptionCreated {
+    pub payer: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub interval_secs: i64,
+    pub next_due: i64,
+}
+
+#[event]
+pub struct SubscriptionExecuted {
+    pub payer: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
+    pub next_due: i64,
+}
+
+#[event]
+pub struct SubscriptionCanceled {
+    pub payer: Pubkey,
+    pub recipient: Pubkey,
+    pub refunded: u64,
+}
+
+#[event]
+pub struct TreasuryUpdated {
+    pub old_treasury: Pubkey,
+    pub new_treasury: Pubkey,
+}
+
+#[event]
+pub struct MultisigUpdated {
+    pub signers: Vec<Pubkey>,
+    pub threshold: u8,
+}
+
+#[event]
+pub struct FeeScheduleUpdated {
+    pub fee_tiers: Vec<FeeTier>,
+}
+
 #[event]
 pub struct FeesWithdrawn {
     pub to: Pubkey,
     pub amount: u64,
 }
 
+#[event]
+pub struct TokenFeesWithdrawn {
+    pub mint: Pubkey,
+    pub to: Pubkey,
+    pub amount: u64,
+}
+
 #[event]
 pub struct PlatformFeeUpdated {
     pub old_fee: u16,
@@ -333,5 +1463,53 @@ pub enum PaymentFacilitatorError {
     Unauthorized,
     #[msg("No fees to withdraw")]
     NoFees,
+    #[msg("Invalid memo")]
+    InvalidMemo,
+    #[msg("Invalid interval")]
+    InvalidInterval,
+    #[msg("Subscription not due")]
+    SubscriptionNotDue,
+    #[msg("Insufficient subscription balance")]
+    InsufficientSubscriptionBalance,
+    #[msg("Invalid recipient")]
+    InvalidRecipient,
+    #[msg("Shares must sum to 10000")]
+    InvalidShares,
+    #[msg("Invalid payer")]
+    InvalidPayer,
+    #[msg("Payment already refunded")]
+    AlreadyRefunded,
+    #[msg("Refund window closed")]
+    RefundWindowClosed,
+    #[msg("Invalid grace period")]
+    InvalidGracePeriod,
+    #[msg("Invalid fee schedule")]
+    InvalidFeeSchedule,
+    #[msg("Invalid treasury")]
+    InvalidTreasury,
+    #[msg("Insufficient funds")]
+    InsufficientFunds,
+    #[msg("Nothing to claim")]
+    NothingToClaim,
+    #[msg("Recipient cannot be the payer")]
+    RecipientIsPayer,
+    #[msg("Recipient cannot be the facilitator")]
+    RecipientIsFacilitator,
+    #[msg("Batch vectors differ in length")]
+    LengthMismatch,
+    #[msg("Batch is empty")]
+    EmptyBatch,
+    #[msg("Batch is too large")]
+    BatchTooLarge,
+    #[msg("Batch contains a duplicate payment ID")]
+    DuplicateBatchId,
+    #[msg("Invalid multisig signers or threshold")]
+    InvalidMultisig,
+    #[msg("Not enough multisig signers approved")]
+    MultisigThresholdNotMet,
+    #[msg("Payment authorization has expired")]
+    PaymentExpired,
+    #[msg("Payment was settled in SPL tokens")]
+    TokenPayment,
 }
 
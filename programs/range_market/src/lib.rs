@@ -2,6 +2,10 @@ use anchor_lang::prelude::*;
 
 declare_id!("rangeMarkM3mP9vK8JqF2nH5xY7wD4bC6eA8g");
 
+pub const EMERGENCY_CANCEL_GRACE_PERIOD: i64 = 30 * 24 * 60 * 60; // 30 days in seconds
+pub const MAX_FEE_BPS: u16 = 2_000; // 20% cap on the market fee
+pub const MAX_QUESTION_LEN: usize = 1_000; // Ceiling for max_question_len
+
 #[program]
 pub mod range_market {
     use super::*;
@@ -10,12 +14,24 @@ pub mod range_market {
         ctx: Context<Initialize>,
         oracle_program: Pubkey,
         fee_percentage: u16,
+        min_bet: u64,
+        max_question_len: u16, // Longest question accepted, at most MAX_QUESTION_LEN
     ) -> Result<()> {
+        require!(fee_percentage <= MAX_FEE_BPS, MarketError::FeeTooHigh);
+        require!(
+            max_question_len > 0 && max_question_len as usize <= MAX_QUESTION_LEN,
+            MarketError::InvalidMaxQuestionLen
+        );
+
         let market_state = &mut ctx.accounts.market_state;
         market_state.authority = ctx.accounts.authority.key();
         market_state.oracle_program = oracle_program;
         market_state.market_counter = 0;
         market_state.fee_percentage = fee_percentage;
+        market_state.accumulated_fees = 0;
+        market_state.min_bet = min_bet;
+        market_state.max_question_len = max_question_len;
+        market_state.treasury = ctx.accounts.authority.key();
         market_state.bump = ctx.bumps.market_state;
         Ok(())
     }
@@ -26,11 +42,25 @@ pub mod range_market {
         lower_bound: u64,
         upper_bound: u64,
         deadline: i64,
+        min_confidence: u8, // Minimum oracle confidence (1-100) required to resolve
+        oracle_question_id: u64, // trace9 question that resolves this market
+        question_type: QuestionType, // Oracle question type, must yield a numeric answer
+        shared: bool, // Let other markets bind the same oracle question
+        resolver: Option<Pubkey>, // Only this key may resolve, None = anyone
     ) -> Result<u64> {
         require!(
-            question.len() > 0 && question.len() <= 500,
+            question.len() > 0
+                && question.len() <= ctx.accounts.market_state.max_question_len as usize,
             MarketError::InvalidQuestion
         );
+        require!(
+            question_type == QuestionType::Numeric || question_type == QuestionType::Price,
+            MarketError::IncompatibleQuestionType
+        );
+        require!(
+            min_confidence > 0 && min_confidence <= 100,
+            MarketError::InvalidMinConfidence
+        );
         require!(upper_bound > lower_bound, MarketError::InvalidRange);
         require!(
             deadline > Clock::get()?.unix_timestamp,
@@ -52,6 +82,29 @@ pub mod range_market {
         market_account.deadline = deadline;
         market_account.resolved = false;
         market_account.in_range = false;
+        market_account.min_confidence = min_confidence;
+        market_account.oracle_question_id = oracle_question_id;
+        market_account.canceled = false;
+        market_account.question_type = question_type;
+        market_account.resolved_value = 0;
+        market_account.resolver = resolver;
+
+        // Bind the oracle question; an exclusive binding admits no further markets
+        let question_binding = &mut ctx.accounts.question_binding;
+        if question_binding.market_count == 0 {
+            question_binding.question_id = oracle_question_id;
+            question_binding.shared = shared;
+            question_binding.bump = ctx.bumps.question_binding;
+        } else {
+            require!(
+                question_binding.shared && shared,
+                MarketError::QuestionAlreadyBound
+            );
+        }
+        question_binding.market_count = question_binding
+            .market_count
+            .checked_add(1)
+            .ok_or(MarketError::Overflow)?;
 
         market_state.market_counter = market_id.checked_add(1).unwrap();
 
@@ -61,6 +114,10 @@ pub mod range_market {
             lower_bound,
             upper_bound,
             deadline,
+            min_confidence,
+            oracle_question_id,
+            question_type,
+            resolver,
         });
 
         Ok(market_id)
@@ -80,6 +137,10 @@ pub mod range_market {
 
         let bet_amount = ctx.accounts.bettor.lamports();
         require!(bet_amount > 0, MarketError::ZeroBet);
+        require!(
+            bet_amount >= ctx.accounts.market_state.min_bet,
+            MarketError::BetTooSmall
+        );
 
         let market_state = &ctx.accounts.market_state;
         let fee = bet_amount
@@ -133,79 +194,301 @@ pub mod range_market {
 
     pub fn resolve_market(ctx: Context<ResolveMarket>, market_id: u64) -> Result<()> {
         let market_account = &mut ctx.accounts.market_account;
-        require!(!market_account.resolved, MarketError::AlreadyResolved);
+        market_account.require_unsettled()?;
+        require!(
+            market_account.resolver.map_or(true, |resolver| resolver == ctx.accounts.resolver.key()),
+            MarketError::NotResolver
+        );
         require!(
             Clock::get()?.unix_timestamp >= market_account.deadline,
             MarketError::TooEarly
         );
 
-        let numeric_answer = ctx.accounts.oracle_answer.numeric_answer;
+        let oracle_program = ctx.accounts.market_state.oracle_program;
+        let question: QuestionAccount = load_oracle_account(&ctx.accounts.oracle_question, &oracle_program)?;
+        require!(
+            question.question_id == market_account.oracle_question_id,
+            MarketError::OracleQuestionMismatch
+        );
+        // The question must be settled on the oracle side, not still open or under dispute
+        require!(
+            matches!(question.status, AnswerStatus::Answered | AnswerStatus::Finalized),
+            MarketError::OracleQuestionNotFinal
+        );
+        let answer: AnswerAccount = load_oracle_account(&ctx.accounts.oracle_answer, &oracle_program)?;
+        require!(
+            answer.question_id == market_account.oracle_question_id,
+            MarketError::OracleQuestionMismatch
+        );
+        require!(
+            question.question_type == market_account.question_type,
+            MarketError::QuestionTypeMismatch
+        );
+
+        // An inconclusive answer carries no usable value: cancel so every position can be refunded
+        if answer.inconclusive {
+            market_account.canceled = true;
+
+            emit!(MarketCanceled { market_id });
+
+            return Ok(());
+        }
+
+        let numeric_answer = answer.numeric_answer;
+        let confidence_score = answer.confidence_score;
         require!(numeric_answer > 0, MarketError::OracleNotAnswered);
+        require!(
+            confidence_score >= market_account.min_confidence,
+            MarketError::ConfidenceTooLow
+        );
 
         let value_in_range = numeric_answer >= market_account.lower_bound
             && numeric_answer <= market_account.upper_bound;
 
+        // Cache the answer so claims never need the oracle accounts again
+        market_account.resolved_value = numeric_answer;
         market_account.in_range = value_in_range;
         market_account.resolved = true;
         market_account.resolved_at = Clock::get()?.unix_timestamp;
 
+        // Sweep the entry fees out of the pool so they become withdrawable; a zero-fee market has nothing to move
+        let fees = market_account.total_fees;
+        if fees > 0 {
+            let market_state = &mut ctx.accounts.market_state;
+            market_state.accumulated_fees = market_state
+                .accumulated_fees
+                .checked_add(fees)
+                .ok_or(MarketError::Overflow)?;
+
+            **ctx.accounts.market_account.to_account_info().try_borrow_mut_lamports()? -= fees;
+            **ctx.accounts.market_state.to_account_info().try_borrow_mut_lamports()? += fees;
+        }
+
         emit!(MarketResolved {
             market_id,
             final_value: numeric_answer,
             in_range: value_in_range,
+            bool_answer: answer.bool_answer,
+            confidence_score,
         });
 
         Ok(())
     }
 
-    pub fn claim_winnings(ctx: Context<ClaimWinnings>, market_id: u64) -> Result<()> {
+    pub fn claim_winnings(ctx: Context<ClaimWinnings>, market_id: u64) -> Result<u64> {
         let market_account = &ctx.accounts.market_account;
+        require!(market_account.market_id == market_id, MarketError::MarketMismatch);
         require!(market_account.resolved, MarketError::NotResolved);
 
         let position = &mut ctx.accounts.position;
         require!(!position.claimed, MarketError::AlreadyClaimed);
 
-        let winning_pool = if market_account.in_range {
-            market_account.in_range_pool
-        } else {
-            market_account.out_range_pool
-        };
-        let losing_pool = if market_account.in_range {
-            market_account.out_range_pool
-        } else {
-            market_account.in_range_pool
-        };
-        let user_winning_amount = if market_account.in_range {
-            position.in_range_amount
+        let payout = market_account.winnings_for(position)?;
+        let payout = capped_payout(&market_account.to_account_info(), payout)?;
+        let cost_basis = market_account.winning_stake(position);
+
+        position.claimed = true;
+
+        **ctx.accounts.market_account.to_account_info().try_borrow_mut_lamports()? -= payout;
+        **ctx.accounts.winner.to_account_info().try_borrow_mut_lamports()? += payout;
+
+        emit!(WinningsClaimed {
+            market_id,
+            user: ctx.accounts.winner.key(),
+            payout,
+        });
+
+        emit!(ClaimRecord {
+            market_id,
+            user: ctx.accounts.winner.key(),
+            cost_basis,
+            payout,
+        });
+
+        Ok(payout)
+    }
+
+    pub fn quote_payout(ctx: Context<QuotePayout>, market_id: u64) -> Result<u64> {
+        let market_account = &ctx.accounts.market_account;
+        require!(market_account.market_id == market_id, MarketError::MarketMismatch);
+        require!(market_account.resolved, MarketError::NotResolved);
+        require!(!ctx.accounts.position.claimed, MarketError::AlreadyClaimed);
+
+        market_account.winnings_for(&ctx.accounts.position)
+    }
+
+    pub fn cancel_market(ctx: Context<CancelMarket>, market_id: u64) -> Result<()> {
+        let market_account = &mut ctx.accounts.market_account;
+        market_account.require_unsettled()?;
+        let oracle_program = ctx.accounts.market_state.oracle_program;
+        let question: QuestionAccount = load_oracle_account(&ctx.accounts.oracle_question, &oracle_program)?;
+        require!(
+            question.question_id == market_account.oracle_question_id,
+            MarketError::OracleQuestionMismatch
+        );
+
+        // Unanswered markets wait out the refund period; low-confidence answers cancel right away
+        if matches!(question.status, AnswerStatus::Pending | AnswerStatus::Expired) {
+            let refund_period: i64 = 7 * 24 * 60 * 60; // 7 days
+            require!(
+                Clock::get()?.unix_timestamp >= market_account.deadline + refund_period,
+                MarketError::TooEarlyToCancel
+            );
         } else {
-            position.out_range_amount
-        };
+            let answer: AnswerAccount = load_oracle_account(&ctx.accounts.oracle_answer, &oracle_program)?;
+            require!(
+                answer.confidence_score < market_account.min_confidence,
+                MarketError::AlreadyAnswered
+            );
+            require!(
+                Clock::get()?.unix_timestamp >= market_account.deadline,
+                MarketError::TooEarly
+            );
+        }
 
-        require!(user_winning_amount > 0, MarketError::NotWinner);
-        require!(winning_pool > 0, MarketError::NoWinnings);
+        market_account.canceled = true;
 
-        let payout = user_winning_amount
-            .checked_add(
-                user_winning_amount
-                    .checked_mul(losing_pool)
-                    .and_then(|x| x.checked_div(winning_pool))
+        emit!(MarketCanceled { market_id });
+
+        Ok(())
+    }
+
+    pub fn emergency_cancel(ctx: Context<EmergencyCancel>, market_id: u64) -> Result<()> {
+        let market_account = &mut ctx.accounts.market_account;
+        market_account.require_unsettled()?;
+        require!(
+            Clock::get()?.unix_timestamp
+                >= market_account.deadline + EMERGENCY_CANCEL_GRACE_PERIOD,
+            MarketError::TooEarlyToCancel
+        );
+
+        market_account.canceled = true;
+
+        emit!(MarketEmergencyCanceled { market_id });
+
+        Ok(())
+    }
+
+    pub fn claim_refund(ctx: Context<ClaimRefund>, market_id: u64) -> Result<u64> {
+        require!(ctx.accounts.market_account.canceled, MarketError::NotCanceled);
+
+        let position = &mut ctx.accounts.position;
+        require!(!position.claimed, MarketError::AlreadyClaimed);
+
+        let stake = position
+            .in_range_amount
+            .checked_add(position.out_range_amount)
+            .ok_or(MarketError::Overflow)?;
+        require!(stake > 0, MarketError::NoPosition);
+
+        // Fees are never collected on a canceled market, so refund the gross stake pro rata;
+        // widen before multiplying so large pools cannot overflow ahead of the division
+        let market_account = &ctx.accounts.market_account;
+        let total_pool = (market_account.in_range_pool as u128)
+            .checked_add(market_account.out_range_pool as u128)
+            .ok_or(MarketError::Overflow)?;
+        let refund_amount = (stake as u128)
+            .checked_mul(
+                total_pool
+                    .checked_add(market_account.total_fees as u128)
                     .ok_or(MarketError::Overflow)?,
             )
+            .and_then(|x| x.checked_div(total_pool))
+            .and_then(|x| u64::try_from(x).ok())
             .ok_or(MarketError::Overflow)?;
 
+        // Mark the position claimed before any lamports leave the market
         position.claimed = true;
 
-        **ctx.accounts.market_account.to_account_info().try_borrow_mut_lamports()? -= payout;
-        **ctx.accounts.winner.to_account_info().try_borrow_mut_lamports()? += payout;
+        **ctx.accounts.market_account.to_account_info().try_borrow_mut_lamports()? -= refund_amount;
+        **ctx.accounts.refundee.to_account_info().try_borrow_mut_lamports()? += refund_amount;
 
-        emit!(WinningsClaimed {
+        emit!(RefundClaimed {
             market_id,
-            user: ctx.accounts.winner.key(),
-            payout,
+            user: ctx.accounts.refundee.key(),
+            amount: refund_amount,
+        });
+
+        Ok(refund_amount)
+    }
+
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>) -> Result<()> {
+        let market_state = &mut ctx.accounts.market_state;
+        let amount = market_state.accumulated_fees;
+        require!(amount > 0, MarketError::NoFees);
+
+        market_state.accumulated_fees = 0;
+
+        **ctx.accounts.market_state.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.treasury.to_account_info().try_borrow_mut_lamports()? += amount;
+
+        Ok(())
+    }
+
+    pub fn set_min_bet(ctx: Context<SetMinBet>, min_bet: u64) -> Result<()> {
+        let old_min_bet = ctx.accounts.market_state.min_bet;
+        ctx.accounts.market_state.min_bet = min_bet;
+
+        emit!(MinBetUpdated {
+            old_min_bet,
+            new_min_bet: min_bet,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_fee_percentage(ctx: Context<SetFeePercentage>, fee_percentage: u16) -> Result<()> {
+        require!(fee_percentage <= MAX_FEE_BPS, MarketError::FeeTooHigh);
+
+        let old_fee_percentage = ctx.accounts.market_state.fee_percentage;
+        ctx.accounts.market_state.fee_percentage = fee_percentage;
+
+        emit!(FeePercentageUpdated {
+            old_fee_percentage,
+            new_fee_percentage: fee_percentage,
         });
 
         Ok(())
     }
+
+    pub fn set_treasury(ctx: Context<SetTreasury>, treasury: Pubkey) -> Result<()> {
+        let old_treasury = ctx.accounts.market_state.treasury;
+        ctx.accounts.market_state.treasury = treasury;
+
+        emit!(TreasuryUpdated {
+            old_treasury,
+            new_treasury: treasury,
+        });
+
+        Ok(())
+    }
+}
+
+/// Cap a payout at the lamports the market holds above rent exemption
+fn capped_payout(market_info: &AccountInfo, payout: u64) -> Result<u64> {
+    let rent_exempt = Rent::get()?.minimum_balance(market_info.data_len());
+    let available = market_info.lamports().saturating_sub(rent_exempt);
+    if payout > available {
+        msg!(
+            "Payout short by {} lamports, paying the remaining {}",
+            payout - available,
+            available
+        );
+        return Ok(available);
+    }
+    Ok(payout)
+}
+
+/// Read an oracle account as `T` once it is known to be owned by the oracle program and hold data,
+/// so a default or uninitialized account cannot pass; the discriminator check keeps a question
+/// account from standing in for an answer
+fn load_oracle_account<T: AccountDeserialize>(account: &AccountInfo, oracle_program: &Pubkey) -> Result<T> {
+    require!(
+        account.owner == oracle_program && !account.data_is_empty(),
+        MarketError::OracleAccountInvalid
+    );
+    let data = account.try_borrow_data()?;
+    T::try_deserialize(&mut &data[..]).map_err(|_| error!(MarketError::OracleAccountInvalid))
 }
 
 #[derive(Accounts)]
@@ -224,17 +507,33 @@ pub struct Initialize<'info> {
 }
 
 #[derive(Accounts)]
+#[instruction(
+    question: String,
+    lower_bound: u64,
+    upper_bound: u64,
+    deadline: i64,
+    min_confidence: u8,
+    oracle_question_id: u64
+)]
 pub struct CreateMarket<'info> {
     #[account(
         init,
         payer = creator,
-        space = 8 + MarketAccount::LEN,
+        space = 8 + MarketAccount::size(market_state.max_question_len),
         seeds = [b"market", market_state.market_counter.to_le_bytes().as_ref()],
         bump
     )]
     pub market_account: Account<'info, MarketAccount>,
     #[account(mut, seeds = [b"market_state"], bump = market_state.bump)]
     pub market_state: Account<'info, MarketState>,
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = 8 + QuestionBinding::LEN,
+        seeds = [b"question_binding", oracle_question_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub question_binding: Account<'info, QuestionBinding>,
     #[account(mut)]
     pub creator: Signer<'info>,
     pub system_program: Program<'info, System>,
@@ -265,8 +564,18 @@ pub struct TakePosition<'info> {
 pub struct ResolveMarket<'info> {
     #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
     pub market_account: Account<'info, MarketAccount>,
-    /// CHECK: Oracle answer from trace9 program
+    #[account(mut, seeds = [b"market_state"], bump = market_state.bump)]
+    pub market_state: Account<'info, MarketState>,
+    /// CHECK: Oracle question from trace9 program; owner and discriminator are checked on resolution
+    pub oracle_question: AccountInfo<'info>,
+    /// CHECK: The market's answer PDA under the oracle program; contents are checked on resolution
+    #[account(
+        seeds = [b"answer", market_account.oracle_question_id.to_le_bytes().as_ref()],
+        bump,
+        seeds::program = market_state.oracle_program
+    )]
     pub oracle_answer: AccountInfo<'info>,
+    pub resolver: Signer<'info>,
 }
 
 #[derive(Accounts)]
@@ -284,17 +593,137 @@ pub struct ClaimWinnings<'info> {
     pub winner: Signer<'info>,
 }
 
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct QuotePayout<'info> {
+    #[account(seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        seeds = [b"position", market_id.to_le_bytes().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct CancelMarket<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(seeds = [b"market_state"], bump = market_state.bump)]
+    pub market_state: Account<'info, MarketState>,
+    /// CHECK: The market's question PDA under the oracle program; contents are checked on cancel
+    #[account(
+        seeds = [b"question", market_account.oracle_question_id.to_le_bytes().as_ref()],
+        bump,
+        seeds::program = market_state.oracle_program
+    )]
+    pub oracle_question: AccountInfo<'info>,
+    /// CHECK: The market's answer PDA under the oracle program; read only once the question is answered
+    #[account(
+        seeds = [b"answer", market_account.oracle_question_id.to_le_bytes().as_ref()],
+        bump,
+        seeds::program = market_state.oracle_program
+    )]
+    pub oracle_answer: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct EmergencyCancel<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct ClaimRefund<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        mut,
+        seeds = [b"position", market_id.to_le_bytes().as_ref(), refundee.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+    #[account(mut)]
+    pub refundee: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    #[account(
+        mut,
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
+    /// CHECK: Verified against the treasury stored on market state
+    #[account(mut, address = market_state.treasury @ MarketError::InvalidTreasury)]
+    pub treasury: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMinBet<'info> {
+    #[account(
+        mut,
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetTreasury<'info> {
+    #[account(
+        mut,
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetFeePercentage<'info> {
+    #[account(
+        mut,
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
+}
+
 #[account]
 pub struct MarketState {
     pub authority: Pubkey,
     pub oracle_program: Pubkey,
     pub market_counter: u64,
     pub fee_percentage: u16,
+    pub accumulated_fees: u64,
+    pub min_bet: u64,
+    pub max_question_len: u16,
+    pub treasury: Pubkey,
     pub bump: u8,
 }
 
 impl MarketState {
-    pub const LEN: usize = 32 + 32 + 8 + 2 + 1;
+    pub const LEN: usize = 32 + 32 + 8 + 2 + 8 + 8 + 2 + 32 + 1;
 }
 
 #[account]
@@ -311,10 +740,65 @@ pub struct MarketAccount {
     pub resolved_at: i64,
     pub resolved: bool,
     pub in_range: bool,
+    pub min_confidence: u8,
+    pub canceled: bool,
+    pub question_type: QuestionType,
+    pub oracle_question_id: u64,
+    pub resolved_value: u64,
+    pub resolver: Option<Pubkey>,
 }
 
 impl MarketAccount {
-    pub const LEN: usize = 8 + (4 + 500) + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 1;
+    /// Account size for a market on a deployment capping questions at `max_question_len` bytes
+    pub fn size(max_question_len: u16) -> usize {
+        8 + (4 + max_question_len as usize) + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 1 + 1 + 1 + 1 + 8 + 8 + (1 + 32)
+    }
+
+    /// Net stake a position has on the winning side, the cost basis of its claim
+    pub fn winning_stake(&self, position: &Position) -> u64 {
+        if self.in_range {
+            position.in_range_amount
+        } else {
+            position.out_range_amount
+        }
+    }
+
+    /// Payout owed to a position on this resolved market
+    pub fn winnings_for(&self, position: &Position) -> Result<u64> {
+        let winning_pool = if self.in_range {
+            self.in_range_pool
+        } else {
+            self.out_range_pool
+        };
+        let losing_pool = if self.in_range {
+            self.out_range_pool
+        } else {
+            self.in_range_pool
+        };
+        let user_winning_amount = self.winning_stake(position);
+
+        require!(winning_pool > 0, MarketError::EmptyWinningPool);
+        require!(user_winning_amount > 0, MarketError::BetOnLosingSide);
+
+        // Widen before multiplying so large pools cannot overflow ahead of the division
+        let payout = user_winning_amount
+            .checked_add(
+                (user_winning_amount as u128)
+                    .checked_mul(losing_pool as u128)
+                    .and_then(|x| x.checked_div(winning_pool as u128))
+                    .and_then(|x| u64::try_from(x).ok())
+                    .ok_or(MarketError::Overflow)?,
+            )
+            .ok_or(MarketError::Overflow)?;
+        Ok(payout)
+    }
+
+    /// Resolution and cancellation are terminal and mutually exclusive: a market settles once
+    pub fn require_unsettled(&self) -> Result<()> {
+        require!(!self.resolved, MarketError::AlreadyResolved);
+        require!(!self.canceled, MarketError::MarketCanceled);
+        Ok(())
+    }
 }
 
 #[account]
@@ -328,6 +812,73 @@ impl Position {
     pub const LEN: usize = 8 + 8 + 1;
 }
 
+// Markets bound to a trace9 question
+#[account]
+pub struct QuestionBinding {
+    pub question_id: u64,
+    pub market_count: u32,
+    pub shared: bool,
+    pub bump: u8,
+}
+
+impl QuestionBinding {
+    pub const LEN: usize = 8 + 4 + 1 + 1;
+}
+
+// Oracle question structure (matches trace9 program); the name must match too, as it sets the discriminator
+#[account]
+pub struct QuestionAccount {
+    pub question_id: u64,
+    pub requester: Pubkey,
+    pub question_type: QuestionType,
+    pub question_hash: [u8; 32],
+    pub category: String,
+    pub bounty: u64,
+    pub timestamp: i64,
+    pub deadline: i64,
+    pub status: AnswerStatus,
+    pub refunded: bool,
+    pub revealed: bool,
+    pub incentive_opt_in: bool,
+    pub last_accrual_at: i64,
+    pub assigned_provider: Option<Pubkey>,
+}
+
+// Oracle answer structure (matches trace9 program)
+#[account]
+pub struct AnswerAccount {
+    pub question_id: u64,
+    pub provider: Pubkey,
+    pub confidence_score: u8,
+    pub bool_answer: bool,
+    pub numeric_answer: u64,
+    pub timestamp: i64,
+    pub challenge_deadline: i64,
+    pub revision: u8,
+    pub bond_locked: u64,
+    pub disputer: Pubkey,
+    pub inconclusive: bool,
+}
+
+// Oracle question type (matches trace9 program)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum QuestionType {
+    General,
+    Price,
+    YesNo,
+    Numeric,
+}
+
+// Oracle question status (matches trace9 program)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum AnswerStatus {
+    Pending,
+    Answered,
+    Disputed,
+    Finalized,
+    Expired,
+}
+
 #[event]
 pub struct MarketCreated {
     pub market_id: u64,
@@ -335,6 +886,10 @@ pub struct MarketCreated {
     pub lower_bound: u64,
     pub upper_bound: u64,
     pub deadline: i64,
+    pub min_confidence: u8,
+    pub oracle_question_id: u64,
+    pub question_type: QuestionType,
+    pub resolver: Option<Pubkey>,
 }
 
 #[event]
@@ -348,8 +903,27 @@ pub struct PositionTaken {
 #[event]
 pub struct MarketResolved {
     pub market_id: u64,
-    pub final_value: u64,
+    pub final_value: u64, // Oracle numeric answer
     pub in_range: bool,
+    pub bool_answer: bool,
+    pub confidence_score: u8,
+}
+
+#[event]
+pub struct MarketCanceled {
+    pub market_id: u64,
+}
+
+#[event]
+pub struct MarketEmergencyCanceled {
+    pub market_id: u64,
+}
+
+#[event]
+pub struct RefundClaimed {
+    pub market_id: u64,
+    pub user: Pubkey,
+    pub amount: u64,
 }
 
 #[event]
@@ -359,6 +933,32 @@ pub struct WinningsClaimed {
     pub payout: u64,
 }
 
+#[event]
+pub struct ClaimRecord {
+    pub market_id: u64,
+    pub user: Pubkey,
+    pub cost_basis: u64, // Net stake on the paying side
+    pub payout: u64,
+}
+
+#[event]
+pub struct MinBetUpdated {
+    pub old_min_bet: u64,
+    pub new_min_bet: u64,
+}
+
+#[event]
+pub struct TreasuryUpdated {
+    pub old_treasury: Pubkey,
+    pub new_treasury: Pubkey,
+}
+
+#[event]
+pub struct FeePercentageUpdated {
+    pub old_fee_percentage: u16,
+    pub new_fee_percentage: u16,
+}
+
 #[error_code]
 pub enum MarketError {
     #[msg("Invalid question")]
@@ -373,8 +973,12 @@ pub enum MarketError {
     MarketClosed,
     #[msg("Zero bet")]
     ZeroBet,
+    #[msg("Bet below minimum")]
+    BetTooSmall,
     #[msg("Overflow")]
     Overflow,
+    #[msg("Unauthorized")]
+    Unauthorized,
     #[msg("Too early")]
     TooEarly,
     #[msg("Oracle not answered")]
@@ -383,9 +987,47 @@ pub enum MarketError {
     NotResolved,
     #[msg("Already claimed")]
     AlreadyClaimed,
-    #[msg("Not winner")]
-    NotWinner,
-    #[msg("No winnings")]
-    NoWinnings,
+    #[msg("Bet on the losing side")]
+    BetOnLosingSide,
+    #[msg("Nobody bet on the winning side")]
+    EmptyWinningPool,
+    #[msg("Invalid minimum confidence")]
+    InvalidMinConfidence,
+    #[msg("Oracle confidence below market minimum")]
+    ConfidenceTooLow,
+    #[msg("Market canceled")]
+    MarketCanceled,
+    #[msg("Too early to cancel")]
+    TooEarlyToCancel,
+    #[msg("Already answered")]
+    AlreadyAnswered,
+    #[msg("Not canceled")]
+    NotCanceled,
+    #[msg("No position")]
+    NoPosition,
+    #[msg("Question type incompatible with market")]
+    IncompatibleQuestionType,
+    #[msg("Oracle question type does not match market")]
+    QuestionTypeMismatch,
+    #[msg("Market account does not match market id")]
+    MarketMismatch,
+    #[msg("Oracle answer is for a different question")]
+    OracleQuestionMismatch,
+    #[msg("Oracle question already bound to a market")]
+    QuestionAlreadyBound,
+    #[msg("Fee exceeds maximum")]
+    FeeTooHigh,
+    #[msg("No fees")]
+    NoFees,
+    #[msg("Max question length must be between 1 and MAX_QUESTION_LEN")]
+    InvalidMaxQuestionLen,
+    #[msg("Signer is not the market's resolver")]
+    NotResolver,
+    #[msg("Oracle account is not an initialized account of the oracle program")]
+    OracleAccountInvalid,
+    #[msg("Oracle question is not answered yet, or its answer is disputed")]
+    OracleQuestionNotFinal,
+    #[msg("Invalid treasury")]
+    InvalidTreasury,
 }
 
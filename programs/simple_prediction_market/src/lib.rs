@@ -1,8 +1,23 @@
 use anchor_lang::prelude::*;
 use anchor_lang::system_program;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::metadata::Metadata;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+
+pub mod lmsr;
+pub mod receipt;
+pub mod stake;
 
 declare_id!("simpPredM3mP9vK8JqF2nH5xY7wD4bC6eA8g");
 
+pub const MAX_BATCH_CLAIMS: usize = 10;
+pub const MAX_FEE_BPS: u16 = 2_000; // 20% cap on the market fee
+pub const MAX_PARLAY_BPS: u16 = 5_000; // 50% cap on the losing pool routed to a parlay market
+pub const CLAIM_PERIOD: i64 = 180 * 24 * 60 * 60; // 180 days in seconds
+pub const PARLAY_RELEASE_WINDOW: i64 = 7 * 24 * 60 * 60; // After claims open, how long only the authority may settle a parlay share
+pub const VOID_ANSWER: u64 = u64::MAX; // Oracle numeric answer marking a binary question void
+pub const MAX_QUESTION_LEN: usize = 1_000; // Ceiling for max_question_len
+
 #[program]
 pub mod simple_prediction_market {
     use super::*;
@@ -12,13 +27,32 @@ pub mod simple_prediction_market {
         ctx: Context<Initialize>,
         oracle_program: Pubkey,
         fee_percentage: u16, // Basis points (e.g., 200 = 2%)
+        min_bet: u64,        // Minimum bet in lamports
+        resolution_dispute_period: i64, // Seconds after resolution before claims open
+        stake_mint: Pubkey, // SPL mint for stakes and payouts, default pubkey = native SOL
+        max_question_len: u16, // Longest question accepted, at most MAX_QUESTION_LEN
     ) -> Result<()> {
+        require!(fee_percentage <= MAX_FEE_BPS, MarketError::FeeTooHigh);
+        require!(resolution_dispute_period >= 0, MarketError::InvalidDisputePeriod);
+        require!(
+            max_question_len > 0 && max_question_len as usize <= MAX_QUESTION_LEN,
+            MarketError::InvalidMaxQuestionLen
+        );
+
         let market_state = &mut ctx.accounts.market_state;
         market_state.authority = ctx.accounts.authority.key();
         market_state.oracle_program = oracle_program;
         market_state.market_counter = 0;
         market_state.fee_percentage = fee_percentage;
         market_state.accumulated_fees = 0;
+        market_state.min_bet = min_bet;
+        market_state.resolution_dispute_period = resolution_dispute_period;
+        market_state.treasury = ctx.accounts.authority.key();
+        market_state.stake_mint = stake_mint;
+        market_state.referral_bps = 0;
+        market_state.creation_deposit = 0;
+        market_state.max_question_len = max_question_len;
+        market_state.position_receipts = false;
         market_state.bump = ctx.bumps.market_state;
         Ok(())
     }
@@ -28,15 +62,63 @@ pub mod simple_prediction_market {
         ctx: Context<CreateMarket>,
         question: String,
         resolution_time: i64,
+        close_time: i64, // Betting cutoff, 0 = resolution time
+        pricing_mode: PricingMode,
+        liquidity_b: u64, // LMSR liquidity parameter, ignored for parimutuel markets
+        min_confidence: u8, // Minimum oracle confidence (1-100) required to resolve
+        oracle_question_id: u64, // trace9 question that resolves this market
+        question_type: QuestionType, // Oracle question type, must yield a bool answer
+        fee_mode: FeeMode,
+        fee_start_bps: u16, // Dynamic mode: fee at market creation, ignored for flat fees
+        fee_end_bps: u16,   // Dynamic mode: fee at close time, ignored for flat fees
+        shared: bool, // Let other markets bind the same oracle question
+        payout_mode: PayoutMode,
+        min_participants: u32, // Distinct bettors needed to resolve, otherwise the market cancels
+        max_total_pool: u64, // Cap on yes_pool + no_pool, 0 = unlimited
+        max_answer_age_secs: i64, // Oldest answer, before resolution time, that may resolve, 0 = no limit
+        min_pool_per_side: u64, // Parimutuel: liquidity each side needs at resolution, otherwise the market cancels
+        single_side_only: bool, // Reject bets against a bettor's existing position
+        opens_at: i64, // Betting opens at this time, leaving a review window; 0 = at creation
     ) -> Result<u64> {
         require!(
-            question.len() > 0 && question.len() <= 500,
+            question.len() > 0
+                && question.len() <= ctx.accounts.market_state.max_question_len as usize,
             MarketError::InvalidQuestion
         );
+        require!(
+            question_type == QuestionType::YesNo || question_type == QuestionType::General,
+            MarketError::IncompatibleQuestionType
+        );
+        require!(
+            min_confidence > 0 && min_confidence <= 100,
+            MarketError::InvalidMinConfidence
+        );
         require!(
             resolution_time > Clock::get()?.unix_timestamp,
             MarketError::InvalidResolutionTime
         );
+        let close_time = if close_time == 0 { resolution_time } else { close_time };
+        require!(
+            close_time > Clock::get()?.unix_timestamp && close_time <= resolution_time,
+            MarketError::InvalidCloseTime
+        );
+        require!(
+            payout_mode == PayoutMode::Flat || pricing_mode == PricingMode::Parimutuel,
+            MarketError::InvalidPayoutMode
+        );
+        require!(
+            pricing_mode == PricingMode::Parimutuel || !ctx.accounts.market_state.token_mode(),
+            MarketError::TokenModeUnsupported
+        );
+        if fee_mode == FeeMode::Dynamic {
+            require!(
+                fee_start_bps <= MAX_FEE_BPS && fee_end_bps <= MAX_FEE_BPS,
+                MarketError::FeeTooHigh
+            );
+        }
+        require!(max_answer_age_secs >= 0, MarketError::InvalidAnswerAge);
+        let opens_at = if opens_at == 0 { Clock::get()?.unix_timestamp } else { opens_at };
+        require!(opens_at < close_time, MarketError::InvalidOpenTime);
 
         let market_state = &mut ctx.accounts.market_state;
         let market_id = market_state.market_counter;
@@ -46,6 +128,7 @@ pub mod simple_prediction_market {
         market_account.market_id = market_id;
         market_account.question = question.clone();
         market_account.resolution_time = resolution_time;
+        market_account.close_time = close_time;
         market_account.yes_pool = 0;
         market_account.no_pool = 0;
         market_account.status = MarketStatus::Open;
@@ -53,6 +136,91 @@ pub mod simple_prediction_market {
         market_account.total_fees = 0;
         market_account.created_at = Clock::get()?.unix_timestamp;
         market_account.creator = ctx.accounts.creator.key();
+        market_account.pricing_mode = pricing_mode;
+        market_account.liquidity_b = 0;
+        market_account.yes_shares = 0;
+        market_account.no_shares = 0;
+        market_account.claimable_at = 0;
+        market_account.disputed = false;
+        market_account.min_confidence = min_confidence;
+        market_account.oracle_question_id = oracle_question_id;
+        market_account.claim_deadline = 0;
+        market_account.question_type = question_type;
+        market_account.fee_mode = fee_mode;
+        market_account.fee_start_bps = fee_start_bps;
+        market_account.fee_end_bps = fee_end_bps;
+        market_account.payout_mode = payout_mode;
+        market_account.yes_weighted = 0;
+        market_account.no_weighted = 0;
+        market_account.participant_count = 0;
+        market_account.min_participants = min_participants;
+        market_account.max_total_pool = max_total_pool;
+        market_account.max_answer_age_secs = max_answer_age_secs;
+        market_account.min_pool_per_side = min_pool_per_side;
+        market_account.single_side_only = single_side_only;
+        market_account.resolved_value = 0;
+        market_account.resolved_bool = false;
+        market_account.parlay_bps = 0;
+        market_account.parlay_recipient = Pubkey::default();
+        market_account.parlay_pool = 0;
+        market_account.parlay_released = false;
+        market_account.opens_at = opens_at;
+        market_account.creation_deposit = market_state.creation_deposit;
+        market_account.finalized = false;
+
+        // LMSR markets are funded by the creator with the market maker's maximum loss
+        if pricing_mode == PricingMode::Lmsr {
+            require!(liquidity_b > 0, MarketError::InvalidLiquidity);
+            market_account.liquidity_b = liquidity_b;
+
+            let subsidy = lmsr::subsidy(liquidity_b).ok_or(MarketError::Overflow)?;
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.creator.to_account_info(),
+                        to: ctx.accounts.market_account.to_account_info(),
+                    },
+                ),
+                subsidy,
+            )?;
+        }
+
+        // Lock the anti-spam deposit in the market until it resolves
+        let creation_deposit = ctx.accounts.market_account.creation_deposit;
+        if creation_deposit > 0 {
+            require!(
+                ctx.accounts.creator.lamports() >= creation_deposit,
+                MarketError::InsufficientDeposit
+            );
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.creator.to_account_info(),
+                        to: ctx.accounts.market_account.to_account_info(),
+                    },
+                ),
+                creation_deposit,
+            )?;
+        }
+
+        // Bind the oracle question; an exclusive binding admits no further markets
+        let question_binding = &mut ctx.accounts.question_binding;
+        if question_binding.market_count == 0 {
+            question_binding.question_id = oracle_question_id;
+            question_binding.shared = shared;
+            question_binding.bump = ctx.bumps.question_binding;
+        } else {
+            require!(
+                question_binding.shared && shared,
+                MarketError::QuestionAlreadyBound
+            );
+        }
+        question_binding.market_count = question_binding
+            .market_count
+            .checked_add(1)
+            .ok_or(MarketError::Overflow)?;
 
         // Increment market counter
         market_state.market_counter = market_id.checked_add(1).unwrap();
@@ -61,179 +229,528 @@ pub mod simple_prediction_market {
             market_id,
             question,
             resolution_time,
+            close_time,
             creator: ctx.accounts.creator.key(),
+            pricing_mode,
+            liquidity_b,
+            min_confidence,
+            oracle_question_id,
+            question_type,
+            fee_mode,
+            fee_start_bps,
+            fee_end_bps,
+            payout_mode,
+            min_participants,
+            max_total_pool,
+            max_answer_age_secs,
+            min_pool_per_side,
+            single_side_only,
+            opens_at,
+            creation_deposit,
         });
 
         Ok(market_id)
     }
 
-    /// Take a position on a market (YES or NO)
+    /// Take a position of `amount` on a market (YES or NO), in lamports or stake mint units
     pub fn take_position(
         ctx: Context<TakePosition>,
         market_id: u64,
         is_yes: bool,
+        amount: u64,
+        referrer: Option<Pubkey>, // Earns a referral_bps cut of the entry fee
     ) -> Result<()> {
-        let market_account = &mut ctx.accounts.market_account;
-        require!(
-            market_account.status == MarketStatus::Open,
-            MarketError::MarketNotOpen
-        );
-        require!(
-            Clock::get()?.unix_timestamp < market_account.resolution_time,
-            MarketError::MarketExpired
-        );
+        let balance = if ctx.accounts.market_state.token_mode() {
+            ctx.accounts
+                .bettor_token_account
+                .as_ref()
+                .ok_or(MarketError::MissingTokenAccounts)?
+                .amount
+        } else {
+            ctx.accounts.bettor.lamports()
+        };
+        require!(amount <= balance, MarketError::InsufficientBalance);
 
-        let market_state = &ctx.accounts.market_state;
-        let fee_percentage = market_state.fee_percentage;
-        let bet_amount = ctx.accounts.bettor.lamports();
+        let fee = record_position(
+            &mut ctx.accounts.market_account,
+            &mut ctx.accounts.position,
+            &ctx.accounts.market_state,
+            market_id,
+            ctx.accounts.bettor.key(),
+            is_yes,
+            amount,
+        )?;
+
+        // The referral cut comes out of the protocol fee and is paid once the market resolves
+        if let Some(referrer) = referrer {
+            require!(referrer != ctx.accounts.bettor.key(), MarketError::InvalidReferrer);
+            let cut = (fee as u128)
+                .checked_mul(ctx.accounts.market_state.referral_bps as u128)
+                .and_then(|x| x.checked_div(10000))
+                .and_then(|x| u64::try_from(x).ok())
+                .ok_or(MarketError::Overflow)?;
 
-        require!(bet_amount > 0, MarketError::ZeroBet);
+            let market_account = &mut ctx.accounts.market_account;
+            market_account.total_fees = market_account
+                .total_fees
+                .checked_sub(cut)
+                .ok_or(MarketError::Overflow)?;
 
-        // Calculate fee (in basis points)
-        let fee = bet_amount
-            .checked_mul(fee_percentage as u64)
-            .and_then(|x| x.checked_div(10000))
-            .ok_or(MarketError::Overflow)?;
-        let net_amount = bet_amount.checked_sub(fee).ok_or(MarketError::Overflow)?;
+            let referral_balance = ctx
+                .accounts
+                .referral_balance
+                .as_mut()
+                .ok_or(MarketError::MissingReferralAccount)?;
+            referral_balance.market_id = market_id;
+            referral_balance.referrer = referrer;
+            referral_balance.balance = referral_balance
+                .balance
+                .checked_add(cut)
+                .ok_or(MarketError::Overflow)?;
 
-        // Update market pools
-        market_account.total_fees = market_account
-            .total_fees
-            .checked_add(fee)
-            .ok_or(MarketError::Overflow)?;
+            emit!(ReferralCredited {
+                market_id,
+                referrer,
+                bettor: ctx.accounts.bettor.key(),
+                amount: cut,
+            });
+        }
 
-        if is_yes {
-            market_account.yes_pool = market_account
-                .yes_pool
-                .checked_add(net_amount)
-                .ok_or(MarketError::Overflow)?;
+        // Transfer the stake from bettor to market account, or into the vault in token mode
+        if ctx.accounts.market_state.token_mode() {
+            stake::deposit(
+                &ctx.accounts.token_program,
+                &ctx.accounts.bettor_token_account,
+                &ctx.accounts.stake_vault,
+                &ctx.accounts.bettor,
+                amount,
+            )?;
         } else {
-            market_account.no_pool = market_account
-                .no_pool
-                .checked_add(net_amount)
-                .ok_or(MarketError::Overflow)?;
+            **ctx.accounts.bettor.to_account_info().try_borrow_mut_lamports()? -= amount;
+            **ctx.accounts.market_account.to_account_info().try_borrow_mut_lamports()? += amount;
         }
 
-        // Update position
-        let position = &mut ctx.accounts.position;
-        if is_yes {
-            position.yes_amount = position
-                .yes_amount
-                .checked_add(net_amount)
-                .ok_or(MarketError::Overflow)?;
+        // With receipts enabled, the position's first bet mints its NFT receipt
+        if ctx.accounts.market_state.position_receipts
+            && ctx.accounts.position.receipt_mint == Pubkey::default()
+        {
+            receipt::mint(&ctx.accounts, market_id)?;
+            let receipt_mint = ctx
+                .accounts
+                .receipt_mint
+                .as_ref()
+                .ok_or(MarketError::MissingReceiptAccounts)?
+                .key();
+            ctx.accounts.position.receipt_mint = receipt_mint;
+
+            emit!(PositionReceiptMinted {
+                market_id,
+                user: ctx.accounts.bettor.key(),
+                mint: receipt_mint,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Let `spender` bet up to `allowance` on the owner's behalf, replacing any previous allowance
+    /// Native mode escrows the allowance in the allowance PDA; token mode approves it as SPL delegate
+    pub fn approve_spender(ctx: Context<ApproveSpender>, spender: Pubkey, allowance: u64) -> Result<()> {
+        let spender_allowance = &mut ctx.accounts.spender_allowance;
+        let previous = spender_allowance.remaining;
+        spender_allowance.owner = ctx.accounts.owner.key();
+        spender_allowance.spender = spender;
+        spender_allowance.remaining = allowance;
+        spender_allowance.bump = ctx.bumps.spender_allowance;
+
+        if ctx.accounts.market_state.token_mode() {
+            stake::approve(
+                &ctx.accounts.token_program,
+                &ctx.accounts.owner_token_account,
+                &ctx.accounts.spender_allowance,
+                &ctx.accounts.owner,
+                allowance,
+            )?;
+        } else if allowance > previous {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.owner.to_account_info(),
+                        to: ctx.accounts.spender_allowance.to_account_info(),
+                    },
+                ),
+                allowance - previous,
+            )?;
         } else {
-            position.no_amount = position
-                .no_amount
-                .checked_add(net_amount)
-                .ok_or(MarketError::Overflow)?;
+            let released = previous - allowance;
+            **ctx.accounts.spender_allowance.to_account_info().try_borrow_mut_lamports()? -= released;
+            **ctx.accounts.owner.to_account_info().try_borrow_mut_lamports()? += released;
         }
 
-        // Transfer SOL from bettor to market account
-        **ctx.accounts.bettor.to_account_info().try_borrow_mut_lamports()? -= bet_amount;
-        **ctx.accounts.market_account.to_account_info().try_borrow_mut_lamports()? += bet_amount;
+        emit!(SpenderApproved {
+            owner: ctx.accounts.owner.key(),
+            spender,
+            allowance,
+        });
+
+        Ok(())
+    }
 
-        emit!(PositionTaken {
+    /// Bet `amount` out of an owner's allowance, crediting the owner's position (approved spender only)
+    pub fn take_position_delegated(
+        ctx: Context<TakePositionDelegated>,
+        market_id: u64,
+        is_yes: bool,
+        amount: u64,
+    ) -> Result<()> {
+        let spender_allowance = &mut ctx.accounts.spender_allowance;
+        require!(amount <= spender_allowance.remaining, MarketError::AllowanceExceeded);
+        spender_allowance.remaining -= amount;
+
+        record_position(
+            &mut ctx.accounts.market_account,
+            &mut ctx.accounts.position,
+            &ctx.accounts.market_state,
             market_id,
-            user: ctx.accounts.bettor.key(),
+            ctx.accounts.owner.key(),
             is_yes,
-            amount: net_amount,
+            amount,
+        )?;
+
+        // Move the stake out of the allowance escrow, or from the owner's tokens in token mode
+        if ctx.accounts.market_state.token_mode() {
+            stake::deposit_delegated(
+                &ctx.accounts.token_program,
+                &ctx.accounts.owner_token_account,
+                &ctx.accounts.stake_vault,
+                &ctx.accounts.spender_allowance,
+                amount,
+            )?;
+        } else {
+            **ctx.accounts.spender_allowance.to_account_info().try_borrow_mut_lamports()? -= amount;
+            **ctx.accounts.market_account.to_account_info().try_borrow_mut_lamports()? += amount;
+        }
+
+        Ok(())
+    }
+
+    /// Stop accepting bets ahead of resolution (creator or authority)
+    pub fn close_market(ctx: Context<CloseMarket>, market_id: u64) -> Result<()> {
+        let market_account = &mut ctx.accounts.market_account;
+        require!(
+            ctx.accounts.closer.key() == market_account.creator
+                || ctx.accounts.closer.key() == ctx.accounts.market_state.authority,
+            MarketError::Unauthorized
+        );
+        require!(
+            market_account.status.can_transition_to(MarketStatus::Closed),
+            MarketError::InvalidStatusTransition
+        );
+
+        market_account.status = MarketStatus::Closed;
+
+        emit!(MarketClosed { market_id });
+
+        Ok(())
+    }
+
+    /// Delete a market nobody has bet on yet, returning its rent to the creator (creator only)
+    /// The creation deposit goes to the treasury, so deleting is no way around it
+    pub fn delete_empty_market(ctx: Context<DeleteEmptyMarket>, market_id: u64) -> Result<()> {
+        let forfeited = delete_market(
+            &ctx.accounts.market_account,
+            &mut ctx.accounts.question_binding,
+            &ctx.accounts.treasury,
+        )?;
+
+        emit!(MarketDeleted {
+            market_id,
+            creator: ctx.accounts.creator.key(),
+            forfeited,
+        });
+
+        Ok(())
+    }
+
+    /// Delete an empty spam market, keeping its creation deposit for the treasury (authority only)
+    /// The rest of the market's lamports go back to the creator
+    pub fn delete_spam_market(ctx: Context<DeleteSpamMarket>, market_id: u64) -> Result<()> {
+        let forfeited = delete_market(
+            &ctx.accounts.market_account,
+            &mut ctx.accounts.question_binding,
+            &ctx.accounts.treasury,
+        )?;
+
+        emit!(SpamMarketDeleted {
+            market_id,
+            creator: ctx.accounts.creator.key(),
+            forfeited,
         });
 
         Ok(())
     }
 
+    /// Return the creation deposit once the market has resolved or been canceled (creator only)
+    pub fn reclaim_creation_deposit(ctx: Context<ReclaimCreationDeposit>, market_id: u64) -> Result<u64> {
+        let market_account = &mut ctx.accounts.market_account;
+        require!(
+            matches!(market_account.status, MarketStatus::Resolved | MarketStatus::Canceled),
+            MarketError::NotResolved
+        );
+
+        let amount = market_account.creation_deposit;
+        require!(amount > 0, MarketError::NoDeposit);
+
+        market_account.creation_deposit = 0;
+
+        **ctx.accounts.market_account.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.creator.to_account_info().try_borrow_mut_lamports()? += amount;
+
+        emit!(CreationDepositReturned {
+            market_id,
+            creator: ctx.accounts.creator.key(),
+            amount,
+        });
+
+        Ok(amount)
+    }
+
+    /// Read-only snapshot of market odds and pools
+    pub fn get_market_info(ctx: Context<GetMarketInfo>, market_id: u64) -> Result<MarketInfo> {
+        let market_account = &ctx.accounts.market_account;
+
+        let total_pool = market_account
+            .yes_pool
+            .checked_add(market_account.no_pool)
+            .ok_or(MarketError::Overflow)?;
+
+        // Implied probability of YES in basis points, priced the same way positions pay out
+        let yes_probability_bps = match market_account.pricing_mode {
+            PricingMode::Parimutuel => {
+                if total_pool == 0 {
+                    5000
+                } else {
+                    ((market_account.yes_pool as u128) * 10000 / (total_pool as u128)) as u16
+                }
+            }
+            PricingMode::Lmsr => lmsr::price_bps(
+                market_account.yes_shares,
+                market_account.no_shares,
+                market_account.liquidity_b,
+            )
+            .ok_or(MarketError::Overflow)? as u16,
+        };
+
+        let time_remaining = market_account
+            .resolution_time
+            .saturating_sub(Clock::get()?.unix_timestamp)
+            .max(0);
+
+        Ok(MarketInfo {
+            market_id,
+            status: market_account.status,
+            yes_probability_bps,
+            no_probability_bps: 10000 - yes_probability_bps,
+            total_pool,
+            total_fees: market_account.total_fees,
+            time_remaining,
+        })
+    }
+
     /// Resolve market using oracle answer
     pub fn resolve_market(ctx: Context<ResolveMarket>, market_id: u64) -> Result<()> {
+        let accounts = &mut *ctx.accounts;
+        resolve_from_oracle(
+            &mut accounts.market_account,
+            &mut accounts.market_state,
+            &accounts.oracle_question,
+            &accounts.oracle_answer,
+            market_id,
+        )
+    }
+
+    /// Resolve market using the oracle accounts at the PDAs derived from its `oracle_question_id`,
+    /// so a keeper passes only the market and cannot substitute another question's answer
+    pub fn resolve_market_from_pda(ctx: Context<ResolveMarketFromPda>, market_id: u64) -> Result<()> {
+        let accounts = &mut *ctx.accounts;
+        resolve_from_oracle(
+            &mut accounts.market_account,
+            &mut accounts.market_state,
+            &accounts.oracle_question,
+            &accounts.oracle_answer,
+            market_id,
+        )
+    }
+
+    /// Flag a resolution as disputed during the dispute window (position holders only)
+    pub fn dispute_resolution(ctx: Context<DisputeResolution>, market_id: u64) -> Result<()> {
         let market_account = &mut ctx.accounts.market_account;
         require!(
-            market_account.status == MarketStatus::Open,
-            MarketError::MarketNotOpen
+            market_account.status == MarketStatus::Resolved,
+            MarketError::NotResolved
         );
         require!(
-            Clock::get()?.unix_timestamp >= market_account.resolution_time,
-            MarketError::TooEarly
+            Clock::get()?.unix_timestamp < market_account.claimable_at,
+            MarketError::DisputeWindowClosed
         );
 
-        // Read oracle answer from the oracle program
-        // Note: In a real implementation, you'd use CPI to call the oracle program
-        // For now, we'll assume the answer is passed via context or stored
-        // This is a simplified version - you'd need to fetch from oracle program
-        
-        // For now, we'll require the oracle answer to be provided
-        // In production, this would be fetched via CPI from trace9 program
-        let bool_answer = ctx.accounts.oracle_answer.bool_answer;
+        let position = &ctx.accounts.position;
         require!(
-            ctx.accounts.oracle_answer.confidence_score > 0,
-            MarketError::OracleNotAnswered
+            position.yes_amount > 0 || position.no_amount > 0,
+            MarketError::NoPosition
         );
 
-        market_account.outcome = if bool_answer {
-            Outcome::Yes
-        } else {
-            Outcome::No
+        market_account.disputed = true;
+
+        emit!(ResolutionDisputed {
+            market_id,
+            disputer: ctx.accounts.disputer.key(),
+            outcome: market_account.outcome,
+        });
+
+        Ok(())
+    }
+
+    /// Correct a resolved outcome during the dispute window (authority only)
+    pub fn override_resolution(
+        ctx: Context<OverrideResolution>,
+        market_id: u64,
+        new_outcome: Outcome,
+    ) -> Result<()> {
+        let market_account = &mut ctx.accounts.market_account;
+        require!(
+            market_account.status == MarketStatus::Resolved,
+            MarketError::NotResolved
+        );
+        require!(
+            Clock::get()?.unix_timestamp < market_account.claimable_at,
+            MarketError::DisputeWindowClosed
+        );
+        require!(new_outcome != Outcome::Unresolved, MarketError::InvalidOutcome);
+
+        let old_outcome = market_account.outcome;
+        market_account.outcome = new_outcome;
+        market_account.disputed = false;
+
+        // Rewrite the cached answer too, so readers of the cache see the overridden outcome
+        market_account.resolved_bool = new_outcome == Outcome::Yes;
+        market_account.resolved_value = match new_outcome {
+            Outcome::Void => VOID_ANSWER,
+            Outcome::Yes => 1,
+            _ => 0,
         };
-        market_account.status = MarketStatus::Resolved;
+        market_account.parlay_pool = market_account.parlay_share()?;
+
+        emit!(ResolutionOverridden {
+            market_id,
+            old_outcome,
+            new_outcome,
+        });
+
+        Ok(())
+    }
+
+    /// Settle a resolution once its dispute window has passed, moving the market's fees to
+    /// accumulated fees (anyone, e.g. keepers)
+    pub fn finalize_market(ctx: Context<FinalizeMarket>, market_id: u64) -> Result<()> {
+        let market_account = &mut ctx.accounts.market_account;
+        require!(
+            market_account.status == MarketStatus::Resolved,
+            MarketError::NotResolved
+        );
+        require!(
+            Clock::get()?.unix_timestamp >= market_account.claimable_at,
+            MarketError::DisputeWindowOpen
+        );
+        require!(!market_account.finalized, MarketError::MarketAlreadyFinalized);
+
+        market_account.finalized = true;
 
-        // Move fees to accumulated fees
+        let fees = market_account.total_fees;
         let market_state = &mut ctx.accounts.market_state;
         market_state.accumulated_fees = market_state
             .accumulated_fees
-            .checked_add(market_account.total_fees)
+            .checked_add(fees)
             .ok_or(MarketError::Overflow)?;
 
-        emit!(MarketResolved {
+        emit!(MarketFinalized {
             market_id,
             outcome: market_account.outcome,
+            fees,
         });
 
+        // Native fees move to market state, which funds withdraw_fees and keeps them out of sweeps
+        if !ctx.accounts.market_state.token_mode() && fees > 0 {
+            **ctx.accounts.market_account.to_account_info().try_borrow_mut_lamports()? -= fees;
+            **ctx.accounts.market_state.to_account_info().try_borrow_mut_lamports()? += fees;
+        }
+
         Ok(())
     }
 
     /// Claim winnings from a resolved market
-    pub fn claim_winnings(ctx: Context<ClaimWinnings>, market_id: u64) -> Result<()> {
+    pub fn claim_winnings(ctx: Context<ClaimWinnings>, market_id: u64) -> Result<u64> {
         let market_account = &ctx.accounts.market_account;
+        require!(market_account.market_id == market_id, MarketError::MarketMismatch);
         require!(
             market_account.status == MarketStatus::Resolved,
             MarketError::NotResolved
         );
+        require!(
+            Clock::get()?.unix_timestamp >= market_account.claimable_at,
+            MarketError::ClaimTooEarly
+        );
+        require!(
+            Clock::get()?.unix_timestamp < market_account.claim_deadline,
+            MarketError::ClaimDeadlinePassed
+        );
+
+        // Winners split a different pool depending on where the parlay share goes
+        require!(!market_account.parlay_pending(), MarketError::ParlayPending);
 
         let position = &mut ctx.accounts.position;
         require!(!position.claimed, MarketError::AlreadyClaimed);
 
-        let total_pool = market_account
-            .yes_pool
-            .checked_add(market_account.no_pool)
-            .ok_or(MarketError::Overflow)?;
-
-        let winnings = if market_account.outcome == Outcome::Yes && position.yes_amount > 0 {
-            if market_account.yes_pool == 0 {
-                return Err(MarketError::NoWinnings.into());
-            }
-            position
-                .yes_amount
-                .checked_mul(total_pool)
-                .and_then(|x| x.checked_div(market_account.yes_pool))
-                .ok_or(MarketError::Overflow)?
-        } else if market_account.outcome == Outcome::No && position.no_amount > 0 {
-            if market_account.no_pool == 0 {
-                return Err(MarketError::NoWinnings.into());
-            }
-            position
-                .no_amount
-                .checked_mul(total_pool)
-                .and_then(|x| x.checked_div(market_account.no_pool))
-                .ok_or(MarketError::Overflow)?
+        // The token vault is shared across markets, so only native payouts can be checked per market
+        let winnings = market_account.winnings_for(position)?;
+        let winnings = if ctx.accounts.market_state.token_mode() {
+            winnings
         } else {
-            return Err(MarketError::NoWinnings.into());
+            capped_payout(&market_account.to_account_info(), winnings, market_account.reserved_lamports()?)?
         };
-
-        require!(winnings > 0, MarketError::NoWinnings);
+        let cost_basis = market_account.winning_stake(position)?;
+
+        // A receipted position pays whoever burns its receipt; any other pays only its bettor
+        if position.receipt_mint != Pubkey::default() {
+            receipt::burn(
+                &ctx.accounts.token_program,
+                &ctx.accounts.receipt_mint,
+                &ctx.accounts.winner_receipt_account,
+                &ctx.accounts.winner,
+            )?;
+        } else {
+            require!(
+                ctx.accounts.winner.key() == ctx.accounts.position_owner.key(),
+                MarketError::Unauthorized
+            );
+        }
 
         position.claimed = true;
 
         // Transfer winnings
-        **ctx.accounts.market_account.to_account_info().try_borrow_mut_lamports()? -= winnings;
-        **ctx.accounts.winner.to_account_info().try_borrow_mut_lamports()? += winnings;
+        if ctx.accounts.market_state.token_mode() {
+            stake::withdraw(
+                &ctx.accounts.market_state,
+                &ctx.accounts.token_program,
+                &ctx.accounts.stake_vault,
+                &ctx.accounts.winner_token_account,
+                winnings,
+            )?;
+        } else {
+            **ctx.accounts.market_account.to_account_info().try_borrow_mut_lamports()? -= winnings;
+            **ctx.accounts.winner.to_account_info().try_borrow_mut_lamports()? += winnings;
+        }
 
         emit!(WinningsClaimed {
             market_id,
@@ -241,93 +758,1050 @@ pub mod simple_prediction_market {
             amount: winnings,
         });
 
-        Ok(())
+        emit!(ClaimRecord {
+            market_id,
+            user: ctx.accounts.winner.key(),
+            cost_basis,
+            payout: winnings,
+        });
+
+        Ok(winnings)
     }
 
-    /// Cancel market if oracle hasn't answered (after 7 days)
-    pub fn cancel_market(ctx: Context<CancelMarket>, market_id: u64) -> Result<()> {
-        let market_account = &mut ctx.accounts.market_account;
+    /// Pay a referrer their cut of a resolved market's entry fees (referrer only)
+    pub fn claim_referral_fees(ctx: Context<ClaimReferralFees>, market_id: u64) -> Result<u64> {
+        let market_account = &ctx.accounts.market_account;
         require!(
-            market_account.status == MarketStatus::Open,
-            MarketError::MarketNotOpen
+            market_account.status == MarketStatus::Resolved,
+            MarketError::NotResolved
         );
-
-        let refund_period: i64 = 7 * 24 * 60 * 60; // 7 days
         require!(
-            Clock::get()?.unix_timestamp
-                >= market_account.resolution_time + refund_period,
-            MarketError::TooEarlyToCancel
+            Clock::get()?.unix_timestamp >= market_account.claimable_at,
+            MarketError::ClaimTooEarly
         );
-
-        // Check oracle hasn't answered (simplified - would check oracle program in production)
         require!(
-            ctx.accounts.oracle_answer.confidence_score == 0,
-            MarketError::AlreadyAnswered
+            Clock::get()?.unix_timestamp < market_account.claim_deadline,
+            MarketError::ClaimDeadlinePassed
         );
 
-        market_account.status = MarketStatus::Canceled;
+        let referral_balance = &mut ctx.accounts.referral_balance;
+        let amount = referral_balance.balance;
+        require!(amount > 0, MarketError::NoFees);
 
-        emit!(MarketCanceled { market_id });
+        referral_balance.balance = 0;
 
-        Ok(())
+        if ctx.accounts.market_state.token_mode() {
+            stake::withdraw(
+                &ctx.accounts.market_state,
+                &ctx.accounts.token_program,
+                &ctx.accounts.stake_vault,
+                &ctx.accounts.referrer_token_account,
+                amount,
+            )?;
+        } else {
+            **ctx.accounts.market_account.to_account_info().try_borrow_mut_lamports()? -= amount;
+            **ctx.accounts.referrer.to_account_info().try_borrow_mut_lamports()? += amount;
+        }
+
+        emit!(ReferralFeesClaimed {
+            market_id,
+            referrer: ctx.accounts.referrer.key(),
+            amount,
+        });
+
+        Ok(amount)
     }
 
-    /// Claim refund from canceled market
-    pub fn claim_refund(ctx: Context<ClaimRefund>, market_id: u64) -> Result<()> {
-        let market_account = &mut ctx.accounts.market_account;
+    /// Read-only quote of what claim_winnings would pay the signer's position right now
+    pub fn quote_payout(ctx: Context<QuotePayout>, market_id: u64) -> Result<u64> {
+        let market_account = &ctx.accounts.market_account;
+        require!(market_account.market_id == market_id, MarketError::MarketMismatch);
         require!(
-            market_account.status == MarketStatus::Canceled,
-            MarketError::NotCanceled
+            market_account.status == MarketStatus::Resolved,
+            MarketError::NotResolved
         );
+        require!(!ctx.accounts.position.claimed, MarketError::AlreadyClaimed);
 
-        let position = &mut ctx.accounts.position;
-        require!(!position.claimed, MarketError::AlreadyClaimed);
-
-        let refund_amount = position
-            .yes_amount
-            .checked_add(position.no_amount)
-            .ok_or(MarketError::Overflow)?;
-
-        require!(refund_amount > 0, MarketError::NoPosition);
-
-        position.claimed = true;
-
-        // Transfer refund
-        **ctx.accounts.market_account.to_account_info().try_borrow_mut_lamports()? -= refund_amount;
-        **ctx.accounts.refundee.to_account_info().try_borrow_mut_lamports()? += refund_amount;
-
-        Ok(())
+        market_account.winnings_for(&ctx.accounts.position)
     }
 
-    /// Withdraw accumulated fees (authority only)
-    pub fn withdraw_fees(ctx: Context<WithdrawFees>) -> Result<()> {
-        let market_state = &mut ctx.accounts.market_state;
+    /// Claim winnings from several resolved markets in one transaction
+    /// Each market's account and the winner's position are passed as remaining accounts,
+    /// in pairs and in the same order as `market_ids`; unclaimable positions are skipped
+    pub fn batch_claim_winnings<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BatchClaimWinnings<'info>>,
+        market_ids: Vec<u64>,
+    ) -> Result<u64> {
         require!(
-            ctx.accounts.authority.key() == market_state.authority,
-            MarketError::Unauthorized
+            market_ids.len() > 0 && market_ids.len() <= MAX_BATCH_CLAIMS,
+            MarketError::InvalidBatchSize
+        );
+        require!(
+            ctx.remaining_accounts.len() == market_ids.len() * 2,
+            MarketError::InvalidBatch
         );
 
-        let amount = market_state.accumulated_fees;
-        require!(amount > 0, MarketError::NoFees);
+        let winner = ctx.accounts.winner.key();
+        let token_mode = ctx.accounts.market_state.token_mode();
+        let now = Clock::get()?.unix_timestamp;
+        let mut claimed_market_ids = Vec::new();
+        let mut skipped_market_ids = Vec::new();
+        let mut total_payout = 0u64;
+
+        for (i, market_id) in market_ids.iter().enumerate() {
+            let market_info = &ctx.remaining_accounts[i * 2];
+            let position_info = &ctx.remaining_accounts[i * 2 + 1];
+
+            // Wrong accounts fail the whole batch; only unclaimable positions are skipped
+            let (market_key, _) = Pubkey::find_program_address(
+                &[b"market", market_id.to_le_bytes().as_ref()],
+                ctx.program_id,
+            );
+            let (position_key, _) = Pubkey::find_program_address(
+                &[b"position", market_id.to_le_bytes().as_ref(), winner.as_ref()],
+                ctx.program_id,
+            );
+            require!(market_info.key() == market_key, MarketError::MarketMismatch);
+            require!(position_info.key() == position_key, MarketError::InvalidBatch);
+
+            let market_account: Account<MarketAccount> = Account::try_from(market_info)?;
+            let mut position: Account<Position> = Account::try_from(position_info)?;
+
+            // Receipted positions must burn their receipt through claim_winnings
+            let winnings = if market_account.claim_open(now)
+                && !position.claimed
+                && position.receipt_mint == Pubkey::default()
+            {
+                market_account.winnings_for(&position).ok()
+            } else {
+                None
+            };
+            let Some(winnings) = winnings else {
+                skipped_market_ids.push(*market_id);
+                continue;
+            };
+            let winnings = if token_mode {
+                winnings
+            } else {
+                capped_payout(market_info, winnings, market_account.reserved_lamports()?)?
+            };
+
+            position.claimed = true;
+            position.exit(ctx.program_id)?;
+
+            emit!(ClaimRecord {
+                market_id: *market_id,
+                user: winner,
+                cost_basis: market_account.winning_stake(&position)?,
+                payout: winnings,
+            });
+
+            if !token_mode {
+                **market_info.try_borrow_mut_lamports()? -= winnings;
+                **ctx.accounts.winner.to_account_info().try_borrow_mut_lamports()? += winnings;
+            }
 
-        market_state.accumulated_fees = 0;
+            total_payout = total_payout
+                .checked_add(winnings)
+                .ok_or(MarketError::Overflow)?;
+            claimed_market_ids.push(*market_id);
+        }
 
-        // Transfer fees
-        **ctx.accounts.market_state.to_account_info().try_borrow_mut_lamports()? -= amount;
-        **ctx.accounts.authority.to_account_info().try_borrow_mut_lamports()? += amount;
+        // Token-mode winnings all come out of the vault in a single transfer
+        if token_mode && total_payout > 0 {
+            stake::withdraw(
+                &ctx.accounts.market_state,
+                &ctx.accounts.token_program,
+                &ctx.accounts.stake_vault,
+                &ctx.accounts.winner_token_account,
+                total_payout,
+            )?;
+        }
 
-        emit!(FeesWithdrawn {
-            amount,
-            authority: ctx.accounts.authority.key(),
+        emit!(BatchWinningsClaimed {
+            user: winner,
+            claimed_market_ids,
+            skipped_market_ids,
+            total_payout,
         });
 
-        Ok(())
+        Ok(total_payout)
     }
-}
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(
+    /// Read-only sum of what the signer could claim right now across several markets
+    /// Each market's account and the signer's position are passed as remaining accounts, in pairs;
+    /// unresolved, out-of-window, already-claimed and losing positions count as zero
+    pub fn total_claimable<'info>(
+        ctx: Context<'_, '_, 'info, 'info, TotalClaimable<'info>>,
+    ) -> Result<u64> {
+        let pairs = ctx.remaining_accounts.len() / 2;
+        require!(
+            pairs > 0 && pairs <= MAX_BATCH_CLAIMS,
+            MarketError::InvalidBatchSize
+        );
+        require!(
+            ctx.remaining_accounts.len() % 2 == 0,
+            MarketError::InvalidBatch
+        );
+
+        let owner = ctx.accounts.owner.key();
+        let now = Clock::get()?.unix_timestamp;
+        let mut total = 0u64;
+
+        for pair in ctx.remaining_accounts.chunks(2) {
+            let market_account: Account<MarketAccount> = Account::try_from(&pair[0])?;
+            let position: Account<Position> = Account::try_from(&pair[1])?;
+
+            // Wrong accounts fail the whole query, as in batch_claim_winnings
+            let market_id = market_account.market_id;
+            let (market_key, _) = Pubkey::find_program_address(
+                &[b"market", market_id.to_le_bytes().as_ref()],
+                ctx.program_id,
+            );
+            let (position_key, _) = Pubkey::find_program_address(
+                &[b"position", market_id.to_le_bytes().as_ref(), owner.as_ref()],
+                ctx.program_id,
+            );
+            require!(pair[0].key() == market_key, MarketError::MarketMismatch);
+            require!(pair[1].key() == position_key, MarketError::InvalidBatch);
+
+            if !market_account.claim_open(now) || position.claimed {
+                continue;
+            }
+            if let Ok(winnings) = market_account.winnings_for(&position) {
+                total = total.checked_add(winnings).ok_or(MarketError::Overflow)?;
+            }
+        }
+
+        Ok(total)
+    }
+
+    /// Cancel market if oracle hasn't answered (after 7 days)
+    pub fn cancel_market(ctx: Context<CancelMarket>, market_id: u64) -> Result<()> {
+        let market_account = &mut ctx.accounts.market_account;
+        require!(
+            market_account.status.can_transition_to(MarketStatus::Canceled),
+            MarketError::InvalidStatusTransition
+        );
+
+        let oracle_program = ctx.accounts.market_state.oracle_program;
+        let question: QuestionAccount = load_oracle_account(&ctx.accounts.oracle_question, &oracle_program)?;
+        require!(
+            question.question_id == market_account.oracle_question_id,
+            MarketError::OracleQuestionMismatch
+        );
+
+        // Unanswered markets wait out the refund period; low-confidence or stale answers cancel right away
+        if matches!(question.status, AnswerStatus::Pending | AnswerStatus::Expired) {
+            let refund_period: i64 = 7 * 24 * 60 * 60; // 7 days
+            require!(
+                Clock::get()?.unix_timestamp
+                    >= market_account.resolution_time + refund_period,
+                MarketError::TooEarlyToCancel
+            );
+        } else {
+            // Check oracle hasn't answered with enough confidence to resolve
+            let answer: AnswerAccount = load_oracle_account(&ctx.accounts.oracle_answer, &oracle_program)?;
+            require!(
+                answer.confidence_score < market_account.min_confidence
+                    || market_account.answer_is_stale(answer.timestamp),
+                MarketError::AlreadyAnswered
+            );
+            require!(
+                Clock::get()?.unix_timestamp >= market_account.resolution_time,
+                MarketError::TooEarly
+            );
+        }
+
+        // No fees are earned on a canceled market; claim_refund hands each entry fee back
+        market_account.status = MarketStatus::Canceled;
+        market_account.total_fees = 0;
+
+        emit!(MarketCanceled { market_id });
+
+        Ok(())
+    }
+
+    /// Claim refund from canceled market
+    pub fn claim_refund(ctx: Context<ClaimRefund>, market_id: u64) -> Result<u64> {
+        let market_account = &mut ctx.accounts.market_account;
+        require!(
+            market_account.status == MarketStatus::Canceled,
+            MarketError::NotCanceled
+        );
+
+        let position = &mut ctx.accounts.position;
+        require!(!position.claimed, MarketError::AlreadyClaimed);
+
+        // Refund the gross stake: the fee was charged for a market that never ran
+        let refund_amount = position
+            .yes_amount
+            .checked_add(position.no_amount)
+            .and_then(|x| x.checked_add(position.fees_paid))
+            .ok_or(MarketError::Overflow)?;
+
+        require!(refund_amount > 0, MarketError::NoPosition);
+
+        // Like a claim, a receipted refund goes to whoever burns the receipt
+        if position.receipt_mint != Pubkey::default() {
+            receipt::burn(
+                &ctx.accounts.token_program,
+                &ctx.accounts.receipt_mint,
+                &ctx.accounts.refundee_receipt_account,
+                &ctx.accounts.refundee,
+            )?;
+        } else {
+            require!(
+                ctx.accounts.refundee.key() == ctx.accounts.position_owner.key(),
+                MarketError::Unauthorized
+            );
+        }
+
+        // Mark the position claimed before any lamports leave the market
+        position.claimed = true;
+
+        // Transfer refund
+        if ctx.accounts.market_state.token_mode() {
+            stake::withdraw(
+                &ctx.accounts.market_state,
+                &ctx.accounts.token_program,
+                &ctx.accounts.stake_vault,
+                &ctx.accounts.refundee_token_account,
+                refund_amount,
+            )?;
+        } else {
+            **ctx.accounts.market_account.to_account_info().try_borrow_mut_lamports()? -= refund_amount;
+            **ctx.accounts.refundee.to_account_info().try_borrow_mut_lamports()? += refund_amount;
+        }
+
+        Ok(refund_amount)
+    }
+
+    /// Refund several positions of a canceled market in one transaction (anyone, e.g. operators
+    /// winding a market down). Each position and its owner's refund destination are passed as
+    /// remaining accounts, in pairs: the owner's wallet, or their stake mint account in token mode.
+    /// Already-claimed and receipted positions are skipped rather than failing the batch; a
+    /// receipted position refunds its receipt holder through claim_refund
+    pub fn batch_refund<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BatchRefund<'info>>,
+        market_id: u64,
+    ) -> Result<u64> {
+        require!(
+            ctx.accounts.market_account.status == MarketStatus::Canceled,
+            MarketError::NotCanceled
+        );
+        let pairs = ctx.remaining_accounts.len() / 2;
+        require!(
+            pairs > 0 && pairs <= MAX_BATCH_CLAIMS,
+            MarketError::InvalidBatchSize
+        );
+        require!(
+            ctx.remaining_accounts.len() % 2 == 0,
+            MarketError::InvalidBatch
+        );
+
+        let token_mode = ctx.accounts.market_state.token_mode();
+        let mut refunded = Vec::new();
+        let mut skipped = Vec::new();
+        let mut total_refund = 0u64;
+
+        for pair in ctx.remaining_accounts.chunks(2) {
+            let (position_info, destination_info) = (&pair[0], &pair[1]);
+
+            // The position must be the destination owner's, so refunds only ever reach their bettor
+            let destination_token_account = if token_mode {
+                let token_account: Account<TokenAccount> = Account::try_from(destination_info)?;
+                require!(
+                    token_account.mint == ctx.accounts.market_state.stake_mint,
+                    MarketError::InvalidBatch
+                );
+                Some(token_account)
+            } else {
+                None
+            };
+            let owner = match &destination_token_account {
+                Some(token_account) => token_account.owner,
+                None => destination_info.key(),
+            };
+            let (position_key, _) = Pubkey::find_program_address(
+                &[b"position", market_id.to_le_bytes().as_ref(), owner.as_ref()],
+                ctx.program_id,
+            );
+            require!(position_info.key() == position_key, MarketError::InvalidBatch);
+
+            let mut position: Account<Position> = Account::try_from(position_info)?;
+            let refund_amount = position
+                .yes_amount
+                .checked_add(position.no_amount)
+                .and_then(|x| x.checked_add(position.fees_paid))
+                .ok_or(MarketError::Overflow)?;
+            if position.claimed || refund_amount == 0 || position.receipt_mint != Pubkey::default() {
+                skipped.push(owner);
+                continue;
+            }
+
+            position.claimed = true;
+            position.exit(ctx.program_id)?;
+
+            if token_mode {
+                stake::withdraw(
+                    &ctx.accounts.market_state,
+                    &ctx.accounts.token_program,
+                    &ctx.accounts.stake_vault,
+                    &destination_token_account,
+                    refund_amount,
+                )?;
+            } else {
+                **ctx.accounts.market_account.to_account_info().try_borrow_mut_lamports()? -= refund_amount;
+                **destination_info.try_borrow_mut_lamports()? += refund_amount;
+            }
+
+            total_refund = total_refund
+                .checked_add(refund_amount)
+                .ok_or(MarketError::Overflow)?;
+            refunded.push(owner);
+        }
+
+        emit!(BatchRefunded {
+            market_id,
+            refunded,
+            skipped,
+            total_refund,
+        });
+
+        Ok(total_refund)
+    }
+
+    /// Withdraw accumulated fees (authority only)
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>) -> Result<()> {
+        let market_state = &mut ctx.accounts.market_state;
+        let amount = market_state.accumulated_fees;
+        require!(amount > 0, MarketError::NoFees);
+
+        market_state.accumulated_fees = 0;
+
+        // Transfer fees to the treasury
+        if ctx.accounts.market_state.token_mode() {
+            stake::withdraw(
+                &ctx.accounts.market_state,
+                &ctx.accounts.token_program,
+                &ctx.accounts.stake_vault,
+                &ctx.accounts.treasury_token_account,
+                amount,
+            )?;
+        } else {
+            **ctx.accounts.market_state.to_account_info().try_borrow_mut_lamports()? -= amount;
+            **ctx.accounts.treasury.to_account_info().try_borrow_mut_lamports()? += amount;
+        }
+
+        emit!(FeesWithdrawn {
+            amount,
+            authority: ctx.accounts.authority.key(),
+            treasury: ctx.accounts.treasury.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Sweep leftover lamports of a resolved market to the treasury after the claim deadline (authority only)
+    pub fn sweep_unclaimed(ctx: Context<SweepUnclaimed>, market_id: u64) -> Result<()> {
+        require!(
+            !ctx.accounts.market_state.token_mode(),
+            MarketError::TokenModeUnsupported
+        );
+        let market_account = &ctx.accounts.market_account;
+        require!(
+            market_account.status == MarketStatus::Resolved,
+            MarketError::NotResolved
+        );
+        require!(
+            Clock::get()?.unix_timestamp >= market_account.claim_deadline,
+            MarketError::ClaimDeadlineNotPassed
+        );
+        // Fees only leave the market account once it is finalized
+        require!(market_account.finalized, MarketError::MarketNotFinalized);
+
+        let market_info = ctx.accounts.market_account.to_account_info();
+        let amount = payable_lamports(&market_info, market_account.reserved_lamports()?)?;
+        require!(amount > 0, MarketError::NothingToSweep);
+
+        **market_info.try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.treasury.to_account_info().try_borrow_mut_lamports()? += amount;
+
+        emit!(UnclaimedSwept { market_id, amount });
+
+        Ok(())
+    }
+
+    /// Donate lamports to a market account so it stays rent exempt (anyone, e.g. keepers);
+    /// donations above the rent floor are paid out like any other market lamports
+    pub fn top_up_market(ctx: Context<TopUpMarket>, market_id: u64, amount: u64) -> Result<()> {
+        require!(amount > 0, MarketError::InvalidTopUpAmount);
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.donor.to_account_info(),
+                    to: ctx.accounts.market_account.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        emit!(MarketToppedUp { market_id, amount });
+
+        Ok(())
+    }
+
+    /// Route a slice of the losing pool to a parlay market once this market resolves (authority only);
+    /// the share is fixed once the first bet lands
+    pub fn set_parlay_share(
+        ctx: Context<SetParlayShare>,
+        market_id: u64,
+        parlay_bps: u16,
+        parlay_recipient: Pubkey, // Conditional market account credited with the share
+    ) -> Result<()> {
+        require!(
+            parlay_bps <= MAX_PARLAY_BPS && (parlay_bps == 0 || parlay_recipient != Pubkey::default()),
+            MarketError::InvalidParlayShare
+        );
+        let market_account = &mut ctx.accounts.market_account;
+        require!(
+            market_account.status == MarketStatus::Open,
+            MarketError::MarketNotOpen
+        );
+        // Bettors priced their stakes against the share in place when they bet
+        require!(
+            market_account.yes_pool == 0 && market_account.no_pool == 0,
+            MarketError::ParlayShareLocked
+        );
+        require!(
+            market_account.pricing_mode == PricingMode::Parimutuel,
+            MarketError::InvalidParlayShare
+        );
+        require!(
+            !ctx.accounts.market_state.token_mode(),
+            MarketError::TokenModeUnsupported
+        );
+
+        market_account.parlay_bps = parlay_bps;
+        market_account.parlay_recipient = parlay_recipient;
+
+        emit!(ParlayShareSet {
+            market_id,
+            parlay_bps,
+            parlay_recipient,
+        });
+
+        Ok(())
+    }
+
+    /// Pay the parlay share to its recipient once the dispute window has passed (authority only)
+    pub fn release_parlay_share(ctx: Context<ReleaseParlayShare>, market_id: u64) -> Result<u64> {
+        let market_account = &mut ctx.accounts.market_account;
+        require!(
+            market_account.status == MarketStatus::Resolved,
+            MarketError::NotResolved
+        );
+        require!(
+            Clock::get()?.unix_timestamp >= market_account.claimable_at,
+            MarketError::ClaimTooEarly
+        );
+        require!(!market_account.parlay_released, MarketError::NoParlayShare);
+        let amount = market_account.parlay_pool;
+        require!(amount > 0, MarketError::NoParlayShare);
+
+        // The share stays excluded from winnings, so claims are unaffected
+        market_account.parlay_released = true;
+
+        **ctx.accounts.market_account.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.recipient.to_account_info().try_borrow_mut_lamports()? += amount;
+
+        emit!(ParlayShareReleased {
+            market_id,
+            recipient: ctx.accounts.recipient.key(),
+            amount,
+        });
+
+        Ok(amount)
+    }
+
+    /// Fold an unreleased parlay share back into the winners' pool, for a parlay market that can
+    /// no longer inherit it (authority once claims open, anyone after PARLAY_RELEASE_WINDOW)
+    pub fn return_parlay_share(ctx: Context<ReturnParlayShare>, market_id: u64) -> Result<u64> {
+        let market_account = &mut ctx.accounts.market_account;
+        require!(
+            market_account.status == MarketStatus::Resolved,
+            MarketError::NotResolved
+        );
+        let now = Clock::get()?.unix_timestamp;
+        require!(now >= market_account.claimable_at, MarketError::ClaimTooEarly);
+        require!(market_account.parlay_pending(), MarketError::NoParlayShare);
+        let release_deadline = market_account
+            .claimable_at
+            .checked_add(PARLAY_RELEASE_WINDOW)
+            .ok_or(MarketError::Overflow)?;
+        require!(
+            ctx.accounts.caller.key() == ctx.accounts.market_state.authority || now >= release_deadline,
+            MarketError::ParlayReleaseWindowOpen
+        );
+
+        // Winnings are split from the pool less parlay_pool, so zeroing it pays the share to winners
+        let amount = market_account.parlay_pool;
+        market_account.parlay_pool = 0;
+
+        emit!(ParlayShareReturned { market_id, amount });
+
+        Ok(amount)
+    }
+
+    /// Update the minimum bet (authority only)
+    pub fn set_min_bet(ctx: Context<SetMinBet>, min_bet: u64) -> Result<()> {
+        let old_min_bet = ctx.accounts.market_state.min_bet;
+        ctx.accounts.market_state.min_bet = min_bet;
+
+        emit!(MinBetUpdated {
+            old_min_bet,
+            new_min_bet: min_bet,
+        });
+
+        Ok(())
+    }
+
+    /// Update the market fee within MAX_FEE_BPS (authority only)
+    pub fn set_fee_percentage(ctx: Context<SetFeePercentage>, fee_percentage: u16) -> Result<()> {
+        require!(fee_percentage <= MAX_FEE_BPS, MarketError::FeeTooHigh);
+
+        let old_fee_percentage = ctx.accounts.market_state.fee_percentage;
+        ctx.accounts.market_state.fee_percentage = fee_percentage;
+
+        emit!(FeePercentageUpdated {
+            old_fee_percentage,
+            new_fee_percentage: fee_percentage,
+        });
+
+        Ok(())
+    }
+
+    /// Update the fee treasury (authority only)
+    pub fn set_treasury(ctx: Context<SetTreasury>, treasury: Pubkey) -> Result<()> {
+        let old_treasury = ctx.accounts.market_state.treasury;
+        ctx.accounts.market_state.treasury = treasury;
+
+        emit!(TreasuryUpdated {
+            old_treasury,
+            new_treasury: treasury,
+        });
+
+        Ok(())
+    }
+
+    /// Update the share of entry fees paid to referrers (authority only)
+    pub fn set_referral_bps(ctx: Context<SetReferralBps>, referral_bps: u16) -> Result<()> {
+        require!(referral_bps <= 10000, MarketError::InvalidFee);
+
+        let old_referral_bps = ctx.accounts.market_state.referral_bps;
+        ctx.accounts.market_state.referral_bps = referral_bps;
+
+        emit!(ReferralBpsUpdated {
+            old_referral_bps,
+            new_referral_bps: referral_bps,
+        });
+
+        Ok(())
+    }
+
+    /// Update the lamport deposit locked by new markets until they resolve (authority only)
+    pub fn set_creation_deposit(ctx: Context<SetCreationDeposit>, creation_deposit: u64) -> Result<()> {
+        let old_creation_deposit = ctx.accounts.market_state.creation_deposit;
+        ctx.accounts.market_state.creation_deposit = creation_deposit;
+
+        emit!(CreationDepositUpdated {
+            old_creation_deposit,
+            new_creation_deposit: creation_deposit,
+        });
+
+        Ok(())
+    }
+
+    /// Turn NFT receipts for new positions on or off (authority only)
+    pub fn set_position_receipts(ctx: Context<SetPositionReceipts>, enabled: bool) -> Result<()> {
+        let old_enabled = ctx.accounts.market_state.position_receipts;
+        ctx.accounts.market_state.position_receipts = enabled;
+
+        emit!(PositionReceiptsUpdated {
+            old_enabled,
+            new_enabled: enabled,
+        });
+
+        Ok(())
+    }
+}
+
+/// Lamports a market account can pay out while staying rent exempt and keeping `reserved` behind
+fn payable_lamports(market_info: &AccountInfo, reserved: u64) -> Result<u64> {
+    let rent_exempt = Rent::get()?.minimum_balance(market_info.data_len());
+    Ok(market_info
+        .lamports()
+        .saturating_sub(rent_exempt)
+        .saturating_sub(reserved))
+}
+
+/// Cap a native payout at what the market account can still pay; rounding drift across many
+/// claims must not leave the last claimant with a failed claim, so it gets the remainder instead
+fn capped_payout(market_info: &AccountInfo, payout: u64, reserved: u64) -> Result<u64> {
+    let available = payable_lamports(market_info, reserved)?;
+    if payout > available {
+        msg!(
+            "Payout short by {} lamports, paying the remaining {}",
+            payout - available,
+            available
+        );
+        return Ok(available);
+    }
+    Ok(payout)
+}
+
+/// Read an oracle account as `T` once it is known to be owned by the oracle program and hold data,
+/// so a default or uninitialized account cannot pass; the discriminator check keeps a question
+/// account from standing in for an answer
+fn load_oracle_account<T: AccountDeserialize>(account: &AccountInfo, oracle_program: &Pubkey) -> Result<T> {
+    require!(
+        account.owner == oracle_program && !account.data_is_empty(),
+        MarketError::OracleAccountInvalid
+    );
+    let data = account.try_borrow_data()?;
+    T::try_deserialize(&mut &data[..]).map_err(|_| error!(MarketError::OracleAccountInvalid))
+}
+
+/// Settle a market from its oracle question and answer accounts, shared by both resolve instructions
+fn resolve_from_oracle(
+    market_account: &mut Account<MarketAccount>,
+    market_state: &mut Account<MarketState>,
+    oracle_question: &AccountInfo,
+    oracle_answer: &AccountInfo,
+    market_id: u64,
+) -> Result<()> {
+    require!(
+        market_account.status.can_transition_to(MarketStatus::Resolved),
+        MarketError::InvalidStatusTransition
+    );
+    require!(
+        Clock::get()?.unix_timestamp >= market_account.resolution_time,
+        MarketError::TooEarly
+    );
+
+    // Too few distinct bettors or too thin a side to settle fairly: cancel so every position can be refunded
+    if market_account.participant_count < market_account.min_participants
+        || market_account.has_thin_side()
+    {
+        market_account.status = MarketStatus::Canceled;
+        market_account.total_fees = 0;
+
+        emit!(MarketCanceled { market_id });
+
+        return Ok(());
+    }
+
+    // Read the question and answer straight from the oracle program's accounts
+    let question: QuestionAccount = load_oracle_account(oracle_question, &market_state.oracle_program)?;
+    require!(
+        question.question_id == market_account.oracle_question_id,
+        MarketError::OracleQuestionMismatch
+    );
+    // The question must be settled on the oracle side, not still open or under dispute
+    require!(
+        matches!(question.status, AnswerStatus::Answered | AnswerStatus::Finalized),
+        MarketError::OracleQuestionNotFinal
+    );
+    let answer: AnswerAccount = load_oracle_account(oracle_answer, &market_state.oracle_program)?;
+    require!(
+        answer.question_id == market_account.oracle_question_id,
+        MarketError::OracleQuestionMismatch
+    );
+    require!(
+        question.question_type == market_account.question_type,
+        MarketError::QuestionTypeMismatch
+    );
+
+    // An inconclusive answer settles nothing: cancel so every position can be refunded
+    if answer.inconclusive {
+        market_account.status = MarketStatus::Canceled;
+        market_account.total_fees = 0;
+
+        emit!(MarketCanceled { market_id });
+
+        return Ok(());
+    }
+
+    let bool_answer = answer.bool_answer;
+    let confidence_score = answer.confidence_score;
+    require!(confidence_score > 0, MarketError::OracleNotAnswered);
+    require!(
+        confidence_score >= market_account.min_confidence,
+        MarketError::ConfidenceTooLow
+    );
+    require!(
+        !market_account.answer_is_stale(answer.timestamp),
+        MarketError::AnswerTooStale
+    );
+
+    // Cache the answer so claims and child markets never need the oracle accounts again
+    market_account.resolved_value = answer.numeric_answer;
+    market_account.resolved_bool = bool_answer;
+
+    // A void answer (postponed, tie) refunds every position instead of paying one side
+    market_account.outcome = if market_account.resolved_value == VOID_ANSWER {
+        Outcome::Void
+    } else if bool_answer {
+        Outcome::Yes
+    } else {
+        Outcome::No
+    };
+    market_account.status = MarketStatus::Resolved;
+    market_account.parlay_pool = market_account.parlay_share()?;
+
+    // Claims open once the dispute window has passed
+    market_account.claimable_at = Clock::get()?
+        .unix_timestamp
+        .checked_add(market_state.resolution_dispute_period)
+        .ok_or(MarketError::Overflow)?;
+    market_account.claim_deadline = market_account
+        .claimable_at
+        .checked_add(CLAIM_PERIOD)
+        .ok_or(MarketError::Overflow)?;
+
+    // Fees stay on the market until finalize_market, so an overridden resolution has not spent them
+
+    emit!(MarketResolved {
+        market_id,
+        outcome: market_account.outcome,
+        claimable_at: market_account.claimable_at,
+        numeric_answer: market_account.resolved_value,
+        bool_answer,
+        confidence_score,
+    });
+
+    Ok(())
+}
+
+/// Delete an open market nobody has bet on, once the accounts struct has checked who may:
+/// frees its oracle question for another market and moves the creation deposit to the treasury,
+/// returning the deposit; closing the account hands the rest of its lamports to the creator
+fn delete_market(
+    market_account: &Account<MarketAccount>,
+    question_binding: &mut Account<QuestionBinding>,
+    treasury: &AccountInfo,
+) -> Result<u64> {
+    require!(
+        market_account.status == MarketStatus::Open,
+        MarketError::MarketNotOpen
+    );
+    require!(
+        market_account.yes_pool == 0 && market_account.no_pool == 0,
+        MarketError::MarketNotEmpty
+    );
+
+    question_binding.market_count = question_binding.market_count.saturating_sub(1);
+
+    let forfeited = market_account.creation_deposit;
+    if forfeited > 0 {
+        **market_account.to_account_info().try_borrow_mut_lamports()? -= forfeited;
+        **treasury.try_borrow_mut_lamports()? += forfeited;
+    }
+
+    Ok(forfeited)
+}
+
+/// Apply a bet of `bet_amount` by `user` to the market's pools and their position,
+/// returning the entry fee charged; callers move the stake itself
+fn record_position(
+    market_account: &mut Account<MarketAccount>,
+    position: &mut Account<Position>,
+    market_state: &Account<MarketState>,
+    market_id: u64,
+    user: Pubkey,
+    is_yes: bool,
+    bet_amount: u64,
+) -> Result<u64> {
+    require!(
+        market_account.status == MarketStatus::Open,
+        MarketError::MarketNotOpen
+    );
+    require!(
+        Clock::get()?.unix_timestamp < market_account.close_time,
+        MarketError::MarketExpired
+    );
+    require!(
+        Clock::get()?.unix_timestamp >= market_account.opens_at,
+        MarketError::OpensLater
+    );
+
+    let fee_percentage = match market_account.fee_mode {
+        FeeMode::Flat => market_state.fee_percentage,
+        FeeMode::Dynamic => market_account
+            .dynamic_fee_bps(Clock::get()?.unix_timestamp)
+            .ok_or(MarketError::Overflow)?,
+    };
+    require!(bet_amount > 0, MarketError::ZeroBet);
+    require!(bet_amount >= market_state.min_bet, MarketError::BetTooSmall);
+
+    // Hedging both sides would let a bettor collect whichever wins
+    if market_account.single_side_only {
+        let opposite = if is_yes { position.no_amount } else { position.yes_amount };
+        require!(opposite == 0, MarketError::SingleSideOnly);
+    }
+
+    // Calculate fee (in basis points), widened so large stakes cannot overflow
+    let fee = (bet_amount as u128)
+        .checked_mul(fee_percentage as u128)
+        .and_then(|x| x.checked_div(10000))
+        .and_then(|x| u64::try_from(x).ok())
+        .ok_or(MarketError::Overflow)?;
+    let net_amount = bet_amount.checked_sub(fee).ok_or(MarketError::Overflow)?;
+
+    // Reject stakes that would push the pools past the market's cap
+    if market_account.max_total_pool > 0 {
+        let total_pool = market_account
+            .yes_pool
+            .checked_add(market_account.no_pool)
+            .and_then(|x| x.checked_add(net_amount))
+            .ok_or(MarketError::Overflow)?;
+        require!(
+            total_pool <= market_account.max_total_pool,
+            MarketError::PoolCapExceeded
+        );
+    }
+
+    // In LMSR mode the net amount buys shares at the current market maker price
+    let shares = match market_account.pricing_mode {
+        PricingMode::Parimutuel => net_amount,
+        PricingMode::Lmsr => {
+            let (q_buy, q_other) = if is_yes {
+                (market_account.yes_shares, market_account.no_shares)
+            } else {
+                (market_account.no_shares, market_account.yes_shares)
+            };
+            let shares = lmsr::shares_for_cost(q_buy, q_other, market_account.liquidity_b, net_amount)
+                .ok_or(MarketError::Overflow)?;
+            require!(shares > 0, MarketError::ZeroBet);
+            shares
+        }
+    };
+
+    // Update market pools
+    market_account.total_fees = market_account
+        .total_fees
+        .checked_add(fee)
+        .ok_or(MarketError::Overflow)?;
+
+    if is_yes {
+        market_account.yes_pool = market_account
+            .yes_pool
+            .checked_add(net_amount)
+            .ok_or(MarketError::Overflow)?;
+    } else {
+        market_account.no_pool = market_account
+            .no_pool
+            .checked_add(net_amount)
+            .ok_or(MarketError::Overflow)?;
+    }
+
+    // Update position
+    let now = Clock::get()?.unix_timestamp;
+    position.fees_paid = position
+        .fees_paid
+        .checked_add(fee)
+        .ok_or(MarketError::Overflow)?;
+    if position.first_bet_at == 0 {
+        position.first_bet_at = now;
+
+        // Indexers rebuild the participant set from this append-only trail
+        emit!(ParticipantJoined {
+            market_id,
+            user,
+            index: market_account.participant_count,
+        });
+        market_account.participant_count = market_account
+            .participant_count
+            .checked_add(1)
+            .ok_or(MarketError::Overflow)?;
+    }
+    if is_yes {
+        position.yes_amount = position
+            .yes_amount
+            .checked_add(net_amount)
+            .ok_or(MarketError::Overflow)?;
+    } else {
+        position.no_amount = position
+            .no_amount
+            .checked_add(net_amount)
+            .ok_or(MarketError::Overflow)?;
+    }
+
+    if market_account.payout_mode == PayoutMode::TimeWeighted {
+        let weighted = market_account
+            .time_weight(net_amount, now)
+            .ok_or(MarketError::Overflow)?;
+        if is_yes {
+            market_account.yes_weighted = market_account
+                .yes_weighted
+                .checked_add(weighted)
+                .ok_or(MarketError::Overflow)?;
+            position.yes_weighted = position
+                .yes_weighted
+                .checked_add(weighted)
+                .ok_or(MarketError::Overflow)?;
+        } else {
+            market_account.no_weighted = market_account
+                .no_weighted
+                .checked_add(weighted)
+                .ok_or(MarketError::Overflow)?;
+            position.no_weighted = position
+                .no_weighted
+                .checked_add(weighted)
+                .ok_or(MarketError::Overflow)?;
+        }
+    }
+
+    if market_account.pricing_mode == PricingMode::Lmsr {
+        if is_yes {
+            market_account.yes_shares = market_account
+                .yes_shares
+                .checked_add(shares)
+                .ok_or(MarketError::Overflow)?;
+            position.yes_shares = position
+                .yes_shares
+                .checked_add(shares)
+                .ok_or(MarketError::Overflow)?;
+        } else {
+            market_account.no_shares = market_account
+                .no_shares
+                .checked_add(shares)
+                .ok_or(MarketError::Overflow)?;
+            position.no_shares = position
+                .no_shares
+                .checked_add(shares)
+                .ok_or(MarketError::Overflow)?;
+        }
+    }
+
+    emit!(PositionTaken {
+        market_id,
+        user,
+        is_yes,
+        amount: net_amount,
+        shares,
+        fee_bps: fee_percentage,
+    });
+
+    Ok(fee)
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(
         init,
         payer = authority,
         space = 8 + MarketState::LEN,
@@ -337,103 +1811,718 @@ pub struct Initialize<'info> {
     pub market_state: Account<'info, MarketState>,
     #[account(mut)]
     pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    question: String,
+    resolution_time: i64,
+    close_time: i64,
+    pricing_mode: PricingMode,
+    liquidity_b: u64,
+    min_confidence: u8,
+    oracle_question_id: u64
+)]
+pub struct CreateMarket<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + MarketAccount::size(market_state.max_question_len),
+        seeds = [b"market", market_state.market_counter.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(mut, seeds = [b"market_state"], bump = market_state.bump)]
+    pub market_state: Account<'info, MarketState>,
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = 8 + QuestionBinding::LEN,
+        seeds = [b"question_binding", oracle_question_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub question_binding: Account<'info, QuestionBinding>,
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64, is_yes: bool, amount: u64, referrer: Option<Pubkey>)]
+pub struct TakePosition<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + Position::LEN,
+        seeds = [b"position", market_id.to_le_bytes().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+    #[account(seeds = [b"market_state"], bump = market_state.bump)]
+    pub market_state: Account<'info, MarketState>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    /// Token mode only: the program's stake vault
+    #[account(
+        mut,
+        associated_token::mint = market_state.stake_mint,
+        associated_token::authority = market_state
+    )]
+    pub stake_vault: Option<Account<'info, TokenAccount>>,
+    /// Token mode only: the bettor's stake mint account
+    #[account(
+        mut,
+        token::mint = market_state.stake_mint,
+        token::authority = bettor
+    )]
+    pub bettor_token_account: Option<Account<'info, TokenAccount>>,
+    /// Referred bets only: the referrer's balance on this market
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        space = 8 + ReferralBalance::LEN,
+        seeds = [b"referral", market_id.to_le_bytes().as_ref(), referrer.unwrap_or_default().as_ref()],
+        bump
+    )]
+    pub referral_balance: Option<Account<'info, ReferralBalance>>,
+    /// Receipt deployments only: the position's receipt mint
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        seeds = [b"receipt", market_id.to_le_bytes().as_ref(), bettor.key().as_ref()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = market_state
+    )]
+    pub receipt_mint: Option<Account<'info, Mint>>,
+    /// Receipt deployments only: the bettor's account for the receipt
+    #[account(
+        init_if_needed,
+        payer = bettor,
+        associated_token::mint = receipt_mint,
+        associated_token::authority = bettor
+    )]
+    pub bettor_receipt_account: Option<Account<'info, TokenAccount>>,
+    /// CHECK: Receipt deployments only: the receipt's metadata account, checked and created by the metadata program
+    #[account(mut)]
+    pub receipt_metadata: Option<UncheckedAccount<'info>>,
+    pub token_program: Option<Program<'info, Token>>,
+    pub token_metadata_program: Option<Program<'info, Metadata>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+    pub rent: Option<Sysvar<'info, Rent>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct ClaimReferralFees<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        mut,
+        seeds = [b"referral", market_id.to_le_bytes().as_ref(), referrer.key().as_ref()],
+        bump
+    )]
+    pub referral_balance: Account<'info, ReferralBalance>,
+    #[account(seeds = [b"market_state"], bump = market_state.bump)]
+    pub market_state: Account<'info, MarketState>,
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+    /// Token mode only: the program's stake vault
+    #[account(
+        mut,
+        associated_token::mint = market_state.stake_mint,
+        associated_token::authority = market_state
+    )]
+    pub stake_vault: Option<Account<'info, TokenAccount>>,
+    /// Token mode only: the referrer's stake mint account
+    #[account(
+        mut,
+        token::mint = market_state.stake_mint,
+        token::authority = referrer
+    )]
+    pub referrer_token_account: Option<Account<'info, TokenAccount>>,
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+#[derive(Accounts)]
+#[instruction(spender: Pubkey)]
+pub struct ApproveSpender<'info> {
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + SpenderAllowance::LEN,
+        seeds = [b"allowance", owner.key().as_ref(), spender.as_ref()],
+        bump
+    )]
+    pub spender_allowance: Account<'info, SpenderAllowance>,
+    #[account(seeds = [b"market_state"], bump = market_state.bump)]
+    pub market_state: Account<'info, MarketState>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    /// Token mode only: the owner's stake mint account the spender draws from
+    #[account(
+        mut,
+        token::mint = market_state.stake_mint,
+        token::authority = owner
+    )]
+    pub owner_token_account: Option<Account<'info, TokenAccount>>,
+    pub token_program: Option<Program<'info, Token>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct TakePositionDelegated<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        init_if_needed,
+        payer = spender,
+        space = 8 + Position::LEN,
+        seeds = [b"position", market_id.to_le_bytes().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+    #[account(
+        mut,
+        seeds = [b"allowance", owner.key().as_ref(), spender.key().as_ref()],
+        bump = spender_allowance.bump
+    )]
+    pub spender_allowance: Account<'info, SpenderAllowance>,
+    #[account(seeds = [b"market_state"], bump = market_state.bump)]
+    pub market_state: Account<'info, MarketState>,
+    /// CHECK: Owner of the allowance, bound by its seeds
+    pub owner: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub spender: Signer<'info>,
+    /// Token mode only: the program's stake vault
+    #[account(
+        mut,
+        associated_token::mint = market_state.stake_mint,
+        associated_token::authority = market_state
+    )]
+    pub stake_vault: Option<Account<'info, TokenAccount>>,
+    /// Token mode only: the owner's stake mint account
+    #[account(
+        mut,
+        token::mint = market_state.stake_mint,
+        token::authority = owner
+    )]
+    pub owner_token_account: Option<Account<'info, TokenAccount>>,
+    pub token_program: Option<Program<'info, Token>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct CloseMarket<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(seeds = [b"market_state"], bump = market_state.bump)]
+    pub market_state: Account<'info, MarketState>,
+    pub closer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct DeleteEmptyMarket<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump,
+        has_one = creator @ MarketError::Unauthorized,
+        close = creator
+    )]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        mut,
+        seeds = [b"question_binding", market_account.oracle_question_id.to_le_bytes().as_ref()],
+        bump = question_binding.bump
+    )]
+    pub question_binding: Account<'info, QuestionBinding>,
+    #[account(seeds = [b"market_state"], bump = market_state.bump)]
+    pub market_state: Account<'info, MarketState>,
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    /// CHECK: Verified against the treasury stored on market state
+    #[account(mut, address = market_state.treasury @ MarketError::InvalidTreasury)]
+    pub treasury: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct DeleteSpamMarket<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump,
+        has_one = creator @ MarketError::Unauthorized,
+        close = creator
+    )]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        mut,
+        seeds = [b"question_binding", market_account.oracle_question_id.to_le_bytes().as_ref()],
+        bump = question_binding.bump
+    )]
+    pub question_binding: Account<'info, QuestionBinding>,
+    #[account(
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
+    /// CHECK: Verified against the creator stored on the market
+    #[account(mut)]
+    pub creator: UncheckedAccount<'info>,
+    /// CHECK: Verified against the treasury stored on market state
+    #[account(mut, address = market_state.treasury @ MarketError::InvalidTreasury)]
+    pub treasury: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
-pub struct CreateMarket<'info> {
+#[instruction(market_id: u64)]
+pub struct ReclaimCreationDeposit<'info> {
     #[account(
-        init,
-        payer = creator,
-        space = 8 + MarketAccount::LEN,
-        seeds = [b"market", market_state.market_counter.to_le_bytes().as_ref()],
+        mut,
+        seeds = [b"market", market_id.to_le_bytes().as_ref()],
+        bump,
+        has_one = creator @ MarketError::Unauthorized
+    )]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(mut)]
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct GetMarketInfo<'info> {
+    #[account(seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct ResolveMarket<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(mut, seeds = [b"market_state"], bump = market_state.bump)]
+    pub market_state: Account<'info, MarketState>,
+    /// Oracle question account (from trace9 program)
+    /// CHECK: Owner and discriminator are checked on resolution, before any field is read
+    pub oracle_question: AccountInfo<'info>,
+    /// CHECK: The market's answer PDA under the oracle program; contents are checked on resolution
+    #[account(
+        seeds = [b"answer", market_account.oracle_question_id.to_le_bytes().as_ref()],
+        bump,
+        seeds::program = market_state.oracle_program
+    )]
+    pub oracle_answer: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct ResolveMarketFromPda<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(mut, seeds = [b"market_state"], bump = market_state.bump)]
+    pub market_state: Account<'info, MarketState>,
+    /// CHECK: The market's question PDA under the oracle program; contents are checked on resolution
+    #[account(
+        seeds = [b"question", market_account.oracle_question_id.to_le_bytes().as_ref()],
+        bump,
+        seeds::program = market_state.oracle_program
+    )]
+    pub oracle_question: AccountInfo<'info>,
+    /// CHECK: The market's answer PDA under the oracle program; contents are checked on resolution
+    #[account(
+        seeds = [b"answer", market_account.oracle_question_id.to_le_bytes().as_ref()],
+        bump,
+        seeds::program = market_state.oracle_program
+    )]
+    pub oracle_answer: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct DisputeResolution<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        seeds = [b"position", market_id.to_le_bytes().as_ref(), disputer.key().as_ref()],
         bump
     )]
+    pub position: Account<'info, Position>,
+    pub disputer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct OverrideResolution<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct FinalizeMarket<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
     pub market_account: Account<'info, MarketAccount>,
     #[account(mut, seeds = [b"market_state"], bump = market_state.bump)]
     pub market_state: Account<'info, MarketState>,
+}
+
+#[derive(Accounts)]
+pub struct BatchClaimWinnings<'info> {
+    #[account(seeds = [b"market_state"], bump = market_state.bump)]
+    pub market_state: Account<'info, MarketState>,
     #[account(mut)]
-    pub creator: Signer<'info>,
-    pub system_program: Program<'info, System>,
+    pub winner: Signer<'info>,
+    /// Token mode only: the program's stake vault
+    #[account(
+        mut,
+        associated_token::mint = market_state.stake_mint,
+        associated_token::authority = market_state
+    )]
+    pub stake_vault: Option<Account<'info, TokenAccount>>,
+    /// Token mode only: the winner's stake mint account
+    #[account(
+        mut,
+        token::mint = market_state.stake_mint,
+        token::authority = winner
+    )]
+    pub winner_token_account: Option<Account<'info, TokenAccount>>,
+    pub token_program: Option<Program<'info, Token>>,
 }
 
 #[derive(Accounts)]
 #[instruction(market_id: u64)]
-pub struct TakePosition<'info> {
+pub struct ClaimWinnings<'info> {
     #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
     pub market_account: Account<'info, MarketAccount>,
     #[account(
-        init_if_needed,
-        payer = bettor,
-        space = 8 + Position::LEN,
-        seeds = [b"position", market_id.to_le_bytes().as_ref(), bettor.key().as_ref()],
+        mut,
+        seeds = [b"position", market_id.to_le_bytes().as_ref(), position_owner.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+    /// CHECK: Bettor the position was opened for, bound by the position seeds
+    pub position_owner: UncheckedAccount<'info>,
+    #[account(seeds = [b"market_state"], bump = market_state.bump)]
+    pub market_state: Account<'info, MarketState>,
+    #[account(mut)]
+    pub winner: Signer<'info>,
+    /// Token mode only: the program's stake vault
+    #[account(
+        mut,
+        associated_token::mint = market_state.stake_mint,
+        associated_token::authority = market_state
+    )]
+    pub stake_vault: Option<Account<'info, TokenAccount>>,
+    /// Token mode only: the winner's stake mint account
+    #[account(
+        mut,
+        token::mint = market_state.stake_mint,
+        token::authority = winner
+    )]
+    pub winner_token_account: Option<Account<'info, TokenAccount>>,
+    /// Receipted positions only: the position's receipt mint
+    #[account(mut, address = position.receipt_mint @ MarketError::ReceiptMismatch)]
+    pub receipt_mint: Option<Account<'info, Mint>>,
+    /// Receipted positions only: the winner's account holding the receipt
+    #[account(
+        mut,
+        token::mint = position.receipt_mint,
+        token::authority = winner
+    )]
+    pub winner_receipt_account: Option<Account<'info, TokenAccount>>,
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct QuotePayout<'info> {
+    #[account(seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        seeds = [b"position", market_id.to_le_bytes().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct TotalClaimable<'info> {
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct CancelMarket<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(seeds = [b"market_state"], bump = market_state.bump)]
+    pub market_state: Account<'info, MarketState>,
+    /// CHECK: The market's question PDA under the oracle program; contents are checked on cancel
+    #[account(
+        seeds = [b"question", market_account.oracle_question_id.to_le_bytes().as_ref()],
+        bump,
+        seeds::program = market_state.oracle_program
+    )]
+    pub oracle_question: AccountInfo<'info>,
+    /// CHECK: The market's answer PDA under the oracle program; read only once the question is answered
+    #[account(
+        seeds = [b"answer", market_account.oracle_question_id.to_le_bytes().as_ref()],
+        bump,
+        seeds::program = market_state.oracle_program
+    )]
+    pub oracle_answer: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct ClaimRefund<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        mut,
+        seeds = [b"position", market_id.to_le_bytes().as_ref(), position_owner.key().as_ref()],
         bump
     )]
-    pub position: Account<'info, Position>,
-    #[account(seeds = [b"market_state"], bump = market_state.bump)]
+    pub position: Account<'info, Position>,
+    /// CHECK: Bettor the position was opened for, bound by the position seeds
+    pub position_owner: UncheckedAccount<'info>,
+    #[account(seeds = [b"market_state"], bump = market_state.bump)]
+    pub market_state: Account<'info, MarketState>,
+    #[account(mut)]
+    pub refundee: Signer<'info>,
+    /// Token mode only: the program's stake vault
+    #[account(
+        mut,
+        associated_token::mint = market_state.stake_mint,
+        associated_token::authority = market_state
+    )]
+    pub stake_vault: Option<Account<'info, TokenAccount>>,
+    /// Token mode only: the refundee's stake mint account
+    #[account(
+        mut,
+        token::mint = market_state.stake_mint,
+        token::authority = refundee
+    )]
+    pub refundee_token_account: Option<Account<'info, TokenAccount>>,
+    /// Receipted positions only: the position's receipt mint
+    #[account(mut, address = position.receipt_mint @ MarketError::ReceiptMismatch)]
+    pub receipt_mint: Option<Account<'info, Mint>>,
+    /// Receipted positions only: the refundee's account holding the receipt
+    #[account(
+        mut,
+        token::mint = position.receipt_mint,
+        token::authority = refundee
+    )]
+    pub refundee_receipt_account: Option<Account<'info, TokenAccount>>,
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct BatchRefund<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(seeds = [b"market_state"], bump = market_state.bump)]
+    pub market_state: Account<'info, MarketState>,
+    /// Token mode only: the program's stake vault
+    #[account(
+        mut,
+        associated_token::mint = market_state.stake_mint,
+        associated_token::authority = market_state
+    )]
+    pub stake_vault: Option<Account<'info, TokenAccount>>,
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    #[account(
+        mut,
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
+    /// CHECK: Verified against the treasury stored on market state
+    #[account(mut, address = market_state.treasury @ MarketError::InvalidTreasury)]
+    pub treasury: UncheckedAccount<'info>,
+    /// Token mode only: the program's stake vault
+    #[account(
+        mut,
+        associated_token::mint = market_state.stake_mint,
+        associated_token::authority = market_state
+    )]
+    pub stake_vault: Option<Account<'info, TokenAccount>>,
+    /// Token mode only: the treasury's stake mint account
+    #[account(
+        mut,
+        token::mint = market_state.stake_mint,
+        token::authority = treasury
+    )]
+    pub treasury_token_account: Option<Account<'info, TokenAccount>>,
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct SweepUnclaimed<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
+    /// CHECK: Verified against the treasury stored on market state
+    #[account(mut, address = market_state.treasury @ MarketError::InvalidTreasury)]
+    pub treasury: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct TopUpMarket<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(mut)]
+    pub donor: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct SetParlayShare<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct ReleaseParlayShare<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
+    /// CHECK: Verified against the parlay recipient stored on the market
+    #[account(
+        mut,
+        constraint = recipient.key() == market_account.parlay_recipient @ MarketError::InvalidParlayShare
+    )]
+    pub recipient: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct ReturnParlayShare<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(seeds = [b"market_state"], bump = market_state.bump)]
+    pub market_state: Account<'info, MarketState>,
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMinBet<'info> {
+    #[account(
+        mut,
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
     pub market_state: Account<'info, MarketState>,
-    #[account(mut)]
-    pub bettor: Signer<'info>,
-    pub system_program: Program<'info, System>,
+    pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
-#[instruction(market_id: u64)]
-pub struct ResolveMarket<'info> {
-    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
-    pub market_account: Account<'info, MarketAccount>,
-    #[account(mut, seeds = [b"market_state"], bump = market_state.bump)]
+pub struct SetFeePercentage<'info> {
+    #[account(
+        mut,
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
     pub market_state: Account<'info, MarketState>,
-    /// Oracle answer account (from trace9 program)
-    /// CHECK: This should be verified to come from the oracle program
-    pub oracle_answer: AccountInfo<'info>,
+    pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
-#[instruction(market_id: u64)]
-pub struct ClaimWinnings<'info> {
-    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
-    pub market_account: Account<'info, MarketAccount>,
+pub struct SetTreasury<'info> {
     #[account(
         mut,
-        seeds = [b"position", market_id.to_le_bytes().as_ref(), winner.key().as_ref()],
-        bump
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
     )]
-    pub position: Account<'info, Position>,
-    #[account(mut)]
-    pub winner: Signer<'info>,
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
-#[instruction(market_id: u64)]
-pub struct CancelMarket<'info> {
-    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
-    pub market_account: Account<'info, MarketAccount>,
-    /// Oracle answer account (from trace9 program)
-    /// CHECK: This should be verified to come from the oracle program
-    pub oracle_answer: AccountInfo<'info>,
+pub struct SetReferralBps<'info> {
+    #[account(
+        mut,
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
-#[instruction(market_id: u64)]
-pub struct ClaimRefund<'info> {
-    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
-    pub market_account: Account<'info, MarketAccount>,
+pub struct SetCreationDeposit<'info> {
     #[account(
         mut,
-        seeds = [b"position", market_id.to_le_bytes().as_ref(), refundee.key().as_ref()],
-        bump
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
     )]
-    pub position: Account<'info, Position>,
-    #[account(mut)]
-    pub refundee: Signer<'info>,
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct WithdrawFees<'info> {
-    #[account(mut, seeds = [b"market_state"], bump = market_state.bump)]
+pub struct SetPositionReceipts<'info> {
+    #[account(
+        mut,
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
     pub market_state: Account<'info, MarketState>,
-    #[account(mut)]
     pub authority: Signer<'info>,
 }
 
@@ -444,11 +2533,24 @@ pub struct MarketState {
     pub market_counter: u64,       // 8 bytes
     pub fee_percentage: u16,       // 2 bytes (basis points)
     pub accumulated_fees: u64,     // 8 bytes
+    pub min_bet: u64,              // 8 bytes (lamports)
+    pub resolution_dispute_period: i64, // 8 bytes (seconds)
+    pub treasury: Pubkey,          // 32 bytes
+    pub stake_mint: Pubkey,        // 32 bytes (default = native SOL stakes)
+    pub referral_bps: u16,         // 2 bytes (share of entry fees paid to referrers)
+    pub creation_deposit: u64,     // 8 bytes (lamports locked per new market, 0 = none)
+    pub max_question_len: u16,     // 2 bytes (longest accepted question)
+    pub position_receipts: bool,   // 1 byte (mint an NFT receipt for each new position)
     pub bump: u8,                  // 1 byte
 }
 
 impl MarketState {
-    pub const LEN: usize = 32 + 32 + 8 + 2 + 8 + 1;
+    pub const LEN: usize = 32 + 32 + 8 + 2 + 8 + 8 + 8 + 32 + 32 + 2 + 8 + 2 + 1 + 1;
+
+    /// Stakes and payouts move as SPL tokens of `stake_mint` instead of lamports
+    pub fn token_mode(&self) -> bool {
+        self.stake_mint != Pubkey::default()
+    }
 }
 
 #[account]
@@ -456,6 +2558,7 @@ pub struct MarketAccount {
     pub market_id: u64,            // 8 bytes
     pub question: String,           // 4 + len bytes
     pub resolution_time: i64,      // 8 bytes
+    pub close_time: i64,           // 8 bytes (betting cutoff)
     pub yes_pool: u64,              // 8 bytes
     pub no_pool: u64,               // 8 bytes
     pub status: MarketStatus,       // 1 byte
@@ -463,10 +2566,203 @@ pub struct MarketAccount {
     pub total_fees: u64,            // 8 bytes
     pub created_at: i64,            // 8 bytes
     pub creator: Pubkey,            // 32 bytes
+    pub pricing_mode: PricingMode,  // 1 byte
+    pub liquidity_b: u64,           // 8 bytes (LMSR liquidity parameter)
+    pub yes_shares: u64,            // 8 bytes (LMSR outstanding YES shares)
+    pub no_shares: u64,             // 8 bytes (LMSR outstanding NO shares)
+    pub claimable_at: i64,          // 8 bytes (end of dispute window)
+    pub disputed: bool,             // 1 byte
+    pub min_confidence: u8,         // 1 byte
+    pub oracle_question_id: u64,    // 8 bytes (trace9 question resolving this market)
+    pub question_type: QuestionType, // 1 byte (expected oracle question type)
+    pub fee_mode: FeeMode,          // 1 byte
+    pub fee_start_bps: u16,         // 2 bytes (dynamic fee at creation)
+    pub fee_end_bps: u16,           // 2 bytes (dynamic fee at close time)
+    pub payout_mode: PayoutMode,    // 1 byte
+    pub yes_weighted: u128,         // 16 bytes (time-weighted YES stake)
+    pub no_weighted: u128,          // 16 bytes (time-weighted NO stake)
+    pub claim_deadline: i64,        // 8 bytes (unclaimed winnings can be swept after this)
+    pub participant_count: u32,     // 4 bytes (distinct bettors)
+    pub min_participants: u32,      // 4 bytes (bettors needed to resolve)
+    pub max_total_pool: u64,        // 8 bytes (cap on yes_pool + no_pool, 0 = unlimited)
+    pub max_answer_age_secs: i64,   // 8 bytes (0 = answers of any age resolve)
+    pub min_pool_per_side: u64,     // 8 bytes (parimutuel liquidity floor per side, 0 = none)
+    pub single_side_only: bool,     // 1 byte (bettors may only back one side)
+    pub resolved_value: u64,        // 8 bytes (oracle numeric answer cached at resolution, or 1/0/VOID_ANSWER after an override)
+    pub resolved_bool: bool,        // 1 byte (oracle bool answer cached at resolution)
+    pub parlay_bps: u16,            // 2 bytes (share of the losing pool routed to a parlay)
+    pub parlay_recipient: Pubkey,   // 32 bytes (conditional market receiving the share)
+    pub parlay_pool: u64,           // 8 bytes (share set aside at resolution, excluded from winnings until returned)
+    pub parlay_released: bool,      // 1 byte
+    pub opens_at: i64,              // 8 bytes (no bets before this time)
+    pub creation_deposit: u64,      // 8 bytes (lamports returned to the creator once resolved)
+    pub finalized: bool,            // 1 byte (dispute window passed, fees moved to accumulated_fees)
 }
 
 impl MarketAccount {
-    pub const LEN: usize = 8 + (4 + 500) + 8 + 8 + 8 + 1 + 1 + 8 + 8 + 32;
+    /// Account size with room for questions up to the deployment's `max_question_len` bytes
+    pub fn size(max_question_len: u16) -> usize {
+        8 + (4 + max_question_len as usize) + 8 + 8 + 8 + 8 + 1 + 1 + 8 + 8 + 32 + 1 + 8 + 8 + 8 + 8 + 1 + 1 + 8 + 1 + 1 + 2 + 2 + 1 + 16 + 16 + 8 + 4 + 4 + 8 + 8 + 8 + 1 + 8 + 1 + 2 + 32 + 8 + 1 + 8 + 8 + 1
+    }
+
+    /// Lamports held back from payouts and sweeps: the unreleased parlay share and the creation deposit
+    pub fn reserved_lamports(&self) -> Result<u64> {
+        let parlay_reserved = if self.parlay_released { 0 } else { self.parlay_pool };
+        Ok(parlay_reserved
+            .checked_add(self.creation_deposit)
+            .ok_or(MarketError::Overflow)?)
+    }
+
+    /// Whether winnings on this market can be claimed at `now`
+    pub fn claim_open(&self, now: i64) -> bool {
+        self.status == MarketStatus::Resolved
+            && now >= self.claimable_at
+            && now < self.claim_deadline
+            && !self.parlay_pending()
+    }
+
+    /// Whether a parlay share is still waiting to be released to its recipient or returned to winners
+    pub fn parlay_pending(&self) -> bool {
+        self.parlay_pool > 0 && !self.parlay_released
+    }
+
+    /// Whether either parimutuel pool sits below `min_pool_per_side`, making the odds degenerate
+    pub fn has_thin_side(&self) -> bool {
+        self.pricing_mode == PricingMode::Parimutuel
+            && (self.yes_pool < self.min_pool_per_side || self.no_pool < self.min_pool_per_side)
+    }
+
+    /// Whether an oracle answer posted at `answered_at` is too old to settle this market;
+    /// answers posted after resolution time are always fresh
+    pub fn answer_is_stale(&self, answered_at: i64) -> bool {
+        self.max_answer_age_secs > 0
+            && self.resolution_time.saturating_sub(answered_at) > self.max_answer_age_secs
+    }
+
+    /// Slice of the losing pool owed to the parlay recipient under the current outcome
+    pub fn parlay_share(&self) -> Result<u64> {
+        let losing_pool = match self.outcome {
+            Outcome::Yes => self.no_pool,
+            Outcome::No => self.yes_pool,
+            Outcome::Unresolved | Outcome::Void => return Ok(0),
+        };
+        let share = (losing_pool as u128)
+            .checked_mul(self.parlay_bps as u128)
+            .and_then(|x| x.checked_div(10000))
+            .and_then(|x| u64::try_from(x).ok())
+            .ok_or(MarketError::Overflow)?;
+        Ok(share)
+    }
+
+    /// Fee in basis points interpolated linearly from `fee_start_bps` at creation
+    /// to `fee_end_bps` at close time
+    pub fn dynamic_fee_bps(&self, now: i64) -> Option<u16> {
+        let lifetime = self.close_time.checked_sub(self.created_at)?;
+        if lifetime <= 0 {
+            return Some(self.fee_end_bps);
+        }
+        let elapsed = now.checked_sub(self.created_at)?.clamp(0, lifetime);
+
+        let start = self.fee_start_bps as i64;
+        let end = self.fee_end_bps as i64;
+        let fee = start.checked_add(end.checked_sub(start)?.checked_mul(elapsed)?.checked_div(lifetime)?)?;
+        u16::try_from(fee).ok()
+    }
+
+    /// Stake weighted by time left until resolution: a bet placed at creation
+    /// counts double one placed at resolution time
+    pub fn time_weight(&self, amount: u64, now: i64) -> Option<u128> {
+        let lifetime = self.resolution_time.checked_sub(self.created_at)?.max(0);
+        let remaining = self.resolution_time.checked_sub(now)?.clamp(0, lifetime);
+        (amount as u128).checked_mul(lifetime.checked_add(remaining)? as u128)
+    }
+
+    /// Net stake a position has on the side that pays out, the cost basis of its claim;
+    /// a void market pays out both sides
+    pub fn winning_stake(&self, position: &Position) -> Result<u64> {
+        match self.outcome {
+            Outcome::Yes => Ok(position.yes_amount),
+            Outcome::No => Ok(position.no_amount),
+            Outcome::Void => Ok(position
+                .yes_amount
+                .checked_add(position.no_amount)
+                .ok_or(MarketError::Overflow)?),
+            Outcome::Unresolved => Ok(0),
+        }
+    }
+
+    /// Payout owed to a position on this resolved market
+    pub fn winnings_for(&self, position: &Position) -> Result<u64> {
+        // Void markets return each position's net stake
+        if self.outcome == Outcome::Void {
+            let refund = self.winning_stake(position)?;
+            require!(refund > 0, MarketError::NoWinnings);
+            return Ok(refund);
+        }
+
+        // The parlay share is carved out of the losing pool before winners split it
+        let total_pool = self
+            .yes_pool
+            .checked_add(self.no_pool)
+            .and_then(|x| x.checked_sub(self.parlay_pool))
+            .ok_or(MarketError::Overflow)?;
+
+        // LMSR shares pay out one lamport each on the winning side
+        let winnings = if self.pricing_mode == PricingMode::Lmsr {
+            let shares = match self.outcome {
+                Outcome::Yes => position.yes_shares,
+                Outcome::No => position.no_shares,
+                Outcome::Unresolved | Outcome::Void => return Err(MarketError::NoWinnings.into()),
+            };
+            require!(shares > 0, MarketError::BetOnLosingSide);
+            shares
+        } else {
+            let (stake, weighted_stake, side_pool, side_weighted) = match self.outcome {
+                Outcome::Yes => (position.yes_amount, position.yes_weighted, self.yes_pool, self.yes_weighted),
+                Outcome::No => (position.no_amount, position.no_weighted, self.no_pool, self.no_weighted),
+                Outcome::Unresolved | Outcome::Void => return Err(MarketError::NoWinnings.into()),
+            };
+            require!(side_pool > 0, MarketError::EmptyWinningPool);
+            require!(stake > 0, MarketError::BetOnLosingSide);
+
+            // Time-weighted markets split the pool by weighted stake instead of raw stake
+            let (share, side_total) = match self.payout_mode {
+                PayoutMode::Flat => (stake as u128, side_pool as u128),
+                PayoutMode::TimeWeighted => (weighted_stake, side_weighted),
+            };
+            share
+                .checked_mul(total_pool as u128)
+                .and_then(|x| x.checked_div(side_total))
+                .and_then(|x| u64::try_from(x).ok())
+                .ok_or(MarketError::Overflow)?
+        };
+
+        require!(winnings > 0, MarketError::PayoutRoundsToZero);
+        Ok(winnings)
+    }
+}
+
+#[account]
+pub struct ReferralBalance {
+    pub market_id: u64,            // 8 bytes
+    pub referrer: Pubkey,          // 32 bytes
+    pub balance: u64,              // 8 bytes (claimable once the market resolves)
+}
+
+impl ReferralBalance {
+    pub const LEN: usize = 8 + 32 + 8;
+}
+
+#[account]
+pub struct SpenderAllowance {
+    pub owner: Pubkey,             // 32 bytes
+    pub spender: Pubkey,           // 32 bytes
+    pub remaining: u64,            // 8 bytes (stake the spender may still bet)
+    pub bump: u8,                  // 1 byte
+}
+
+impl SpenderAllowance {
+    pub const LEN: usize = 32 + 32 + 8 + 1;
 }
 
 #[account]
@@ -474,21 +2770,95 @@ pub struct Position {
     pub yes_amount: u64,           // 8 bytes
     pub no_amount: u64,            // 8 bytes
     pub claimed: bool,              // 1 byte
+    pub yes_shares: u64,           // 8 bytes (LMSR only)
+    pub no_shares: u64,            // 8 bytes (LMSR only)
+    pub first_bet_at: i64,         // 8 bytes
+    pub yes_weighted: u128,        // 16 bytes (time-weighted payout only)
+    pub no_weighted: u128,         // 16 bytes (time-weighted payout only)
+    pub fees_paid: u64,            // 8 bytes (entry fees, refunded on cancel)
+    pub receipt_mint: Pubkey,      // 32 bytes (default = no NFT receipt)
 }
 
 impl Position {
-    pub const LEN: usize = 8 + 8 + 1;
+    pub const LEN: usize = 8 + 8 + 1 + 8 + 8 + 8 + 16 + 16 + 8 + 32;
+}
+
+// Markets bound to a trace9 question
+#[account]
+pub struct QuestionBinding {
+    pub question_id: u64,          // 8 bytes
+    pub market_count: u32,         // 4 bytes
+    pub shared: bool,              // 1 byte
+    pub bump: u8,                  // 1 byte
+}
+
+impl QuestionBinding {
+    pub const LEN: usize = 8 + 4 + 1 + 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct MarketInfo {
+    pub market_id: u64,
+    pub status: MarketStatus,
+    pub yes_probability_bps: u16,
+    pub no_probability_bps: u16,
+    pub total_pool: u64,
+    pub total_fees: u64,
+    pub time_remaining: i64, // Seconds until resolution time, 0 once passed
+}
+
+// Oracle question structure (matches trace9 program); the name must match too, as it sets the discriminator
+#[account]
+pub struct QuestionAccount {
+    pub question_id: u64,
+    pub requester: Pubkey,
+    pub question_type: QuestionType,
+    pub question_hash: [u8; 32],
+    pub category: String,
+    pub bounty: u64,
+    pub timestamp: i64,
+    pub deadline: i64,
+    pub status: AnswerStatus,
+    pub refunded: bool,
+    pub revealed: bool,
+    pub incentive_opt_in: bool,
+    pub last_accrual_at: i64,
+    pub assigned_provider: Option<Pubkey>,
 }
 
 // Oracle answer structure (matches trace9 program)
 #[account]
-pub struct OracleAnswer {
+pub struct AnswerAccount {
     pub question_id: u64,
     pub provider: Pubkey,
     pub confidence_score: u8,
     pub bool_answer: bool,
     pub numeric_answer: u64,
     pub timestamp: i64,
+    pub challenge_deadline: i64,
+    pub revision: u8,
+    pub bond_locked: u64,
+    pub disputer: Pubkey,
+    pub inconclusive: bool,
+}
+
+// Oracle question type (matches trace9 program)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum QuestionType {
+    General,
+    Price,
+    YesNo,
+    Numeric,
+}
+
+// Oracle question status (matches trace9 program)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum AnswerStatus {
+    Pending,
+    Answered,
+    Disputed,
+    Finalized,
+    Expired,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
@@ -499,11 +2869,42 @@ pub enum MarketStatus {
     Canceled,
 }
 
+impl MarketStatus {
+    /// Lifecycle transitions a market may make; resolved and canceled are terminal
+    pub fn can_transition_to(self, next: MarketStatus) -> bool {
+        matches!(
+            (self, next),
+            (MarketStatus::Open, MarketStatus::Closed)
+                | (MarketStatus::Open | MarketStatus::Closed, MarketStatus::Resolved)
+                | (MarketStatus::Open | MarketStatus::Closed, MarketStatus::Canceled)
+        )
+    }
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
 pub enum Outcome {
     Unresolved,
     Yes,
     No,
+    Void,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum PricingMode {
+    Parimutuel,
+    Lmsr,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum FeeMode {
+    Flat,
+    Dynamic,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum PayoutMode {
+    Flat,
+    TimeWeighted,
 }
 
 #[event]
@@ -511,7 +2912,24 @@ pub struct MarketCreated {
     pub market_id: u64,
     pub question: String,
     pub resolution_time: i64,
+    pub close_time: i64,
     pub creator: Pubkey,
+    pub pricing_mode: PricingMode,
+    pub liquidity_b: u64,
+    pub min_confidence: u8,
+    pub oracle_question_id: u64,
+    pub question_type: QuestionType,
+    pub fee_mode: FeeMode,
+    pub fee_start_bps: u16,
+    pub fee_end_bps: u16,
+    pub payout_mode: PayoutMode,
+    pub min_participants: u32,
+    pub max_total_pool: u64,
+    pub max_answer_age_secs: i64,
+    pub min_pool_per_side: u64,
+    pub single_side_only: bool,
+    pub opens_at: i64,
+    pub creation_deposit: u64,
 }
 
 #[event]
@@ -520,12 +2938,80 @@ pub struct PositionTaken {
     pub user: Pubkey,
     pub is_yes: bool,
     pub amount: u64,
+    pub shares: u64,
+    pub fee_bps: u16,
+}
+
+#[event]
+pub struct ReferralCredited {
+    pub market_id: u64,
+    pub referrer: Pubkey,
+    pub bettor: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct ReferralFeesClaimed {
+    pub market_id: u64,
+    pub referrer: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct SpenderApproved {
+    pub owner: Pubkey,
+    pub spender: Pubkey,
+    pub allowance: u64,
+}
+
+#[event]
+pub struct ParticipantJoined {
+    pub market_id: u64,
+    pub user: Pubkey,
+    pub index: u32,
+}
+
+#[event]
+pub struct MarketClosed {
+    pub market_id: u64,
+}
+
+#[event]
+pub struct MarketDeleted {
+    pub market_id: u64,
+    pub creator: Pubkey,
+    pub forfeited: u64,
 }
 
 #[event]
 pub struct MarketResolved {
     pub market_id: u64,
     pub outcome: Outcome,
+    pub claimable_at: i64,
+    pub numeric_answer: u64, // Oracle values the outcome was read from
+    pub bool_answer: bool,
+    pub confidence_score: u8,
+}
+
+#[event]
+pub struct ResolutionDisputed {
+    pub market_id: u64,
+    pub disputer: Pubkey,
+    pub outcome: Outcome,
+}
+
+#[event]
+pub struct ResolutionOverridden {
+    pub market_id: u64,
+    pub old_outcome: Outcome,
+    pub new_outcome: Outcome,
+}
+
+#[event]
+pub struct MarketFinalized {
+    pub market_id: u64,
+    pub outcome: Outcome,
+    pub fees: u64,
 }
 
 #[event]
@@ -540,10 +3026,124 @@ pub struct WinningsClaimed {
     pub amount: u64,
 }
 
+#[event]
+pub struct ClaimRecord {
+    pub market_id: u64,
+    pub user: Pubkey,
+    pub cost_basis: u64, // Net stake on the paying side
+    pub payout: u64,
+}
+
+#[event]
+pub struct BatchWinningsClaimed {
+    pub user: Pubkey,
+    pub claimed_market_ids: Vec<u64>,
+    pub skipped_market_ids: Vec<u64>,
+    pub total_payout: u64,
+}
+
+#[event]
+pub struct BatchRefunded {
+    pub market_id: u64,
+    pub refunded: Vec<Pubkey>,
+    pub skipped: Vec<Pubkey>,
+    pub total_refund: u64,
+}
+
 #[event]
 pub struct FeesWithdrawn {
     pub amount: u64,
     pub authority: Pubkey,
+    pub treasury: Pubkey,
+}
+
+#[event]
+pub struct TreasuryUpdated {
+    pub old_treasury: Pubkey,
+    pub new_treasury: Pubkey,
+}
+
+#[event]
+pub struct ReferralBpsUpdated {
+    pub old_referral_bps: u16,
+    pub new_referral_bps: u16,
+}
+
+#[event]
+pub struct UnclaimedSwept {
+    pub market_id: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct MarketToppedUp {
+    pub market_id: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct ParlayShareSet {
+    pub market_id: u64,
+    pub parlay_bps: u16,
+    pub parlay_recipient: Pubkey,
+}
+
+#[event]
+pub struct ParlayShareReleased {
+    pub market_id: u64,
+    pub recipient: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct ParlayShareReturned {
+    pub market_id: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct SpamMarketDeleted {
+    pub market_id: u64,
+    pub creator: Pubkey,
+    pub forfeited: u64,
+}
+
+#[event]
+pub struct CreationDepositReturned {
+    pub market_id: u64,
+    pub creator: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct CreationDepositUpdated {
+    pub old_creation_deposit: u64,
+    pub new_creation_deposit: u64,
+}
+
+#[event]
+pub struct PositionReceiptMinted {
+    pub market_id: u64,
+    pub user: Pubkey,
+    pub mint: Pubkey,
+}
+
+#[event]
+pub struct PositionReceiptsUpdated {
+    pub old_enabled: bool,
+    pub new_enabled: bool,
+}
+
+#[event]
+pub struct MinBetUpdated {
+    pub old_min_bet: u64,
+    pub new_min_bet: u64,
+}
+
+#[event]
+pub struct FeePercentageUpdated {
+    pub old_fee_percentage: u16,
+    pub new_fee_percentage: u16,
 }
 
 #[error_code]
@@ -552,12 +3152,16 @@ pub enum MarketError {
     InvalidQuestion,
     #[msg("Invalid resolution time")]
     InvalidResolutionTime,
+    #[msg("Invalid close time")]
+    InvalidCloseTime,
     #[msg("Market not open")]
     MarketNotOpen,
     #[msg("Market expired")]
     MarketExpired,
     #[msg("Zero bet")]
     ZeroBet,
+    #[msg("Bet below minimum")]
+    BetTooSmall,
     #[msg("Overflow")]
     Overflow,
     #[msg("Too early")]
@@ -568,7 +3172,7 @@ pub enum MarketError {
     NotResolved,
     #[msg("Already claimed")]
     AlreadyClaimed,
-    #[msg("No winnings")]
+    #[msg("Position has nothing to claim")]
     NoWinnings,
     #[msg("Too early to cancel")]
     TooEarlyToCancel,
@@ -582,5 +3186,115 @@ pub enum MarketError {
     Unauthorized,
     #[msg("No fees")]
     NoFees,
+    #[msg("Invalid liquidity parameter")]
+    InvalidLiquidity,
+    #[msg("Invalid dispute period")]
+    InvalidDisputePeriod,
+    #[msg("Dispute window closed")]
+    DisputeWindowClosed,
+    #[msg("Claim too early")]
+    ClaimTooEarly,
+    #[msg("Invalid outcome")]
+    InvalidOutcome,
+    #[msg("Invalid minimum confidence")]
+    InvalidMinConfidence,
+    #[msg("Oracle confidence below market minimum")]
+    ConfidenceTooLow,
+    #[msg("Invalid treasury")]
+    InvalidTreasury,
+    #[msg("Question type incompatible with market")]
+    IncompatibleQuestionType,
+    #[msg("Oracle question type does not match market")]
+    QuestionTypeMismatch,
+    #[msg("Market account does not match market id")]
+    MarketMismatch,
+    #[msg("Invalid fee")]
+    InvalidFee,
+    #[msg("Invalid batch")]
+    InvalidBatch,
+    #[msg("Invalid batch size")]
+    InvalidBatchSize,
+    #[msg("Oracle answer is for a different question")]
+    OracleQuestionMismatch,
+    #[msg("Market has bets")]
+    MarketNotEmpty,
+    #[msg("Oracle question already bound to a market")]
+    QuestionAlreadyBound,
+    #[msg("Fee exceeds maximum")]
+    FeeTooHigh,
+    #[msg("Time-weighted payouts require parimutuel pricing")]
+    InvalidPayoutMode,
+    #[msg("Claim deadline has passed")]
+    ClaimDeadlinePassed,
+    #[msg("Claim deadline has not passed")]
+    ClaimDeadlineNotPassed,
+    #[msg("Nothing to sweep")]
+    NothingToSweep,
+    #[msg("Token accounts required for the stake mint")]
+    MissingTokenAccounts,
+    #[msg("Not supported when staking an SPL mint")]
+    TokenModeUnsupported,
+    #[msg("Bet would exceed the market's pool cap")]
+    PoolCapExceeded,
+    #[msg("Market cannot move to that status")]
+    InvalidStatusTransition,
+    #[msg("Invalid max answer age")]
+    InvalidAnswerAge,
+    #[msg("Oracle answer is too old to resolve this market")]
+    AnswerTooStale,
+    #[msg("Bet exceeds the spender allowance")]
+    AllowanceExceeded,
+    #[msg("Bettor cannot refer themselves")]
+    InvalidReferrer,
+    #[msg("Referral balance account required for a referred bet")]
+    MissingReferralAccount,
+    #[msg("Market only allows betting on one side")]
+    SingleSideOnly,
+    #[msg("Invalid parlay share")]
+    InvalidParlayShare,
+    #[msg("No parlay share to release")]
+    NoParlayShare,
+    #[msg("Open time must be before the market closes")]
+    InvalidOpenTime,
+    #[msg("Market does not accept bets yet")]
+    OpensLater,
+    #[msg("Creator cannot cover the creation deposit")]
+    InsufficientDeposit,
+    #[msg("No creation deposit to return")]
+    NoDeposit,
+    #[msg("Max question length must be between 1 and MAX_QUESTION_LEN")]
+    InvalidMaxQuestionLen,
+    #[msg("Receipt accounts are required for this position")]
+    MissingReceiptAccounts,
+    #[msg("Receipt mint does not match the position")]
+    ReceiptMismatch,
+    #[msg("Winner does not hold the position's receipt")]
+    ReceiptNotHeld,
+    #[msg("Oracle account is not an initialized account of the oracle program")]
+    OracleAccountInvalid,
+    #[msg("Bet on the losing side")]
+    BetOnLosingSide,
+    #[msg("Nobody bet on the winning side")]
+    EmptyWinningPool,
+    #[msg("Payout rounds down to zero")]
+    PayoutRoundsToZero,
+    #[msg("Top-up amount must be greater than zero")]
+    InvalidTopUpAmount,
+    #[msg("Oracle question is not answered yet, or its answer is disputed")]
+    OracleQuestionNotFinal,
+    #[msg("Dispute window is still open")]
+    DisputeWindowOpen,
+    #[msg("Market is already finalized")]
+    MarketAlreadyFinalized,
+    #[msg("Market is not finalized")]
+    MarketNotFinalized,
+    #[msg("Parlay share cannot change after the first bet")]
+    ParlayShareLocked,
+    #[msg("Parlay share has not been released or returned yet")]
+    ParlayPending,
+    #[msg("Only the authority may return a parlay share inside the release window")]
+    ParlayReleaseWindowOpen,
+    #[msg("Bet exceeds the bettor's balance")]
+    InsufficientBalance,
 }
 
@@ -0,0 +1,187 @@
+//! Fixed-point helpers for the LMSR (logarithmic market scoring rule) pricing mode.
+//!
+//! The binary cost function `C(q) = b * ln(e^(q_yes/b) + e^(q_no/b))` is evaluated in the
+//! numerically stable form `max(q) + b * ln(1 + e^(-|q_yes - q_no| / b))`, so `exp` is only
+//! ever taken of non-positive values. All intermediate values are `i128` fixed-point numbers
+//! scaled by `SCALE`; share quantities and costs are plain lamports.
+
+/// Fixed-point scale (1e12)
+pub const SCALE: i128 = 1_000_000_000_000;
+/// ln(2) in fixed point
+pub const LN_2: i128 = 693_147_180_560;
+
+/// Beyond this exponent e^(-x) is below the fixed-point resolution
+const EXP_NEG_CUTOFF: i128 = 30 * SCALE;
+
+/// e^(-x) for x >= 0
+pub fn exp_neg(x: i128) -> Option<i128> {
+    if x < 0 {
+        return None;
+    }
+    if x >= EXP_NEG_CUTOFF {
+        return Some(0);
+    }
+
+    // e^(-x) = e^(-r) / 2^k with x = k*ln(2) + r, 0 <= r < ln(2)
+    let k = x / LN_2;
+    let r = x - k * LN_2;
+
+    let mut sum = SCALE;
+    let mut term = SCALE;
+    let mut n: i128 = 1;
+    while term != 0 {
+        term = -term * r / (n * SCALE);
+        sum += term;
+        n += 1;
+    }
+
+    Some(sum >> k)
+}
+
+/// ln(x) for x > 0
+pub fn ln(x: i128) -> Option<i128> {
+    if x <= 0 {
+        return None;
+    }
+
+    // ln(x) = k*ln(2) + ln(m) with x = 2^k * m, 1 <= m < 2
+    let mut k: i128 = 0;
+    let mut m = x;
+    while m >= 2 * SCALE {
+        m /= 2;
+        k += 1;
+    }
+    while m < SCALE {
+        m *= 2;
+        k -= 1;
+    }
+
+    // ln(m) = 2 * atanh(z) with z = (m - 1) / (m + 1), 0 <= z < 1/3
+    let z = (m - SCALE) * SCALE / (m + SCALE);
+    let z2 = z * z / SCALE;
+    let mut sum = 0;
+    let mut term = z;
+    let mut n: i128 = 1;
+    while term != 0 {
+        sum += term / n;
+        term = term * z2 / SCALE;
+        n += 2;
+    }
+
+    Some(k * LN_2 + 2 * sum)
+}
+
+/// ln(1 + e^x)
+fn softplus(x: i128) -> Option<i128> {
+    let tail = ln(SCALE + exp_neg(x.abs())?)?;
+    Some(x.max(0) + tail)
+}
+
+/// Inverse of `softplus`: ln(e^y - 1) for y > 0
+fn inv_softplus(y: i128) -> Option<i128> {
+    let e = exp_neg(y)?;
+    if e >= SCALE {
+        return None;
+    }
+    Some(y + ln(SCALE - e)?)
+}
+
+/// Market maker cost `C(q_yes, q_no)` in lamports, rounded up
+pub fn cost(q_yes: u64, q_no: u64, b: u64) -> Option<u64> {
+    if b == 0 {
+        return None;
+    }
+    let diff = (q_yes.abs_diff(q_no) as i128).checked_mul(SCALE)? / b as i128;
+    let tail = ln(SCALE + exp_neg(diff)?)?;
+    let scaled = tail.checked_mul(b as i128)?;
+    let tail_lamports = (scaled + SCALE - 1) / SCALE;
+    u64::try_from(q_yes.max(q_no) as i128 + tail_lamports).ok()
+}
+
+/// Lamports the market creator must deposit to cover the maximum LMSR loss of `b * ln(2)`
+pub fn subsidy(b: u64) -> Option<u64> {
+    cost(0, 0, b)
+}
+
+/// Number of shares on one side that `amount` lamports buys, rounded down.
+///
+/// Solves `C(q_buy + shares, q_other) - C(q_buy, q_other) = amount` in closed form:
+/// with `d = (q_buy - q_other) / b`, the new difference is `inv_softplus(softplus(d) + amount / b)`.
+pub fn shares_for_cost(q_buy: u64, q_other: u64, b: u64, amount: u64) -> Option<u64> {
+    if b == 0 {
+        return None;
+    }
+    let b = b as i128;
+    let d = (q_buy as i128 - q_other as i128).checked_mul(SCALE)? / b;
+    let y = softplus(d)?.checked_add((amount as i128).checked_mul(SCALE)? / b)?;
+    let d_new = inv_softplus(y)?;
+    let shares = d_new.checked_sub(d)?.checked_mul(b)? / SCALE;
+    u64::try_from(shares).ok()
+}
+
+/// Instantaneous price of the `q_buy` side in basis points: `1 / (1 + e^((q_other - q_buy) / b))`
+pub fn price_bps(q_buy: u64, q_other: u64, b: u64) -> Option<u64> {
+    if b == 0 {
+        return None;
+    }
+    let d = (q_buy as i128 - q_other as i128).checked_mul(SCALE)? / b as i128;
+    let e = exp_neg(d.abs())?;
+    let p = if d >= 0 {
+        SCALE * SCALE / (SCALE + e)
+    } else {
+        e * SCALE / (SCALE + e)
+    };
+    u64::try_from(p * 10_000 / SCALE).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const B: u64 = 1_000_000;
+
+    #[test]
+    fn exp_and_ln_hit_known_points() {
+        assert_eq!(exp_neg(0), Some(SCALE));
+        assert!((exp_neg(LN_2).unwrap() - SCALE / 2).abs() <= 1);
+        assert_eq!(exp_neg(EXP_NEG_CUTOFF), Some(0));
+        assert_eq!(exp_neg(-1), None);
+
+        assert_eq!(ln(SCALE), Some(0));
+        assert!((ln(2 * SCALE).unwrap() - LN_2).abs() <= 1);
+        assert_eq!(ln(0), None);
+    }
+
+    #[test]
+    fn cost_matches_closed_form() {
+        // C(0, 0) = b * ln(2), rounded up
+        assert_eq!(cost(0, 0, B), Some(693_148));
+        assert_eq!(subsidy(B), Some(693_148));
+        // C(b, 0) = b * (1 + ln(1 + e^-1)) = 1_313_261.69
+        assert_eq!(cost(B, 0, B), Some(1_313_262));
+        assert_eq!(cost(0, B, B), cost(B, 0, B));
+        assert_eq!(cost(0, 0, 0), None);
+    }
+
+    #[test]
+    fn price_matches_logistic() {
+        assert_eq!(price_bps(0, 0, B), Some(5_000));
+        assert_eq!(price_bps(5 * B, 5 * B, B), Some(5_000));
+        // 1 / (1 + e^-1) = 0.731058..., and its complement 0.268941...
+        assert_eq!(price_bps(B, 0, B), Some(7_310));
+        assert_eq!(price_bps(0, B, B), Some(2_689));
+        assert_eq!(price_bps(0, 0, 0), None);
+    }
+
+    #[test]
+    fn shares_never_cost_more_than_paid() {
+        for (q_yes, q_no, amount) in [(0, 0, 100_000), (B, 0, 250_000), (0, 3 * B, 1_000_000)] {
+            let shares = shares_for_cost(q_yes, q_no, B, amount).unwrap();
+            assert!(shares > 0);
+            let paid = cost(q_yes + shares, q_no, B).unwrap() - cost(q_yes, q_no, B).unwrap();
+            // Rounding shares down and cost up keeps the trade within a lamport or two of the stake
+            assert!(paid <= amount + 2, "paid {paid} for {amount}");
+            assert!(paid + 2 >= amount, "paid {paid} for {amount}");
+        }
+    }
+}
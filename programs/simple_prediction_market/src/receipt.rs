@@ -0,0 +1,110 @@
+//! NFT receipts for positions, minted when `position_receipts` is enabled on the market state.
+//!
+//! A bettor's first `take_position` on a market mints a receipt: a 0-decimal mint at the
+//! `[b"receipt", market_id, bettor]` PDA with a supply of one, held in the bettor's associated
+//! token account and described by a token metadata account. The `market_state` PDA is mint and
+//! update authority. Receipts trade freely and the claim travels with them: a receipted position
+//! pays out, through `claim_winnings` or `claim_refund`, to whoever burns the receipt from their
+//! account, and the batch instructions skip it. Positions opened before receipts were enabled,
+//! and delegated bets, carry no receipt and pay only their bettor.
+
+use anchor_lang::prelude::*;
+use anchor_spl::metadata::mpl_token_metadata::types::DataV2;
+use anchor_spl::metadata::{self, CreateMetadataAccountsV3};
+use anchor_spl::token::{self, Burn, Mint, MintTo, Token, TokenAccount};
+
+use crate::{MarketError, TakePosition};
+
+pub const RECEIPT_SYMBOL: &str = "T9POS";
+
+/// Create the receipt's metadata and mint the single receipt token to the bettor
+pub fn mint(accounts: &TakePosition, market_id: u64) -> Result<()> {
+    let (
+        Some(token_program),
+        Some(metadata_program),
+        Some(receipt_mint),
+        Some(receipt_metadata),
+        Some(to),
+        Some(rent),
+    ) = (
+        &accounts.token_program,
+        &accounts.token_metadata_program,
+        &accounts.receipt_mint,
+        &accounts.receipt_metadata,
+        &accounts.bettor_receipt_account,
+        &accounts.rent,
+    )
+    else {
+        return err!(MarketError::MissingReceiptAccounts);
+    };
+    let market_state = &accounts.market_state;
+
+    let bump = [market_state.bump];
+    let signer_seeds: &[&[u8]] = &[b"market_state", &bump];
+
+    metadata::create_metadata_accounts_v3(
+        CpiContext::new_with_signer(
+            metadata_program.to_account_info(),
+            CreateMetadataAccountsV3 {
+                metadata: receipt_metadata.to_account_info(),
+                mint: receipt_mint.to_account_info(),
+                mint_authority: market_state.to_account_info(),
+                payer: accounts.bettor.to_account_info(),
+                update_authority: market_state.to_account_info(),
+                system_program: accounts.system_program.to_account_info(),
+                rent: rent.to_account_info(),
+            },
+            &[signer_seeds],
+        ),
+        DataV2 {
+            name: format!("Trace9 position #{}", market_id),
+            symbol: RECEIPT_SYMBOL.to_string(),
+            uri: String::new(),
+            seller_fee_basis_points: 0,
+            creators: None,
+            collection: None,
+            uses: None,
+        },
+        false,
+        true,
+        None,
+    )?;
+
+    token::mint_to(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            MintTo {
+                mint: receipt_mint.to_account_info(),
+                to: to.to_account_info(),
+                authority: market_state.to_account_info(),
+            },
+            &[signer_seeds],
+        ),
+        1,
+    )
+}
+
+/// Burn the receipt out of its holder's account, failing unless the holder still has it
+pub fn burn<'info>(
+    token_program: &Option<Program<'info, Token>>,
+    receipt_mint: &Option<Account<'info, Mint>>,
+    from: &Option<Account<'info, TokenAccount>>,
+    holder: &Signer<'info>,
+) -> Result<()> {
+    let (Some(token_program), Some(receipt_mint), Some(from)) = (token_program, receipt_mint, from) else {
+        return err!(MarketError::MissingReceiptAccounts);
+    };
+    require!(from.amount == 1, MarketError::ReceiptNotHeld);
+
+    token::burn(
+        CpiContext::new(
+            token_program.to_account_info(),
+            Burn {
+                mint: receipt_mint.to_account_info(),
+                from: from.to_account_info(),
+                authority: holder.to_account_info(),
+            },
+        ),
+        1,
+    )
+}
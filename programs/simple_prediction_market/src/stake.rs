@@ -0,0 +1,125 @@
+//! SPL token stakes for programs initialized with a `stake_mint`.
+//!
+//! In token mode every stake, fee and payout sits in a single vault: the stake mint's
+//! associated token account owned by the `market_state` PDA. Markets keep the same pool
+//! accounting as in native-SOL mode; only the movement of funds differs. LMSR markets and
+//! unclaimed sweeps stay native-SOL only, since the shared vault does not track per-market
+//! balances. Delegated bets draw straight from the owner's token account, with the
+//! spender allowance PDA approved as its SPL delegate.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Approve, Token, TokenAccount, Transfer};
+
+use crate::{MarketError, MarketState, SpenderAllowance};
+
+/// Move `amount` of the stake mint from a user's token account into the vault
+pub fn deposit<'info>(
+    token_program: &Option<Program<'info, Token>>,
+    from: &Option<Account<'info, TokenAccount>>,
+    vault: &Option<Account<'info, TokenAccount>>,
+    authority: &Signer<'info>,
+    amount: u64,
+) -> Result<()> {
+    let (Some(token_program), Some(from), Some(vault)) = (token_program, from, vault) else {
+        return err!(MarketError::MissingTokenAccounts);
+    };
+
+    token::transfer(
+        CpiContext::new(
+            token_program.to_account_info(),
+            Transfer {
+                from: from.to_account_info(),
+                to: vault.to_account_info(),
+                authority: authority.to_account_info(),
+            },
+        ),
+        amount,
+    )
+}
+
+/// Pay `amount` of the stake mint out of the vault, signed by the market state PDA
+pub fn withdraw<'info>(
+    market_state: &Account<'info, MarketState>,
+    token_program: &Option<Program<'info, Token>>,
+    vault: &Option<Account<'info, TokenAccount>>,
+    to: &Option<Account<'info, TokenAccount>>,
+    amount: u64,
+) -> Result<()> {
+    let (Some(token_program), Some(vault), Some(to)) = (token_program, vault, to) else {
+        return err!(MarketError::MissingTokenAccounts);
+    };
+
+    let bump = [market_state.bump];
+    let signer_seeds: &[&[u8]] = &[b"market_state", &bump];
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            Transfer {
+                from: vault.to_account_info(),
+                to: to.to_account_info(),
+                authority: market_state.to_account_info(),
+            },
+            &[signer_seeds],
+        ),
+        amount,
+    )
+}
+
+/// Approve a spender allowance PDA as SPL delegate over `amount` of the owner's stake mint
+pub fn approve<'info>(
+    token_program: &Option<Program<'info, Token>>,
+    from: &Option<Account<'info, TokenAccount>>,
+    spender_allowance: &Account<'info, SpenderAllowance>,
+    owner: &Signer<'info>,
+    amount: u64,
+) -> Result<()> {
+    let (Some(token_program), Some(from)) = (token_program, from) else {
+        return err!(MarketError::MissingTokenAccounts);
+    };
+
+    token::approve(
+        CpiContext::new(
+            token_program.to_account_info(),
+            Approve {
+                to: from.to_account_info(),
+                delegate: spender_allowance.to_account_info(),
+                authority: owner.to_account_info(),
+            },
+        ),
+        amount,
+    )
+}
+
+/// Move `amount` of the stake mint from an owner's token account into the vault,
+/// signed by the spender allowance PDA acting as delegate
+pub fn deposit_delegated<'info>(
+    token_program: &Option<Program<'info, Token>>,
+    from: &Option<Account<'info, TokenAccount>>,
+    vault: &Option<Account<'info, TokenAccount>>,
+    spender_allowance: &Account<'info, SpenderAllowance>,
+    amount: u64,
+) -> Result<()> {
+    let (Some(token_program), Some(from), Some(vault)) = (token_program, from, vault) else {
+        return err!(MarketError::MissingTokenAccounts);
+    };
+
+    let bump = [spender_allowance.bump];
+    let signer_seeds: &[&[u8]] = &[
+        b"allowance",
+        spender_allowance.owner.as_ref(),
+        spender_allowance.spender.as_ref(),
+        &bump,
+    ];
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            Transfer {
+                from: from.to_account_info(),
+                to: vault.to_account_info(),
+                authority: spender_allowance.to_account_info(),
+            },
+            &[signer_seeds],
+        ),
+        amount,
+    )
+}
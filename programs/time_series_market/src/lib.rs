@@ -2,6 +2,11 @@ use anchor_lang::prelude::*;
 
 declare_id!("timeSeriM3mP9vK8JqF2nH5xY7wD4bC6eA8g");
 
+pub const EMERGENCY_CANCEL_GRACE_PERIOD: i64 = 30 * 24 * 60 * 60; // 30 days in seconds
+pub const MAX_FEE_BPS: u16 = 2_000; // 20% cap on the market fee
+pub const DEFAULT_MIN_PERIOD_GAP: i64 = 60 * 60; // 1 hour in seconds
+pub const MAX_QUESTION_LEN: usize = 1_000; // Ceiling for max_question_len
+
 #[program]
 pub mod time_series_market {
     use super::*;
@@ -10,12 +15,25 @@ pub mod time_series_market {
         ctx: Context<Initialize>,
         oracle_program: Pubkey,
         fee_percentage: u16,
+        min_bet: u64,
+        max_question_len: u16, // Longest question accepted, at most MAX_QUESTION_LEN
     ) -> Result<()> {
+        require!(fee_percentage <= MAX_FEE_BPS, MarketError::FeeTooHigh);
+        require!(
+            max_question_len > 0 && max_question_len as usize <= MAX_QUESTION_LEN,
+            MarketError::InvalidMaxQuestionLen
+        );
+
         let market_state = &mut ctx.accounts.market_state;
         market_state.authority = ctx.accounts.authority.key();
         market_state.oracle_program = oracle_program;
         market_state.market_counter = 0;
         market_state.fee_percentage = fee_percentage;
+        market_state.accumulated_fees = 0;
+        market_state.min_bet = min_bet;
+        market_state.min_period_gap_secs = DEFAULT_MIN_PERIOD_GAP;
+        market_state.max_question_len = max_question_len;
+        market_state.treasury = ctx.accounts.authority.key();
         market_state.bump = ctx.bumps.market_state;
         Ok(())
     }
@@ -24,24 +42,60 @@ pub mod time_series_market {
         ctx: Context<CreateMarket>,
         question: String,
         deadlines: Vec<i64>,
+        close_time: i64, // Betting cutoff, 0 = first period deadline
+        oracle_question_ids: Vec<u64>, // trace9 question that resolves each period
+        min_confidence: u8, // Minimum oracle confidence (1-100) required to resolve a period
+        question_type: QuestionType, // Oracle question type, must yield a numeric answer
+        ordered: bool, // Require periods to be resolved in sequence
+        resolver: Option<Pubkey>, // Only this key may resolve, None = anyone
     ) -> Result<u64> {
         require!(
-            question.len() > 0 && question.len() <= 500,
+            question.len() > 0
+                && question.len() <= ctx.accounts.market_state.max_question_len as usize,
             MarketError::InvalidQuestion
         );
+        require!(
+            question_type == QuestionType::Numeric || question_type == QuestionType::Price,
+            MarketError::IncompatibleQuestionType
+        );
+        require!(
+            min_confidence > 0 && min_confidence <= 100,
+            MarketError::InvalidMinConfidence
+        );
         require!(
             deadlines.len() >= 2 && deadlines.len() <= 12,
             MarketError::InvalidPeriodCount
         );
+        require!(
+            oracle_question_ids.len() == deadlines.len(),
+            MarketError::InvalidPeriodCount
+        );
 
-        // Verify deadlines are in ascending order
+        require!(
+            deadlines[0] > Clock::get()?.unix_timestamp,
+            MarketError::PeriodInPast
+        );
+
+        // Verify deadlines are in ascending order and far enough apart to answer each period
+        let min_period_gap_secs = ctx.accounts.market_state.min_period_gap_secs;
         for i in 1..deadlines.len() {
             require!(
                 deadlines[i] > deadlines[i - 1],
                 MarketError::DeadlinesNotAscending
             );
+            require!(
+                deadlines[i] - deadlines[i - 1] >= min_period_gap_secs,
+                MarketError::PeriodGapTooSmall
+            );
         }
 
+        // Betting stops by the first deadline at the latest, before any period can resolve
+        let close_time = if close_time == 0 { deadlines[0] } else { close_time };
+        require!(
+            close_time > Clock::get()?.unix_timestamp && close_time <= deadlines[0],
+            MarketError::InvalidCloseTime
+        );
+
         let market_state = &mut ctx.accounts.market_state;
         let market_id = market_state.market_counter;
         let market_account = &mut ctx.accounts.market_account;
@@ -52,14 +106,20 @@ pub mod time_series_market {
         market_account.failure_pool = 0;
         market_account.total_fees = 0;
         market_account.created_at = Clock::get()?.unix_timestamp;
+        market_account.close_time = close_time;
         market_account.all_resolved = false;
         market_account.all_success = false;
+        market_account.min_confidence = min_confidence;
+        market_account.canceled = false;
+        market_account.question_type = question_type;
+        market_account.ordered = ordered;
+        market_account.resolver = resolver;
 
         // Initialize periods
-        for deadline in deadlines.iter() {
+        for (deadline, question_id) in deadlines.iter().zip(oracle_question_ids.iter()) {
             market_account.periods.push(TimePeriod {
                 deadline: *deadline,
-                question_id: 0, // Will be set when oracle question is asked
+                question_id: *question_id,
                 result: 0,
                 resolved: false,
             });
@@ -71,6 +131,12 @@ pub mod time_series_market {
             market_id,
             question,
             period_count: deadlines.len() as u8,
+            close_time,
+            min_confidence,
+            oracle_question_ids,
+            question_type,
+            ordered,
+            resolver,
         });
 
         Ok(market_id)
@@ -82,10 +148,18 @@ pub mod time_series_market {
         predict_all_success: bool,
     ) -> Result<()> {
         let market_account = &mut ctx.accounts.market_account;
-        require!(!market_account.all_resolved, MarketError::MarketResolved);
+        market_account.require_unsettled()?;
+        require!(
+            Clock::get()?.unix_timestamp < market_account.close_time,
+            MarketError::MarketExpired
+        );
 
         let bet_amount = ctx.accounts.bettor.lamports();
         require!(bet_amount > 0, MarketError::ZeroBet);
+        require!(
+            bet_amount >= ctx.accounts.market_state.min_bet,
+            MarketError::BetTooSmall
+        );
 
         let market_state = &ctx.accounts.market_state;
         let fee = bet_amount
@@ -143,11 +217,24 @@ pub mod time_series_market {
         period_index: u8,
     ) -> Result<()> {
         let market_account = &mut ctx.accounts.market_account;
+        market_account.require_unsettled()?;
+        require!(
+            market_account.resolver.map_or(true, |resolver| resolver == ctx.accounts.resolver.key()),
+            MarketError::NotResolver
+        );
         require!(
             (period_index as usize) < market_account.periods.len(),
             MarketError::InvalidPeriod
         );
+        if market_account.ordered && period_index > 0 {
+            require!(
+                market_account.periods[period_index as usize - 1].resolved,
+                MarketError::OutOfOrderResolution
+            );
+        }
 
+        let min_confidence = market_account.min_confidence;
+        let question_type = market_account.question_type;
         let period = &mut market_account.periods[period_index as usize];
         require!(!period.resolved, MarketError::PeriodAlreadyResolved);
         require!(
@@ -155,8 +242,43 @@ pub mod time_series_market {
             MarketError::TooEarly
         );
 
-        let numeric_answer = ctx.accounts.oracle_answer.numeric_answer;
+        let oracle_program = ctx.accounts.market_state.oracle_program;
+        let question: QuestionAccount = load_oracle_account(&ctx.accounts.oracle_question, &oracle_program)?;
+        require!(
+            question.question_id == period.question_id,
+            MarketError::OracleQuestionMismatch
+        );
+        // The question must be settled on the oracle side, not still open or under dispute
+        require!(
+            matches!(question.status, AnswerStatus::Answered | AnswerStatus::Finalized),
+            MarketError::OracleQuestionNotFinal
+        );
+        let answer: AnswerAccount = load_oracle_account(&ctx.accounts.oracle_answer, &oracle_program)?;
+        require!(
+            answer.question_id == period.question_id,
+            MarketError::OracleQuestionMismatch
+        );
+        require!(
+            question.question_type == question_type,
+            MarketError::QuestionTypeMismatch
+        );
+
+        // An inconclusive answer leaves the period unsettleable, which voids the whole series
+        if answer.inconclusive {
+            market_account.canceled = true;
+
+            emit!(MarketCanceled {
+                market_id,
+                period_index,
+            });
+
+            return Ok(());
+        }
+
+        let numeric_answer = answer.numeric_answer;
+        let confidence_score = answer.confidence_score;
         require!(numeric_answer > 0, MarketError::OracleNotAnswered);
+        require!(confidence_score >= min_confidence, MarketError::ConfidenceTooLow);
 
         period.result = numeric_answer;
         period.resolved = true;
@@ -165,11 +287,26 @@ pub mod time_series_market {
             market_id,
             period_index,
             result: numeric_answer,
+            bool_answer: answer.bool_answer,
+            confidence_score,
         });
 
         // Check if all periods resolved
         check_all_resolved(&mut market_account)?;
 
+        // Once the last period settles, sweep the entry fees out of the pool so they become withdrawable
+        if market_account.all_resolved && market_account.total_fees > 0 {
+            let fees = market_account.total_fees;
+            let market_state = &mut ctx.accounts.market_state;
+            market_state.accumulated_fees = market_state
+                .accumulated_fees
+                .checked_add(fees)
+                .ok_or(MarketError::Overflow)?;
+
+            **ctx.accounts.market_account.to_account_info().try_borrow_mut_lamports()? -= fees;
+            **ctx.accounts.market_state.to_account_info().try_borrow_mut_lamports()? += fees;
+        }
+
         Ok(())
     }
 
@@ -201,54 +338,249 @@ pub mod time_series_market {
         Ok(())
     }
 
-    pub fn claim_winnings(ctx: Context<ClaimWinnings>, market_id: u64) -> Result<()> {
+    pub fn claim_winnings(ctx: Context<ClaimWinnings>, market_id: u64) -> Result<u64> {
         let market_account = &ctx.accounts.market_account;
+        require!(market_account.market_id == market_id, MarketError::MarketMismatch);
         require!(market_account.all_resolved, MarketError::NotAllResolved);
 
         let position = &mut ctx.accounts.position;
         require!(!position.claimed, MarketError::AlreadyClaimed);
 
-        let winning_pool = if market_account.all_success {
-            market_account.success_pool
-        } else {
-            market_account.failure_pool
-        };
-        let losing_pool = if market_account.all_success {
-            market_account.failure_pool
-        } else {
-            market_account.success_pool
-        };
-        let user_winning_amount = if market_account.all_success {
-            position.success_amount
+        let payout = market_account.winnings_for(position)?;
+        let payout = capped_payout(&market_account.to_account_info(), payout)?;
+        let cost_basis = market_account.winning_stake(position);
+
+        position.claimed = true;
+
+        **ctx.accounts.market_account.to_account_info().try_borrow_mut_lamports()? -= payout;
+        **ctx.accounts.winner.to_account_info().try_borrow_mut_lamports()? += payout;
+
+        emit!(WinningsClaimed {
+            market_id,
+            user: ctx.accounts.winner.key(),
+            payout,
+        });
+
+        emit!(ClaimRecord {
+            market_id,
+            user: ctx.accounts.winner.key(),
+            cost_basis,
+            payout,
+        });
+
+        Ok(payout)
+    }
+
+    pub fn quote_payout(ctx: Context<QuotePayout>, market_id: u64) -> Result<u64> {
+        let market_account = &ctx.accounts.market_account;
+        require!(market_account.market_id == market_id, MarketError::MarketMismatch);
+        require!(market_account.all_resolved, MarketError::NotAllResolved);
+        require!(!ctx.accounts.position.claimed, MarketError::AlreadyClaimed);
+
+        market_account.winnings_for(&ctx.accounts.position)
+    }
+
+    pub fn cancel_market(
+        ctx: Context<CancelMarket>,
+        market_id: u64,
+        period_index: u8,
+    ) -> Result<()> {
+        let market_account = &mut ctx.accounts.market_account;
+        market_account.require_unsettled()?;
+        require!(
+            (period_index as usize) < market_account.periods.len(),
+            MarketError::InvalidPeriod
+        );
+
+        let period = &market_account.periods[period_index as usize];
+        require!(!period.resolved, MarketError::PeriodAlreadyResolved);
+
+        let oracle_program = ctx.accounts.market_state.oracle_program;
+        let question: QuestionAccount = load_oracle_account(&ctx.accounts.oracle_question, &oracle_program)?;
+        require!(
+            question.question_id == period.question_id,
+            MarketError::OracleQuestionMismatch
+        );
+
+        // A single period that can't be resolved voids the whole series; unanswered periods wait
+        // out the refund period, low-confidence answers cancel right away
+        if matches!(question.status, AnswerStatus::Pending | AnswerStatus::Expired) {
+            let refund_period: i64 = 7 * 24 * 60 * 60; // 7 days
+            require!(
+                Clock::get()?.unix_timestamp >= period.deadline + refund_period,
+                MarketError::TooEarlyToCancel
+            );
         } else {
-            position.failure_amount
-        };
+            let answer: AnswerAccount = load_oracle_account(&ctx.accounts.oracle_answer, &oracle_program)?;
+            require!(
+                answer.confidence_score < market_account.min_confidence,
+                MarketError::AlreadyAnswered
+            );
+            require!(
+                Clock::get()?.unix_timestamp >= period.deadline,
+                MarketError::TooEarly
+            );
+        }
 
-        require!(user_winning_amount > 0, MarketError::NotWinner);
-        require!(winning_pool > 0, MarketError::NoWinnings);
+        market_account.canceled = true;
 
-        let payout = user_winning_amount
-            .checked_add(
-                user_winning_amount
-                    .checked_mul(losing_pool)
-                    .and_then(|x| x.checked_div(winning_pool))
+        emit!(MarketCanceled {
+            market_id,
+            period_index,
+        });
+
+        Ok(())
+    }
+
+    pub fn emergency_cancel(ctx: Context<EmergencyCancel>, market_id: u64) -> Result<()> {
+        let market_account = &mut ctx.accounts.market_account;
+        market_account.require_unsettled()?;
+
+        // The series resolves at its last period's deadline
+        let resolution_time = market_account
+            .periods
+            .last()
+            .map(|period| period.deadline)
+            .ok_or(MarketError::InvalidPeriod)?;
+        require!(
+            Clock::get()?.unix_timestamp >= resolution_time + EMERGENCY_CANCEL_GRACE_PERIOD,
+            MarketError::TooEarlyToCancel
+        );
+
+        market_account.canceled = true;
+
+        emit!(MarketEmergencyCanceled { market_id });
+
+        Ok(())
+    }
+
+    pub fn claim_refund(ctx: Context<ClaimRefund>, market_id: u64) -> Result<u64> {
+        require!(ctx.accounts.market_account.canceled, MarketError::NotCanceled);
+
+        let position = &mut ctx.accounts.position;
+        require!(!position.claimed, MarketError::AlreadyClaimed);
+
+        let stake = position
+            .success_amount
+            .checked_add(position.failure_amount)
+            .ok_or(MarketError::Overflow)?;
+        require!(stake > 0, MarketError::NoPosition);
+
+        // Fees are never collected on a canceled market, so refund the gross stake pro rata;
+        // widen before multiplying so large pools cannot overflow ahead of the division
+        let market_account = &ctx.accounts.market_account;
+        let total_pool = (market_account.success_pool as u128)
+            .checked_add(market_account.failure_pool as u128)
+            .ok_or(MarketError::Overflow)?;
+        let refund_amount = (stake as u128)
+            .checked_mul(
+                total_pool
+                    .checked_add(market_account.total_fees as u128)
                     .ok_or(MarketError::Overflow)?,
             )
+            .and_then(|x| x.checked_div(total_pool))
+            .and_then(|x| u64::try_from(x).ok())
             .ok_or(MarketError::Overflow)?;
 
+        // Mark the position claimed before any lamports leave the market
         position.claimed = true;
 
-        **ctx.accounts.market_account.to_account_info().try_borrow_mut_lamports()? -= payout;
-        **ctx.accounts.winner.to_account_info().try_borrow_mut_lamports()? += payout;
+        **ctx.accounts.market_account.to_account_info().try_borrow_mut_lamports()? -= refund_amount;
+        **ctx.accounts.refundee.to_account_info().try_borrow_mut_lamports()? += refund_amount;
 
-        emit!(WinningsClaimed {
+        emit!(RefundClaimed {
             market_id,
-            user: ctx.accounts.winner.key(),
-            payout,
+            user: ctx.accounts.refundee.key(),
+            amount: refund_amount,
+        });
+
+        Ok(refund_amount)
+    }
+
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>) -> Result<()> {
+        let market_state = &mut ctx.accounts.market_state;
+        let amount = market_state.accumulated_fees;
+        require!(amount > 0, MarketError::NoFees);
+
+        market_state.accumulated_fees = 0;
+
+        **ctx.accounts.market_state.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.treasury.to_account_info().try_borrow_mut_lamports()? += amount;
+
+        Ok(())
+    }
+
+    pub fn set_min_bet(ctx: Context<SetMinBet>, min_bet: u64) -> Result<()> {
+        let old_min_bet = ctx.accounts.market_state.min_bet;
+        ctx.accounts.market_state.min_bet = min_bet;
+
+        emit!(MinBetUpdated {
+            old_min_bet,
+            new_min_bet: min_bet,
         });
 
         Ok(())
     }
+
+    pub fn set_min_period_gap(ctx: Context<SetMinPeriodGap>, min_period_gap_secs: i64) -> Result<()> {
+        require!(min_period_gap_secs >= 0, MarketError::InvalidPeriodGap);
+
+        ctx.accounts.market_state.min_period_gap_secs = min_period_gap_secs;
+        Ok(())
+    }
+
+    pub fn set_fee_percentage(ctx: Context<SetFeePercentage>, fee_percentage: u16) -> Result<()> {
+        require!(fee_percentage <= MAX_FEE_BPS, MarketError::FeeTooHigh);
+
+        let old_fee_percentage = ctx.accounts.market_state.fee_percentage;
+        ctx.accounts.market_state.fee_percentage = fee_percentage;
+
+        emit!(FeePercentageUpdated {
+            old_fee_percentage,
+            new_fee_percentage: fee_percentage,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_treasury(ctx: Context<SetTreasury>, treasury: Pubkey) -> Result<()> {
+        let old_treasury = ctx.accounts.market_state.treasury;
+        ctx.accounts.market_state.treasury = treasury;
+
+        emit!(TreasuryUpdated {
+            old_treasury,
+            new_treasury: treasury,
+        });
+
+        Ok(())
+    }
+}
+
+/// Cap a payout at the lamports the market holds above rent exemption
+fn capped_payout(market_info: &AccountInfo, payout: u64) -> Result<u64> {
+    let rent_exempt = Rent::get()?.minimum_balance(market_info.data_len());
+    let available = market_info.lamports().saturating_sub(rent_exempt);
+    if payout > available {
+        msg!(
+            "Payout short by {} lamports, paying the remaining {}",
+            payout - available,
+            available
+        );
+        return Ok(available);
+    }
+    Ok(payout)
+}
+
+/// Read an oracle account as `T` once it is known to be owned by the oracle program and hold data,
+/// so a default or uninitialized account cannot pass; the discriminator check keeps a question
+/// account from standing in for an answer
+fn load_oracle_account<T: AccountDeserialize>(account: &AccountInfo, oracle_program: &Pubkey) -> Result<T> {
+    require!(
+        account.owner == oracle_program && !account.data_is_empty(),
+        MarketError::OracleAccountInvalid
+    );
+    let data = account.try_borrow_data()?;
+    T::try_deserialize(&mut &data[..]).map_err(|_| error!(MarketError::OracleAccountInvalid))
 }
 
 #[derive(Accounts)]
@@ -271,7 +603,7 @@ pub struct CreateMarket<'info> {
     #[account(
         init,
         payer = creator,
-        space = 8 + MarketAccount::LEN,
+        space = 8 + MarketAccount::size(market_state.max_question_len),
         seeds = [b"market", market_state.market_counter.to_le_bytes().as_ref()],
         bump
     )]
@@ -308,8 +640,18 @@ pub struct TakePosition<'info> {
 pub struct ResolvePeriod<'info> {
     #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
     pub market_account: Account<'info, MarketAccount>,
-    /// CHECK: Oracle answer from trace9 program
+    #[account(mut, seeds = [b"market_state"], bump = market_state.bump)]
+    pub market_state: Account<'info, MarketState>,
+    /// CHECK: Oracle question from trace9 program; owner and discriminator are checked on resolution
+    pub oracle_question: AccountInfo<'info>,
+    /// CHECK: The period's answer PDA under the oracle program; contents are checked on resolution
+    #[account(
+        seeds = [b"answer", market_account.period_question_id(period_index).to_le_bytes().as_ref()],
+        bump,
+        seeds::program = market_state.oracle_program
+    )]
     pub oracle_answer: AccountInfo<'info>,
+    pub resolver: Signer<'info>,
 }
 
 #[derive(Accounts)]
@@ -327,17 +669,150 @@ pub struct ClaimWinnings<'info> {
     pub winner: Signer<'info>,
 }
 
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct QuotePayout<'info> {
+    #[account(seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        seeds = [b"position", market_id.to_le_bytes().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64, period_index: u8)]
+pub struct CancelMarket<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(seeds = [b"market_state"], bump = market_state.bump)]
+    pub market_state: Account<'info, MarketState>,
+    /// CHECK: The period's question PDA under the oracle program; contents are checked on cancel
+    #[account(
+        seeds = [b"question", market_account.period_question_id(period_index).to_le_bytes().as_ref()],
+        bump,
+        seeds::program = market_state.oracle_program
+    )]
+    pub oracle_question: AccountInfo<'info>,
+    /// CHECK: The period's answer PDA under the oracle program; read only once the question is answered
+    #[account(
+        seeds = [b"answer", market_account.period_question_id(period_index).to_le_bytes().as_ref()],
+        bump,
+        seeds::program = market_state.oracle_program
+    )]
+    pub oracle_answer: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct EmergencyCancel<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_id: u64)]
+pub struct ClaimRefund<'info> {
+    #[account(mut, seeds = [b"market", market_id.to_le_bytes().as_ref()], bump)]
+    pub market_account: Account<'info, MarketAccount>,
+    #[account(
+        mut,
+        seeds = [b"position", market_id.to_le_bytes().as_ref(), refundee.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+    #[account(mut)]
+    pub refundee: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    #[account(
+        mut,
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
+    /// CHECK: Verified against the treasury stored on market state
+    #[account(mut, address = market_state.treasury @ MarketError::InvalidTreasury)]
+    pub treasury: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMinBet<'info> {
+    #[account(
+        mut,
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMinPeriodGap<'info> {
+    #[account(
+        mut,
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetTreasury<'info> {
+    #[account(
+        mut,
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetFeePercentage<'info> {
+    #[account(
+        mut,
+        seeds = [b"market_state"],
+        bump = market_state.bump,
+        has_one = authority @ MarketError::Unauthorized
+    )]
+    pub market_state: Account<'info, MarketState>,
+    pub authority: Signer<'info>,
+}
+
 #[account]
 pub struct MarketState {
     pub authority: Pubkey,
     pub oracle_program: Pubkey,
     pub market_counter: u64,
     pub fee_percentage: u16,
+    pub accumulated_fees: u64,
+    pub min_bet: u64,
+    pub min_period_gap_secs: i64,
+    pub max_question_len: u16,
+    pub treasury: Pubkey,
     pub bump: u8,
 }
 
 impl MarketState {
-    pub const LEN: usize = 32 + 32 + 8 + 2 + 1;
+    pub const LEN: usize = 32 + 32 + 8 + 2 + 8 + 8 + 8 + 2 + 32 + 1;
 }
 
 #[account]
@@ -349,12 +824,75 @@ pub struct MarketAccount {
     pub failure_pool: u64,
     pub total_fees: u64,
     pub created_at: i64,
+    pub close_time: i64,
     pub all_resolved: bool,
     pub all_success: bool,
+    pub min_confidence: u8,
+    pub canceled: bool,
+    pub question_type: QuestionType,
+    pub ordered: bool,
+    pub resolver: Option<Pubkey>,
 }
 
 impl MarketAccount {
-    pub const LEN: usize = 8 + (4 + 500) + (4 + 12 * TimePeriod::LEN) + 8 + 8 + 8 + 8 + 1 + 1;
+    /// Account size, question sized to the deployment's `max_question_len` cap
+    pub fn size(max_question_len: u16) -> usize {
+        8 + (4 + max_question_len as usize) + (4 + 12 * TimePeriod::LEN) + 8 + 8 + 8 + 8 + 8 + 1 + 1 + 1 + 1 + 1 + 1 + (1 + 32)
+    }
+
+    /// Oracle question behind a period, for deriving its oracle PDAs in account constraints;
+    /// an out-of-range index falls back to an id no period uses, so the seeds check rejects it
+    pub fn period_question_id(&self, period_index: u8) -> u64 {
+        self.periods
+            .get(period_index as usize)
+            .map_or(u64::MAX, |period| period.question_id)
+    }
+
+    /// Net stake a position has on the winning side, the cost basis of its claim
+    pub fn winning_stake(&self, position: &Position) -> u64 {
+        if self.all_success {
+            position.success_amount
+        } else {
+            position.failure_amount
+        }
+    }
+
+    /// Payout owed to a position on this resolved market
+    pub fn winnings_for(&self, position: &Position) -> Result<u64> {
+        let winning_pool = if self.all_success {
+            self.success_pool
+        } else {
+            self.failure_pool
+        };
+        let losing_pool = if self.all_success {
+            self.failure_pool
+        } else {
+            self.success_pool
+        };
+        let user_winning_amount = self.winning_stake(position);
+
+        require!(winning_pool > 0, MarketError::EmptyWinningPool);
+        require!(user_winning_amount > 0, MarketError::BetOnLosingSide);
+
+        // Widen before multiplying so large pools cannot overflow ahead of the division
+        let payout = user_winning_amount
+            .checked_add(
+                (user_winning_amount as u128)
+                    .checked_mul(losing_pool as u128)
+                    .and_then(|x| x.checked_div(winning_pool as u128))
+                    .and_then(|x| u64::try_from(x).ok())
+                    .ok_or(MarketError::Overflow)?,
+            )
+            .ok_or(MarketError::Overflow)?;
+        Ok(payout)
+    }
+
+    /// Resolution and cancellation are terminal and mutually exclusive: a market settles once
+    pub fn require_unsettled(&self) -> Result<()> {
+        require!(!self.all_resolved, MarketError::MarketResolved);
+        require!(!self.canceled, MarketError::MarketCanceled);
+        Ok(())
+    }
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
@@ -380,11 +918,71 @@ impl Position {
     pub const LEN: usize = 8 + 8 + 1;
 }
 
+// Oracle question structure (matches trace9 program); the name must match too, as it sets the discriminator
+#[account]
+pub struct QuestionAccount {
+    pub question_id: u64,
+    pub requester: Pubkey,
+    pub question_type: QuestionType,
+    pub question_hash: [u8; 32],
+    pub category: String,
+    pub bounty: u64,
+    pub timestamp: i64,
+    pub deadline: i64,
+    pub status: AnswerStatus,
+    pub refunded: bool,
+    pub revealed: bool,
+    pub incentive_opt_in: bool,
+    pub last_accrual_at: i64,
+    pub assigned_provider: Option<Pubkey>,
+}
+
+// Oracle answer structure (matches trace9 program)
+#[account]
+pub struct AnswerAccount {
+    pub question_id: u64,
+    pub provider: Pubkey,
+    pub confidence_score: u8,
+    pub bool_answer: bool,
+    pub numeric_answer: u64,
+    pub timestamp: i64,
+    pub challenge_deadline: i64,
+    pub revision: u8,
+    pub bond_locked: u64,
+    pub disputer: Pubkey,
+    pub inconclusive: bool,
+}
+
+// Oracle question type (matches trace9 program)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum QuestionType {
+    General,
+    Price,
+    YesNo,
+    Numeric,
+}
+
+// Oracle question status (matches trace9 program)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum AnswerStatus {
+    Pending,
+    Answered,
+    Disputed,
+    Finalized,
+    Expired,
+}
+
 #[event]
 pub struct MarketCreated {
     pub market_id: u64,
     pub question: String,
     pub period_count: u8,
+    pub close_time: i64,
+    pub min_confidence: u8,
+    pub oracle_question_ids: Vec<u64>,
+    pub question_type: QuestionType,
+    pub ordered: bool,
+    pub resolver: Option<Pubkey>,
 }
 
 #[event]
@@ -399,7 +997,9 @@ pub struct PositionTaken {
 pub struct PeriodResolved {
     pub market_id: u64,
     pub period_index: u8,
-    pub result: u64,
+    pub result: u64, // Oracle numeric answer
+    pub bool_answer: bool,
+    pub confidence_score: u8,
 }
 
 #[event]
@@ -408,6 +1008,24 @@ pub struct MarketResolved {
     pub all_success: bool,
 }
 
+#[event]
+pub struct MarketCanceled {
+    pub market_id: u64,
+    pub period_index: u8,
+}
+
+#[event]
+pub struct MarketEmergencyCanceled {
+    pub market_id: u64,
+}
+
+#[event]
+pub struct RefundClaimed {
+    pub market_id: u64,
+    pub user: Pubkey,
+    pub amount: u64,
+}
+
 #[event]
 pub struct WinningsClaimed {
     pub market_id: u64,
@@ -415,6 +1033,32 @@ pub struct WinningsClaimed {
     pub payout: u64,
 }
 
+#[event]
+pub struct ClaimRecord {
+    pub market_id: u64,
+    pub user: Pubkey,
+    pub cost_basis: u64, // Net stake on the paying side
+    pub payout: u64,
+}
+
+#[event]
+pub struct MinBetUpdated {
+    pub old_min_bet: u64,
+    pub new_min_bet: u64,
+}
+
+#[event]
+pub struct TreasuryUpdated {
+    pub old_treasury: Pubkey,
+    pub new_treasury: Pubkey,
+}
+
+#[event]
+pub struct FeePercentageUpdated {
+    pub old_fee_percentage: u16,
+    pub new_fee_percentage: u16,
+}
+
 #[error_code]
 pub enum MarketError {
     #[msg("Invalid question")]
@@ -427,8 +1071,12 @@ pub enum MarketError {
     MarketResolved,
     #[msg("Zero bet")]
     ZeroBet,
+    #[msg("Bet below minimum")]
+    BetTooSmall,
     #[msg("Overflow")]
     Overflow,
+    #[msg("Unauthorized")]
+    Unauthorized,
     #[msg("Invalid period")]
     InvalidPeriod,
     #[msg("Period already resolved")]
@@ -441,9 +1089,57 @@ pub enum MarketError {
     NotAllResolved,
     #[msg("Already claimed")]
     AlreadyClaimed,
-    #[msg("Not winner")]
-    NotWinner,
-    #[msg("No winnings")]
-    NoWinnings,
+    #[msg("Bet on the losing side")]
+    BetOnLosingSide,
+    #[msg("Nobody bet on the winning side")]
+    EmptyWinningPool,
+    #[msg("Invalid minimum confidence")]
+    InvalidMinConfidence,
+    #[msg("Oracle confidence below market minimum")]
+    ConfidenceTooLow,
+    #[msg("Market canceled")]
+    MarketCanceled,
+    #[msg("Too early to cancel")]
+    TooEarlyToCancel,
+    #[msg("Already answered")]
+    AlreadyAnswered,
+    #[msg("Not canceled")]
+    NotCanceled,
+    #[msg("No position")]
+    NoPosition,
+    #[msg("Question type incompatible with market")]
+    IncompatibleQuestionType,
+    #[msg("Oracle question type does not match market")]
+    QuestionTypeMismatch,
+    #[msg("Previous period not resolved")]
+    OutOfOrderResolution,
+    #[msg("Oracle answer is for a different question")]
+    OracleQuestionMismatch,
+    #[msg("Market account does not match market id")]
+    MarketMismatch,
+    #[msg("Fee exceeds maximum")]
+    FeeTooHigh,
+    #[msg("Period deadlines too close together")]
+    PeriodGapTooSmall,
+    #[msg("First period deadline is in the past")]
+    PeriodInPast,
+    #[msg("Invalid period gap")]
+    InvalidPeriodGap,
+    #[msg("No fees")]
+    NoFees,
+    #[msg("Max question length must be between 1 and MAX_QUESTION_LEN")]
+    InvalidMaxQuestionLen,
+    #[msg("Signer is not the market's resolver")]
+    NotResolver,
+    #[msg("Oracle account is not an initialized account of the oracle program")]
+    OracleAccountInvalid,
+    #[msg("Oracle question is not answered yet, or its answer is disputed")]
+    OracleQuestionNotFinal,
+    #[msg("Invalid treasury")]
+    InvalidTreasury,
+    #[msg("Invalid close time")]
+    InvalidCloseTime,
+    #[msg("Market expired")]
+    MarketExpired,
 }
 
@@ -0,0 +1,96 @@
+//! Verification of off-chain answer attestations signed with an ed25519 key.
+//!
+//! The signature itself is checked by the native ed25519 program, which must run as the
+//! instruction immediately before `provide_answer` or `revise_answer`. This module only
+//! inspects that instruction through the instructions sysvar and confirms it verified the
+//! expected key, signature and answer message.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+
+use crate::Trace9Error;
+
+/// Size of the ed25519 instruction header (signature count + padding)
+const HEADER_LEN: usize = 2;
+/// Size of one `Ed25519SignatureOffsets` entry
+const OFFSETS_LEN: usize = 14;
+/// Instruction index meaning "data lives in this same instruction"
+const CURRENT_INSTRUCTION: u16 = u16::MAX;
+
+/// Canonical bytes an attestation key signs for an answer:
+/// question id and numeric answer (little endian), then bool answer, confidence score
+/// and inconclusive flag
+pub fn answer_message(
+    question_id: u64,
+    numeric_answer: u64,
+    bool_answer: bool,
+    confidence_score: u8,
+    inconclusive: bool,
+) -> Vec<u8> {
+    let mut message = Vec::with_capacity(8 + 8 + 1 + 1 + 1);
+    message.extend_from_slice(&question_id.to_le_bytes());
+    message.extend_from_slice(&numeric_answer.to_le_bytes());
+    message.push(bool_answer as u8);
+    message.push(confidence_score);
+    message.push(inconclusive as u8);
+    message
+}
+
+/// True if `ix` is an ed25519 program instruction verifying exactly one signature
+/// of `message` by `pubkey`, with all data embedded in the instruction itself
+pub fn verifies(ix: &Instruction, pubkey: &[u8; 32], signature: &[u8; 64], message: &[u8]) -> bool {
+    if ix.program_id != ed25519_program::ID || !ix.accounts.is_empty() {
+        return false;
+    }
+
+    let data = &ix.data;
+    if data.len() < HEADER_LEN + OFFSETS_LEN || data[0] != 1 {
+        return false;
+    }
+
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let offsets = HEADER_LEN;
+    let signature_offset = read_u16(offsets) as usize;
+    let signature_ix = read_u16(offsets + 2);
+    let pubkey_offset = read_u16(offsets + 4) as usize;
+    let pubkey_ix = read_u16(offsets + 6);
+    let message_offset = read_u16(offsets + 8) as usize;
+    let message_len = read_u16(offsets + 10) as usize;
+    let message_ix = read_u16(offsets + 12);
+
+    if signature_ix != CURRENT_INSTRUCTION
+        || pubkey_ix != CURRENT_INSTRUCTION
+        || message_ix != CURRENT_INSTRUCTION
+    {
+        return false;
+    }
+
+    let slice = |at: usize, len: usize| data.get(at..at.checked_add(len)?);
+    slice(pubkey_offset, 32) == Some(&pubkey[..])
+        && slice(signature_offset, 64) == Some(&signature[..])
+        && slice(message_offset, message_len) == Some(message)
+}
+
+/// Require the instruction before the current one to be an ed25519 check of `message` by
+/// `attestation_pubkey`, with `signature` being the one it verified
+pub fn require_attested(
+    instructions: &AccountInfo,
+    attestation_pubkey: &Pubkey,
+    signature: Option<[u8; 64]>,
+    message: &[u8],
+) -> Result<()> {
+    let signature = signature.ok_or(Trace9Error::MissingAttestation)?;
+    let current_index = load_current_index_checked(instructions)?;
+    require!(current_index > 0, Trace9Error::MissingAttestation);
+    let ed25519_ix = load_instruction_at_checked(current_index as usize - 1, instructions)?;
+
+    require!(
+        verifies(&ed25519_ix, &attestation_pubkey.to_bytes(), &signature, message),
+        Trace9Error::InvalidAttestation
+    );
+    Ok(())
+}
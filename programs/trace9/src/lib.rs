@@ -1,43 +1,109 @@
 use anchor_lang::prelude::*;
 use anchor_lang::system_program;
 
+pub mod attestation;
+
 declare_id!("trc9oRacL3mP9vK8JqF2nH5xY7wD4bC6eA8g");
 
+pub const MAX_CATEGORY_LEN: usize = 32;
+pub const DEFAULT_CHALLENGE_PERIOD: i64 = 24 * 60 * 60; // 1 day in seconds
+pub const DEFAULT_MIN_BOND: u64 = 100_000_000; // 0.1 SOL in lamports
+pub const DEFAULT_DISPUTE_BOND: u64 = 50_000_000; // 0.05 SOL in lamports
+pub const INCENTIVE_DECAY_BPS_PER_DAY: u64 = 100; // 1% of an overdue opted-in bounty per day
+pub const INCENTIVE_PAYOUT_BPS: u64 = 1_000; // 10% of the incentive pool goes to each answer
+pub const MAX_DEADLINE_HORIZON: i64 = 365 * 24 * 60 * 60; // Deadlines at most 1 year out
+pub const MAX_BATCH_SIZE: usize = 20; // Entries per batch instruction
+pub const MAX_MULTISIG_SIGNERS: usize = 10; // Keys that can approve authority actions
+pub const MAX_QUESTION_LEN: usize = 1_000; // Ceiling for a deployment's max_question_len
+pub const MAX_STATUS_QUERY: usize = 100; // Question statuses per get_statuses call, bounded by return data size
+
 #[program]
 pub mod trace9 {
     use super::*;
 
     /// Initialize the oracle program
-    pub fn initialize(ctx: Context<Initialize>, oracle_provider: Pubkey) -> Result<()> {
+    pub fn initialize(
+        ctx: Context<Initialize>,
+        oracle_provider: Pubkey,
+        max_question_len: u16, // Longest question accepted, at most MAX_QUESTION_LEN
+    ) -> Result<()> {
+        require!(
+            max_question_len > 0 && max_question_len as usize <= MAX_QUESTION_LEN,
+            Trace9Error::InvalidMaxQuestionLen
+        );
+
         let oracle_state = &mut ctx.accounts.oracle_state;
         oracle_state.authority = ctx.accounts.authority.key();
         oracle_state.oracle_provider = oracle_provider;
         oracle_state.question_counter = 0;
         oracle_state.oracle_fee = 10_000_000; // 0.01 SOL in lamports
         oracle_state.provider_balance = 0;
+        oracle_state.challenge_period = DEFAULT_CHALLENGE_PERIOD;
+        oracle_state.min_bond = DEFAULT_MIN_BOND;
+        oracle_state.dispute_bond = DEFAULT_DISPUTE_BOND;
+        oracle_state.allow_self_answer = false;
+        oracle_state.withdraw_cooldown_secs = 0;
+        oracle_state.last_answer_at = 0;
+        oracle_state.incentive_pool = 0;
+        oracle_state.attestation_pubkey = Pubkey::default();
+        oracle_state.multisig_signers = Vec::new();
+        oracle_state.multisig_threshold = 1;
+        oracle_state.requester_allowlist_enabled = false;
+        oracle_state.max_question_len = max_question_len;
+        oracle_state.short_deadline_fee_bps = 0;
+        oracle_state.long_deadline_fee_bps = 0;
+        oracle_state.treasury = ctx.accounts.authority.key();
         oracle_state.bump = ctx.bumps.oracle_state;
+
+        let global_stats = &mut ctx.accounts.global_stats;
+        global_stats.total_questions = 0;
+        global_stats.total_answered = 0;
+        global_stats.total_refunded = 0;
+        global_stats.total_bounties_paid = 0;
+        global_stats.bump = ctx.bumps.global_stats;
         Ok(())
     }
 
-    /// Ask a question to the oracle (pay with SOL)
+    /// Ask a question to the oracle (pay with SOL), returning the new question ID
     pub fn ask_question(
         ctx: Context<AskQuestion>,
         question_type: QuestionType,
         question: String,
+        category: String,
         deadline: i64,
-    ) -> Result<()> {
+        bounty: u64, // 0 = use the default oracle fee
+        assigned_provider: Option<Pubkey>, // Only this provider may answer, None = the registered provider
+    ) -> Result<u64> {
         require!(
-            question.len() > 0 && question.len() <= 500,
+            !ctx.accounts.oracle_state.requester_allowlist_enabled
+                || ctx.accounts.allowed_requester.is_some(),
+            Trace9Error::RequesterNotAllowed
+        );
+        require!(
+            question.len() > 0
+                && question.len() <= ctx.accounts.oracle_state.max_question_len as usize,
             Trace9Error::InvalidQuestion
         );
+        require!(
+            category.len() > 0 && category.len() <= MAX_CATEGORY_LEN,
+            Trace9Error::InvalidCategory
+        );
         require!(deadline > Clock::get()?.unix_timestamp, Trace9Error::InvalidDeadline);
+        require!(
+            deadline <= Clock::get()?.unix_timestamp + MAX_DEADLINE_HORIZON,
+            Trace9Error::DeadlineTooFar
+        );
 
         let question_id = ctx.accounts.oracle_state.question_counter;
         let question_account = &mut ctx.accounts.question_account;
         let oracle_state = &mut ctx.accounts.oracle_state;
 
+        // Custom bounties must cover at least the fee for this deadline
+        let deadline_fee = oracle_state.fee_for_deadline(deadline, Clock::get()?.unix_timestamp)?;
+        let fee = if bounty == 0 { deadline_fee } else { bounty };
+        require!(fee >= deadline_fee, Trace9Error::BountyTooLow);
+
         // Verify sufficient fee was sent
-        let fee = oracle_state.oracle_fee;
         require!(
             ctx.accounts.requester.to_account_info().lamports() >= fee,
             Trace9Error::InsufficientFee
@@ -62,25 +128,38 @@ pub mod trace9 {
         question_account.requester = ctx.accounts.requester.key();
         question_account.question_type = question_type;
         question_account.question_hash = anchor_lang::solana_program::keccak::hash(question.as_bytes()).to_bytes();
+        question_account.category = category.clone();
         question_account.bounty = fee;
         question_account.timestamp = Clock::get()?.unix_timestamp;
         question_account.deadline = deadline;
         question_account.status = AnswerStatus::Pending;
         question_account.refunded = false;
+        question_account.revealed = false;
+        question_account.incentive_opt_in = false;
+        question_account.last_accrual_at = 0;
+        question_account.assigned_provider = assigned_provider;
 
         // Increment question counter
         oracle_state.question_counter = question_id.checked_add(1).unwrap();
 
+        let global_stats = &mut ctx.accounts.global_stats;
+        global_stats.total_questions = global_stats
+            .total_questions
+            .checked_add(1)
+            .ok_or(Trace9Error::Overflow)?;
+
         emit!(QuestionAsked {
             question_id,
             requester: ctx.accounts.requester.key(),
             question_type,
             question,
+            category,
             bounty: fee,
             deadline,
+            assigned_provider,
         });
 
-        Ok(())
+        Ok(question_id)
     }
 
     /// Provide an answer to a question (oracle provider only)
@@ -91,18 +170,62 @@ pub mod trace9 {
         bool_answer: bool,
         confidence_score: u8,
         data_source: String,
+        signature: Option<[u8; 64]>, // ed25519 attestation, required when an attestation key is set
+        inconclusive: bool, // No consensus: settles the question without a usable value, so linked markets cancel
     ) -> Result<()> {
-        require!(
-            ctx.accounts.oracle_provider.key() == ctx.accounts.oracle_state.oracle_provider,
-            Trace9Error::Unauthorized
-        );
+        match ctx.accounts.question_account.assigned_provider {
+            Some(assigned_provider) => require!(
+                ctx.accounts.oracle_provider.key() == assigned_provider,
+                Trace9Error::NotAssignedProvider
+            ),
+            None => require!(
+                ctx.accounts.oracle_provider.key() == ctx.accounts.oracle_state.oracle_provider,
+                Trace9Error::Unauthorized
+            ),
+        }
         require!(
             ctx.accounts.question_account.status == AnswerStatus::Pending,
             Trace9Error::AlreadyAnswered
         );
         require!(!ctx.accounts.question_account.refunded, Trace9Error::AlreadyRefunded);
+        require!(
+            ctx.accounts.oracle_state.allow_self_answer
+                || ctx.accounts.oracle_provider.key() != ctx.accounts.question_account.requester,
+            Trace9Error::SelfAnswerForbidden
+        );
         require!(confidence_score <= 100, Trace9Error::InvalidConfidence);
 
+        // The preceding instruction must be an ed25519 check of the answer by the attestation key
+        let attestation_pubkey = ctx.accounts.oracle_state.attestation_pubkey;
+        if attestation_pubkey != Pubkey::default() {
+            let message = attestation::answer_message(
+                ctx.accounts.question_account.question_id,
+                numeric_answer,
+                bool_answer,
+                confidence_score,
+                inconclusive,
+            );
+            attestation::require_attested(
+                &ctx.accounts.instructions.to_account_info(),
+                &attestation_pubkey,
+                signature,
+                &message,
+            )?;
+        }
+
+        // Lock part of the provider's bond until the answer is finalized
+        let min_bond = ctx.accounts.oracle_state.min_bond;
+        let provider_bond = &mut ctx.accounts.provider_bond;
+        let unlocked = provider_bond
+            .bond_balance
+            .checked_sub(provider_bond.locked_balance)
+            .ok_or(Trace9Error::Overflow)?;
+        require!(unlocked >= min_bond, Trace9Error::InsufficientBond);
+        provider_bond.locked_balance = provider_bond
+            .locked_balance
+            .checked_add(min_bond)
+            .ok_or(Trace9Error::Overflow)?;
+
         let question_account = &mut ctx.accounts.question_account;
         let oracle_state = &mut ctx.accounts.oracle_state;
 
@@ -117,11 +240,45 @@ pub mod trace9 {
         answer_account.bool_answer = bool_answer;
         answer_account.numeric_answer = numeric_answer;
         answer_account.timestamp = Clock::get()?.unix_timestamp;
+        answer_account.challenge_deadline = answer_account
+            .timestamp
+            .checked_add(oracle_state.challenge_period)
+            .ok_or(Trace9Error::Overflow)?;
+        answer_account.revision = 0;
+        answer_account.bond_locked = min_bond;
+        answer_account.disputer = Pubkey::default();
+        answer_account.inconclusive = inconclusive;
 
-        // Transfer bounty to provider balance
+        // Pay the bounty plus a slice of the incentive pool
         let bounty = question_account.bounty;
-        oracle_state.provider_balance = oracle_state
-            .provider_balance
+        let incentive_bonus = (oracle_state.incentive_pool as u128)
+            .checked_mul(INCENTIVE_PAYOUT_BPS as u128)
+            .and_then(|b| b.checked_div(10_000))
+            .ok_or(Trace9Error::Overflow)? as u64;
+        oracle_state.incentive_pool -= incentive_bonus;
+        let earned = bounty
+            .checked_add(incentive_bonus)
+            .ok_or(Trace9Error::Overflow)?;
+        oracle_state.last_answer_at = answer_account.timestamp;
+
+        // provider_balance only ever pays the registered provider, so an assigned one is paid now
+        if ctx.accounts.oracle_provider.key() == oracle_state.oracle_provider {
+            oracle_state.provider_balance = oracle_state
+                .provider_balance
+                .checked_add(earned)
+                .ok_or(Trace9Error::Overflow)?;
+        } else {
+            **ctx.accounts.oracle_state.to_account_info().try_borrow_mut_lamports()? -= earned;
+            **ctx.accounts.oracle_provider.to_account_info().try_borrow_mut_lamports()? += earned;
+        }
+
+        let global_stats = &mut ctx.accounts.global_stats;
+        global_stats.total_answered = global_stats
+            .total_answered
+            .checked_add(1)
+            .ok_or(Trace9Error::Overflow)?;
+        global_stats.total_bounties_paid = global_stats
+            .total_bounties_paid
             .checked_add(bounty)
             .ok_or(Trace9Error::Overflow)?;
 
@@ -132,6 +289,430 @@ pub mod trace9 {
             bool_answer,
             confidence_score,
             data_source,
+            incentive_bonus,
+            inconclusive,
+        });
+
+        Ok(())
+    }
+
+    /// Revise an answer while it is still within its challenge window (answering provider only)
+    pub fn revise_answer(
+        ctx: Context<ReviseAnswer>,
+        text_answer: String,
+        numeric_answer: u64,
+        bool_answer: bool,
+        confidence_score: u8,
+        data_source: String,
+        signature: Option<[u8; 64]>, // ed25519 attestation of the revised answer, required when an attestation key is set
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.question_account.assigned_provider.is_some()
+                || ctx.accounts.oracle_provider.key() == ctx.accounts.oracle_state.oracle_provider,
+            Trace9Error::Unauthorized
+        );
+        require!(
+            ctx.accounts.oracle_provider.key() == ctx.accounts.answer_account.provider,
+            Trace9Error::Unauthorized
+        );
+        require!(
+            ctx.accounts.question_account.status != AnswerStatus::Finalized,
+            Trace9Error::AnswerFinalized
+        );
+        require!(
+            ctx.accounts.question_account.status == AnswerStatus::Answered,
+            Trace9Error::NotAnswered
+        );
+        require!(
+            Clock::get()?.unix_timestamp < ctx.accounts.answer_account.challenge_deadline,
+            Trace9Error::ChallengeWindowClosed
+        );
+        require!(confidence_score <= 100, Trace9Error::InvalidConfidence);
+
+        // A revision replaces the attested values, so it needs an attestation of its own
+        let attestation_pubkey = ctx.accounts.oracle_state.attestation_pubkey;
+        if attestation_pubkey != Pubkey::default() {
+            let message = attestation::answer_message(
+                ctx.accounts.answer_account.question_id,
+                numeric_answer,
+                bool_answer,
+                confidence_score,
+                ctx.accounts.answer_account.inconclusive,
+            );
+            attestation::require_attested(
+                &ctx.accounts.instructions.to_account_info(),
+                &attestation_pubkey,
+                signature,
+                &message,
+            )?;
+        }
+
+        let answer_account = &mut ctx.accounts.answer_account;
+        answer_account.confidence_score = confidence_score;
+        answer_account.bool_answer = bool_answer;
+        answer_account.numeric_answer = numeric_answer;
+        answer_account.timestamp = Clock::get()?.unix_timestamp;
+        answer_account.revision = answer_account
+            .revision
+            .checked_add(1)
+            .ok_or(Trace9Error::Overflow)?;
+
+        emit!(AnswerRevised {
+            question_id: answer_account.question_id,
+            revision: answer_account.revision,
+            text_answer,
+            numeric_answer,
+            bool_answer,
+            confidence_score,
+            data_source,
+        });
+
+        Ok(())
+    }
+
+    /// Finalize an answer once its challenge window has passed (permissionless)
+    pub fn finalize_answer(ctx: Context<FinalizeAnswer>) -> Result<()> {
+        require!(
+            ctx.accounts.question_account.status == AnswerStatus::Answered,
+            Trace9Error::NotAnswered
+        );
+        require!(
+            Clock::get()?.unix_timestamp >= ctx.accounts.answer_account.challenge_deadline,
+            Trace9Error::ChallengeWindowOpen
+        );
+
+        let question_account = &mut ctx.accounts.question_account;
+        question_account.status = AnswerStatus::Finalized;
+
+        // Release the bond locked for this answer
+        let provider_bond = &mut ctx.accounts.provider_bond;
+        provider_bond.locked_balance = provider_bond
+            .locked_balance
+            .checked_sub(ctx.accounts.answer_account.bond_locked)
+            .ok_or(Trace9Error::Overflow)?;
+
+        emit!(AnswerFinalized {
+            question_id: question_account.question_id,
+        });
+
+        Ok(())
+    }
+
+    /// Dispute an answer during its challenge window, posting the dispute bond
+    pub fn dispute_answer(ctx: Context<DisputeAnswer>) -> Result<()> {
+        require!(
+            ctx.accounts.question_account.status == AnswerStatus::Answered,
+            Trace9Error::NotAnswered
+        );
+        require!(
+            Clock::get()?.unix_timestamp < ctx.accounts.answer_account.challenge_deadline,
+            Trace9Error::ChallengeWindowClosed
+        );
+
+        // The bond sits in the dispute account until the dispute is resolved
+        let bond = ctx.accounts.oracle_state.dispute_bond;
+        anchor_lang::solana_program::program::invoke(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                ctx.accounts.disputer.key,
+                &ctx.accounts.dispute_account.key(),
+                bond,
+            ),
+            &[
+                ctx.accounts.disputer.to_account_info(),
+                ctx.accounts.dispute_account.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        let question_account = &mut ctx.accounts.question_account;
+        question_account.status = AnswerStatus::Disputed;
+        ctx.accounts.answer_account.disputer = ctx.accounts.disputer.key();
+
+        let dispute_account = &mut ctx.accounts.dispute_account;
+        dispute_account.question_id = question_account.question_id;
+        dispute_account.disputer = ctx.accounts.disputer.key();
+        dispute_account.bond = bond;
+        dispute_account.settled = false;
+        dispute_account.upheld = false;
+        dispute_account.bump = ctx.bumps.dispute_account;
+
+        emit!(AnswerDisputed {
+            question_id: question_account.question_id,
+            disputer: ctx.accounts.disputer.key(),
+            bond,
+        });
+
+        Ok(())
+    }
+
+    /// Settle a disputed answer (authority only). An overturned answer slashes the provider's bond:
+    /// the disputer gets their bond back plus as much again from the slashed bond, the rest goes to
+    /// the treasury. An upheld answer forfeits the dispute bond to the provider's bond.
+    pub fn resolve_dispute(
+        ctx: Context<ResolveDispute>,
+        overturn: bool,
+        numeric_answer: u64,
+        bool_answer: bool,
+        confidence_score: u8,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.question_account.status == AnswerStatus::Disputed,
+            Trace9Error::NotDisputed
+        );
+        require!(
+            ctx.accounts.disputer.key() == ctx.accounts.answer_account.disputer,
+            Trace9Error::Unauthorized
+        );
+        require!(confidence_score <= 100, Trace9Error::InvalidConfidence);
+
+        let bond_locked = ctx.accounts.answer_account.bond_locked;
+        let provider_bond = &mut ctx.accounts.provider_bond;
+        provider_bond.locked_balance = provider_bond
+            .locked_balance
+            .checked_sub(bond_locked)
+            .ok_or(Trace9Error::Overflow)?;
+
+        let dispute_bond = ctx.accounts.dispute_account.bond;
+        let mut slashed = 0u64;
+        let mut disputer_payout = 0u64;
+        if overturn {
+            // Correct the answer and slash the locked bond
+            let answer_account = &mut ctx.accounts.answer_account;
+            answer_account.numeric_answer = numeric_answer;
+            answer_account.bool_answer = bool_answer;
+            answer_account.confidence_score = confidence_score;
+            answer_account.timestamp = Clock::get()?.unix_timestamp;
+
+            slashed = bond_locked;
+            provider_bond.bond_balance = provider_bond
+                .bond_balance
+                .checked_sub(slashed)
+                .ok_or(Trace9Error::Overflow)?;
+
+            // A winning disputer doubles their bond, capped at what was slashed
+            let disputer_reward = dispute_bond.min(slashed);
+            let treasury_share = slashed - disputer_reward;
+            disputer_payout = dispute_bond
+                .checked_add(disputer_reward)
+                .ok_or(Trace9Error::Overflow)?;
+
+            **ctx.accounts.provider_bond.to_account_info().try_borrow_mut_lamports()? -= slashed;
+            **ctx.accounts.dispute_account.to_account_info().try_borrow_mut_lamports()? -= dispute_bond;
+            **ctx.accounts.disputer.to_account_info().try_borrow_mut_lamports()? += disputer_payout;
+            **ctx.accounts.treasury.to_account_info().try_borrow_mut_lamports()? += treasury_share;
+        } else {
+            // A rejected dispute forfeits its bond to the provider it challenged
+            let provider_bond = &mut ctx.accounts.provider_bond;
+            provider_bond.bond_balance = provider_bond
+                .bond_balance
+                .checked_add(dispute_bond)
+                .ok_or(Trace9Error::Overflow)?;
+
+            **ctx.accounts.dispute_account.to_account_info().try_borrow_mut_lamports()? -= dispute_bond;
+            **ctx.accounts.provider_bond.to_account_info().try_borrow_mut_lamports()? += dispute_bond;
+        }
+
+        let dispute_account = &mut ctx.accounts.dispute_account;
+        dispute_account.settled = true;
+        dispute_account.upheld = overturn;
+
+        let question_account = &mut ctx.accounts.question_account;
+        question_account.status = AnswerStatus::Finalized;
+
+        emit!(DisputeResolved {
+            question_id: question_account.question_id,
+            overturned: overturn,
+            slashed,
+            disputer_payout,
+        });
+
+        Ok(())
+    }
+
+    /// Post a bond that backs the provider's answers
+    pub fn post_bond(ctx: Context<PostBond>, amount: u64) -> Result<()> {
+        require!(amount > 0, Trace9Error::InvalidAmount);
+
+        anchor_lang::solana_program::program::invoke(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                ctx.accounts.provider.key,
+                &ctx.accounts.provider_bond.key(),
+                amount,
+            ),
+            &[
+                ctx.accounts.provider.to_account_info(),
+                ctx.accounts.provider_bond.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        let provider_bond = &mut ctx.accounts.provider_bond;
+        provider_bond.provider = ctx.accounts.provider.key();
+        provider_bond.bond_balance = provider_bond
+            .bond_balance
+            .checked_add(amount)
+            .ok_or(Trace9Error::Overflow)?;
+        provider_bond.bump = ctx.bumps.provider_bond;
+
+        emit!(BondPosted {
+            provider: provider_bond.provider,
+            amount,
+            bond_balance: provider_bond.bond_balance,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraw bond that is not locked behind an unfinalized answer
+    pub fn withdraw_bond(ctx: Context<WithdrawBond>, amount: u64) -> Result<()> {
+        let provider_bond = &mut ctx.accounts.provider_bond;
+        let unlocked = provider_bond
+            .bond_balance
+            .checked_sub(provider_bond.locked_balance)
+            .ok_or(Trace9Error::Overflow)?;
+        require!(amount > 0 && amount <= unlocked, Trace9Error::InsufficientBond);
+
+        provider_bond.bond_balance -= amount;
+
+        **ctx.accounts.provider_bond.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.provider.to_account_info().try_borrow_mut_lamports()? += amount;
+
+        emit!(BondWithdrawn {
+            provider: ctx.accounts.provider.key(),
+            amount,
+            bond_balance: ctx.accounts.provider_bond.bond_balance,
+        });
+
+        Ok(())
+    }
+
+    /// Reveal the full question text, verified against the stored hash (permissionless)
+    pub fn reveal_question(ctx: Context<RevealQuestion>, question: String) -> Result<()> {
+        require!(
+            question.len() > 0 && question.len() <= MAX_QUESTION_LEN,
+            Trace9Error::InvalidQuestion
+        );
+
+        let question_hash = anchor_lang::solana_program::keccak::hash(question.as_bytes()).to_bytes();
+        let question_account = &mut ctx.accounts.question_account;
+        require!(
+            question_hash == question_account.question_hash,
+            Trace9Error::QuestionHashMismatch
+        );
+
+        question_account.revealed = true;
+
+        emit!(QuestionRevealed {
+            question_id: question_account.question_id,
+            question_hash,
+            question,
+        });
+
+        Ok(())
+    }
+
+    /// Mark a question as expired once its deadline passes without an answer (permissionless)
+    pub fn expire_question(ctx: Context<ExpireQuestion>) -> Result<()> {
+        require!(
+            ctx.accounts.question_account.status == AnswerStatus::Pending,
+            Trace9Error::AlreadyAnswered
+        );
+        require!(
+            Clock::get()?.unix_timestamp > ctx.accounts.question_account.deadline,
+            Trace9Error::DeadlineNotPassed
+        );
+
+        let question_account = &mut ctx.accounts.question_account;
+        question_account.status = AnswerStatus::Expired;
+
+        emit!(QuestionExpired {
+            question_id: question_account.question_id,
+        });
+
+        Ok(())
+    }
+
+    /// Push back the deadline of an unanswered question (requester only)
+    /// The refund period still runs from when the question was asked
+    pub fn extend_deadline(ctx: Context<ExtendDeadline>, deadline: i64) -> Result<()> {
+        let question_account = &mut ctx.accounts.question_account;
+        require!(
+            question_account.status == AnswerStatus::Pending,
+            Trace9Error::AlreadyAnswered
+        );
+        require!(!question_account.refunded, Trace9Error::AlreadyRefunded);
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            deadline > question_account.deadline && deadline > now,
+            Trace9Error::InvalidDeadline
+        );
+        require!(deadline <= now + MAX_DEADLINE_HORIZON, Trace9Error::DeadlineTooFar);
+
+        let old_deadline = question_account.deadline;
+        question_account.deadline = deadline;
+
+        emit!(DeadlineExtended {
+            question_id: question_account.question_id,
+            old_deadline,
+            new_deadline: deadline,
+        });
+
+        Ok(())
+    }
+
+    /// Opt a pending question into bounty decay once its deadline passes (requester only)
+    pub fn enable_incentive_decay(ctx: Context<EnableIncentiveDecay>) -> Result<()> {
+        let question_account = &mut ctx.accounts.question_account;
+        require!(
+            question_account.status == AnswerStatus::Pending,
+            Trace9Error::AlreadyAnswered
+        );
+        require!(!question_account.refunded, Trace9Error::AlreadyRefunded);
+        require!(!question_account.incentive_opt_in, Trace9Error::IncentiveAlreadyEnabled);
+
+        question_account.incentive_opt_in = true;
+        question_account.last_accrual_at = question_account
+            .deadline
+            .max(Clock::get()?.unix_timestamp);
+
+        Ok(())
+    }
+
+    /// Move the decayed part of an overdue opted-in bounty into the incentive pool (permissionless)
+    pub fn accrue_incentive(ctx: Context<AccrueIncentive>) -> Result<()> {
+        let question_account = &mut ctx.accounts.question_account;
+        require!(question_account.incentive_opt_in, Trace9Error::IncentiveNotEnabled);
+        require!(
+            question_account.status == AnswerStatus::Pending
+                || question_account.status == AnswerStatus::Expired,
+            Trace9Error::AlreadyAnswered
+        );
+        require!(!question_account.refunded, Trace9Error::AlreadyRefunded);
+
+        let now = Clock::get()?.unix_timestamp;
+        let elapsed = now.saturating_sub(question_account.last_accrual_at).max(0) as u128;
+        let amount = (question_account.bounty as u128)
+            .checked_mul(INCENTIVE_DECAY_BPS_PER_DAY as u128)
+            .and_then(|a| a.checked_mul(elapsed))
+            .and_then(|a| a.checked_div(10_000 * 24 * 60 * 60))
+            .ok_or(Trace9Error::Overflow)?
+            .min(question_account.bounty as u128) as u64;
+        require!(amount > 0, Trace9Error::NothingToAccrue);
+
+        question_account.bounty -= amount;
+        question_account.last_accrual_at = now;
+
+        let oracle_state = &mut ctx.accounts.oracle_state;
+        oracle_state.incentive_pool = oracle_state
+            .incentive_pool
+            .checked_add(amount)
+            .ok_or(Trace9Error::Overflow)?;
+
+        emit!(IncentiveAccrued {
+            question_id: question_account.question_id,
+            amount,
+            incentive_pool: oracle_state.incentive_pool,
         });
 
         Ok(())
@@ -144,12 +725,25 @@ pub mod trace9 {
         questions: Vec<String>,
         deadlines: Vec<i64>,
     ) -> Result<Vec<u64>> {
-        require!(questions.len() == deadlines.len() && questions.len() == question_types.len(), Trace9Error::InvalidBatch);
-        require!(questions.len() > 0 && questions.len() <= 20, Trace9Error::InvalidBatchSize);
+        require!(
+            !ctx.accounts.oracle_state.requester_allowlist_enabled
+                || ctx.accounts.allowed_requester.is_some(),
+            Trace9Error::RequesterNotAllowed
+        );
+        check_batch_size(questions.len())?;
+        check_batch_len("deadlines", deadlines.len(), questions.len())?;
+        check_batch_len("question_types", question_types.len(), questions.len())?;
 
         let oracle_state = &mut ctx.accounts.oracle_state;
-        let fee = oracle_state.oracle_fee;
-        let total_fee = fee.checked_mul(questions.len() as u64).ok_or(Trace9Error::Overflow)?;
+        let now = Clock::get()?.unix_timestamp;
+        let fees = deadlines
+            .iter()
+            .map(|deadline| oracle_state.fee_for_deadline(*deadline, now))
+            .collect::<Result<Vec<u64>>>()?;
+        let total_fee = fees
+            .iter()
+            .try_fold(0u64, |total, fee| total.checked_add(*fee))
+            .ok_or(Trace9Error::Overflow)?;
 
         require!(
             ctx.accounts.requester.to_account_info().lamports() >= total_fee,
@@ -160,15 +754,22 @@ pub mod trace9 {
         let mut current_question_id = oracle_state.question_counter;
 
         for i in 0..questions.len() {
-            require!(questions[i].len() > 0 && questions[i].len() <= 500, Trace9Error::InvalidQuestion);
+            require!(
+                questions[i].len() > 0 && questions[i].len() <= oracle_state.max_question_len as usize,
+                Trace9Error::InvalidQuestion
+            );
             require!(deadlines[i] > Clock::get()?.unix_timestamp, Trace9Error::InvalidDeadline);
+            require!(
+                deadlines[i] <= Clock::get()?.unix_timestamp + MAX_DEADLINE_HORIZON,
+                Trace9Error::DeadlineTooFar
+            );
 
             // Transfer fee for this question
             anchor_lang::solana_program::program::invoke(
                 &anchor_lang::solana_program::system_instruction::transfer(
                     ctx.accounts.requester.key,
                     ctx.accounts.oracle_state.key,
-                    fee,
+                    fees[i],
                 ),
                 &[
                     ctx.accounts.requester.to_account_info(),
@@ -183,6 +784,12 @@ pub mod trace9 {
 
         oracle_state.question_counter = current_question_id;
 
+        let global_stats = &mut ctx.accounts.global_stats;
+        global_stats.total_questions = global_stats
+            .total_questions
+            .checked_add(questions.len() as u64)
+            .ok_or(Trace9Error::Overflow)?;
+
         emit!(BatchQuestionsAsked {
             question_ids: question_ids.clone(),
             requester: ctx.accounts.requester.key(),
@@ -205,15 +812,24 @@ pub mod trace9 {
             ctx.accounts.oracle_provider.key() == ctx.accounts.oracle_state.oracle_provider,
             Trace9Error::Unauthorized
         );
+        check_batch_size(question_ids.len())?;
+        check_batch_len("text_answers", text_answers.len(), question_ids.len())?;
+        check_batch_len("numeric_answers", numeric_answers.len(), question_ids.len())?;
+        check_batch_len("bool_answers", bool_answers.len(), question_ids.len())?;
+        check_batch_len("confidence_scores", confidence_scores.len(), question_ids.len())?;
+        check_batch_len("data_sources", data_sources.len(), question_ids.len())?;
+        require!(
+            question_ids
+                .iter()
+                .enumerate()
+                .all(|(i, id)| !question_ids[..i].contains(id)),
+            Trace9Error::DuplicateBatchId
+        );
+        // Batched answers carry no attestations
         require!(
-            question_ids.len() == text_answers.len() &&
-            question_ids.len() == numeric_answers.len() &&
-            question_ids.len() == bool_answers.len() &&
-            question_ids.len() == confidence_scores.len() &&
-            question_ids.len() == data_sources.len(),
-            Trace9Error::InvalidBatch
+            ctx.accounts.oracle_state.attestation_pubkey == Pubkey::default(),
+            Trace9Error::MissingAttestation
         );
-        require!(question_ids.len() > 0 && question_ids.len() <= 20, Trace9Error::InvalidBatchSize);
 
         let oracle_state = &mut ctx.accounts.oracle_state;
         let mut total_bounty = 0u64;
@@ -229,6 +845,8 @@ pub mod trace9 {
             .provider_balance
             .checked_add(total_bounty)
             .ok_or(Trace9Error::Overflow)?;
+        oracle_state.last_answer_at = Clock::get()?.unix_timestamp;
+        // No answer account is written here, so global_stats.total_answered is left to provide_answer
 
         emit!(BatchAnswersProvided {
             question_ids: question_ids.clone(),
@@ -245,7 +863,8 @@ pub mod trace9 {
             Trace9Error::Unauthorized
         );
         require!(
-            ctx.accounts.question_account.status == AnswerStatus::Pending,
+            ctx.accounts.question_account.status == AnswerStatus::Pending
+                || ctx.accounts.question_account.status == AnswerStatus::Expired,
             Trace9Error::AlreadyAnswered
         );
         require!(!ctx.accounts.question_account.refunded, Trace9Error::AlreadyRefunded);
@@ -262,6 +881,12 @@ pub mod trace9 {
         question_account.refunded = true;
         question_account.bounty = 0;
 
+        let global_stats = &mut ctx.accounts.global_stats;
+        global_stats.total_refunded = global_stats
+            .total_refunded
+            .checked_add(1)
+            .ok_or(Trace9Error::Overflow)?;
+
         // Transfer refund from oracle state to requester
         anchor_lang::solana_program::program::invoke(
             &anchor_lang::solana_program::system_instruction::transfer(
@@ -279,32 +904,45 @@ pub mod trace9 {
         Ok(())
     }
 
-    /// Withdraw provider earnings
+    /// Withdraw provider earnings (multisig approvals as signing remaining accounts)
     pub fn withdraw(ctx: Context<Withdraw>) -> Result<()> {
         require!(
             ctx.accounts.oracle_provider.key() == ctx.accounts.oracle_state.oracle_provider,
             Trace9Error::Unauthorized
         );
+        ctx.accounts.oracle_state.require_multisig(ctx.remaining_accounts)?;
 
         let oracle_state = &mut ctx.accounts.oracle_state;
         let amount = oracle_state.provider_balance;
         require!(amount > 0, Trace9Error::NoBalance);
 
+        // Give disputes time to land before recent earnings can leave
+        let withdrawable_at = oracle_state
+            .last_answer_at
+            .checked_add(oracle_state.withdraw_cooldown_secs)
+            .ok_or(Trace9Error::Overflow)?;
+        require!(
+            Clock::get()?.unix_timestamp >= withdrawable_at,
+            Trace9Error::WithdrawCooldownActive
+        );
+
         oracle_state.provider_balance = 0;
 
         // Transfer to provider
         **ctx.accounts.oracle_state.to_account_info().try_borrow_mut_lamports()? -= amount;
         **ctx.accounts.oracle_provider.to_account_info().try_borrow_mut_lamports()? += amount;
 
+        emit!(ProviderWithdrawn {
+            provider: ctx.accounts.oracle_provider.key(),
+            amount,
+        });
+
         Ok(())
     }
 
-    /// Update oracle fee (authority only)
+    /// Update oracle fee (authority only, plus multisig approvals as signing remaining accounts)
     pub fn set_oracle_fee(ctx: Context<SetOracleFee>, new_fee: u64) -> Result<()> {
-        require!(
-            ctx.accounts.authority.key() == ctx.accounts.oracle_state.authority,
-            Trace9Error::Unauthorized
-        );
+        ctx.accounts.oracle_state.require_multisig(ctx.remaining_accounts)?;
 
         let old_fee = ctx.accounts.oracle_state.oracle_fee;
         ctx.accounts.oracle_state.oracle_fee = new_fee;
@@ -317,16 +955,218 @@ pub mod trace9 {
         Ok(())
     }
 
+    /// Price questions by deadline horizon, as bps of the oracle fee from `short_bps` for an
+    /// immediate deadline down to `long_bps` at MAX_DEADLINE_HORIZON; both 0 restores flat pricing
+    /// (authority only, plus multisig approvals as signing remaining accounts)
+    pub fn set_deadline_fee_schedule(
+        ctx: Context<SetDeadlineFeeSchedule>,
+        short_bps: u16,
+        long_bps: u16,
+    ) -> Result<()> {
+        ctx.accounts.oracle_state.require_multisig(ctx.remaining_accounts)?;
+
+        require!(
+            (short_bps == 0 && long_bps == 0) || (long_bps > 0 && short_bps >= long_bps),
+            Trace9Error::InvalidFeeSchedule
+        );
+
+        let oracle_state = &mut ctx.accounts.oracle_state;
+        let old_short_bps = oracle_state.short_deadline_fee_bps;
+        let old_long_bps = oracle_state.long_deadline_fee_bps;
+        oracle_state.short_deadline_fee_bps = short_bps;
+        oracle_state.long_deadline_fee_bps = long_bps;
+
+        emit!(DeadlineFeeScheduleUpdated {
+            old_short_bps,
+            old_long_bps,
+            new_short_bps: short_bps,
+            new_long_bps: long_bps,
+        });
+
+        Ok(())
+    }
+
     /// Update oracle provider (authority only)
     pub fn set_oracle_provider(ctx: Context<SetOracleProvider>, new_provider: Pubkey) -> Result<()> {
+        ctx.accounts.oracle_state.oracle_provider = new_provider;
+        Ok(())
+    }
+
+    /// Update the answer challenge period (authority only)
+    pub fn set_challenge_period(ctx: Context<SetChallengePeriod>, challenge_period: i64) -> Result<()> {
+        require!(challenge_period >= 0, Trace9Error::InvalidChallengePeriod);
+
+        ctx.accounts.oracle_state.challenge_period = challenge_period;
+        Ok(())
+    }
+
+    /// Update the minimum bond a provider must lock per answer (authority only)
+    pub fn set_min_bond(ctx: Context<SetMinBond>, min_bond: u64) -> Result<()> {
+        ctx.accounts.oracle_state.min_bond = min_bond;
+        Ok(())
+    }
+
+    /// Update the bond a disputer must post to challenge an answer (authority only)
+    pub fn set_dispute_bond(ctx: Context<SetDisputeBond>, dispute_bond: u64) -> Result<()> {
+        ctx.accounts.oracle_state.dispute_bond = dispute_bond;
+        Ok(())
+    }
+
+    /// Allow or forbid providers answering questions they asked themselves (authority only)
+    pub fn set_allow_self_answer(ctx: Context<SetAllowSelfAnswer>, allow_self_answer: bool) -> Result<()> {
+        ctx.accounts.oracle_state.allow_self_answer = allow_self_answer;
+        Ok(())
+    }
+
+    /// Update how long after the last answer provider earnings stay locked (authority only)
+    pub fn set_withdraw_cooldown(ctx: Context<SetWithdrawCooldown>, withdraw_cooldown_secs: i64) -> Result<()> {
+        require!(withdraw_cooldown_secs >= 0, Trace9Error::InvalidCooldown);
+
+        ctx.accounts.oracle_state.withdraw_cooldown_secs = withdraw_cooldown_secs;
+        Ok(())
+    }
+
+    /// Point question_counter at an unused question ID after counter drift (authority only);
+    /// the question PDA for `question_counter` goes first in remaining accounts and must not exist
+    pub fn resync_counter(ctx: Context<ResyncCounter>, question_counter: u64) -> Result<()> {
+        let old_counter = ctx.accounts.oracle_state.question_counter;
+        require!(question_counter != old_counter, Trace9Error::InvalidCounter);
+
+        let target = ctx
+            .remaining_accounts
+            .first()
+            .ok_or(Trace9Error::InvalidQuestionAccount)?;
+        let (expected, _) = Pubkey::find_program_address(
+            &[b"question", question_counter.to_le_bytes().as_ref()],
+            ctx.program_id,
+        );
+        require!(target.key() == expected, Trace9Error::InvalidQuestionAccount);
+        // An existing question would be unreachable anyway, but never point the counter at one
         require!(
-            ctx.accounts.authority.key() == ctx.accounts.oracle_state.authority,
-            Trace9Error::Unauthorized
+            target.data_is_empty() && *target.owner == system_program::ID,
+            Trace9Error::QuestionExists
         );
 
-        ctx.accounts.oracle_state.oracle_provider = new_provider;
+        ctx.accounts.oracle_state.question_counter = question_counter;
+
+        emit!(CounterResynced {
+            old_counter,
+            new_counter: question_counter,
+        });
+
+        Ok(())
+    }
+
+    /// Restrict asking questions to allowlisted requesters, or reopen it to everyone (authority only)
+    pub fn set_requester_allowlist(ctx: Context<SetRequesterAllowlist>, enabled: bool) -> Result<()> {
+        ctx.accounts.oracle_state.requester_allowlist_enabled = enabled;
+        Ok(())
+    }
+
+    /// Approve a requester for asking questions while the allowlist is enabled (authority only)
+    pub fn add_requester(ctx: Context<AddRequester>, requester: Pubkey) -> Result<()> {
+        let allowed_requester = &mut ctx.accounts.allowed_requester;
+        allowed_requester.requester = requester;
+        allowed_requester.bump = ctx.bumps.allowed_requester;
+        Ok(())
+    }
+
+    /// Revoke a requester's approval, returning the allowlist entry's rent (authority only)
+    pub fn remove_requester(_ctx: Context<RemoveRequester>, _requester: Pubkey) -> Result<()> {
         Ok(())
     }
+
+    /// Set the ed25519 key answers must be attested by, default pubkey to disable (authority only)
+    pub fn set_attestation_pubkey(ctx: Context<SetAttestationPubkey>, attestation_pubkey: Pubkey) -> Result<()> {
+        ctx.accounts.oracle_state.attestation_pubkey = attestation_pubkey;
+        Ok(())
+    }
+
+    /// Replace the M-of-N signer set gating fee changes and withdrawals (authority only, plus current approvals)
+    /// A threshold of 1 keeps single-authority mode
+    pub fn set_multisig(ctx: Context<SetMultisig>, signers: Vec<Pubkey>, threshold: u8) -> Result<()> {
+        ctx.accounts.oracle_state.require_multisig(ctx.remaining_accounts)?;
+
+        require!(
+            signers.len() <= MAX_MULTISIG_SIGNERS
+                && threshold >= 1
+                && (threshold == 1 || threshold as usize <= signers.len()),
+            Trace9Error::InvalidMultisig
+        );
+        for (i, signer) in signers.iter().enumerate() {
+            require!(!signers[..i].contains(signer), Trace9Error::InvalidMultisig);
+        }
+
+        let oracle_state = &mut ctx.accounts.oracle_state;
+        oracle_state.multisig_signers = signers.clone();
+        oracle_state.multisig_threshold = threshold;
+
+        emit!(MultisigUpdated {
+            signers,
+            threshold,
+        });
+
+        Ok(())
+    }
+
+    /// Update the treasury receiving the protocol share of slashed bonds (authority only, plus multisig approvals)
+    pub fn set_treasury(ctx: Context<SetTreasury>, treasury: Pubkey) -> Result<()> {
+        ctx.accounts.oracle_state.require_multisig(ctx.remaining_accounts)?;
+
+        let old_treasury = ctx.accounts.oracle_state.treasury;
+        ctx.accounts.oracle_state.treasury = treasury;
+
+        emit!(TreasuryUpdated {
+            old_treasury,
+            new_treasury: treasury,
+        });
+
+        Ok(())
+    }
+
+    /// Read-only status lookup for question accounts passed as remaining accounts, meant for simulation
+    pub fn get_statuses(ctx: Context<GetStatuses>) -> Result<Vec<QuestionStatus>> {
+        require!(!ctx.remaining_accounts.is_empty(), Trace9Error::EmptyBatch);
+        require!(
+            ctx.remaining_accounts.len() <= MAX_STATUS_QUERY,
+            Trace9Error::BatchTooLarge
+        );
+
+        let mut statuses = Vec::with_capacity(ctx.remaining_accounts.len());
+        for info in ctx.remaining_accounts.iter() {
+            require!(info.owner == ctx.program_id, Trace9Error::InvalidQuestionAccount);
+            let question = QuestionAccount::try_deserialize(&mut &info.data.borrow()[..])?;
+            let (expected, _) = Pubkey::find_program_address(
+                &[b"question", question.question_id.to_le_bytes().as_ref()],
+                ctx.program_id,
+            );
+            require!(info.key() == expected, Trace9Error::InvalidQuestionAccount);
+
+            statuses.push(QuestionStatus {
+                question_id: question.question_id,
+                status: question.status,
+                refunded: question.refunded,
+            });
+        }
+
+        Ok(statuses)
+    }
+}
+
+/// Reject empty batches and batches over MAX_BATCH_SIZE
+fn check_batch_size(len: usize) -> Result<()> {
+    require!(len > 0, Trace9Error::EmptyBatch);
+    require!(len <= MAX_BATCH_SIZE, Trace9Error::BatchTooLarge);
+    Ok(())
+}
+
+/// Reject a batch vector whose length differs from the batch's, logging which field mismatched
+fn check_batch_len(field: &str, len: usize, expected: usize) -> Result<()> {
+    if len != expected {
+        msg!("{} has {} entries, expected {}", field, len, expected);
+        return err!(Trace9Error::LengthMismatch);
+    }
+    Ok(())
 }
 
 #[derive(Accounts)]
@@ -339,6 +1179,14 @@ pub struct Initialize<'info> {
         bump
     )]
     pub oracle_state: Account<'info, OracleState>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + GlobalStats::LEN,
+        seeds = [b"global_stats"],
+        bump
+    )]
+    pub global_stats: Account<'info, GlobalStats>,
     #[account(mut)]
     pub authority: Signer<'info>,
     pub system_program: Program<'info, System>,
@@ -356,8 +1204,13 @@ pub struct AskQuestion<'info> {
     pub question_account: Account<'info, QuestionAccount>,
     #[account(mut, seeds = [b"oracle_state"], bump = oracle_state.bump)]
     pub oracle_state: Account<'info, OracleState>,
+    #[account(mut, seeds = [b"global_stats"], bump = global_stats.bump)]
+    pub global_stats: Account<'info, GlobalStats>,
     #[account(mut)]
     pub requester: Signer<'info>,
+    /// Allowlist entry, required while the requester allowlist is enabled
+    #[account(seeds = [b"requester", requester.key().as_ref()], bump = allowed_requester.bump)]
+    pub allowed_requester: Option<Account<'info, AllowedRequester>>,
     pub system_program: Program<'info, System>,
 }
 
@@ -375,16 +1228,170 @@ pub struct ProvideAnswer<'info> {
     pub answer_account: Account<'info, AnswerAccount>,
     #[account(mut, seeds = [b"oracle_state"], bump = oracle_state.bump)]
     pub oracle_state: Account<'info, OracleState>,
+    #[account(mut, seeds = [b"global_stats"], bump = global_stats.bump)]
+    pub global_stats: Account<'info, GlobalStats>,
+    #[account(mut, seeds = [b"bond", oracle_provider.key().as_ref()], bump = provider_bond.bump)]
+    pub provider_bond: Account<'info, ProviderBond>,
+    #[account(mut)]
+    pub oracle_provider: Signer<'info>,
+    /// CHECK: Instructions sysvar, used to inspect the ed25519 attestation
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReviseAnswer<'info> {
+    #[account(seeds = [b"question", question_account.question_id.to_le_bytes().as_ref()], bump)]
+    pub question_account: Account<'info, QuestionAccount>,
+    #[account(mut, seeds = [b"answer", question_account.question_id.to_le_bytes().as_ref()], bump)]
+    pub answer_account: Account<'info, AnswerAccount>,
+    #[account(seeds = [b"oracle_state"], bump = oracle_state.bump)]
+    pub oracle_state: Account<'info, OracleState>,
     pub oracle_provider: Signer<'info>,
+    /// CHECK: Instructions sysvar, used to inspect the ed25519 attestation
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeAnswer<'info> {
+    #[account(mut, seeds = [b"question", question_account.question_id.to_le_bytes().as_ref()], bump)]
+    pub question_account: Account<'info, QuestionAccount>,
+    #[account(seeds = [b"answer", question_account.question_id.to_le_bytes().as_ref()], bump)]
+    pub answer_account: Account<'info, AnswerAccount>,
+    #[account(mut, seeds = [b"bond", answer_account.provider.as_ref()], bump = provider_bond.bump)]
+    pub provider_bond: Account<'info, ProviderBond>,
+}
+
+#[derive(Accounts)]
+pub struct DisputeAnswer<'info> {
+    #[account(mut, seeds = [b"question", question_account.question_id.to_le_bytes().as_ref()], bump)]
+    pub question_account: Account<'info, QuestionAccount>,
+    #[account(mut, seeds = [b"answer", question_account.question_id.to_le_bytes().as_ref()], bump)]
+    pub answer_account: Account<'info, AnswerAccount>,
+    #[account(
+        init,
+        payer = disputer,
+        space = 8 + DisputeAccount::LEN,
+        seeds = [b"dispute", question_account.question_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub dispute_account: Account<'info, DisputeAccount>,
+    #[account(seeds = [b"oracle_state"], bump = oracle_state.bump)]
+    pub oracle_state: Account<'info, OracleState>,
+    #[account(mut)]
+    pub disputer: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct ResolveDispute<'info> {
+    #[account(mut, seeds = [b"question", question_account.question_id.to_le_bytes().as_ref()], bump)]
+    pub question_account: Account<'info, QuestionAccount>,
+    #[account(mut, seeds = [b"answer", question_account.question_id.to_le_bytes().as_ref()], bump)]
+    pub answer_account: Account<'info, AnswerAccount>,
+    #[account(mut, seeds = [b"bond", answer_account.provider.as_ref()], bump = provider_bond.bump)]
+    pub provider_bond: Account<'info, ProviderBond>,
+    #[account(
+        mut,
+        seeds = [b"dispute", question_account.question_id.to_le_bytes().as_ref()],
+        bump = dispute_account.bump
+    )]
+    pub dispute_account: Account<'info, DisputeAccount>,
+    #[account(
+        seeds = [b"oracle_state"],
+        bump = oracle_state.bump,
+        has_one = authority @ Trace9Error::Unauthorized
+    )]
+    pub oracle_state: Account<'info, OracleState>,
+    /// CHECK: Verified against the disputer recorded on the answer
+    #[account(mut)]
+    pub disputer: UncheckedAccount<'info>,
+    /// CHECK: Verified against the treasury stored on oracle state
+    #[account(mut, address = oracle_state.treasury @ Trace9Error::InvalidTreasury)]
+    pub treasury: UncheckedAccount<'info>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PostBond<'info> {
+    #[account(
+        init_if_needed,
+        payer = provider,
+        space = 8 + ProviderBond::LEN,
+        seeds = [b"bond", provider.key().as_ref()],
+        bump
+    )]
+    pub provider_bond: Account<'info, ProviderBond>,
+    #[account(mut)]
+    pub provider: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawBond<'info> {
+    #[account(mut, seeds = [b"bond", provider.key().as_ref()], bump = provider_bond.bump)]
+    pub provider_bond: Account<'info, ProviderBond>,
+    #[account(mut)]
+    pub provider: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RevealQuestion<'info> {
+    #[account(mut, seeds = [b"question", question_account.question_id.to_le_bytes().as_ref()], bump)]
+    pub question_account: Account<'info, QuestionAccount>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireQuestion<'info> {
+    #[account(mut, seeds = [b"question", question_account.question_id.to_le_bytes().as_ref()], bump)]
+    pub question_account: Account<'info, QuestionAccount>,
+}
+
+#[derive(Accounts)]
+pub struct ExtendDeadline<'info> {
+    #[account(
+        mut,
+        seeds = [b"question", question_account.question_id.to_le_bytes().as_ref()],
+        bump,
+        has_one = requester @ Trace9Error::Unauthorized
+    )]
+    pub question_account: Account<'info, QuestionAccount>,
+    pub requester: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct EnableIncentiveDecay<'info> {
+    #[account(
+        mut,
+        seeds = [b"question", question_account.question_id.to_le_bytes().as_ref()],
+        bump,
+        has_one = requester @ Trace9Error::Unauthorized
+    )]
+    pub question_account: Account<'info, QuestionAccount>,
+    pub requester: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AccrueIncentive<'info> {
+    #[account(mut, seeds = [b"question", question_account.question_id.to_le_bytes().as_ref()], bump)]
+    pub question_account: Account<'info, QuestionAccount>,
+    #[account(mut, seeds = [b"oracle_state"], bump = oracle_state.bump)]
+    pub oracle_state: Account<'info, OracleState>,
+}
+
 #[derive(Accounts)]
 pub struct BatchAskQuestions<'info> {
     #[account(mut, seeds = [b"oracle_state"], bump = oracle_state.bump)]
     pub oracle_state: Account<'info, OracleState>,
+    #[account(mut, seeds = [b"global_stats"], bump = global_stats.bump)]
+    pub global_stats: Account<'info, GlobalStats>,
     #[account(mut)]
     pub requester: Signer<'info>,
+    /// Allowlist entry, required while the requester allowlist is enabled
+    #[account(seeds = [b"requester", requester.key().as_ref()], bump = allowed_requester.bump)]
+    pub allowed_requester: Option<Account<'info, AllowedRequester>>,
     pub system_program: Program<'info, System>,
 }
 
@@ -401,6 +1408,8 @@ pub struct RefundQuestion<'info> {
     pub question_account: Account<'info, QuestionAccount>,
     #[account(mut, seeds = [b"oracle_state"], bump = oracle_state.bump)]
     pub oracle_state: Account<'info, OracleState>,
+    #[account(mut, seeds = [b"global_stats"], bump = global_stats.bump)]
+    pub global_stats: Account<'info, GlobalStats>,
     #[account(mut)]
     pub requester: Signer<'info>,
     pub system_program: Program<'info, System>,
@@ -416,18 +1425,198 @@ pub struct Withdraw<'info> {
 
 #[derive(Accounts)]
 pub struct SetOracleFee<'info> {
-    #[account(mut, seeds = [b"oracle_state"], bump = oracle_state.bump)]
+    #[account(
+        mut,
+        seeds = [b"oracle_state"],
+        bump = oracle_state.bump,
+        has_one = authority @ Trace9Error::Unauthorized
+    )]
+    pub oracle_state: Account<'info, OracleState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetDeadlineFeeSchedule<'info> {
+    #[account(
+        mut,
+        seeds = [b"oracle_state"],
+        bump = oracle_state.bump,
+        has_one = authority @ Trace9Error::Unauthorized
+    )]
     pub oracle_state: Account<'info, OracleState>,
     pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
 pub struct SetOracleProvider<'info> {
-    #[account(mut, seeds = [b"oracle_state"], bump = oracle_state.bump)]
+    #[account(
+        mut,
+        seeds = [b"oracle_state"],
+        bump = oracle_state.bump,
+        has_one = authority @ Trace9Error::Unauthorized
+    )]
+    pub oracle_state: Account<'info, OracleState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetChallengePeriod<'info> {
+    #[account(
+        mut,
+        seeds = [b"oracle_state"],
+        bump = oracle_state.bump,
+        has_one = authority @ Trace9Error::Unauthorized
+    )]
     pub oracle_state: Account<'info, OracleState>,
     pub authority: Signer<'info>,
 }
 
+#[derive(Accounts)]
+pub struct SetMinBond<'info> {
+    #[account(
+        mut,
+        seeds = [b"oracle_state"],
+        bump = oracle_state.bump,
+        has_one = authority @ Trace9Error::Unauthorized
+    )]
+    pub oracle_state: Account<'info, OracleState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetDisputeBond<'info> {
+    #[account(
+        mut,
+        seeds = [b"oracle_state"],
+        bump = oracle_state.bump,
+        has_one = authority @ Trace9Error::Unauthorized
+    )]
+    pub oracle_state: Account<'info, OracleState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAllowSelfAnswer<'info> {
+    #[account(
+        mut,
+        seeds = [b"oracle_state"],
+        bump = oracle_state.bump,
+        has_one = authority @ Trace9Error::Unauthorized
+    )]
+    pub oracle_state: Account<'info, OracleState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetWithdrawCooldown<'info> {
+    #[account(
+        mut,
+        seeds = [b"oracle_state"],
+        bump = oracle_state.bump,
+        has_one = authority @ Trace9Error::Unauthorized
+    )]
+    pub oracle_state: Account<'info, OracleState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ResyncCounter<'info> {
+    #[account(
+        mut,
+        seeds = [b"oracle_state"],
+        bump = oracle_state.bump,
+        has_one = authority @ Trace9Error::Unauthorized
+    )]
+    pub oracle_state: Account<'info, OracleState>,
+    pub authority: Signer<'info>,
+    // Remaining accounts: the question PDA for the new counter value
+}
+
+#[derive(Accounts)]
+pub struct SetRequesterAllowlist<'info> {
+    #[account(
+        mut,
+        seeds = [b"oracle_state"],
+        bump = oracle_state.bump,
+        has_one = authority @ Trace9Error::Unauthorized
+    )]
+    pub oracle_state: Account<'info, OracleState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(requester: Pubkey)]
+pub struct AddRequester<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + AllowedRequester::LEN,
+        seeds = [b"requester", requester.as_ref()],
+        bump
+    )]
+    pub allowed_requester: Account<'info, AllowedRequester>,
+    #[account(seeds = [b"oracle_state"], bump = oracle_state.bump, has_one = authority @ Trace9Error::Unauthorized)]
+    pub oracle_state: Account<'info, OracleState>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(requester: Pubkey)]
+pub struct RemoveRequester<'info> {
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"requester", requester.as_ref()],
+        bump = allowed_requester.bump
+    )]
+    pub allowed_requester: Account<'info, AllowedRequester>,
+    #[account(seeds = [b"oracle_state"], bump = oracle_state.bump, has_one = authority @ Trace9Error::Unauthorized)]
+    pub oracle_state: Account<'info, OracleState>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAttestationPubkey<'info> {
+    #[account(
+        mut,
+        seeds = [b"oracle_state"],
+        bump = oracle_state.bump,
+        has_one = authority @ Trace9Error::Unauthorized
+    )]
+    pub oracle_state: Account<'info, OracleState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetMultisig<'info> {
+    #[account(
+        mut,
+        seeds = [b"oracle_state"],
+        bump = oracle_state.bump,
+        has_one = authority @ Trace9Error::Unauthorized
+    )]
+    pub oracle_state: Account<'info, OracleState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetTreasury<'info> {
+    #[account(
+        mut,
+        seeds = [b"oracle_state"],
+        bump = oracle_state.bump,
+        has_one = authority @ Trace9Error::Unauthorized
+    )]
+    pub oracle_state: Account<'info, OracleState>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetStatuses {}
+
 #[account]
 pub struct OracleState {
     pub authority: Pubkey,           // 32 bytes
@@ -435,11 +1624,66 @@ pub struct OracleState {
     pub question_counter: u64,        // 8 bytes
     pub oracle_fee: u64,             // 8 bytes (in lamports)
     pub provider_balance: u64,        // 8 bytes (in lamports)
+    pub challenge_period: i64,        // 8 bytes (seconds)
+    pub min_bond: u64,                // 8 bytes (in lamports)
+    pub allow_self_answer: bool,      // 1 byte
+    pub withdraw_cooldown_secs: i64,  // 8 bytes (seconds)
+    pub last_answer_at: i64,          // 8 bytes
+    pub incentive_pool: u64,          // 8 bytes (in lamports)
+    pub attestation_pubkey: Pubkey,   // 32 bytes (default = attestations disabled)
+    pub multisig_signers: Vec<Pubkey>, // 4 + MAX_MULTISIG_SIGNERS * 32 bytes
+    pub multisig_threshold: u8,       // 1 byte (1 = single authority)
+    pub requester_allowlist_enabled: bool, // 1 byte (false = anyone may ask)
+    pub max_question_len: u16,        // 2 bytes (longest accepted question)
+    pub short_deadline_fee_bps: u16,  // 2 bytes (fee for an immediate deadline, bps of oracle_fee; 0 = flat pricing)
+    pub long_deadline_fee_bps: u16,   // 2 bytes (fee at MAX_DEADLINE_HORIZON, bps of oracle_fee)
+    pub dispute_bond: u64,            // 8 bytes (in lamports, posted per dispute)
+    pub treasury: Pubkey,             // 32 bytes (receives the protocol share of slashed bonds)
     pub bump: u8,                     // 1 byte
 }
 
 impl OracleState {
-    pub const LEN: usize = 32 + 32 + 8 + 8 + 8 + 1;
+    pub const LEN: usize = 32 + 32 + 8 + 8 + 8 + 8 + 8 + 1 + 8 + 8 + 8 + 32 + (4 + MAX_MULTISIG_SIGNERS * 32) + 1 + 1 + 2 + 2 + 2 + 8 + 32 + 1;
+
+    /// Fee for a question due at `deadline`: the flat oracle fee, or under a deadline fee
+    /// schedule, scaled linearly so shorter horizons pay more
+    pub fn fee_for_deadline(&self, deadline: i64, now: i64) -> Result<u64> {
+        if self.short_deadline_fee_bps == 0 {
+            return Ok(self.oracle_fee);
+        }
+
+        let horizon = deadline
+            .checked_sub(now)
+            .ok_or(Trace9Error::Overflow)?
+            .clamp(0, MAX_DEADLINE_HORIZON) as u128;
+        let short_bps = self.short_deadline_fee_bps as u128;
+        let long_bps = self.long_deadline_fee_bps as u128;
+        let bps = short_bps - (short_bps - long_bps) * horizon / MAX_DEADLINE_HORIZON as u128;
+
+        (self.oracle_fee as u128)
+            .checked_mul(bps)
+            .and_then(|fee| fee.checked_div(10_000))
+            .and_then(|fee| u64::try_from(fee).ok())
+            .ok_or(Trace9Error::Overflow.into())
+    }
+
+    /// Require at least `multisig_threshold` of the configured signers among `approvals`
+    pub fn require_multisig(&self, approvals: &[AccountInfo]) -> Result<()> {
+        if self.multisig_threshold <= 1 {
+            return Ok(());
+        }
+
+        let approved = self
+            .multisig_signers
+            .iter()
+            .filter(|signer| approvals.iter().any(|a| a.is_signer && a.key == *signer))
+            .count();
+        require!(
+            approved >= self.multisig_threshold as usize,
+            Trace9Error::MultisigThresholdNotMet
+        );
+        Ok(())
+    }
 }
 
 #[account]
@@ -448,15 +1692,20 @@ pub struct QuestionAccount {
     pub requester: Pubkey,             // 32 bytes
     pub question_type: QuestionType,  // 1 byte
     pub question_hash: [u8; 32],      // 32 bytes
+    pub category: String,              // 4 + MAX_CATEGORY_LEN bytes
     pub bounty: u64,                   // 8 bytes (in lamports)
     pub timestamp: i64,                // 8 bytes
     pub deadline: i64,                 // 8 bytes
     pub status: AnswerStatus,          // 1 byte
     pub refunded: bool,                // 1 byte
+    pub revealed: bool,                // 1 byte
+    pub incentive_opt_in: bool,        // 1 byte
+    pub last_accrual_at: i64,          // 8 bytes
+    pub assigned_provider: Option<Pubkey>, // 1 + 32 bytes (None = the registered provider)
 }
 
 impl QuestionAccount {
-    pub const LEN: usize = 8 + 32 + 1 + 32 + 8 + 8 + 8 + 1 + 1;
+    pub const LEN: usize = 8 + 32 + 1 + 32 + (4 + MAX_CATEGORY_LEN) + 8 + 8 + 8 + 1 + 1 + 1 + 1 + 8 + (1 + 32);
 }
 
 #[account]
@@ -467,10 +1716,64 @@ pub struct AnswerAccount {
     pub bool_answer: bool,              // 1 byte
     pub numeric_answer: u64,           // 8 bytes
     pub timestamp: i64,                // 8 bytes
+    pub challenge_deadline: i64,       // 8 bytes
+    pub revision: u8,                  // 1 byte
+    pub bond_locked: u64,              // 8 bytes (in lamports)
+    pub disputer: Pubkey,              // 32 bytes
+    pub inconclusive: bool,            // 1 byte (no consensus, value fields carry no answer)
 }
 
 impl AnswerAccount {
-    pub const LEN: usize = 8 + 32 + 1 + 1 + 8 + 8;
+    pub const LEN: usize = 8 + 32 + 1 + 1 + 8 + 8 + 8 + 1 + 8 + 32 + 1;
+}
+
+#[account]
+pub struct ProviderBond {
+    pub provider: Pubkey,              // 32 bytes
+    pub bond_balance: u64,             // 8 bytes (in lamports)
+    pub locked_balance: u64,           // 8 bytes (in lamports)
+    pub bump: u8,                      // 1 byte
+}
+
+impl ProviderBond {
+    pub const LEN: usize = 32 + 8 + 8 + 1;
+}
+
+#[account]
+pub struct DisputeAccount {
+    pub question_id: u64,              // 8 bytes
+    pub disputer: Pubkey,              // 32 bytes
+    pub bond: u64,                     // 8 bytes (in lamports, held here until resolution)
+    pub settled: bool,                 // 1 byte
+    pub upheld: bool,                  // 1 byte (dispute won, answer overturned)
+    pub bump: u8,                      // 1 byte
+}
+
+impl DisputeAccount {
+    pub const LEN: usize = 8 + 32 + 8 + 1 + 1 + 1;
+}
+
+#[account]
+pub struct AllowedRequester {
+    pub requester: Pubkey,             // 32 bytes
+    pub bump: u8,                      // 1 byte
+}
+
+impl AllowedRequester {
+    pub const LEN: usize = 32 + 1;
+}
+
+#[account]
+pub struct GlobalStats {
+    pub total_questions: u64,          // 8 bytes
+    pub total_answered: u64,           // 8 bytes
+    pub total_refunded: u64,           // 8 bytes
+    pub total_bounties_paid: u64,      // 8 bytes (in lamports)
+    pub bump: u8,                      // 1 byte
+}
+
+impl GlobalStats {
+    pub const LEN: usize = 8 + 8 + 8 + 8 + 1;
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
@@ -487,6 +1790,15 @@ pub enum AnswerStatus {
     Answered,
     Disputed,
     Finalized,
+    Expired,
+}
+
+// Packed get_statuses entry: 10 bytes per question
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct QuestionStatus {
+    pub question_id: u64,
+    pub status: AnswerStatus,
+    pub refunded: bool, // Refunds keep the Pending/Expired status
 }
 
 #[event]
@@ -495,8 +1807,10 @@ pub struct QuestionAsked {
     pub requester: Pubkey,
     pub question_type: QuestionType,
     pub question: String,
+    pub category: String,
     pub bounty: u64,
     pub deadline: i64,
+    pub assigned_provider: Option<Pubkey>,
 }
 
 #[event]
@@ -507,6 +1821,85 @@ pub struct AnswerProvided {
     pub bool_answer: bool,
     pub confidence_score: u8,
     pub data_source: String,
+    pub incentive_bonus: u64,
+    pub inconclusive: bool,
+}
+
+#[event]
+pub struct AnswerRevised {
+    pub question_id: u64,
+    pub revision: u8,
+    pub text_answer: String,
+    pub numeric_answer: u64,
+    pub bool_answer: bool,
+    pub confidence_score: u8,
+    pub data_source: String,
+}
+
+#[event]
+pub struct AnswerFinalized {
+    pub question_id: u64,
+}
+
+#[event]
+pub struct AnswerDisputed {
+    pub question_id: u64,
+    pub disputer: Pubkey,
+    pub bond: u64,
+}
+
+#[event]
+pub struct DisputeResolved {
+    pub question_id: u64,
+    pub overturned: bool,
+    pub slashed: u64,
+    pub disputer_payout: u64, // Bond plus reward when overturned, 0 when the bond is forfeited
+}
+
+#[event]
+pub struct BondPosted {
+    pub provider: Pubkey,
+    pub amount: u64,
+    pub bond_balance: u64,
+}
+
+#[event]
+pub struct BondWithdrawn {
+    pub provider: Pubkey,
+    pub amount: u64,
+    pub bond_balance: u64,
+}
+
+#[event]
+pub struct ProviderWithdrawn {
+    pub provider: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct QuestionRevealed {
+    pub question_id: u64,
+    pub question_hash: [u8; 32],
+    pub question: String,
+}
+
+#[event]
+pub struct QuestionExpired {
+    pub question_id: u64,
+}
+
+#[event]
+pub struct DeadlineExtended {
+    pub question_id: u64,
+    pub old_deadline: i64,
+    pub new_deadline: i64,
+}
+
+#[event]
+pub struct IncentiveAccrued {
+    pub question_id: u64,
+    pub amount: u64,
+    pub incentive_pool: u64,
 }
 
 #[event]
@@ -527,6 +1920,32 @@ pub struct OracleFeeUpdated {
     pub new_fee: u64,
 }
 
+#[event]
+pub struct DeadlineFeeScheduleUpdated {
+    pub old_short_bps: u16,
+    pub old_long_bps: u16,
+    pub new_short_bps: u16,
+    pub new_long_bps: u16,
+}
+
+#[event]
+pub struct MultisigUpdated {
+    pub signers: Vec<Pubkey>,
+    pub threshold: u8,
+}
+
+#[event]
+pub struct TreasuryUpdated {
+    pub old_treasury: Pubkey,
+    pub new_treasury: Pubkey,
+}
+
+#[event]
+pub struct CounterResynced {
+    pub old_counter: u64,
+    pub new_counter: u64,
+}
+
 #[error_code]
 pub enum Trace9Error {
     #[msg("Invalid question")]
@@ -553,4 +1972,74 @@ pub enum Trace9Error {
     InvalidBatch,
     #[msg("Invalid batch size")]
     InvalidBatchSize,
+    #[msg("Bounty below oracle fee")]
+    BountyTooLow,
+    #[msg("Invalid category")]
+    InvalidCategory,
+    #[msg("Not answered")]
+    NotAnswered,
+    #[msg("Answer finalized")]
+    AnswerFinalized,
+    #[msg("Challenge window closed")]
+    ChallengeWindowClosed,
+    #[msg("Challenge window still open")]
+    ChallengeWindowOpen,
+    #[msg("Invalid challenge period")]
+    InvalidChallengePeriod,
+    #[msg("Not disputed")]
+    NotDisputed,
+    #[msg("Insufficient bond")]
+    InsufficientBond,
+    #[msg("Invalid amount")]
+    InvalidAmount,
+    #[msg("Deadline not passed")]
+    DeadlineNotPassed,
+    #[msg("Question does not match stored hash")]
+    QuestionHashMismatch,
+    #[msg("Provider cannot answer its own question")]
+    SelfAnswerForbidden,
+    #[msg("Withdraw cooldown has not elapsed")]
+    WithdrawCooldownActive,
+    #[msg("Invalid withdraw cooldown")]
+    InvalidCooldown,
+    #[msg("Incentive decay not enabled")]
+    IncentiveNotEnabled,
+    #[msg("Incentive decay already enabled")]
+    IncentiveAlreadyEnabled,
+    #[msg("Nothing to accrue")]
+    NothingToAccrue,
+    #[msg("Missing answer attestation")]
+    MissingAttestation,
+    #[msg("Answer attestation does not match")]
+    InvalidAttestation,
+    #[msg("Deadline is too far in the future")]
+    DeadlineTooFar,
+    #[msg("Batch vectors differ in length")]
+    LengthMismatch,
+    #[msg("Batch is empty")]
+    EmptyBatch,
+    #[msg("Batch is too large")]
+    BatchTooLarge,
+    #[msg("Batch contains a duplicate ID")]
+    DuplicateBatchId,
+    #[msg("Invalid multisig signers or threshold")]
+    InvalidMultisig,
+    #[msg("Not enough multisig signers approved")]
+    MultisigThresholdNotMet,
+    #[msg("Account is not a question account")]
+    InvalidQuestionAccount,
+    #[msg("Requester is not on the allowlist")]
+    RequesterNotAllowed,
+    #[msg("Counter is already at that value")]
+    InvalidCounter,
+    #[msg("Question already exists")]
+    QuestionExists,
+    #[msg("Question is assigned to another provider")]
+    NotAssignedProvider,
+    #[msg("Max question length must be between 1 and MAX_QUESTION_LEN")]
+    InvalidMaxQuestionLen,
+    #[msg("Deadline fee schedule must be both 0 or short_bps >= long_bps > 0")]
+    InvalidFeeSchedule,
+    #[msg("Invalid treasury")]
+    InvalidTreasury,
 }
@@ -174,6 +174,7 @@ export class SimplePredictionMarketClient {
       .accounts({
         marketAccount: marketPDA,
         position: positionPDA,
+        positionOwner: this.provider.wallet.publicKey,
         winner: this.provider.wallet.publicKey,
       })
       .rpc();
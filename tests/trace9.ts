@@ -1,7 +1,32 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { Trace9 } from "../target/types/trace9";
-import { PublicKey, Keypair, SystemProgram, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import { SimplePredictionMarket } from "../target/types/simple_prediction_market";
+import { MultiOutcomeMarket } from "../target/types/multi_outcome_market";
+import { RangeMarket } from "../target/types/range_market";
+import { TimeSeriesMarket } from "../target/types/time_series_market";
+import { ConditionalMarket } from "../target/types/conditional_market";
+import { PaymentFacilitator } from "../target/types/payment_facilitator";
+import {
+  PublicKey,
+  Keypair,
+  SystemProgram,
+  LAMPORTS_PER_SOL,
+  Ed25519Program,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+  Transaction,
+} from "@solana/web3.js";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+  createMint,
+  getAccount,
+  getAssociatedTokenAddressSync,
+  getMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+  transfer,
+} from "@solana/spl-token";
 import { expect } from "chai";
 
 describe("trace9", () => {
@@ -17,6 +42,8 @@ describe("trace9", () => {
 
   let oracleStatePDA: PublicKey;
   let oracleBump: number;
+  let providerBondPDA: PublicKey;
+  let globalStatsPDA: PublicKey;
 
   before(async () => {
     // Airdrop SOL to test accounts
@@ -32,14 +59,173 @@ describe("trace9", () => {
       [Buffer.from("oracle_state")],
       program.programId
     );
+
+    [globalStatsPDA] = await PublicKey.findProgramAddress(
+      [Buffer.from("global_stats")],
+      program.programId
+    );
+
+    [providerBondPDA] = await PublicKey.findProgramAddress(
+      [Buffer.from("bond"), oracleProvider.publicKey.toBuffer()],
+      program.programId
+    );
   });
 
+  // Derive the question and answer PDAs for a question id
+  const findQuestionPDAs = async (questionId: number) => {
+    const questionIdBuffer = Buffer.allocUnsafe(8);
+    questionIdBuffer.writeBigUInt64LE(BigInt(questionId), 0);
+
+    const [questionPDA] = await PublicKey.findProgramAddress(
+      [Buffer.from("question"), questionIdBuffer],
+      program.programId
+    );
+    const [answerPDA] = await PublicKey.findProgramAddress(
+      [Buffer.from("answer"), questionIdBuffer],
+      program.programId
+    );
+
+    return { questionPDA, answerPDA };
+  };
+
+  const findDisputePDA = async (questionPDA: PublicKey) => {
+    const { questionId } = await program.account.questionAccount.fetch(questionPDA);
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("dispute"), questionId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+  };
+
+  // Ask a fresh question and answer it, returning its PDAs
+  const askAndAnswer = async (question: string, numericAnswer: number | anchor.BN = 100, inconclusive = false) => {
+    const deadline = Math.floor(Date.now() / 1000) + 86400;
+    const state = await program.account.oracleState.fetch(oracleStatePDA);
+    const questionId = state.questionCounter.toNumber();
+    const { questionPDA, answerPDA } = await findQuestionPDAs(questionId);
+
+    await program.methods
+      .askQuestion({ numeric: {} }, question, "general", new anchor.BN(deadline), new anchor.BN(0), null)
+      .accounts({
+        questionAccount: questionPDA,
+        oracleState: oracleStatePDA,
+        globalStats: globalStatsPDA,
+        requester: requester.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([requester])
+      .rpc();
+
+    await program.methods
+      .provideAnswer(String(numericAnswer), new anchor.BN(numericAnswer), false, 80, "Initial source", null, inconclusive)
+      .accounts({
+        questionAccount: questionPDA,
+        answerAccount: answerPDA,
+        oracleState: oracleStatePDA,
+        globalStats: globalStatsPDA,
+        providerBond: providerBondPDA,
+        oracleProvider: oracleProvider.publicKey,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([oracleProvider])
+      .rpc();
+
+    return { questionId, questionPDA, answerPDA };
+  };
+
+  // Market on a freshly answered question, bound through the market program's question_binding PDA.
+  // `marketArgs` gives the program's createMarket arguments for the question id
+  const createAnsweredMarket = async (
+    marketProgram: Program<any>,
+    marketStatePDA: PublicKey,
+    question: string,
+    marketArgs: (questionId: anchor.BN) => unknown[],
+    numericAnswer: number | anchor.BN = 100,
+    inconclusive = false
+  ) => {
+    const { questionId, questionPDA, answerPDA } = await askAndAnswer(question, numericAnswer, inconclusive);
+
+    const marketId = (await marketProgram.account.marketState.fetch(marketStatePDA)).marketCounter as anchor.BN;
+    const [marketPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("market"), marketId.toArrayLike(Buffer, "le", 8)],
+      marketProgram.programId
+    );
+    const [questionBindingPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("question_binding"), new anchor.BN(questionId).toArrayLike(Buffer, "le", 8)],
+      marketProgram.programId
+    );
+
+    await marketProgram.methods
+      .createMarket(...marketArgs(new anchor.BN(questionId)))
+      .accounts({
+        marketAccount: marketPDA,
+        marketState: marketStatePDA,
+        questionBinding: questionBindingPDA,
+        creator: authority.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    return { marketId, marketPDA, questionPDA, answerPDA, questionBindingPDA };
+  };
+
+  // Bettors on the lamport markets stake their whole balance, so each one gets a fresh airdrop
+  const bet = async (
+    marketProgram: Program<any>,
+    marketStatePDA: PublicKey,
+    market: { marketId: anchor.BN; marketPDA: PublicKey },
+    side: number | boolean,
+    airdrop = LAMPORTS_PER_SOL
+  ) => {
+    const bettor = Keypair.generate();
+    await provider.connection.requestAirdrop(bettor.publicKey, airdrop);
+    await new Promise(resolve => setTimeout(resolve, 1000));
+
+    const [position] = PublicKey.findProgramAddressSync(
+      [Buffer.from("position"), market.marketId.toArrayLike(Buffer, "le", 8), bettor.publicKey.toBuffer()],
+      marketProgram.programId
+    );
+    await marketProgram.methods
+      .takePosition(market.marketId, side)
+      .accounts({
+        marketAccount: market.marketPDA,
+        position,
+        marketState: marketStatePDA,
+        bettor: bettor.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([bettor])
+      .rpc();
+
+    return bettor;
+  };
+
+  const expectError = async (call: Promise<unknown>, code: string) => {
+    try {
+      await call;
+      expect.fail(`should have failed with ${code}`);
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal(code);
+    }
+  };
+
+  const setChallengePeriod = async (seconds: number) => {
+    await program.methods
+      .setChallengePeriod(new anchor.BN(seconds))
+      .accounts({
+        oracleState: oracleStatePDA,
+        authority: authority.publicKey,
+      })
+      .rpc();
+  };
+
   it("Initializes the oracle", async () => {
     try {
       const tx = await program.methods
-        .initialize(oracleProvider.publicKey)
+        .initialize(oracleProvider.publicKey, 500)
         .accounts({
           oracleState: oracleStatePDA,
+          globalStats: globalStatsPDA,
           authority: authority.publicKey,
           systemProgram: SystemProgram.programId,
         })
@@ -53,6 +239,7 @@ describe("trace9", () => {
       expect(state.oracleProvider.toString()).to.equal(oracleProvider.publicKey.toString());
       expect(state.questionCounter.toNumber()).to.equal(0);
       expect(state.oracleFee.toNumber()).to.equal(10_000_000); // 0.01 SOL
+      expect(state.maxQuestionLen).to.equal(500);
     } catch (error) {
       // If already initialized, that's okay
       if (error.message && error.message.includes("already in use")) {
@@ -63,6 +250,22 @@ describe("trace9", () => {
     }
   });
 
+  it("Posts a provider bond", async () => {
+    await program.methods
+      .postBond(new anchor.BN(LAMPORTS_PER_SOL))
+      .accounts({
+        providerBond: providerBondPDA,
+        provider: oracleProvider.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([oracleProvider])
+      .rpc();
+
+    const bond = await program.account.providerBond.fetch(providerBondPDA);
+    expect(bond.provider.toString()).to.equal(oracleProvider.publicKey.toString());
+    expect(bond.bondBalance.toNumber()).to.be.at.least(LAMPORTS_PER_SOL);
+  });
+
   it("Asks a question", async () => {
     const questionType = { general: {} };
     const question = "What is the price of BTC?";
@@ -82,10 +285,11 @@ describe("trace9", () => {
     );
 
     const tx = await program.methods
-      .askQuestion(questionType, question, new anchor.BN(deadline))
+      .askQuestion(questionType, question, "crypto", new anchor.BN(deadline), new anchor.BN(0), null)
       .accounts({
         questionAccount: questionPDA,
         oracleState: oracleStatePDA,
+        globalStats: globalStatsPDA,
         requester: requester.publicKey,
         systemProgram: SystemProgram.programId,
       })
@@ -125,13 +329,18 @@ describe("trace9", () => {
         new anchor.BN(45000),
         false,
         95,
-        "CoinGecko API"
+        "CoinGecko API",
+        null,
+        false
       )
       .accounts({
         questionAccount: questionPDA,
         answerAccount: answerPDA,
         oracleState: oracleStatePDA,
+        globalStats: globalStatsPDA,
+        providerBond: providerBondPDA,
         oracleProvider: oracleProvider.publicKey,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
         systemProgram: SystemProgram.programId,
       })
       .signers([oracleProvider])
@@ -173,5 +382,6598 @@ describe("trace9", () => {
     expect(questionAccount.status).to.deep.equal({ answered: {} });
     expect(answerAccount.numericAnswer.toNumber()).to.equal(45000);
   });
+
+  it("Asks a question with a custom bounty and pays it to the provider", async () => {
+    const bounty = 50_000_000; // 0.05 SOL
+    const deadline = Math.floor(Date.now() / 1000) + 86400;
+
+    const state = await program.account.oracleState.fetch(oracleStatePDA);
+    const questionId = state.questionCounter.toNumber();
+
+    const questionIdBuffer = Buffer.allocUnsafe(8);
+    questionIdBuffer.writeBigUInt64LE(BigInt(questionId), 0);
+
+    const [questionPDA] = await PublicKey.findProgramAddress(
+      [Buffer.from("question"), questionIdBuffer],
+      program.programId
+    );
+
+    const [answerPDA] = await PublicKey.findProgramAddress(
+      [Buffer.from("answer"), questionIdBuffer],
+      program.programId
+    );
+
+    await program.methods
+      .askQuestion({ numeric: {} }, "Total rainfall in London in May (mm)?", "weather", new anchor.BN(deadline), new anchor.BN(bounty), null)
+      .accounts({
+        questionAccount: questionPDA,
+        oracleState: oracleStatePDA,
+        globalStats: globalStatsPDA,
+        requester: requester.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([requester])
+      .rpc();
+
+    const questionAccount = await program.account.questionAccount.fetch(questionPDA);
+    expect(questionAccount.bounty.toNumber()).to.equal(bounty);
+
+    const balanceBefore = state.providerBalance.toNumber();
+
+    await program.methods
+      .provideAnswer("42mm", new anchor.BN(42), false, 90, "Met Office", null, false)
+      .accounts({
+        questionAccount: questionPDA,
+        answerAccount: answerPDA,
+        oracleState: oracleStatePDA,
+        globalStats: globalStatsPDA,
+        providerBond: providerBondPDA,
+        oracleProvider: oracleProvider.publicKey,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([oracleProvider])
+      .rpc();
+
+    const stateAfter = await program.account.oracleState.fetch(oracleStatePDA);
+    expect(stateAfter.providerBalance.toNumber()).to.equal(balanceBefore + bounty);
+  });
+
+  it("Rejects a custom bounty below the oracle fee", async () => {
+    const deadline = Math.floor(Date.now() / 1000) + 86400;
+
+    const state = await program.account.oracleState.fetch(oracleStatePDA);
+    const questionIdBuffer = Buffer.allocUnsafe(8);
+    questionIdBuffer.writeBigUInt64LE(BigInt(state.questionCounter.toNumber()), 0);
+
+    const [questionPDA] = await PublicKey.findProgramAddress(
+      [Buffer.from("question"), questionIdBuffer],
+      program.programId
+    );
+
+    try {
+      await program.methods
+        .askQuestion({ general: {} }, "Too cheap?", "general", new anchor.BN(deadline), new anchor.BN(1), null)
+        .accounts({
+          questionAccount: questionPDA,
+          oracleState: oracleStatePDA,
+          globalStats: globalStatsPDA,
+          requester: requester.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([requester])
+        .rpc();
+      expect.fail("should have rejected a bounty below the oracle fee");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("BountyTooLow");
+    }
+  });
+
+  it("Round-trips the question category through the account and event", async () => {
+    const deadline = Math.floor(Date.now() / 1000) + 86400;
+
+    const state = await program.account.oracleState.fetch(oracleStatePDA);
+    const questionIdBuffer = Buffer.allocUnsafe(8);
+    questionIdBuffer.writeBigUInt64LE(BigInt(state.questionCounter.toNumber()), 0);
+
+    const [questionPDA] = await PublicKey.findProgramAddress(
+      [Buffer.from("question"), questionIdBuffer],
+      program.programId
+    );
+
+    let eventCategory: string | null = null;
+    const listener = program.addEventListener("questionAsked", (event) => {
+      eventCategory = event.category;
+    });
+
+    await program.methods
+      .askQuestion({ yesNo: {} }, "Will the home team win on Saturday?", "sports", new anchor.BN(deadline), new anchor.BN(0), null)
+      .accounts({
+        questionAccount: questionPDA,
+        oracleState: oracleStatePDA,
+        globalStats: globalStatsPDA,
+        requester: requester.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([requester])
+      .rpc();
+
+    await new Promise(resolve => setTimeout(resolve, 1000));
+    await program.removeEventListener(listener);
+
+    const questionAccount = await program.account.questionAccount.fetch(questionPDA);
+    expect(questionAccount.category).to.equal("sports");
+    expect(eventCategory).to.equal("sports");
+  });
+
+  it("Revises an answer within the challenge window", async () => {
+    await setChallengePeriod(86400);
+    const { questionPDA, answerPDA } = await askAndAnswer("ETH gas price in gwei?");
+
+    await program.methods
+      .reviseAnswer("120", new anchor.BN(120), false, 95, "Better source", null)
+      .accounts({
+        questionAccount: questionPDA,
+        answerAccount: answerPDA,
+        oracleState: oracleStatePDA,
+        oracleProvider: oracleProvider.publicKey,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+      })
+      .signers([oracleProvider])
+      .rpc();
+
+    const answerAccount = await program.account.answerAccount.fetch(answerPDA);
+    expect(answerAccount.numericAnswer.toNumber()).to.equal(120);
+    expect(answerAccount.confidenceScore).to.equal(95);
+    expect(answerAccount.revision).to.equal(1);
+  });
+
+  it("Rejects revising a finalized answer", async () => {
+    await setChallengePeriod(0);
+    const { questionPDA, answerPDA } = await askAndAnswer("SOL staking APY?");
+
+    await program.methods
+      .finalizeAnswer()
+      .accounts({
+        questionAccount: questionPDA,
+        answerAccount: answerPDA,
+        providerBond: providerBondPDA,
+      })
+      .rpc();
+
+    const questionAccount = await program.account.questionAccount.fetch(questionPDA);
+    expect(questionAccount.status).to.deep.equal({ finalized: {} });
+
+    try {
+      await program.methods
+        .reviseAnswer("7", new anchor.BN(7), false, 90, "Late source", null)
+        .accounts({
+          questionAccount: questionPDA,
+          answerAccount: answerPDA,
+          oracleState: oracleStatePDA,
+          oracleProvider: oracleProvider.publicKey,
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+        })
+        .signers([oracleProvider])
+        .rpc();
+      expect.fail("should have rejected revising a finalized answer");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("AnswerFinalized");
+    } finally {
+      await setChallengePeriod(86400);
+    }
+  });
+
+  const disputeAnswer = async (questionPDA: PublicKey, answerPDA: PublicKey) => {
+    const disputePDA = await findDisputePDA(questionPDA);
+    await program.methods
+      .disputeAnswer()
+      .accounts({
+        questionAccount: questionPDA,
+        answerAccount: answerPDA,
+        disputeAccount: disputePDA,
+        oracleState: oracleStatePDA,
+        disputer: requester.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([requester])
+      .rpc();
+    return disputePDA;
+  };
+
+  const resolveDispute = (
+    questionPDA: PublicKey,
+    answerPDA: PublicKey,
+    disputePDA: PublicKey,
+    overturn: boolean,
+    treasury: PublicKey = authority.publicKey
+  ) =>
+    program.methods
+      .resolveDispute(overturn, new anchor.BN(52), false, 90)
+      .accounts({
+        questionAccount: questionPDA,
+        answerAccount: answerPDA,
+        providerBond: providerBondPDA,
+        disputeAccount: disputePDA,
+        oracleState: oracleStatePDA,
+        disputer: requester.publicKey,
+        treasury,
+        authority: authority.publicKey,
+      })
+      .rpc();
+
+  const setTreasury = (treasury: PublicKey) =>
+    program.methods
+      .setTreasury(treasury)
+      .accounts({ oracleState: oracleStatePDA, authority: authority.publicKey })
+      .rpc();
+
+  it("Slashes the provider bond and doubles the dispute bond when a disputed answer is overturned", async () => {
+    const { questionPDA, answerPDA } = await askAndAnswer("BTC dominance percentage?");
+    const disputePDA = await disputeAnswer(questionPDA, answerPDA);
+
+    // The dispute bond is held in the dispute account until resolution
+    const dispute = await program.account.disputeAccount.fetch(disputePDA);
+    const disputeBond = dispute.bond.toNumber();
+    const { disputeBond: configuredBond } = await program.account.oracleState.fetch(oracleStatePDA);
+    expect(disputeBond).to.equal(configuredBond.toNumber());
+    expect(dispute.disputer.toBase58()).to.equal(requester.publicKey.toBase58());
+
+    const bondBefore = await program.account.providerBond.fetch(providerBondPDA);
+    const answerBefore = await program.account.answerAccount.fetch(answerPDA);
+    const disputerBalanceBefore = await provider.connection.getBalance(requester.publicKey);
+    const disputeLamportsBefore = await provider.connection.getBalance(disputePDA);
+
+    await resolveDispute(questionPDA, answerPDA, disputePDA, true);
+
+    const slashed = answerBefore.bondLocked.toNumber();
+    const bondAfter = await program.account.providerBond.fetch(providerBondPDA);
+    expect(bondAfter.bondBalance.toNumber()).to.equal(bondBefore.bondBalance.toNumber() - slashed);
+    expect(bondAfter.lockedBalance.toNumber()).to.equal(bondBefore.lockedBalance.toNumber() - slashed);
+
+    // The bond comes back along with an equal reward out of the slashed provider bond
+    const disputerBalanceAfter = await provider.connection.getBalance(requester.publicKey);
+    expect(disputerBalanceAfter - disputerBalanceBefore).to.equal(disputeBond + Math.min(disputeBond, slashed));
+    expect(await provider.connection.getBalance(disputePDA)).to.equal(disputeLamportsBefore - disputeBond);
+
+    const settled = await program.account.disputeAccount.fetch(disputePDA);
+    expect(settled.settled).to.equal(true);
+    expect(settled.upheld).to.equal(true);
+
+    const answerAccount = await program.account.answerAccount.fetch(answerPDA);
+    expect(answerAccount.numericAnswer.toNumber()).to.equal(52);
+
+    const questionAccount = await program.account.questionAccount.fetch(questionPDA);
+    expect(questionAccount.status).to.deep.equal({ finalized: {} });
+  });
+
+  it("Pays the rest of a slashed bond to the configured treasury", async () => {
+    const { questionPDA, answerPDA } = await askAndAnswer("ETH gas price in gwei?");
+    const disputePDA = await disputeAnswer(questionPDA, answerPDA);
+    const { bond } = await program.account.disputeAccount.fetch(disputePDA);
+    const slashed = (await program.account.answerAccount.fetch(answerPDA)).bondLocked.toNumber();
+
+    const treasury = Keypair.generate().publicKey;
+    await setTreasury(treasury);
+    try {
+      try {
+        await resolveDispute(questionPDA, answerPDA, disputePDA, true);
+        expect.fail("should have rejected a treasury other than the configured one");
+      } catch (error) {
+        expect(error.error.errorCode.code).to.equal("InvalidTreasury");
+      }
+
+      await resolveDispute(questionPDA, answerPDA, disputePDA, true, treasury);
+      expect(await provider.connection.getBalance(treasury)).to.equal(slashed - Math.min(bond.toNumber(), slashed));
+    } finally {
+      await setTreasury(authority.publicKey);
+    }
+  });
+
+  it("Forfeits the dispute bond to the provider when the answer stands", async () => {
+    const { questionPDA, answerPDA } = await askAndAnswer("Total SOL supply in millions?");
+    const disputerBalanceBefore = await provider.connection.getBalance(requester.publicKey);
+    const disputePDA = await disputeAnswer(questionPDA, answerPDA);
+
+    const { bond } = await program.account.disputeAccount.fetch(disputePDA);
+    const disputeBond = bond.toNumber();
+    const rent = await provider.connection.getBalance(disputePDA) - disputeBond;
+    expect(await provider.connection.getBalance(requester.publicKey)).to.equal(disputerBalanceBefore - disputeBond - rent);
+
+    const bondBefore = await program.account.providerBond.fetch(providerBondPDA);
+    const answerBefore = await program.account.answerAccount.fetch(answerPDA);
+    const bondLamportsBefore = await provider.connection.getBalance(providerBondPDA);
+    const disputerBalanceMid = await provider.connection.getBalance(requester.publicKey);
+
+    await resolveDispute(questionPDA, answerPDA, disputePDA, false);
+
+    // Nothing comes back to the disputer; the provider's bond grows by the forfeited dispute bond
+    expect(await provider.connection.getBalance(requester.publicKey)).to.equal(disputerBalanceMid);
+    const bondAfter = await program.account.providerBond.fetch(providerBondPDA);
+    expect(bondAfter.bondBalance.toNumber()).to.equal(bondBefore.bondBalance.toNumber() + disputeBond);
+    expect(bondAfter.lockedBalance.toNumber()).to.equal(
+      bondBefore.lockedBalance.toNumber() - answerBefore.bondLocked.toNumber()
+    );
+    expect(await provider.connection.getBalance(providerBondPDA)).to.equal(bondLamportsBefore + disputeBond);
+    expect(await provider.connection.getBalance(disputePDA)).to.equal(rent);
+
+    const settled = await program.account.disputeAccount.fetch(disputePDA);
+    expect(settled.settled).to.equal(true);
+    expect(settled.upheld).to.equal(false);
+
+    const answerAccount = await program.account.answerAccount.fetch(answerPDA);
+    expect(answerAccount.numericAnswer.toNumber()).to.equal(answerBefore.numericAnswer.toNumber());
+  });
+
+  it("Withdraws unlocked provider bond", async () => {
+    const bondBefore = await program.account.providerBond.fetch(providerBondPDA);
+    const unlocked = bondBefore.bondBalance.toNumber() - bondBefore.lockedBalance.toNumber();
+    const providerBalanceBefore = await provider.connection.getBalance(oracleProvider.publicKey);
+
+    await program.methods
+      .withdrawBond(new anchor.BN(unlocked))
+      .accounts({
+        providerBond: providerBondPDA,
+        provider: oracleProvider.publicKey,
+      })
+      .signers([oracleProvider])
+      .rpc();
+
+    const bondAfter = await program.account.providerBond.fetch(providerBondPDA);
+    expect(bondAfter.bondBalance.toNumber()).to.equal(bondBefore.lockedBalance.toNumber());
+
+    const providerBalanceAfter = await provider.connection.getBalance(oracleProvider.publicKey);
+    expect(providerBalanceAfter).to.be.greaterThan(providerBalanceBefore);
+
+    // Locked bond stays behind
+    try {
+      await program.methods
+        .withdrawBond(new anchor.BN(1))
+        .accounts({
+          providerBond: providerBondPDA,
+          provider: oracleProvider.publicKey,
+        })
+        .signers([oracleProvider])
+        .rpc();
+      expect.fail("should have rejected withdrawing locked bond");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("InsufficientBond");
+    }
+
+    // Later tests answer as this provider, so put the bond back
+    await program.methods
+      .postBond(new anchor.BN(LAMPORTS_PER_SOL))
+      .accounts({
+        providerBond: providerBondPDA,
+        provider: oracleProvider.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([oracleProvider])
+      .rpc();
+  });
+
+  it("Expires an unanswered question only after its deadline", async () => {
+    const deadline = Math.floor(Date.now() / 1000) + 3;
+
+    const state = await program.account.oracleState.fetch(oracleStatePDA);
+    const { questionPDA } = await findQuestionPDAs(state.questionCounter.toNumber());
+
+    await program.methods
+      .askQuestion({ yesNo: {} }, "Will this question be answered?", "general", new anchor.BN(deadline), new anchor.BN(0), null)
+      .accounts({
+        questionAccount: questionPDA,
+        oracleState: oracleStatePDA,
+        globalStats: globalStatsPDA,
+        requester: requester.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([requester])
+      .rpc();
+
+    try {
+      await program.methods
+        .expireQuestion()
+        .accounts({ questionAccount: questionPDA })
+        .rpc();
+      expect.fail("should have rejected expiring before the deadline");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("DeadlineNotPassed");
+    }
+
+    await new Promise(resolve => setTimeout(resolve, 5000));
+
+    await program.methods
+      .expireQuestion()
+      .accounts({ questionAccount: questionPDA })
+      .rpc();
+
+    const questionAccount = await program.account.questionAccount.fetch(questionPDA);
+    expect(questionAccount.status).to.deep.equal({ expired: {} });
+  });
+
+  it("Reveals a question only when it matches the stored hash", async () => {
+    const question = "Will the ECB cut rates in June?";
+    const deadline = Math.floor(Date.now() / 1000) + 86400;
+
+    const state = await program.account.oracleState.fetch(oracleStatePDA);
+    const { questionPDA } = await findQuestionPDAs(state.questionCounter.toNumber());
+
+    await program.methods
+      .askQuestion({ yesNo: {} }, question, "finance", new anchor.BN(deadline), new anchor.BN(0), null)
+      .accounts({
+        questionAccount: questionPDA,
+        oracleState: oracleStatePDA,
+        globalStats: globalStatsPDA,
+        requester: requester.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([requester])
+      .rpc();
+
+    try {
+      await program.methods
+        .revealQuestion("Will the Fed cut rates in June?")
+        .accounts({ questionAccount: questionPDA })
+        .rpc();
+      expect.fail("should have rejected a mismatching reveal");
+    } catch (error) {
+      expect(error.error.errorCode.code).to.equal("QuestionHashMismatch");
+    }
+
+    await program.methods
+      .revealQuestion(question)
+      .accounts({ questionAccount: questionPDA })
+      .rpc();
+
+    const questionAccount = await program.account.questionAccount.fetch(questionPDA);
+    expect(questionAccount.revealed).to.equal(true);
+  });
+
+  describe("self-answering", () => {
+    // Ask a question with the oracle provider as the requester
+    const askAsProvider = async () => {
+      const deadline = Math.floor(Date.now() / 1000) + 86400;
+      const state = await program.account.oracleState.fetch(oracleStatePDA);
+      const { questionPDA, answerPDA } = await findQuestionPDAs(state.questionCounter.toNumber());
+
+      await program.methods
+        .askQuestion({ yesNo: {} }, "Will my own market resolve YES?", "general", new anchor.BN(deadline), new anchor.BN(0), null)
+        .accounts({
+          questionAccount: questionPDA,
+          oracleState: oracleStatePDA,
+          globalStats: globalStatsPDA,
+          requester: oracleProvider.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([oracleProvider])
+        .rpc();
+
+      return { questionPDA, answerPDA };
+    };
+
+    const answer = (questionPDA: PublicKey, answerPDA: PublicKey) =>
+      program.methods
+        .provideAnswer("Yes", new anchor.BN(0), true, 90, "Self", null, false)
+        .accounts({
+          questionAccount: questionPDA,
+          answerAccount: answerPDA,
+          oracleState: oracleStatePDA,
+          globalStats: globalStatsPDA,
+          providerBond: providerBondPDA,
+          oracleProvider: oracleProvider.publicKey,
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([oracleProvider])
+        .rpc();
+
+    const setAllowSelfAnswer = (allow: boolean) =>
+      program.methods
+        .setAllowSelfAnswer(allow)
+        .accounts({
+          oracleState: oracleStatePDA,
+          authority: authority.publicKey,
+        })
+        .rpc();
+
+    it("Rejects a provider answering its own question by default", async () => {
+      const { questionPDA, answerPDA } = await askAsProvider();
+
+      try {
+        await answer(questionPDA, answerPDA);
+        expect.fail("should have rejected a self-answer");
+      } catch (error) {
+        expect(error.error.errorCode.code).to.equal("SelfAnswerForbidden");
+      }
+    });
+
+    it("Accepts a self-answer when allowed by the authority", async () => {
+      await setAllowSelfAnswer(true);
+
+      try {
+        const { questionPDA, answerPDA } = await askAsProvider();
+        await answer(questionPDA, answerPDA);
+
+        const answerAccount = await program.account.answerAccount.fetch(answerPDA);
+        expect(answerAccount.boolAnswer).to.equal(true);
+      } finally {
+        await setAllowSelfAnswer(false);
+      }
+    });
+  });
+
+  it("Tracks global stats across ask and answer", async () => {
+    const before = await program.account.globalStats.fetch(globalStatsPDA);
+
+    // One question left pending, one asked and answered
+    const deadline = Math.floor(Date.now() / 1000) + 86400;
+    const state = await program.account.oracleState.fetch(oracleStatePDA);
+    const { questionPDA } = await findQuestionPDAs(state.questionCounter.toNumber());
+    await program.methods
+      .askQuestion({ yesNo: {} }, "Will the stats be counted?", "general", new anchor.BN(deadline), new anchor.BN(0), null)
+      .accounts({
+        questionAccount: questionPDA,
+        oracleState: oracleStatePDA,
+        globalStats: globalStatsPDA,
+        requester: requester.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([requester])
+      .rpc();
+
+    const { questionPDA: answeredPDA } = await askAndAnswer("How many stats were counted?");
+    const answered = await program.account.questionAccount.fetch(answeredPDA);
+
+    const after = await program.account.globalStats.fetch(globalStatsPDA);
+    expect(after.totalQuestions.toNumber()).to.equal(before.totalQuestions.toNumber() + 2);
+    expect(after.totalAnswered.toNumber()).to.equal(before.totalAnswered.toNumber() + 1);
+    expect(after.totalBountiesPaid.toNumber()).to.equal(
+      before.totalBountiesPaid.toNumber() + answered.bounty.toNumber()
+    );
+    // Refunds require the 7 day refund period, so none happen here
+    expect(after.totalRefunded.toNumber()).to.equal(before.totalRefunded.toNumber());
+  });
+
+  describe("access control", () => {
+    it("Rejects a second initialize", async () => {
+      try {
+        await program.methods
+          .initialize(oracleProvider.publicKey, 500)
+          .accounts({
+            oracleState: oracleStatePDA,
+            globalStats: globalStatsPDA,
+            authority: authority.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+        expect.fail("should have rejected re-initialization");
+      } catch (error) {
+        expect(error.toString()).to.include("already in use");
+      }
+    });
+
+    it("Rejects an oracle state account at the wrong address", async () => {
+      try {
+        await program.methods
+          .setOracleFee(new anchor.BN(1))
+          .accounts({
+            oracleState: globalStatsPDA,
+            authority: authority.publicKey,
+          })
+          .rpc();
+        expect.fail("should have rejected a non-canonical oracle state");
+      } catch (error) {
+        expect(error.error.errorCode.code).to.be.oneOf([
+          "AccountDiscriminatorMismatch",
+          "ConstraintSeeds",
+        ]);
+      }
+    });
+
+    const authorityGated: [string, () => any][] = [
+      ["setOracleFee", () => program.methods.setOracleFee(new anchor.BN(1))],
+      ["setOracleProvider", () => program.methods.setOracleProvider(requester.publicKey)],
+      ["setChallengePeriod", () => program.methods.setChallengePeriod(new anchor.BN(0))],
+      ["setMinBond", () => program.methods.setMinBond(new anchor.BN(0))],
+      ["setAllowSelfAnswer", () => program.methods.setAllowSelfAnswer(true)],
+      ["setWithdrawCooldown", () => program.methods.setWithdrawCooldown(new anchor.BN(0))],
+      ["setTreasury", () => program.methods.setTreasury(requester.publicKey)],
+    ];
+
+    authorityGated.forEach(([name, build]) => {
+      it(`Rejects ${name} from a non-authority signer`, async () => {
+        try {
+          await build()
+            .accounts({
+              oracleState: oracleStatePDA,
+              authority: requester.publicKey,
+            })
+            .signers([requester])
+            .rpc();
+          expect.fail(`${name} should be authority only`);
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("Unauthorized");
+        }
+      });
+    });
+  });
+
+  describe("provider withdrawals", () => {
+    const setWithdrawCooldown = (seconds: number) =>
+      program.methods
+        .setWithdrawCooldown(new anchor.BN(seconds))
+        .accounts({
+          oracleState: oracleStatePDA,
+          authority: authority.publicKey,
+        })
+        .rpc();
+
+    const withdraw = () =>
+      program.methods
+        .withdraw()
+        .accounts({
+          oracleState: oracleStatePDA,
+          oracleProvider: oracleProvider.publicKey,
+        })
+        .signers([oracleProvider])
+        .rpc();
+
+    it("Rejects a withdraw during the cooldown", async () => {
+      await askAndAnswer("Will the cooldown hold?");
+      await setWithdrawCooldown(86400);
+
+      try {
+        await withdraw();
+        expect.fail("should have rejected a withdraw during the cooldown");
+      } catch (error) {
+        expect(error.error.errorCode.code).to.equal("WithdrawCooldownActive");
+      }
+    });
+
+    it("Withdraws once the cooldown has elapsed", async () => {
+      await setWithdrawCooldown(0);
+
+      const state = await program.account.oracleState.fetch(oracleStatePDA);
+      const earnings = state.providerBalance.toNumber();
+      expect(earnings).to.be.greaterThan(0);
+
+      let withdrawn = 0;
+      const listener = program.addEventListener("providerWithdrawn", (event) => {
+        withdrawn = event.amount.toNumber();
+      });
+
+      try {
+        await withdraw();
+        await new Promise(resolve => setTimeout(resolve, 500));
+      } finally {
+        await program.removeEventListener(listener);
+      }
+
+      expect(withdrawn).to.equal(earnings);
+      const after = await program.account.oracleState.fetch(oracleStatePDA);
+      expect(after.providerBalance.toNumber()).to.equal(0);
+    });
+  });
+
+  describe("incentive pool", () => {
+    it("Accrues an overdue opted-in bounty and pays a slice to the next answer", async () => {
+      const deadline = Math.floor(Date.now() / 1000) + 2;
+      const bounty = 0.2 * LAMPORTS_PER_SOL;
+
+      const state = await program.account.oracleState.fetch(oracleStatePDA);
+      const { questionPDA } = await findQuestionPDAs(state.questionCounter.toNumber());
+
+      await program.methods
+        .askQuestion({ yesNo: {} }, "Will anyone answer this in time?", "general", new anchor.BN(deadline), new anchor.BN(bounty), null)
+        .accounts({
+          questionAccount: questionPDA,
+          oracleState: oracleStatePDA,
+          globalStats: globalStatsPDA,
+          requester: requester.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([requester])
+        .rpc();
+
+      await program.methods
+        .enableIncentiveDecay()
+        .accounts({
+          questionAccount: questionPDA,
+          requester: requester.publicKey,
+        })
+        .signers([requester])
+        .rpc();
+
+      const accrue = () =>
+        program.methods
+          .accrueIncentive()
+          .accounts({
+            questionAccount: questionPDA,
+            oracleState: oracleStatePDA,
+          })
+          .rpc();
+
+      try {
+        await accrue();
+        expect.fail("should have rejected accruing before the deadline");
+      } catch (error) {
+        expect(error.error.errorCode.code).to.equal("NothingToAccrue");
+      }
+
+      await new Promise(resolve => setTimeout(resolve, 5000));
+      await accrue();
+
+      const questionAccount = await program.account.questionAccount.fetch(questionPDA);
+      const accrued = await program.account.oracleState.fetch(oracleStatePDA);
+      const pool = accrued.incentivePool.toNumber();
+      expect(pool).to.be.greaterThan(0);
+      expect(questionAccount.bounty.toNumber()).to.be.lessThan(bounty);
+
+      const expectedBonus = Math.floor((pool * 1_000) / 10_000);
+      await askAndAnswer("Who collects the incentive bonus?");
+
+      const after = await program.account.oracleState.fetch(oracleStatePDA);
+      expect(after.providerBalance.toNumber() - accrued.providerBalance.toNumber()).to.equal(
+        accrued.oracleFee.toNumber() + expectedBonus
+      );
+      expect(after.incentivePool.toNumber()).to.equal(pool - expectedBonus);
+    });
+  });
+
+  describe("answer attestation", () => {
+    const attestationKey = Keypair.generate();
+
+    const setAttestationPubkey = (key: PublicKey) =>
+      program.methods
+        .setAttestationPubkey(key)
+        .accounts({
+          oracleState: oracleStatePDA,
+          authority: authority.publicKey,
+        })
+        .rpc();
+
+    // Canonical bytes the attestation key signs for an answer
+    const answerMessage = (
+      questionId: number,
+      numericAnswer: number,
+      boolAnswer: boolean,
+      confidence: number,
+      inconclusive = false
+    ) => {
+      const message = Buffer.alloc(19);
+      message.writeBigUInt64LE(BigInt(questionId), 0);
+      message.writeBigUInt64LE(BigInt(numericAnswer), 8);
+      message.writeUInt8(boolAnswer ? 1 : 0, 16);
+      message.writeUInt8(confidence, 17);
+      message.writeUInt8(inconclusive ? 1 : 0, 18);
+      return message;
+    };
+
+    const askPending = async () => {
+      const deadline = Math.floor(Date.now() / 1000) + 86400;
+      const state = await program.account.oracleState.fetch(oracleStatePDA);
+      const questionId = state.questionCounter.toNumber();
+      const { questionPDA, answerPDA } = await findQuestionPDAs(questionId);
+
+      await program.methods
+        .askQuestion({ yesNo: {} }, "Did the attested feed report YES?", "general", new anchor.BN(deadline), new anchor.BN(0), null)
+        .accounts({
+          questionAccount: questionPDA,
+          oracleState: oracleStatePDA,
+          globalStats: globalStatsPDA,
+          requester: requester.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([requester])
+        .rpc();
+
+      return { questionId, questionPDA, answerPDA };
+    };
+
+    // Answer YES / 1 / 90, attaching an ed25519 check of `signedMessage`
+    const provideAttested = async (signedMessage: Buffer, inconclusive = false) => {
+      const { questionPDA, answerPDA } = await askPending();
+      const ed25519Ix = Ed25519Program.createInstructionWithPrivateKey({
+        privateKey: attestationKey.secretKey,
+        message: signedMessage,
+      });
+      // Signature follows the 16-byte header and 32-byte public key
+      const signature = Array.from(ed25519Ix.data.subarray(48, 112));
+
+      await program.methods
+        .provideAnswer("Yes", new anchor.BN(1), true, 90, "Attested feed", signature, inconclusive)
+        .accounts({
+          questionAccount: questionPDA,
+          answerAccount: answerPDA,
+          oracleState: oracleStatePDA,
+          globalStats: globalStatsPDA,
+          providerBond: providerBondPDA,
+          oracleProvider: oracleProvider.publicKey,
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+        })
+        .preInstructions([ed25519Ix])
+        .signers([oracleProvider])
+        .rpc();
+
+      return questionPDA;
+    };
+
+    before(async () => {
+      await setAttestationPubkey(attestationKey.publicKey);
+    });
+
+    after(async () => {
+      await setAttestationPubkey(PublicKey.default);
+    });
+
+    it("Accepts an answer attested by the configured key", async () => {
+      const state = await program.account.oracleState.fetch(oracleStatePDA);
+      const questionId = state.questionCounter.toNumber();
+
+      const questionPDA = await provideAttested(answerMessage(questionId, 1, true, 90));
+
+      const questionAccount = await program.account.questionAccount.fetch(questionPDA);
+      expect(questionAccount.status).to.deep.equal({ answered: {} });
+    });
+
+    it("Rejects an answer whose attestation covers different data", async () => {
+      const state = await program.account.oracleState.fetch(oracleStatePDA);
+      const questionId = state.questionCounter.toNumber();
+
+      try {
+        await provideAttested(answerMessage(questionId, 2, true, 90));
+        expect.fail("should have rejected a tampered attestation");
+      } catch (error) {
+        expect(error.error.errorCode.code).to.equal("InvalidAttestation");
+      }
+    });
+
+    it("Rejects flipping the inconclusive flag on an attested answer", async () => {
+      const state = await program.account.oracleState.fetch(oracleStatePDA);
+      const questionId = state.questionCounter.toNumber();
+
+      try {
+        // The key attested a conclusive answer; the provider submits it as inconclusive
+        await provideAttested(answerMessage(questionId, 1, true, 90, false), true);
+        expect.fail("should have rejected an inconclusive flag the key never signed");
+      } catch (error) {
+        expect(error.error.errorCode.code).to.equal("InvalidAttestation");
+      }
+    });
+
+    it("Requires a revision of an attested answer to be attested too", async () => {
+      await setChallengePeriod(86400);
+      const state = await program.account.oracleState.fetch(oracleStatePDA);
+      const questionId = state.questionCounter.toNumber();
+      const questionPDA = await provideAttested(answerMessage(questionId, 1, true, 90));
+      const { answerPDA } = await findQuestionPDAs(questionId);
+
+      // Revise to NO / 0 / 95, with the ed25519 check of `signedMessage` when one is given
+      const revise = (signedMessage: Buffer | null) => {
+        const ed25519Ix = signedMessage
+          ? Ed25519Program.createInstructionWithPrivateKey({ privateKey: attestationKey.secretKey, message: signedMessage })
+          : null;
+        return program.methods
+          .reviseAnswer("No", new anchor.BN(0), false, 95, "Attested feed", ed25519Ix ? Array.from(ed25519Ix.data.subarray(48, 112)) : null)
+          .accounts({
+            questionAccount: questionPDA,
+            answerAccount: answerPDA,
+            oracleState: oracleStatePDA,
+            oracleProvider: oracleProvider.publicKey,
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          })
+          .preInstructions(ed25519Ix ? [ed25519Ix] : [])
+          .signers([oracleProvider])
+          .rpc();
+      };
+
+      try {
+        await revise(null);
+        expect.fail("should have rejected an unattested revision");
+      } catch (error) {
+        expect(error.error.errorCode.code).to.equal("MissingAttestation");
+      }
+      let answer = await program.account.answerAccount.fetch(answerPDA);
+      expect(answer.boolAnswer).to.equal(true);
+      expect(answer.revision).to.equal(0);
+
+      await revise(answerMessage(questionId, 0, false, 95));
+      answer = await program.account.answerAccount.fetch(answerPDA);
+      expect(answer.boolAnswer).to.equal(false);
+      expect(answer.confidenceScore).to.equal(95);
+      expect(answer.revision).to.equal(1);
+    });
+  });
+
+  describe("batch validation", () => {
+    const batchAsk = (count: number, deadlineCount: number) => {
+      const deadline = new anchor.BN(Math.floor(Date.now() / 1000) + 86400);
+      return program.methods
+        .batchAskQuestions(
+          Array(count).fill({ yesNo: {} }),
+          Array.from({ length: count }, (_, i) => `Batch question ${i}?`),
+          Array(deadlineCount).fill(deadline)
+        )
+        .accounts({
+          oracleState: oracleStatePDA,
+          globalStats: globalStatsPDA,
+          requester: requester.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([requester])
+        .rpc();
+    };
+
+    it("Rejects vectors of different lengths", async () => {
+      await expectError(batchAsk(2, 1), "LengthMismatch");
+    });
+
+    it("Rejects an empty batch", async () => {
+      await expectError(batchAsk(0, 0), "EmptyBatch");
+    });
+
+    it("Rejects an oversized batch", async () => {
+      await expectError(batchAsk(21, 21), "BatchTooLarge");
+    });
+
+    it("Rejects duplicate question IDs", async () => {
+      await expectError(
+        program.methods
+          .batchProvideAnswers(
+            [new anchor.BN(0), new anchor.BN(0)],
+            ["Yes", "Yes"],
+            [new anchor.BN(1), new anchor.BN(1)],
+            [true, true],
+            Buffer.from([90, 90]),
+            ["Batch", "Batch"]
+          )
+          .accounts({
+            oracleState: oracleStatePDA,
+            oracleProvider: oracleProvider.publicKey,
+          })
+          .signers([oracleProvider])
+          .rpc(),
+        "DuplicateBatchId"
+      );
+    });
+
+    it("Leaves the global answered total alone, as no answer account is written", async () => {
+      const before = (await program.account.globalStats.fetch(globalStatsPDA)).totalAnswered.toNumber();
+
+      await program.methods
+        .batchProvideAnswers(
+          [new anchor.BN(0), new anchor.BN(1)],
+          ["Yes", "No"],
+          [new anchor.BN(1), new anchor.BN(0)],
+          [true, false],
+          Buffer.from([90, 90]),
+          ["Batch", "Batch"]
+        )
+        .accounts({
+          oracleState: oracleStatePDA,
+          oracleProvider: oracleProvider.publicKey,
+        })
+        .signers([oracleProvider])
+        .rpc();
+
+      expect((await program.account.globalStats.fetch(globalStatsPDA)).totalAnswered.toNumber()).to.equal(before);
+    });
+  });
+
+  describe("deadline extension", () => {
+    let questionPDA: PublicKey;
+    let deadline: number;
+
+    const extendDeadline = (newDeadline: number) =>
+      program.methods
+        .extendDeadline(new anchor.BN(newDeadline))
+        .accounts({
+          questionAccount: questionPDA,
+          requester: requester.publicKey,
+        })
+        .signers([requester])
+        .rpc();
+
+    before(async () => {
+      deadline = Math.floor(Date.now() / 1000) + 3600;
+      const state = await program.account.oracleState.fetch(oracleStatePDA);
+      ({ questionPDA } = await findQuestionPDAs(state.questionCounter.toNumber()));
+
+      await program.methods
+        .askQuestion({ yesNo: {} }, "Will this question need more time?", "general", new anchor.BN(deadline), new anchor.BN(0), null)
+        .accounts({
+          questionAccount: questionPDA,
+          oracleState: oracleStatePDA,
+          globalStats: globalStatsPDA,
+          requester: requester.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([requester])
+        .rpc();
+    });
+
+    it("Extends a pending question's deadline", async () => {
+      const before = await program.account.questionAccount.fetch(questionPDA);
+
+      await extendDeadline(deadline + 86400);
+
+      const questionAccount = await program.account.questionAccount.fetch(questionPDA);
+      expect(questionAccount.deadline.toNumber()).to.equal(deadline + 86400);
+      expect(questionAccount.status).to.deep.equal({ pending: {} });
+      // The refund clock still runs from when the question was asked
+      expect(questionAccount.timestamp.toNumber()).to.equal(before.timestamp.toNumber());
+    });
+
+    it("Rejects moving the deadline earlier", async () => {
+      try {
+        await extendDeadline(deadline);
+        expect.fail("should have rejected an earlier deadline");
+      } catch (error) {
+        expect(error.error.errorCode.code).to.equal("InvalidDeadline");
+      }
+    });
+  });
+
+  describe("returned question ids", () => {
+    it("Returns the new question id from askQuestion, matching the event", async () => {
+      const state = await program.account.oracleState.fetch(oracleStatePDA);
+      const expectedId = state.questionCounter.toNumber();
+      const { questionPDA } = await findQuestionPDAs(expectedId);
+
+      const signature = await program.methods
+        .askQuestion({ yesNo: {} }, "Will the returned id match the event?", "general", new anchor.BN(Math.floor(Date.now() / 1000) + 3600), new anchor.BN(0), null)
+        .accounts({
+          questionAccount: questionPDA,
+          oracleState: oracleStatePDA,
+          globalStats: globalStatsPDA,
+          requester: requester.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([requester])
+        .rpc({ commitment: "confirmed" });
+
+      const tx = await provider.connection.getTransaction(signature, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const [returnData] = tx.meta.returnData.data;
+      const returnedId = Number(Buffer.from(returnData, "base64").readBigUInt64LE(0));
+
+      const eventParser = new anchor.EventParser(program.programId, program.coder);
+      const asked = [...eventParser.parseLogs(tx.meta.logMessages)].find(event => event.name === "questionAsked");
+
+      expect(returnedId).to.equal(expectedId);
+      expect(asked.data.questionId.toNumber()).to.equal(returnedId);
+    });
+  });
+
+  describe("question statuses", () => {
+    const askFresh = async (question: string) => {
+      const state = await program.account.oracleState.fetch(oracleStatePDA);
+      const questionId = state.questionCounter.toNumber();
+      const pdas = await findQuestionPDAs(questionId);
+
+      await program.methods
+        .askQuestion({ yesNo: {} }, question, "general", new anchor.BN(Math.floor(Date.now() / 1000) + 3600), new anchor.BN(0), null)
+        .accounts({
+          questionAccount: pdas.questionPDA,
+          oracleState: oracleStatePDA,
+          globalStats: globalStatsPDA,
+          requester: requester.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([requester])
+        .rpc();
+
+      return { questionId, ...pdas };
+    };
+
+    const asReadonly = (pubkeys: PublicKey[]) =>
+      pubkeys.map(pubkey => ({ pubkey, isSigner: false, isWritable: false }));
+
+    it("Returns packed statuses for a mix of questions", async () => {
+      const pending = await askFresh("Will this question stay pending?");
+      const answered = await askFresh("Will this question get answered?");
+
+      await program.methods
+        .provideAnswer("Yes", new anchor.BN(1), true, 90, "Status test", null, false)
+        .accounts({
+          questionAccount: answered.questionPDA,
+          answerAccount: answered.answerPDA,
+          oracleState: oracleStatePDA,
+          globalStats: globalStatsPDA,
+          providerBond: providerBondPDA,
+          oracleProvider: oracleProvider.publicKey,
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([oracleProvider])
+        .rpc();
+
+      const statuses = await program.methods
+        .getStatuses()
+        .accounts({})
+        .remainingAccounts(asReadonly([pending.questionPDA, answered.questionPDA]))
+        .view();
+
+      expect(statuses.map(entry => entry.questionId.toNumber())).to.deep.equal([pending.questionId, answered.questionId]);
+      expect(statuses[0].status).to.deep.equal({ pending: {} });
+      expect(statuses[1].status).to.deep.equal({ answered: {} });
+      expect(statuses.every(entry => !entry.refunded)).to.equal(true);
+    });
+
+    it("Rejects an account that is not a question", async () => {
+      try {
+        await program.methods
+          .getStatuses()
+          .accounts({})
+          .remainingAccounts(asReadonly([oracleStatePDA]))
+          .view();
+        expect.fail("should have rejected a non-question account");
+      } catch (error) {
+        expect(error.toString()).to.match(/AccountDiscriminatorMismatch|InvalidQuestionAccount/);
+      }
+    });
+  });
+
+  describe("counter resync", () => {
+    const resync = async (questionCounter: number) => {
+      const { questionPDA } = await findQuestionPDAs(questionCounter);
+      return program.methods
+        .resyncCounter(new anchor.BN(questionCounter))
+        .accounts({
+          oracleState: oracleStatePDA,
+          authority: authority.publicKey,
+        })
+        .remainingAccounts([{ pubkey: questionPDA, isSigner: false, isWritable: false }])
+        .rpc();
+    };
+
+    it("Moves the counter to an unused question id", async () => {
+      const before = (await program.account.oracleState.fetch(oracleStatePDA)).questionCounter.toNumber();
+      await resync(before + 1);
+
+      const after = await program.account.oracleState.fetch(oracleStatePDA);
+      expect(after.questionCounter.toNumber()).to.equal(before + 1);
+    });
+
+    it("Rejects a counter pointing at an existing question", async () => {
+      const current = (await program.account.oracleState.fetch(oracleStatePDA)).questionCounter.toNumber();
+      // Question 0 was created by the first test suite
+      try {
+        await resync(0);
+        expect.fail("should have rejected resyncing onto an existing question");
+      } catch (error) {
+        expect(error.error.errorCode.code).to.equal("QuestionExists");
+      }
+
+      const state = await program.account.oracleState.fetch(oracleStatePDA);
+      expect(state.questionCounter.toNumber()).to.equal(current);
+    });
+  });
+
+  describe("provider assignment", () => {
+    const specialist = Keypair.generate();
+
+    const findBondPDA = (provider: PublicKey) =>
+      PublicKey.findProgramAddressSync([Buffer.from("bond"), provider.toBuffer()], program.programId)[0];
+
+    const askAssigned = async (assignedProvider: PublicKey | null) => {
+      const state = await program.account.oracleState.fetch(oracleStatePDA);
+      const { questionPDA, answerPDA } = await findQuestionPDAs(state.questionCounter.toNumber());
+
+      await program.methods
+        .askQuestion({ yesNo: {} }, "Will the specialist answer this?", "general", new anchor.BN(Math.floor(Date.now() / 1000) + 3600), new anchor.BN(0), assignedProvider)
+        .accounts({
+          questionAccount: questionPDA,
+          oracleState: oracleStatePDA,
+          globalStats: globalStatsPDA,
+          requester: requester.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([requester])
+        .rpc();
+
+      return { questionPDA, answerPDA };
+    };
+
+    const answerAs = (answerer: Keypair, questionPDA: PublicKey, answerPDA: PublicKey) =>
+      program.methods
+        .provideAnswer("Yes", new anchor.BN(0), true, 90, "Specialist", null, false)
+        .accounts({
+          questionAccount: questionPDA,
+          answerAccount: answerPDA,
+          oracleState: oracleStatePDA,
+          globalStats: globalStatsPDA,
+          providerBond: findBondPDA(answerer.publicKey),
+          oracleProvider: answerer.publicKey,
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([answerer])
+        .rpc();
+
+    before(async () => {
+      await provider.connection.requestAirdrop(specialist.publicKey, 2 * LAMPORTS_PER_SOL);
+      await new Promise(resolve => setTimeout(resolve, 1000));
+
+      await program.methods
+        .postBond(new anchor.BN(LAMPORTS_PER_SOL))
+        .accounts({
+          providerBond: findBondPDA(specialist.publicKey),
+          provider: specialist.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([specialist])
+        .rpc();
+    });
+
+    it("Records the assigned provider on the question", async () => {
+      const { questionPDA } = await askAssigned(specialist.publicKey);
+
+      const question = await program.account.questionAccount.fetch(questionPDA);
+      expect(question.assignedProvider.toBase58()).to.equal(specialist.publicKey.toBase58());
+    });
+
+    it("Rejects an unassigned provider on an assigned question", async () => {
+      const { questionPDA, answerPDA } = await askAssigned(specialist.publicKey);
+
+      try {
+        await answerAs(oracleProvider, questionPDA, answerPDA);
+        expect.fail("should have rejected the registered provider");
+      } catch (error) {
+        expect(error.error.errorCode.code).to.equal("NotAssignedProvider");
+      }
+
+      const question = await program.account.questionAccount.fetch(questionPDA);
+      expect(question.status).to.deep.equal({ pending: {} });
+    });
+
+    it("Lets the assigned provider answer", async () => {
+      const { questionPDA, answerPDA } = await askAssigned(specialist.publicKey);
+
+      await answerAs(specialist, questionPDA, answerPDA);
+
+      const answer = await program.account.answerAccount.fetch(answerPDA);
+      expect(answer.provider.toBase58()).to.equal(specialist.publicKey.toBase58());
+    });
+
+    it("Pays the assigned provider's bounty to them rather than the registered provider's balance", async () => {
+      const { questionPDA, answerPDA } = await askAssigned(specialist.publicKey);
+      const { bounty } = await program.account.questionAccount.fetch(questionPDA);
+      const stateBefore = await program.account.oracleState.fetch(oracleStatePDA);
+      const specialistBefore = await provider.connection.getBalance(specialist.publicKey);
+
+      await answerAs(specialist, questionPDA, answerPDA);
+
+      // The specialist pays the answer account's rent and receives the bounty plus the incentive slice
+      const stateAfter = await program.account.oracleState.fetch(oracleStatePDA);
+      const incentiveBonus = stateBefore.incentivePool.toNumber() - stateAfter.incentivePool.toNumber();
+      const answerRent = await provider.connection.getBalance(answerPDA);
+      expect(await provider.connection.getBalance(specialist.publicKey)).to.equal(
+        specialistBefore - answerRent + bounty.toNumber() + incentiveBonus
+      );
+      expect(stateAfter.providerBalance.toNumber()).to.equal(stateBefore.providerBalance.toNumber());
+    });
+
+    it("Keeps unassigned questions to the registered provider", async () => {
+      const { questionPDA, answerPDA } = await askAssigned(null);
+
+      try {
+        await answerAs(specialist, questionPDA, answerPDA);
+        expect.fail("should have rejected a provider that is not registered");
+      } catch (error) {
+        expect(error.error.errorCode.code).to.equal("Unauthorized");
+      }
+
+      await answerAs(oracleProvider, questionPDA, answerPDA);
+      const question = await program.account.questionAccount.fetch(questionPDA);
+      expect(question.assignedProvider).to.equal(null);
+      expect(question.status).to.deep.equal({ answered: {} });
+    });
+  });
+
+  describe("requester allowlist", () => {
+    const approved = Keypair.generate();
+    const stranger = Keypair.generate();
+
+    const findAllowedRequesterPDA = (requester: PublicKey) =>
+      PublicKey.findProgramAddressSync([Buffer.from("requester"), requester.toBuffer()], program.programId)[0];
+
+    const setAllowlist = (enabled: boolean) =>
+      program.methods
+        .setRequesterAllowlist(enabled)
+        .accounts({
+          oracleState: oracleStatePDA,
+          authority: authority.publicKey,
+        })
+        .rpc();
+
+    const askAs = async (requester: Keypair, allowlisted: boolean) => {
+      const state = await program.account.oracleState.fetch(oracleStatePDA);
+      const { questionPDA } = await findQuestionPDAs(state.questionCounter.toNumber());
+
+      return program.methods
+        .askQuestion({ yesNo: {} }, "Is this requester allowed to ask?", "general", new anchor.BN(Math.floor(Date.now() / 1000) + 3600), new anchor.BN(0), null)
+        .accounts({
+          questionAccount: questionPDA,
+          oracleState: oracleStatePDA,
+          globalStats: globalStatsPDA,
+          requester: requester.publicKey,
+          allowedRequester: allowlisted ? findAllowedRequesterPDA(requester.publicKey) : null,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([requester])
+        .rpc();
+    };
+
+    before(async () => {
+      for (const keypair of [approved, stranger]) {
+        await provider.connection.requestAirdrop(keypair.publicKey, LAMPORTS_PER_SOL);
+      }
+      await new Promise(resolve => setTimeout(resolve, 1000));
+
+      await program.methods
+        .addRequester(approved.publicKey)
+        .accounts({
+          allowedRequester: findAllowedRequesterPDA(approved.publicKey),
+          oracleState: oracleStatePDA,
+          authority: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      await setAllowlist(true);
+    });
+
+    after(async () => {
+      // Back to open access for the remaining suites
+      await setAllowlist(false);
+    });
+
+    it("Lets an allowlisted requester ask", async () => {
+      const before = await program.account.oracleState.fetch(oracleStatePDA);
+      await askAs(approved, true);
+
+      const after = await program.account.oracleState.fetch(oracleStatePDA);
+      expect(after.questionCounter.toNumber()).to.equal(before.questionCounter.toNumber() + 1);
+    });
+
+    it("Rejects a requester missing from the allowlist", async () => {
+      try {
+        await askAs(stranger, false);
+        expect.fail("should have rejected a requester off the allowlist");
+      } catch (error) {
+        expect(error.error.errorCode.code).to.equal("RequesterNotAllowed");
+      }
+    });
+
+    it("Rejects a removed requester", async () => {
+      await program.methods
+        .removeRequester(approved.publicKey)
+        .accounts({
+          allowedRequester: findAllowedRequesterPDA(approved.publicKey),
+          oracleState: oracleStatePDA,
+          authority: authority.publicKey,
+        })
+        .rpc();
+
+      try {
+        await askAs(approved, false);
+        expect.fail("should have rejected a removed requester");
+      } catch (error) {
+        expect(error.error.errorCode.code).to.equal("RequesterNotAllowed");
+      }
+    });
+  });
+
+  describe("authority multisig", () => {
+    const signers = [Keypair.generate(), Keypair.generate(), Keypair.generate()];
+    const outsider = Keypair.generate();
+
+    const approvals = (...keypairs: Keypair[]) =>
+      keypairs.map(keypair => ({ pubkey: keypair.publicKey, isSigner: true, isWritable: false }));
+
+    const setOracleFee = (fee: number, ...approvers: Keypair[]) =>
+      program.methods
+        .setOracleFee(new anchor.BN(fee))
+        .accounts({
+          oracleState: oracleStatePDA,
+          authority: authority.publicKey,
+        })
+        .remainingAccounts(approvals(...approvers))
+        .signers(approvers)
+        .rpc();
+
+    before(async () => {
+      await program.methods
+        .setMultisig(signers.map(signer => signer.publicKey), 2)
+        .accounts({
+          oracleState: oracleStatePDA,
+          authority: authority.publicKey,
+        })
+        .rpc();
+    });
+
+    after(async () => {
+      // Back to single-authority mode for the remaining suites
+      await program.methods
+        .setMultisig([], 1)
+        .accounts({
+          oracleState: oracleStatePDA,
+          authority: authority.publicKey,
+        })
+        .remainingAccounts(approvals(signers[0], signers[1]))
+        .signers([signers[0], signers[1]])
+        .rpc();
+    });
+
+    it("Rejects a fee change below the signer threshold", async () => {
+      for (const approvers of [[], [signers[0]], [signers[0], outsider]]) {
+        try {
+          await setOracleFee(20_000_000, ...approvers);
+          expect.fail("should have rejected an under-approved fee change");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("MultisigThresholdNotMet");
+        }
+      }
+    });
+
+    it("Applies a fee change once enough signers approve", async () => {
+      const before = await program.account.oracleState.fetch(oracleStatePDA);
+
+      await setOracleFee(20_000_000, signers[1], signers[2]);
+
+      const after = await program.account.oracleState.fetch(oracleStatePDA);
+      expect(after.oracleFee.toNumber()).to.equal(20_000_000);
+
+      await setOracleFee(before.oracleFee.toNumber(), signers[0], signers[2]);
+    });
+
+    it("Rejects a provider withdrawal below the signer threshold", async () => {
+      try {
+        await program.methods
+          .withdraw()
+          .accounts({
+            oracleState: oracleStatePDA,
+            oracleProvider: oracleProvider.publicKey,
+          })
+          .remainingAccounts(approvals(signers[0]))
+          .signers([oracleProvider, signers[0]])
+          .rpc();
+        expect.fail("should have rejected an under-approved withdrawal");
+      } catch (error) {
+        expect(error.error.errorCode.code).to.equal("MultisigThresholdNotMet");
+      }
+    });
+  });
+
+  describe("deadline fee schedule", () => {
+    const hour = 60 * 60;
+    const month = 30 * 24 * hour;
+    const maxHorizon = 365 * 24 * hour;
+
+    const setSchedule = (shortBps: number, longBps: number) =>
+      program.methods
+        .setDeadlineFeeSchedule(shortBps, longBps)
+        .accounts({
+          oracleState: oracleStatePDA,
+          authority: authority.publicKey,
+        })
+        .rpc();
+
+    // Ask a question due `horizon` seconds out and return the fee it was charged
+    const bountyFor = async (horizon: number) => {
+      const state = await program.account.oracleState.fetch(oracleStatePDA);
+      const { questionPDA } = await findQuestionPDAs(state.questionCounter.toNumber());
+
+      await program.methods
+        .askQuestion({ yesNo: {} }, `Is a ${horizon} second deadline priced right?`, "general", new anchor.BN(Math.floor(Date.now() / 1000) + horizon), new anchor.BN(0), null)
+        .accounts({
+          questionAccount: questionPDA,
+          oracleState: oracleStatePDA,
+          globalStats: globalStatsPDA,
+          requester: requester.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([requester])
+        .rpc();
+
+      return (await program.account.questionAccount.fetch(questionPDA)).bounty.toNumber();
+    };
+
+    after(async () => {
+      await setSchedule(0, 0);
+    });
+
+    it("Charges the flat oracle fee for any deadline by default", async () => {
+      const { oracleFee } = await program.account.oracleState.fetch(oracleStatePDA);
+
+      expect(await bountyFor(hour)).to.equal(oracleFee.toNumber());
+      expect(await bountyFor(month)).to.equal(oracleFee.toNumber());
+    });
+
+    it("Charges a 1-hour deadline more than a 30-day one under a schedule", async () => {
+      // Twice the oracle fee for an immediate deadline, half of it at the one year horizon
+      await setSchedule(20_000, 5_000);
+      const oracleFee = (await program.account.oracleState.fetch(oracleStatePDA)).oracleFee.toNumber();
+      const expectedFee = (horizon: number) =>
+        (oracleFee * (20_000 - Math.floor((15_000 * horizon) / maxHorizon))) / 10_000;
+
+      const hourFee = await bountyFor(hour);
+      const monthFee = await bountyFor(month);
+
+      expect(hourFee).to.be.greaterThan(monthFee);
+      // Allow a bps of drift between the local and cluster clocks
+      expect(hourFee).to.be.closeTo(expectedFee(hour), oracleFee / 10_000);
+      expect(monthFee).to.be.closeTo(expectedFee(month), oracleFee / 10_000);
+    });
+
+    it("Rejects a schedule that charges long deadlines more", async () => {
+      try {
+        await setSchedule(5_000, 20_000);
+        expect.fail("should have rejected an increasing schedule");
+      } catch (error) {
+        expect(error.error.errorCode.code).to.equal("InvalidFeeSchedule");
+      }
+    });
+  });
+
+  describe("simple prediction market", () => {
+    const marketProgram = anchor.workspace.SimplePredictionMarket as Program<SimplePredictionMarket>;
+    const payer = (provider.wallet as anchor.Wallet).payer;
+    const feeBps = 200;
+    const [marketStatePDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("market_state")],
+      marketProgram.programId
+    );
+
+    // Stakes are held in this mint, in a vault owned by the market state PDA
+    let stakeMint: PublicKey;
+    let stakeVault: PublicKey;
+
+    // Stake left in the pool once the entry fee is taken
+    const netOf = (amount: number) => amount - Math.floor((amount * feeBps) / 10_000);
+
+    const tokenBalance = async (account: PublicKey) =>
+      Number((await getAccount(provider.connection, account)).amount);
+
+    // Parimutuel market on a fresh oracle question, resolving `resolveIn` seconds from now
+    const createMarket = async (
+      question: string,
+      resolveIn = 5,
+      minConfidence = 50,
+      questionType: object = { general: {} },
+      feeMode: object = { flat: {} },
+      feeStartBps = 0,
+      feeEndBps = 0,
+      payoutMode: object = { flat: {} },
+      minParticipants = 0,
+      maxTotalPool = 0,
+      maxAnswerAgeSecs = 0,
+      minPoolPerSide = 0,
+      singleSideOnly = false,
+      opensAt = 0
+    ) => {
+      const oracleState = await program.account.oracleState.fetch(oracleStatePDA);
+      const questionId = oracleState.questionCounter.toNumber();
+      const { questionPDA, answerPDA } = await findQuestionPDAs(questionId);
+
+      await program.methods
+        .askQuestion({ general: {} }, question, "general", new anchor.BN(Math.floor(Date.now() / 1000) + 86400), new anchor.BN(0), null)
+        .accounts({
+          questionAccount: questionPDA,
+          oracleState: oracleStatePDA,
+          globalStats: globalStatsPDA,
+          requester: requester.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([requester])
+        .rpc();
+
+      const marketId = (await marketProgram.account.marketState.fetch(marketStatePDA)).marketCounter;
+      const [marketPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("market"), marketId.toArrayLike(Buffer, "le", 8)],
+        marketProgram.programId
+      );
+      const [questionBindingPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("question_binding"), new anchor.BN(questionId).toArrayLike(Buffer, "le", 8)],
+        marketProgram.programId
+      );
+
+      await marketProgram.methods
+        .createMarket(question, new anchor.BN(Math.floor(Date.now() / 1000) + resolveIn), new anchor.BN(0), { parimutuel: {} }, new anchor.BN(0), minConfidence, new anchor.BN(questionId), questionType, feeMode, feeStartBps, feeEndBps, false, payoutMode, minParticipants, new anchor.BN(maxTotalPool), new anchor.BN(maxAnswerAgeSecs), new anchor.BN(minPoolPerSide), singleSideOnly, new anchor.BN(opensAt))
+        .accounts({
+          marketAccount: marketPDA,
+          marketState: marketStatePDA,
+          questionBinding: questionBindingPDA,
+          creator: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      return { marketId, marketPDA, questionPDA, answerPDA, questionBindingPDA };
+    };
+
+    const findPositionPDA = (marketId: anchor.BN, owner: PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), marketId.toArrayLike(Buffer, "le", 8), owner.toBuffer()],
+        marketProgram.programId
+      )[0];
+
+    const findReferralPDA = (marketId: anchor.BN, referrer: PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("referral"), marketId.toArrayLike(Buffer, "le", 8), referrer.toBuffer()],
+        marketProgram.programId
+      )[0];
+
+    // A new bettor holding `amount` of the stake mint
+    const newBettor = async (amount: number | bigint) => {
+      const bettor = Keypair.generate();
+      await provider.connection.requestAirdrop(bettor.publicKey, LAMPORTS_PER_SOL);
+      await new Promise(resolve => setTimeout(resolve, 1000));
+
+      const tokenAccount = (
+        await getOrCreateAssociatedTokenAccount(provider.connection, payer, stakeMint, bettor.publicKey)
+      ).address;
+      await mintTo(provider.connection, payer, stakeMint, tokenAccount, payer, amount);
+
+      return { bettor, tokenAccount };
+    };
+
+    // Stake `amount` on one side, by default the bettor's whole token balance
+    const takeSide = async (
+      isYes: boolean,
+      marketId: anchor.BN,
+      marketPDA: PublicKey,
+      bettor: Keypair,
+      tokenAccount: PublicKey | null,
+      referrer: PublicKey | null = null,
+      amount: number | null = null
+    ) =>
+      marketProgram.methods
+        .takePosition(
+          marketId,
+          isYes,
+          new anchor.BN(amount ?? (tokenAccount ? await tokenBalance(tokenAccount) : 0)),
+          referrer
+        )
+        .accounts({
+          marketAccount: marketPDA,
+          position: findPositionPDA(marketId, bettor.publicKey),
+          marketState: marketStatePDA,
+          bettor: bettor.publicKey,
+          stakeVault: tokenAccount ? stakeVault : null,
+          bettorTokenAccount: tokenAccount,
+          referralBalance: referrer ? findReferralPDA(marketId, referrer) : null,
+          tokenProgram: tokenAccount ? TOKEN_PROGRAM_ID : null,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([bettor])
+        .rpc();
+
+    type Market = Awaited<ReturnType<typeof createMarket>>;
+
+    // Answer the market's oracle question YES with enough confidence to resolve it
+    const answerYes = (market: Market) =>
+      program.methods
+        .provideAnswer("Yes", new anchor.BN(1), true, 90, "Market test", null, false)
+        .accounts({
+          questionAccount: market.questionPDA,
+          answerAccount: market.answerPDA,
+          oracleState: oracleStatePDA,
+          globalStats: globalStatsPDA,
+          providerBond: providerBondPDA,
+          oracleProvider: oracleProvider.publicKey,
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([oracleProvider])
+        .rpc();
+
+    const resolveMarket = (market: Market) =>
+      marketProgram.methods
+        .resolveMarket(market.marketId)
+        .accounts({
+          marketAccount: market.marketPDA,
+          marketState: marketStatePDA,
+          oracleQuestion: market.questionPDA,
+          oracleAnswer: market.answerPDA,
+        })
+        .rpc();
+
+    // Fees only count toward withdrawals once their market is finalized
+    const finalizeMarket = (market: Market) =>
+      marketProgram.methods
+        .finalizeMarket(market.marketId)
+        .accounts({
+          marketAccount: market.marketPDA,
+          marketState: marketStatePDA,
+        })
+        .rpc();
+
+    const withdrawFees = async () => {
+      const { treasury } = await marketProgram.account.marketState.fetch(marketStatePDA);
+      const treasuryTokenAccount = (
+        await getOrCreateAssociatedTokenAccount(provider.connection, payer, stakeMint, treasury)
+      ).address;
+      return marketProgram.methods
+        .withdrawFees()
+        .accounts({
+          marketState: marketStatePDA,
+          authority: authority.publicKey,
+          treasury,
+          stakeVault,
+          treasuryTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+    };
+
+    // Markets created by createMarket resolve five seconds out
+    const waitForResolutionTime = () => new Promise(resolve => setTimeout(resolve, 6000));
+
+    before(async () => {
+      // The vault is the stake mint's associated token account of the market state PDA
+      stakeMint = await createMint(provider.connection, payer, authority.publicKey, null, 6);
+      stakeVault = (
+        await getOrCreateAssociatedTokenAccount(provider.connection, payer, stakeMint, marketStatePDA, true)
+      ).address;
+
+      // Claims open as soon as a market resolves
+      await marketProgram.methods
+        .initialize(program.programId, feeBps, new anchor.BN(1), new anchor.BN(0), stakeMint, 500)
+        .accounts({
+          marketState: marketStatePDA,
+          authority: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    });
+
+    describe("SPL stakes", () => {
+      const stake = 1_000_000;
+
+      it("Rejects a bet without the token accounts", async () => {
+        const market = await createMarket("Can a token market take lamports?");
+        const { bettor } = await newBettor(stake);
+
+        await expectError(takeSide(true, market.marketId, market.marketPDA, bettor, null), "MissingTokenAccounts");
+      });
+
+      it("Bets and claims winnings in the stake mint", async () => {
+        const market = await createMarket("Will the token-staked market pay out?");
+        const { bettor, tokenAccount } = await newBettor(stake);
+        const vaultBefore = await tokenBalance(stakeVault);
+
+        await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount);
+
+        expect(await tokenBalance(stakeVault)).to.equal(vaultBefore + stake);
+        expect(await tokenBalance(tokenAccount)).to.equal(0);
+        const position = await marketProgram.account.position.fetch(findPositionPDA(market.marketId, bettor.publicKey));
+        expect(position.yesAmount.toNumber()).to.equal(netOf(stake));
+
+        await answerYes(market);
+        await waitForResolutionTime();
+        await resolveMarket(market);
+
+        const quote = await marketProgram.methods
+          .quotePayout(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            position: findPositionPDA(market.marketId, bettor.publicKey),
+            owner: bettor.publicKey,
+          })
+          .signers([bettor])
+          .view();
+
+        await marketProgram.methods
+          .claimWinnings(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            position: findPositionPDA(market.marketId, bettor.publicKey),
+            positionOwner: bettor.publicKey,
+            marketState: marketStatePDA,
+            winner: bettor.publicKey,
+            stakeVault,
+            winnerTokenAccount: tokenAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([bettor])
+          .rpc();
+
+        // The sole bettor gets the whole net pool back, exactly as quoted; the fee stays in the vault
+        expect(quote.toNumber()).to.equal(netOf(stake));
+        expect(await tokenBalance(tokenAccount)).to.equal(quote.toNumber());
+        expect(await tokenBalance(stakeVault)).to.equal(vaultBefore + stake - netOf(stake));
+      });
+
+      it("Stakes only the amount asked for, within the balance and the minimum bet", async () => {
+        const { marketId, marketPDA } = await createMarket("Does a token bet stake just its amount?");
+        const { bettor, tokenAccount } = await newBettor(stake);
+
+        await expectError(takeSide(true, marketId, marketPDA, bettor, tokenAccount, null, stake + 1), "InsufficientBalance");
+
+        const setMinBet = (minBet: number) =>
+          marketProgram.methods
+            .setMinBet(new anchor.BN(minBet))
+            .accounts({ marketState: marketStatePDA, authority: authority.publicKey })
+            .rpc();
+        await setMinBet(stake / 2);
+        try {
+          await expectError(takeSide(true, marketId, marketPDA, bettor, tokenAccount, null, stake / 4), "BetTooSmall");
+        } finally {
+          await setMinBet(1);
+        }
+
+        const vaultBefore = await tokenBalance(stakeVault);
+        await takeSide(true, marketId, marketPDA, bettor, tokenAccount, null, stake / 4);
+
+        expect(await tokenBalance(stakeVault)).to.equal(vaultBefore + stake / 4);
+        expect(await tokenBalance(tokenAccount)).to.equal(stake - stake / 4);
+        const position = await marketProgram.account.position.fetch(findPositionPDA(marketId, bettor.publicKey));
+        expect(position.yesAmount.toNumber()).to.equal(netOf(stake / 4));
+      });
+    });
+
+    describe("market info", () => {
+      it("Reports pools, fees, implied odds and time left", async () => {
+        const market = await createMarket("Will the info view add up?");
+        const marketInfo = () =>
+          marketProgram.methods.getMarketInfo(market.marketId).accounts({ marketAccount: market.marketPDA }).view();
+
+        // An empty parimutuel market sits at even odds
+        let info = await marketInfo();
+        expect(info.yesProbabilityBps).to.equal(5_000);
+        expect(info.noProbabilityBps).to.equal(5_000);
+        expect(info.totalPool.toNumber()).to.equal(0);
+
+        const yes = await newBettor(1_000_000);
+        const no = await newBettor(3_000_000);
+        await takeSide(true, market.marketId, market.marketPDA, yes.bettor, yes.tokenAccount);
+        await takeSide(false, market.marketId, market.marketPDA, no.bettor, no.tokenAccount);
+
+        info = await marketInfo();
+        expect(info.marketId.toNumber()).to.equal(market.marketId.toNumber());
+        expect(info.status).to.deep.equal({ open: {} });
+        expect(info.totalPool.toNumber()).to.equal(netOf(1_000_000) + netOf(3_000_000));
+        expect(info.totalFees.toNumber()).to.equal(4_000_000 - info.totalPool.toNumber());
+        expect(info.yesProbabilityBps).to.equal(2_500);
+        expect(info.noProbabilityBps).to.equal(7_500);
+        expect(info.timeRemaining.toNumber()).to.be.within(0, 5);
+      });
+    });
+
+    describe("dispute window", () => {
+      it("Opens claims at the end of the window and closes disputes with it", async () => {
+        const market = await createMarket("Will the dispute window close on time?");
+        const { bettor, tokenAccount } = await newBettor(1_000_000);
+        await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount);
+
+        const dispute = () =>
+          marketProgram.methods
+            .disputeResolution(market.marketId)
+            .accounts({
+              marketAccount: market.marketPDA,
+              position: findPositionPDA(market.marketId, bettor.publicKey),
+              disputer: bettor.publicKey,
+            })
+            .signers([bettor])
+            .rpc();
+
+        try {
+          await dispute();
+          expect.fail("should have rejected a dispute before resolution");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("NotResolved");
+        }
+
+        await answerYes(market);
+        await waitForResolutionTime();
+        await resolveMarket(market);
+
+        // The suite runs with a zero second dispute period, so claims open at resolution
+        const account = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.claimableAt.toNumber()).to.be.greaterThan(0);
+
+        try {
+          await dispute();
+          expect.fail("should have rejected a dispute after the window");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("DisputeWindowClosed");
+        }
+
+        try {
+          await marketProgram.methods
+            .overrideResolution(market.marketId, { no: {} })
+            .accounts({
+              marketAccount: market.marketPDA,
+              marketState: marketStatePDA,
+              authority: authority.publicKey,
+            })
+            .rpc();
+          expect.fail("should have rejected an override after the window");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("DisputeWindowClosed");
+        }
+
+        // The lone YES bettor takes back the whole pool
+        await marketProgram.methods
+          .claimWinnings(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            position: findPositionPDA(market.marketId, bettor.publicKey),
+            positionOwner: bettor.publicKey,
+            marketState: marketStatePDA,
+            winner: bettor.publicKey,
+            stakeVault,
+            winnerTokenAccount: tokenAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([bettor])
+          .rpc();
+        expect(await tokenBalance(tokenAccount)).to.equal(netOf(1_000_000));
+
+        const settled = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(settled.outcome).to.deep.equal({ yes: {} });
+        expect(settled.disputed).to.equal(false);
+      });
+    });
+
+    describe("minimum confidence", () => {
+      it("Cancels and refunds instead of resolving off a low-confidence answer", async () => {
+        // answerYes answers with confidence 90
+        const market = await createMarket("Will the shaky answer settle it?", 5, 95);
+        const { bettor, tokenAccount } = await newBettor(1_000_000);
+        await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount);
+        await answerYes(market);
+        await waitForResolutionTime();
+
+        try {
+          await resolveMarket(market);
+          expect.fail("should have rejected a low-confidence answer");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("ConfidenceTooLow");
+        }
+
+        // A low-confidence answer cancels right away, without waiting out the refund period
+        await marketProgram.methods
+          .cancelMarket(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            marketState: marketStatePDA,
+            oracleQuestion: market.questionPDA,
+            oracleAnswer: market.answerPDA,
+          })
+          .rpc();
+        expect((await marketProgram.account.marketAccount.fetch(market.marketPDA)).status).to.deep.equal({ canceled: {} });
+
+        await marketProgram.methods
+          .claimRefund(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            position: findPositionPDA(market.marketId, bettor.publicKey),
+            positionOwner: bettor.publicKey,
+            marketState: marketStatePDA,
+            refundee: bettor.publicKey,
+            stakeVault,
+            refundeeTokenAccount: tokenAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([bettor])
+          .rpc();
+        // Canceled markets hand back the entry fee with the stake
+        expect(await tokenBalance(tokenAccount)).to.equal(1_000_000);
+      });
+
+      it("Rejects a minimum confidence outside 1-100", async () => {
+        for (const minConfidence of [0, 101]) {
+          try {
+            await createMarket(`Will confidence ${minConfidence} be accepted?`, 5, minConfidence);
+            expect.fail("should have rejected an out-of-range minimum confidence");
+          } catch (error) {
+            expect(error.error.errorCode.code).to.equal("InvalidMinConfidence");
+          }
+        }
+      });
+    });
+
+    describe("fee treasury", () => {
+      it("Withdraws only to the configured treasury, which only the authority can change", async () => {
+        try {
+          await marketProgram.methods
+            .setTreasury(requester.publicKey)
+            .accounts({ marketState: marketStatePDA, authority: requester.publicKey })
+            .signers([requester])
+            .rpc();
+          expect.fail("should have rejected a treasury change from outside the authority");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("Unauthorized");
+        }
+
+        // The treasury defaults to the authority
+        try {
+          await marketProgram.methods
+            .withdrawFees()
+            .accounts({
+              marketState: marketStatePDA,
+              authority: authority.publicKey,
+              treasury: requester.publicKey,
+              stakeVault,
+              treasuryTokenAccount: null,
+              tokenProgram: TOKEN_PROGRAM_ID,
+            })
+            .rpc();
+          expect.fail("should have rejected a treasury other than the configured one");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("InvalidTreasury");
+        }
+      });
+    });
+
+    describe("question type", () => {
+      it("Refuses to resolve off a question of another type than the market expects", async () => {
+        // createMarket asks a general question, while this market expects a yes/no one
+        const market = await createMarket("Will the general question settle a yes/no market?", 5, 50, { yesNo: {} });
+        await answerYes(market);
+        await waitForResolutionTime();
+
+        try {
+          await resolveMarket(market);
+          expect.fail("should have rejected a mismatched question type");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("QuestionTypeMismatch");
+        }
+      });
+
+      it("Rejects a question type that yields no yes/no answer", async () => {
+        for (const questionType of [{ numeric: {} }, { price: {} }]) {
+          try {
+            await createMarket("Will a numeric question settle a binary market?", 5, 50, questionType);
+            expect.fail("should have rejected a non-binary question type");
+          } catch (error) {
+            expect(error.error.errorCode.code).to.equal("IncompatibleQuestionType");
+          }
+        }
+      });
+    });
+
+    describe("dynamic fees", () => {
+      it("Raises the fee along the schedule as the market nears its close", async () => {
+        const stake = 1_000_000;
+        const early = await newBettor(stake);
+        const late = await newBettor(stake);
+
+        // 2% at creation rising to 20% at close about five seconds later
+        const market = await createMarket("Will the fee rise on schedule?", 5, 50, { general: {} }, { dynamic: {} }, 200, 2_000);
+        const totalFees = async () =>
+          (await marketProgram.account.marketAccount.fetch(market.marketPDA)).totalFees.toNumber();
+
+        await takeSide(true, market.marketId, market.marketPDA, early.bettor, early.tokenAccount);
+        const earlyFee = await totalFees();
+        await new Promise(resolve => setTimeout(resolve, 2000));
+        await takeSide(true, market.marketId, market.marketPDA, late.bettor, late.tokenAccount);
+        const lateFee = (await totalFees()) - earlyFee;
+
+        expect(earlyFee).to.be.at.least(Math.floor((stake * 200) / 10_000));
+        expect(lateFee).to.be.above(earlyFee);
+        expect(lateFee).to.be.at.most(Math.floor((stake * 2_000) / 10_000));
+      });
+
+      it("Rejects a schedule above 100%", async () => {
+        await expectError(
+          createMarket("Will the fee exceed the stake?", 5, 50, { general: {} }, { dynamic: {} }, 0, 10_001),
+          "InvalidFee"
+        );
+      });
+    });
+
+    describe("batch claims", () => {
+      it("Claims every winning market in one call and skips what is not claimable", async () => {
+        const stake = 1_000_000;
+        const { bettor, tokenAccount } = await newBettor(stake);
+        // Three bets go in ahead of the close, so the resolved markets run a little longer than usual
+        const first = await createMarket("Will the first batched market pay?", 8);
+        const second = await createMarket("Will the second batched market pay?", 8);
+        const open = await createMarket("Will the unresolved batched market be skipped?", 60);
+
+        // Each bet stakes the whole token balance, so mint one more stake per market
+        for (const market of [first, second, open]) {
+          await mintTo(provider.connection, payer, stakeMint, tokenAccount, payer, stake);
+          await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount);
+        }
+
+        await answerYes(first);
+        await answerYes(second);
+        await waitForResolutionTime();
+        await resolveMarket(first);
+        await resolveMarket(second);
+
+        const batchClaim = () =>
+          marketProgram.methods
+            .batchClaimWinnings([first, second, open].map(market => market.marketId))
+            .accounts({
+              marketState: marketStatePDA,
+              winner: bettor.publicKey,
+              stakeVault,
+              winnerTokenAccount: tokenAccount,
+              tokenProgram: TOKEN_PROGRAM_ID,
+            })
+            .remainingAccounts(
+              [first, second, open].flatMap(market => [
+                { pubkey: market.marketPDA, isSigner: false, isWritable: true },
+                { pubkey: findPositionPDA(market.marketId, bettor.publicKey), isSigner: false, isWritable: true },
+              ])
+            )
+            .signers([bettor])
+            .rpc();
+
+        // The first bet staked the initial balance too, and each sole bettor is owed its whole net pool
+        await batchClaim();
+        expect(await tokenBalance(tokenAccount)).to.equal(netOf(2 * stake) + netOf(stake));
+        for (const market of [first, second]) {
+          const position = await marketProgram.account.position.fetch(findPositionPDA(market.marketId, bettor.publicKey));
+          expect(position.claimed).to.equal(true);
+        }
+        const openPosition = await marketProgram.account.position.fetch(findPositionPDA(open.marketId, bettor.publicKey));
+        expect(openPosition.claimed).to.equal(false);
+
+        // Claimed positions are skipped on a second pass rather than paid twice
+        await batchClaim();
+        expect(await tokenBalance(tokenAccount)).to.equal(netOf(2 * stake) + netOf(stake));
+      });
+    });
+
+    describe("question binding", () => {
+      it("Refuses to resolve or cancel a market off another question's answer", async () => {
+        const market = await createMarket("Will the bound question settle this market?");
+        const other = await createMarket("Will a foreign answer settle it instead?");
+        await answerYes(other);
+        await waitForResolutionTime();
+
+        // The answer address is derived from the market's question, so only the question can be swapped
+        const borrowed = { ...market, questionPDA: other.questionPDA };
+        await expectError(resolveMarket(borrowed), "OracleQuestionMismatch");
+
+        // Cancel only reads the oracle PDAs derived from the market's own question
+        await expectError(
+          marketProgram.methods
+            .cancelMarket(market.marketId)
+            .accounts({
+              marketAccount: market.marketPDA,
+              marketState: marketStatePDA,
+              oracleQuestion: market.questionPDA,
+              oracleAnswer: other.answerPDA,
+            })
+            .rpc(),
+          "ConstraintSeeds"
+        );
+
+        const account = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.status).to.deep.equal({ open: {} });
+      });
+    });
+
+    describe("empty market deletion", () => {
+      // No creation deposit is set here, so nothing goes to the treasury, which is the authority
+      const deleteMarket = (market: Market) =>
+        marketProgram.methods
+          .deleteEmptyMarket(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            questionBinding: market.questionBindingPDA,
+            marketState: marketStatePDA,
+            creator: authority.publicKey,
+            treasury: authority.publicKey,
+          })
+          .rpc();
+
+      it("Closes a market nobody bet on and returns its rent to the creator", async () => {
+        const market = await createMarket("Will anyone bet on the typo'd market?");
+        const rent = await provider.connection.getBalance(market.marketPDA);
+        const before = await provider.connection.getBalance(authority.publicKey);
+
+        await deleteMarket(market);
+
+        expect(await provider.connection.getAccountInfo(market.marketPDA)).to.equal(null);
+        // The creator also pays the transaction fee
+        const after = await provider.connection.getBalance(authority.publicKey);
+        expect(after).to.be.within(before + rent - 10_000, before + rent);
+      });
+
+      it("Refuses to delete a market holding a bet", async () => {
+        const market = await createMarket("Will the funded market survive deletion?");
+        const { bettor, tokenAccount } = await newBettor(1_000_000);
+        await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount);
+
+        await expectError(deleteMarket(market), "MarketNotEmpty");
+        expect(await provider.connection.getAccountInfo(market.marketPDA)).to.not.equal(null);
+      });
+    });
+
+    describe("time-weighted payouts", () => {
+      it("Pays an earlier bet more than an equal later one, in proportion to its weight", async () => {
+        const stake = 1_000_000;
+        const early = await newBettor(stake);
+        const late = await newBettor(stake);
+        const no = await newBettor(stake);
+        const market = await createMarket("Will the early bettor earn more?", 5, 50, { general: {} }, { flat: {} }, 0, 0, { timeWeighted: {} });
+
+        await takeSide(true, market.marketId, market.marketPDA, early.bettor, early.tokenAccount);
+        await takeSide(false, market.marketId, market.marketPDA, no.bettor, no.tokenAccount);
+        await new Promise(resolve => setTimeout(resolve, 2000));
+        await takeSide(true, market.marketId, market.marketPDA, late.bettor, late.tokenAccount);
+
+        await answerYes(market);
+        await waitForResolutionTime();
+        await resolveMarket(market);
+
+        // Each bettor staked their whole token balance, so their balance after claiming is the payout
+        const account = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        const totalPool = account.yesPool.add(account.noPool);
+        for (const bettor of [early, late]) {
+          const position = await marketProgram.account.position.fetch(findPositionPDA(market.marketId, bettor.bettor.publicKey));
+          expect(position.firstBetAt.toNumber()).to.be.greaterThan(0);
+
+          await marketProgram.methods
+            .claimWinnings(market.marketId)
+            .accounts({
+              marketAccount: market.marketPDA,
+              position: findPositionPDA(market.marketId, bettor.bettor.publicKey),
+              positionOwner: bettor.bettor.publicKey,
+              marketState: marketStatePDA,
+              winner: bettor.bettor.publicKey,
+              stakeVault,
+              winnerTokenAccount: bettor.tokenAccount,
+              tokenProgram: TOKEN_PROGRAM_ID,
+            })
+            .signers([bettor.bettor])
+            .rpc();
+
+          const expected = position.yesWeighted.mul(totalPool).div(account.yesWeighted).toNumber();
+          expect(await tokenBalance(bettor.tokenAccount)).to.equal(expected);
+        }
+
+        // Equal stakes, but the early bet carried more weight and so more of the pool
+        expect(await tokenBalance(early.tokenAccount)).to.be.greaterThan(await tokenBalance(late.tokenAccount));
+      });
+    });
+
+    describe("participant minimum", () => {
+      const stake = 1_000_000;
+
+      // Default market settings, requiring two distinct bettors
+      const createGatedMarket = (question: string) =>
+        createMarket(question, 5, 50, { general: {} }, { flat: {} }, 0, 0, { flat: {} }, 2);
+
+      it("Counts a repeat bettor once and cancels below the minimum", async () => {
+        const { bettor, tokenAccount } = await newBettor(stake);
+        const market = await createGatedMarket("Will one eager bettor be enough?");
+        await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount);
+        await mintTo(provider.connection, payer, stakeMint, tokenAccount, payer, stake);
+        await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount);
+
+        await answerYes(market);
+        await waitForResolutionTime();
+        await resolveMarket(market);
+
+        const account = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.participantCount).to.equal(1);
+        expect(account.status).to.deep.equal({ canceled: {} });
+      });
+
+      it("Resolves once enough distinct bettors have joined", async () => {
+        const yes = await newBettor(stake);
+        const no = await newBettor(stake);
+        const market = await createGatedMarket("Will two bettors be enough?");
+        await takeSide(true, market.marketId, market.marketPDA, yes.bettor, yes.tokenAccount);
+        await takeSide(false, market.marketId, market.marketPDA, no.bettor, no.tokenAccount);
+
+        await answerYes(market);
+        await waitForResolutionTime();
+        await resolveMarket(market);
+
+        const account = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.participantCount).to.equal(2);
+        expect(account.status).to.deep.equal({ resolved: {} });
+      });
+    });
+
+    describe("void outcome", () => {
+      it("Returns each side its net stake when the oracle voids the question", async () => {
+        const stake = 1_000_000;
+        const yes = await newBettor(stake);
+        const no = await newBettor(3 * stake);
+        const market = await createMarket("Will the postponed match settle anything?");
+        await takeSide(true, market.marketId, market.marketPDA, yes.bettor, yes.tokenAccount);
+        await takeSide(false, market.marketId, market.marketPDA, no.bettor, no.tokenAccount);
+
+        // u64::MAX is the oracle's void answer
+        await program.methods
+          .provideAnswer("Void", new anchor.BN("18446744073709551615"), false, 90, "Market test", null, false)
+          .accounts({
+            questionAccount: market.questionPDA,
+            answerAccount: market.answerPDA,
+            oracleState: oracleStatePDA,
+            globalStats: globalStatsPDA,
+            providerBond: providerBondPDA,
+            oracleProvider: oracleProvider.publicKey,
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([oracleProvider])
+          .rpc();
+        await waitForResolutionTime();
+        await resolveMarket(market);
+
+        const account = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.outcome).to.deep.equal({ void: {} });
+
+        for (const [bettor, gross] of [[yes, stake], [no, 3 * stake]] as const) {
+          await marketProgram.methods
+            .claimWinnings(market.marketId)
+            .accounts({
+              marketAccount: market.marketPDA,
+              position: findPositionPDA(market.marketId, bettor.bettor.publicKey),
+              positionOwner: bettor.bettor.publicKey,
+              marketState: marketStatePDA,
+              winner: bettor.bettor.publicKey,
+              stakeVault,
+              winnerTokenAccount: bettor.tokenAccount,
+              tokenProgram: TOKEN_PROGRAM_ID,
+            })
+            .signers([bettor.bettor])
+            .rpc();
+          expect(await tokenBalance(bettor.tokenAccount)).to.equal(netOf(gross));
+        }
+      });
+    });
+
+    describe("repeat claims", () => {
+      it("Refunds a position once and rejects the second attempt", async () => {
+        // Two distinct bettors are required, so a lone bettor's market cancels at resolution
+        const market = await createMarket("Will the refund be paid twice?", 5, 50, { general: {} }, { flat: {} }, 0, 0, { flat: {} }, 2);
+        const { bettor, tokenAccount } = await newBettor(1_000_000);
+        await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount);
+        await answerYes(market);
+        await waitForResolutionTime();
+        await resolveMarket(market);
+
+        const claimRefund = () =>
+          marketProgram.methods
+            .claimRefund(market.marketId)
+            .accounts({
+              marketAccount: market.marketPDA,
+              position: findPositionPDA(market.marketId, bettor.publicKey),
+              positionOwner: bettor.publicKey,
+              marketState: marketStatePDA,
+              refundee: bettor.publicKey,
+              stakeVault,
+              refundeeTokenAccount: tokenAccount,
+              tokenProgram: TOKEN_PROGRAM_ID,
+            })
+            .signers([bettor])
+            .rpc();
+
+        await claimRefund();
+        const refunded = await tokenBalance(tokenAccount);
+        const position = await marketProgram.account.position.fetch(findPositionPDA(market.marketId, bettor.publicKey));
+        expect(position.claimed).to.equal(true);
+
+        await expectError(claimRefund(), "AlreadyClaimed");
+        expect(await tokenBalance(tokenAccount)).to.equal(refunded);
+      });
+    });
+
+    describe("pool cap", () => {
+      const cap = 1_000_000;
+      let market: Market;
+
+      before(async () => {
+        market = await createMarket("Will the capped market fill up?", 5, 50, { general: {} }, { flat: {} }, 0, 0, { flat: {} }, 0, cap);
+      });
+
+      it("Fills a market to just under its pool cap", async () => {
+        const { bettor, tokenAccount } = await newBettor(cap);
+        await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount);
+
+        const marketAccount = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(marketAccount.maxTotalPool.toNumber()).to.equal(cap);
+        expect(marketAccount.yesPool.toNumber()).to.equal(netOf(cap));
+        expect(marketAccount.yesPool.toNumber()).to.be.lessThan(cap);
+      });
+
+      it("Rejects a bet that would push the pool past the cap", async () => {
+        // Smallest stake whose net amount overshoots the remaining room by one
+        let overshoot = cap - netOf(cap);
+        while (netOf(overshoot) <= cap - netOf(cap)) {
+          overshoot++;
+        }
+        const { bettor, tokenAccount } = await newBettor(overshoot);
+
+        await expectError(takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount), "PoolCapExceeded");
+
+        const marketAccount = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(marketAccount.yesPool.toNumber()).to.equal(netOf(cap));
+      });
+    });
+
+    describe("status transitions", () => {
+      let canceled: Market;
+      let resolved: Market;
+
+      before(async () => {
+        // Nobody bets, so the first market cancels on resolve for lack of participants
+        canceled = await createMarket("Will this market find a single bettor?", 5, 50, { general: {} }, { flat: {} }, 0, 0, { flat: {} }, 1);
+        resolved = await createMarket("Will this empty market still resolve?");
+        await answerYes(resolved);
+        await waitForResolutionTime();
+
+        await resolveMarket(canceled);
+        await resolveMarket(resolved);
+      });
+
+      it("Rejects resolving a canceled market", async () => {
+        const before = await marketProgram.account.marketAccount.fetch(canceled.marketPDA);
+        expect(before.status).to.deep.equal({ canceled: {} });
+
+        await expectError(resolveMarket(canceled), "InvalidStatusTransition");
+      });
+
+      it("Rejects canceling a resolved market", async () => {
+        const before = await marketProgram.account.marketAccount.fetch(resolved.marketPDA);
+        expect(before.status).to.deep.equal({ resolved: {} });
+
+        await expectError(
+          marketProgram.methods
+            .cancelMarket(resolved.marketId)
+            .accounts({
+              marketAccount: resolved.marketPDA,
+              marketState: marketStatePDA,
+              oracleQuestion: resolved.questionPDA,
+              oracleAnswer: resolved.answerPDA,
+            })
+            .rpc(),
+          "InvalidStatusTransition"
+        );
+      });
+    });
+
+    describe("participant trail", () => {
+      it("Emits ParticipantJoined once per new bettor across repeat bets", async () => {
+        const market = await createMarket("Will the participant trail skip repeat bettors?");
+        const first = await newBettor(1_000_000);
+        const second = await newBettor(1_000_000);
+
+        const joined: { user: PublicKey; index: number }[] = [];
+        const listener = marketProgram.addEventListener("participantJoined", (event) => {
+          if (event.marketId.eq(market.marketId)) {
+            joined.push({ user: event.user, index: event.index });
+          }
+        });
+
+        try {
+          await takeSide(true, market.marketId, market.marketPDA, first.bettor, first.tokenAccount);
+          await mintTo(provider.connection, payer, stakeMint, first.tokenAccount, payer, 1_000_000);
+          await takeSide(true, market.marketId, market.marketPDA, first.bettor, first.tokenAccount);
+          await takeSide(true, market.marketId, market.marketPDA, second.bettor, second.tokenAccount);
+          await new Promise(resolve => setTimeout(resolve, 500));
+        } finally {
+          await marketProgram.removeEventListener(listener);
+        }
+
+        expect(joined.map(({ user }) => user.toBase58())).to.deep.equal([
+          first.bettor.publicKey.toBase58(),
+          second.bettor.publicKey.toBase58(),
+        ]);
+        expect(joined.map(({ index }) => index)).to.deep.equal([0, 1]);
+
+        const account = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.participantCount).to.equal(2);
+      });
+    });
+
+    describe("cancellation refunds", () => {
+      it("Refunds the gross stake, fee included, on a canceled market", async () => {
+        const stake = 1_000_000;
+        // Two bettors are required, so a lone bettor leaves the market to cancel on resolve
+        const market = await createMarket("Will a second bettor ever show up?", 5, 50, { general: {} }, { flat: {} }, 0, 0, { flat: {} }, 2);
+        const { bettor, tokenAccount } = await newBettor(stake);
+
+        await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount);
+        expect(await tokenBalance(tokenAccount)).to.equal(0);
+
+        await waitForResolutionTime();
+        await resolveMarket(market);
+
+        const canceled = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(canceled.status).to.deep.equal({ canceled: {} });
+        expect(canceled.totalFees.toNumber()).to.equal(0);
+
+        await marketProgram.methods
+          .claimRefund(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            position: findPositionPDA(market.marketId, bettor.publicKey),
+            positionOwner: bettor.publicKey,
+            marketState: marketStatePDA,
+            refundee: bettor.publicKey,
+            stakeVault,
+            refundeeTokenAccount: tokenAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([bettor])
+          .rpc();
+
+        expect(netOf(stake)).to.be.lessThan(stake);
+        expect(await tokenBalance(tokenAccount)).to.equal(stake);
+      });
+
+      it("Batch refunds a canceled market's positions, skipping one already claimed", async () => {
+        const stake = 1_000_000;
+        // Three bettors fall short of the four required, so the market cancels on resolve
+        const bettors = [await newBettor(stake), await newBettor(2 * stake), await newBettor(3 * stake)];
+        const market = await createMarket("Will a fourth bettor ever show up?", 5, 50, { general: {} }, { flat: {} }, 0, 0, { flat: {} }, 4);
+        for (const { bettor, tokenAccount } of bettors) {
+          await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount);
+        }
+
+        await waitForResolutionTime();
+        await resolveMarket(market);
+        expect((await marketProgram.account.marketAccount.fetch(market.marketPDA)).status).to.deep.equal({ canceled: {} });
+
+        // The first bettor claims on their own before the operator's batch
+        const [first] = bettors;
+        await marketProgram.methods
+          .claimRefund(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            position: findPositionPDA(market.marketId, first.bettor.publicKey),
+            positionOwner: first.bettor.publicKey,
+            marketState: marketStatePDA,
+            refundee: first.bettor.publicKey,
+            stakeVault,
+            refundeeTokenAccount: first.tokenAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([first.bettor])
+          .rpc();
+        expect(await tokenBalance(first.tokenAccount)).to.equal(stake);
+
+        const signature = await marketProgram.methods
+          .batchRefund(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            marketState: marketStatePDA,
+            stakeVault,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .remainingAccounts(
+            bettors.flatMap(({ bettor, tokenAccount }) => [
+              { pubkey: findPositionPDA(market.marketId, bettor.publicKey), isSigner: false, isWritable: true },
+              { pubkey: tokenAccount, isSigner: false, isWritable: true },
+            ])
+          )
+          .rpc({ commitment: "confirmed" });
+
+        // Every bettor ends up with their gross stake exactly once
+        expect(await tokenBalance(bettors[0].tokenAccount)).to.equal(stake);
+        expect(await tokenBalance(bettors[1].tokenAccount)).to.equal(2 * stake);
+        expect(await tokenBalance(bettors[2].tokenAccount)).to.equal(3 * stake);
+        for (const { bettor } of bettors) {
+          const position = await marketProgram.account.position.fetch(findPositionPDA(market.marketId, bettor.publicKey));
+          expect(position.claimed).to.equal(true);
+        }
+
+        const tx = await provider.connection.getTransaction(signature, {
+          commitment: "confirmed",
+          maxSupportedTransactionVersion: 0,
+        });
+        const eventParser = new anchor.EventParser(marketProgram.programId, marketProgram.coder);
+        const batch = [...eventParser.parseLogs(tx.meta.logMessages)].find(event => event.name === "batchRefunded");
+        expect(batch.data.refunded.map(key => key.toBase58())).to.deep.equal(
+          [bettors[1], bettors[2]].map(({ bettor }) => bettor.publicKey.toBase58())
+        );
+        expect(batch.data.skipped.map(key => key.toBase58())).to.deep.equal([first.bettor.publicKey.toBase58()]);
+        expect(batch.data.totalRefund.toNumber()).to.equal(5 * stake);
+      });
+    });
+
+    describe("inconclusive answers", () => {
+      it("Cancels and refunds a market whose oracle answer is inconclusive", async () => {
+        const stake = 1_000_000;
+        const market = await createMarket("Will the disputed match result stand?");
+        const { bettor, tokenAccount } = await newBettor(stake);
+        await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount);
+
+        await program.methods
+          .provideAnswer("Unknown", new anchor.BN(0), false, 0, "No consensus", null, true)
+          .accounts({
+            questionAccount: market.questionPDA,
+            answerAccount: market.answerPDA,
+            oracleState: oracleStatePDA,
+            globalStats: globalStatsPDA,
+            providerBond: providerBondPDA,
+            oracleProvider: oracleProvider.publicKey,
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([oracleProvider])
+          .rpc();
+
+        const answer = await program.account.answerAccount.fetch(market.answerPDA);
+        expect(answer.inconclusive).to.equal(true);
+        const question = await program.account.questionAccount.fetch(market.questionPDA);
+        expect(question.status).to.deep.equal({ answered: {} });
+
+        await waitForResolutionTime();
+        await resolveMarket(market);
+
+        const canceled = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(canceled.status).to.deep.equal({ canceled: {} });
+        expect(canceled.outcome).to.deep.equal({ unresolved: {} });
+
+        await marketProgram.methods
+          .claimRefund(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            position: findPositionPDA(market.marketId, bettor.publicKey),
+            positionOwner: bettor.publicKey,
+            marketState: marketStatePDA,
+            refundee: bettor.publicKey,
+            stakeVault,
+            refundeeTokenAccount: tokenAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([bettor])
+          .rpc();
+
+        expect(await tokenBalance(tokenAccount)).to.equal(stake);
+      });
+    });
+
+    describe("referrals", () => {
+      const referralBps = 5_000;
+      const feeOf = (amount: number) => amount - netOf(amount);
+      const referrer = Keypair.generate();
+      let market: Market;
+      let referrerTokenAccount: PublicKey;
+
+      before(async () => {
+        await marketProgram.methods
+          .setReferralBps(referralBps)
+          .accounts({
+            marketState: marketStatePDA,
+            authority: authority.publicKey,
+          })
+          .rpc();
+
+        await provider.connection.requestAirdrop(referrer.publicKey, LAMPORTS_PER_SOL);
+        referrerTokenAccount = (
+          await getOrCreateAssociatedTokenAccount(provider.connection, payer, stakeMint, referrer.publicKey)
+        ).address;
+        market = await createMarket("Will referred bettors back this market?");
+      });
+
+      it("Credits the referrer a cut of a referred bet's fee", async () => {
+        const stake = 1_000_000;
+        const { bettor, tokenAccount } = await newBettor(stake);
+
+        await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount, referrer.publicKey);
+
+        const cut = Math.floor((feeOf(stake) * referralBps) / 10_000);
+        const referral = await marketProgram.account.referralBalance.fetch(findReferralPDA(market.marketId, referrer.publicKey));
+        expect(referral.balance.toNumber()).to.equal(cut);
+
+        // The cut comes out of the protocol fee, not the pool
+        const account = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.totalFees.toNumber()).to.equal(feeOf(stake) - cut);
+        expect(account.yesPool.toNumber()).to.equal(netOf(stake));
+      });
+
+      it("Leaves the referrer's balance alone for a non-referred bet", async () => {
+        const stake = 1_000_000;
+        const before = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        const referralBefore = await marketProgram.account.referralBalance.fetch(findReferralPDA(market.marketId, referrer.publicKey));
+        const { bettor, tokenAccount } = await newBettor(stake);
+
+        await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount);
+
+        const referral = await marketProgram.account.referralBalance.fetch(findReferralPDA(market.marketId, referrer.publicKey));
+        expect(referral.balance.toNumber()).to.equal(referralBefore.balance.toNumber());
+        const account = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.totalFees.toNumber()).to.equal(before.totalFees.toNumber() + feeOf(stake));
+      });
+
+      it("Pays the referrer once the market resolves", async () => {
+        const referralPDA = findReferralPDA(market.marketId, referrer.publicKey);
+        const { balance } = await marketProgram.account.referralBalance.fetch(referralPDA);
+
+        await answerYes(market);
+        await waitForResolutionTime();
+        await resolveMarket(market);
+
+        await marketProgram.methods
+          .claimReferralFees(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            referralBalance: referralPDA,
+            marketState: marketStatePDA,
+            referrer: referrer.publicKey,
+            stakeVault,
+            referrerTokenAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([referrer])
+          .rpc();
+
+        expect(await tokenBalance(referrerTokenAccount)).to.equal(balance.toNumber());
+        const referral = await marketProgram.account.referralBalance.fetch(referralPDA);
+        expect(referral.balance.toNumber()).to.equal(0);
+      });
+    });
+
+    describe("delegated betting", () => {
+      const spender = Keypair.generate();
+      let market: Market;
+      let owner: Keypair;
+      let ownerTokenAccount: PublicKey;
+      let spenderAllowancePDA: PublicKey;
+
+      const betDelegated = (amount: number) =>
+        marketProgram.methods
+          .takePositionDelegated(market.marketId, true, new anchor.BN(amount))
+          .accounts({
+            marketAccount: market.marketPDA,
+            position: findPositionPDA(market.marketId, owner.publicKey),
+            spenderAllowance: spenderAllowancePDA,
+            marketState: marketStatePDA,
+            owner: owner.publicKey,
+            spender: spender.publicKey,
+            stakeVault,
+            ownerTokenAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([spender])
+          .rpc();
+
+      before(async () => {
+        market = await createMarket("Will the bot's delegated bets land?");
+        ({ bettor: owner, tokenAccount: ownerTokenAccount } = await newBettor(1_000_000));
+        await provider.connection.requestAirdrop(spender.publicKey, LAMPORTS_PER_SOL);
+        await new Promise(resolve => setTimeout(resolve, 1000));
+
+        [spenderAllowancePDA] = PublicKey.findProgramAddressSync(
+          [Buffer.from("allowance"), owner.publicKey.toBuffer(), spender.publicKey.toBuffer()],
+          marketProgram.programId
+        );
+
+        await marketProgram.methods
+          .approveSpender(spender.publicKey, new anchor.BN(600_000))
+          .accounts({
+            spenderAllowance: spenderAllowancePDA,
+            marketState: marketStatePDA,
+            owner: owner.publicKey,
+            ownerTokenAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([owner])
+          .rpc();
+      });
+
+      it("Bets on the owner's behalf within the allowance", async () => {
+        await betDelegated(400_000);
+
+        const position = await marketProgram.account.position.fetch(findPositionPDA(market.marketId, owner.publicKey));
+        expect(position.yesAmount.toNumber()).to.equal(netOf(400_000));
+        expect(await tokenBalance(ownerTokenAccount)).to.equal(600_000);
+
+        const allowance = await marketProgram.account.spenderAllowance.fetch(spenderAllowancePDA);
+        expect(allowance.remaining.toNumber()).to.equal(200_000);
+      });
+
+      it("Rejects a delegated bet beyond the remaining allowance", async () => {
+        await expectError(betDelegated(300_000), "AllowanceExceeded");
+      });
+    });
+
+    describe("liquidity floor", () => {
+      it("Cancels and refunds a market with a side below the floor", async () => {
+        const stake = 1_000_000;
+        // Everyone backs YES, so the NO pool stays empty and below the floor
+        const market = await createMarket("Will a one-sided market still pay out?", 5, 50, { general: {} }, { flat: {} }, 0, 0, { flat: {} }, 0, 0, 0, 1_000);
+        const { bettor, tokenAccount } = await newBettor(stake);
+
+        await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount);
+        await answerYes(market);
+        await waitForResolutionTime();
+        await resolveMarket(market);
+
+        const account = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.status).to.deep.equal({ canceled: {} });
+
+        await marketProgram.methods
+          .claimRefund(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            position: findPositionPDA(market.marketId, bettor.publicKey),
+            positionOwner: bettor.publicKey,
+            marketState: marketStatePDA,
+            refundee: bettor.publicKey,
+            stakeVault,
+            refundeeTokenAccount: tokenAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([bettor])
+          .rpc();
+
+        expect(await tokenBalance(tokenAccount)).to.equal(stake);
+      });
+    });
+
+    describe("review window", () => {
+      it("Rejects bets before the open time and accepts them after", async () => {
+        const stake = 1_000_000;
+        const opensAt = Math.floor(Date.now() / 1000) + 2;
+        const market = await createMarket("Will bettors wait for the review window?", 5, 50, { general: {} }, { flat: {} }, 0, 0, { flat: {} }, 0, 0, 0, 0, false, opensAt);
+        const { bettor, tokenAccount } = await newBettor(stake);
+
+        await expectError(takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount), "OpensLater");
+
+        await new Promise(resolve => setTimeout(resolve, 3000));
+        await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount);
+
+        const account = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.opensAt.toNumber()).to.equal(opensAt);
+        expect(account.yesPool.toNumber()).to.equal(netOf(stake));
+      });
+
+      it("Rejects an open time at or after the close time", async () => {
+        await expectError(
+          createMarket("Will this market ever open?", 5, 50, { general: {} }, { flat: {} }, 0, 0, { flat: {} }, 0, 0, 0, 0, false, Math.floor(Date.now() / 1000) + 3600),
+          "InvalidOpenTime"
+        );
+      });
+    });
+
+    describe("single side betting", () => {
+      const stake = 1_000_000;
+
+      it("Rejects a bet against the bettor's own position", async () => {
+        const market = await createMarket("Will hedged bettors be turned away?", 5, 50, { general: {} }, { flat: {} }, 0, 0, { flat: {} }, 0, 0, 0, 0, true);
+        const { bettor, tokenAccount } = await newBettor(stake);
+        await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount);
+
+        await mintTo(provider.connection, payer, stakeMint, tokenAccount, payer, stake);
+        await expectError(takeSide(false, market.marketId, market.marketPDA, bettor, tokenAccount), "SingleSideOnly");
+
+        // Adding to the same side is still allowed
+        await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount);
+        const position = await marketProgram.account.position.fetch(findPositionPDA(market.marketId, bettor.publicKey));
+        expect(position.yesAmount.toNumber()).to.equal(2 * netOf(stake));
+        expect(position.noAmount.toNumber()).to.equal(0);
+      });
+
+      it("Accepts bets on both sides when the market allows it", async () => {
+        const market = await createMarket("Will hedged bettors be welcome?");
+        const { bettor, tokenAccount } = await newBettor(stake);
+        await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount);
+
+        await mintTo(provider.connection, payer, stakeMint, tokenAccount, payer, stake);
+        await takeSide(false, market.marketId, market.marketPDA, bettor, tokenAccount);
+
+        const position = await marketProgram.account.position.fetch(findPositionPDA(market.marketId, bettor.publicKey));
+        expect(position.yesAmount.toNumber()).to.equal(netOf(stake));
+        expect(position.noAmount.toNumber()).to.equal(netOf(stake));
+      });
+    });
+
+    describe("dynamic fee bounds", () => {
+      it("Caps both ends of a dynamic fee schedule at MAX_FEE_BPS", async () => {
+        const marketId = (await marketProgram.account.marketState.fetch(marketStatePDA)).marketCounter;
+        const [marketPDA] = PublicKey.findProgramAddressSync(
+          [Buffer.from("market"), marketId.toArrayLike(Buffer, "le", 8)],
+          marketProgram.programId
+        );
+        // Creation fails on the fee check, so the binding is never written and any question id will do
+        const questionId = new anchor.BN(Date.now());
+        const [questionBindingPDA] = PublicKey.findProgramAddressSync(
+          [Buffer.from("question_binding"), questionId.toArrayLike(Buffer, "le", 8)],
+          marketProgram.programId
+        );
+
+        for (const [feeStartBps, feeEndBps] of [[2_001, 100], [100, 2_001]]) {
+          try {
+            await marketProgram.methods
+              .createMarket(
+                "Can a dynamic fee climb past the cap?",
+                new anchor.BN(Math.floor(Date.now() / 1000) + 5),
+                new anchor.BN(0),
+                { parimutuel: {} },
+                new anchor.BN(0),
+                50,
+                questionId,
+                { general: {} },
+                { dynamic: {} },
+                feeStartBps,
+                feeEndBps,
+                false,
+                { flat: {} },
+                0,
+                new anchor.BN(0),
+                new anchor.BN(0),
+                new anchor.BN(0),
+                false,
+                new anchor.BN(0)
+              )
+              .accounts({
+                marketAccount: marketPDA,
+                marketState: marketStatePDA,
+                questionBinding: questionBindingPDA,
+                creator: authority.publicKey,
+                systemProgram: SystemProgram.programId,
+              })
+              .rpc();
+            expect.fail("should have rejected a dynamic fee over the cap");
+          } catch (error) {
+            expect(error.error.errorCode.code).to.equal("FeeTooHigh");
+          }
+        }
+      });
+    });
+
+    describe("zero fees", () => {
+      const stake = 1_000_000;
+
+      const setFee = (fee: number) =>
+        marketProgram.methods
+          .setFeePercentage(fee)
+          .accounts({
+            marketState: marketStatePDA,
+            authority: authority.publicKey,
+          })
+          .rpc();
+
+      before(async () => {
+        // Start from an empty fee balance so the zero-fee lifecycle is the only contributor
+        const { accumulatedFees } = await marketProgram.account.marketState.fetch(marketStatePDA);
+        if (accumulatedFees.toNumber() > 0) {
+          await withdrawFees();
+        }
+        await setFee(0);
+      });
+
+      after(async () => {
+        await setFee(feeBps);
+      });
+
+      it("Runs a full market lifecycle with exact pool and payout accounting", async () => {
+        const market = await createMarket("Will a fee-free market pay out exactly?");
+        const yes = await newBettor(stake);
+        const no = await newBettor(2 * stake);
+
+        await takeSide(true, market.marketId, market.marketPDA, yes.bettor, yes.tokenAccount);
+        await takeSide(false, market.marketId, market.marketPDA, no.bettor, no.tokenAccount);
+
+        // With no fee every lamport of the stake lands in the pool
+        const account = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.yesPool.toNumber()).to.equal(stake);
+        expect(account.noPool.toNumber()).to.equal(2 * stake);
+        expect(account.totalFees.toNumber()).to.equal(0);
+        const position = await marketProgram.account.position.fetch(findPositionPDA(market.marketId, yes.bettor.publicKey));
+        expect(position.yesAmount.toNumber()).to.equal(stake);
+        expect(position.feesPaid.toNumber()).to.equal(0);
+
+        await answerYes(market);
+        await waitForResolutionTime();
+        await resolveMarket(market);
+
+        await marketProgram.methods
+          .claimWinnings(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            position: findPositionPDA(market.marketId, yes.bettor.publicKey),
+            positionOwner: yes.bettor.publicKey,
+            marketState: marketStatePDA,
+            winner: yes.bettor.publicKey,
+            stakeVault,
+            winnerTokenAccount: yes.tokenAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([yes.bettor])
+          .rpc();
+
+        // The sole winner takes both pools in full
+        expect(await tokenBalance(yes.tokenAccount)).to.equal(3 * stake);
+        const state = await marketProgram.account.marketState.fetch(marketStatePDA);
+        expect(state.accumulatedFees.toNumber()).to.equal(0);
+      });
+
+      it("Rejects a fee withdrawal with nothing accumulated", async () => {
+        await expectError(withdrawFees(), "NoFees");
+      });
+    });
+
+    describe("fee finalization", () => {
+      const stake = 1_000_000;
+
+      it("Holds a resolved market's fees back from withdrawal until it is finalized", async () => {
+        const { accumulatedFees } = await marketProgram.account.marketState.fetch(marketStatePDA);
+        if (accumulatedFees.toNumber() > 0) {
+          await withdrawFees();
+        }
+
+        const market = await createMarket("Will fees wait for finalization?");
+        const yes = await newBettor(stake);
+        await takeSide(true, market.marketId, market.marketPDA, yes.bettor, yes.tokenAccount);
+        await answerYes(market);
+        await waitForResolutionTime();
+        await resolveMarket(market);
+
+        // Resolution leaves the fees on the market, out of the treasury's reach
+        const fees = (await marketProgram.account.marketAccount.fetch(market.marketPDA)).totalFees.toNumber();
+        expect(fees).to.be.greaterThan(0);
+        expect((await marketProgram.account.marketState.fetch(marketStatePDA)).accumulatedFees.toNumber()).to.equal(0);
+        await expectError(withdrawFees(), "NoFees");
+
+        await finalizeMarket(market);
+        const account = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.finalized).to.equal(true);
+        expect((await marketProgram.account.marketState.fetch(marketStatePDA)).accumulatedFees.toNumber()).to.equal(fees);
+
+        const { treasury } = await marketProgram.account.marketState.fetch(marketStatePDA);
+        const treasuryTokenAccount = (
+          await getOrCreateAssociatedTokenAccount(provider.connection, payer, stakeMint, treasury)
+        ).address;
+        const before = await tokenBalance(treasuryTokenAccount);
+        await withdrawFees();
+        expect(await tokenBalance(treasuryTokenAccount)).to.equal(before + fees);
+
+        await expectError(finalizeMarket(market), "MarketAlreadyFinalized");
+      });
+
+      it("Rejects finalizing a market that has not resolved", async () => {
+        const market = await createMarket("Will an open market finalize?");
+
+        await expectError(finalizeMarket(market), "NotResolved");
+      });
+    });
+
+    describe("large pools", () => {
+      it("Pays out pools near u64::MAX / 2 without overflowing", async () => {
+        // Each side stakes 2^62, so stake * total pool needs well over 64 bits
+        const stake = new anchor.BN(1).shln(62);
+        const net = stake.sub(stake.muln(feeBps).divn(10_000));
+        const market = await createMarket("Will a huge market still pay out?");
+        const yes = await newBettor(BigInt(stake.toString()));
+        const no = await newBettor(BigInt(stake.toString()));
+
+        await takeSide(true, market.marketId, market.marketPDA, yes.bettor, yes.tokenAccount);
+        await takeSide(false, market.marketId, market.marketPDA, no.bettor, no.tokenAccount);
+
+        const account = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.yesPool.toString()).to.equal(net.toString());
+        expect(account.noPool.toString()).to.equal(net.toString());
+
+        await answerYes(market);
+        await waitForResolutionTime();
+        await resolveMarket(market);
+
+        await marketProgram.methods
+          .claimWinnings(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            position: findPositionPDA(market.marketId, yes.bettor.publicKey),
+            positionOwner: yes.bettor.publicKey,
+            marketState: marketStatePDA,
+            winner: yes.bettor.publicKey,
+            stakeVault,
+            winnerTokenAccount: yes.tokenAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([yes.bettor])
+          .rpc();
+
+        const { amount } = await getAccount(provider.connection, yes.tokenAccount);
+        expect(amount.toString()).to.equal(net.muln(2).toString());
+      });
+    });
+
+    describe("parlay shares", () => {
+      const setParlayShare = (market: Market, parlayBps: number) =>
+        marketProgram.methods
+          .setParlayShare(market.marketId, parlayBps, Keypair.generate().publicKey)
+          .accounts({
+            marketAccount: market.marketPDA,
+            marketState: marketStatePDA,
+            authority: authority.publicKey,
+          })
+          .rpc();
+
+      it("Rejects a parlay share while stakes are held in the token vault", async () => {
+        const market = await createMarket("Can a token market feed a parlay?");
+        await expectError(setParlayShare(market, 5_000), "TokenModeUnsupported");
+      });
+
+      it("Caps the parlay share at MAX_PARLAY_BPS", async () => {
+        const market = await createMarket("Can a parlay take most of the losing pool?");
+        await expectError(setParlayShare(market, 5_001), "InvalidParlayShare");
+      });
+
+      it("Locks the parlay share once the first bet lands", async () => {
+        const market = await createMarket("Can a parlay share move under a bettor?");
+        const { bettor, tokenAccount } = await newBettor(1_000_000);
+        await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount);
+
+        await expectError(setParlayShare(market, 1_000), "ParlayShareLocked");
+      });
+
+      it("Rejects returning a parlay share the market never set aside", async () => {
+        const market = await createMarket("Is there a parlay share to hand back?");
+        await answerYes(market);
+        await waitForResolutionTime();
+        await resolveMarket(market);
+
+        await expectError(
+          marketProgram.methods
+            .returnParlayShare(market.marketId)
+            .accounts({
+              marketAccount: market.marketPDA,
+              marketState: marketStatePDA,
+              caller: authority.publicKey,
+            })
+            .rpc(),
+          "NoParlayShare"
+        );
+      });
+    });
+
+    describe("claim errors", () => {
+      const claim = (market: Market, bettor: { bettor: Keypair; tokenAccount: PublicKey }) =>
+        marketProgram.methods
+          .claimWinnings(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            position: findPositionPDA(market.marketId, bettor.bettor.publicKey),
+            positionOwner: bettor.bettor.publicKey,
+            marketState: marketStatePDA,
+            winner: bettor.bettor.publicKey,
+            stakeVault,
+            winnerTokenAccount: bettor.tokenAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([bettor.bettor])
+          .rpc();
+
+      it("Rejects a claim on the losing side", async () => {
+        const market = await createMarket("Will the NO side be told it lost?");
+        const yes = await newBettor(1_000_000);
+        const no = await newBettor(1_000_000);
+        await takeSide(true, market.marketId, market.marketPDA, yes.bettor, yes.tokenAccount);
+        await takeSide(false, market.marketId, market.marketPDA, no.bettor, no.tokenAccount);
+
+        await answerYes(market);
+        await waitForResolutionTime();
+        await resolveMarket(market);
+
+        await expectError(claim(market, no), "BetOnLosingSide");
+      });
+
+      it("Rejects a claim when nobody bet on the winning side", async () => {
+        const market = await createMarket("Will a one-sided NO market pay anyone?");
+        const no = await newBettor(1_000_000);
+        await takeSide(false, market.marketId, market.marketPDA, no.bettor, no.tokenAccount);
+
+        await answerYes(market);
+        await waitForResolutionTime();
+        await resolveMarket(market);
+
+        await expectError(claim(market, no), "EmptyWinningPool");
+      });
+
+      it("Rejects a time-weighted claim whose payout rounds to zero", async () => {
+        // A one-unit bet placed after a large early one is outweighed, so its share of the pool floors to zero
+        const early = await newBettor(1_000_000);
+        const late = await newBettor(1);
+        const market = await createMarket("Will a dust bet round away?", 5, 50, { general: {} }, { flat: {} }, 0, 0, { timeWeighted: {} });
+        await takeSide(true, market.marketId, market.marketPDA, early.bettor, early.tokenAccount);
+        await new Promise(resolve => setTimeout(resolve, 2000));
+        await takeSide(true, market.marketId, market.marketPDA, late.bettor, late.tokenAccount);
+
+        await answerYes(market);
+        await waitForResolutionTime();
+        await resolveMarket(market);
+
+        await expectError(claim(market, late), "PayoutRoundsToZero");
+      });
+    });
+
+    describe("cached answer", () => {
+      it("Claims after resolution without the oracle accounts", async () => {
+        const stake = 1_000_000;
+        const market = await createMarket("Will the claim skip the oracle?");
+        const { bettor, tokenAccount } = await newBettor(stake);
+        await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount);
+
+        await answerYes(market);
+        await waitForResolutionTime();
+        await resolveMarket(market);
+
+        const account = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.resolvedValue.toNumber()).to.equal(1);
+        expect(account.resolvedBool).to.equal(true);
+
+        const claim = await marketProgram.methods
+          .claimWinnings(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            position: findPositionPDA(market.marketId, bettor.publicKey),
+            positionOwner: bettor.publicKey,
+            marketState: marketStatePDA,
+            winner: bettor.publicKey,
+            stakeVault,
+            winnerTokenAccount: tokenAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .instruction();
+        const keys = claim.keys.map(meta => meta.pubkey.toBase58());
+        expect(keys).to.not.include(market.questionPDA.toBase58());
+        expect(keys).to.not.include(market.answerPDA.toBase58());
+
+        await provider.sendAndConfirm(new Transaction().add(claim), [bettor]);
+        expect(await tokenBalance(tokenAccount)).to.equal(netOf(stake));
+      });
+    });
+
+    describe("answer freshness", () => {
+      // Default market settings, accepting answers at most `maxAnswerAgeSecs` old at resolution
+      const createFreshMarket = (question: string, maxAnswerAgeSecs: number) =>
+        createMarket(question, 5, 50, { general: {} }, { flat: {} }, 0, 0, { flat: {} }, 0, 0, maxAnswerAgeSecs);
+
+      it("Rejects a stale answer and lets the market cancel instead", async () => {
+        // Answered right away, about five seconds before resolution time, with a one second window
+        const market = await createFreshMarket("Will the stale answer be refused?", 1);
+        await answerYes(market);
+        await waitForResolutionTime();
+
+        await expectError(resolveMarket(market), "AnswerTooStale");
+
+        await marketProgram.methods
+          .cancelMarket(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            marketState: marketStatePDA,
+            oracleQuestion: market.questionPDA,
+            oracleAnswer: market.answerPDA,
+          })
+          .rpc();
+
+        const account = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.status).to.deep.equal({ canceled: {} });
+      });
+
+      it("Makes an unanswered market wait out the refund period before canceling", async () => {
+        // The question stays pending, so there is no answer account to read at all
+        const market = await createMarket("Will the oracle ever answer this one?");
+        await waitForResolutionTime();
+
+        await expectError(
+          marketProgram.methods
+            .cancelMarket(market.marketId)
+            .accounts({
+              marketAccount: market.marketPDA,
+              marketState: marketStatePDA,
+              oracleQuestion: market.questionPDA,
+              oracleAnswer: market.answerPDA,
+            })
+            .rpc(),
+          "TooEarlyToCancel"
+        );
+
+        const account = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.status).to.deep.equal({ open: {} });
+      });
+
+      it("Resolves off an answer inside the freshness window", async () => {
+        const market = await createFreshMarket("Will the fresh answer be accepted?", 3600);
+        await answerYes(market);
+        await waitForResolutionTime();
+
+        await resolveMarket(market);
+
+        const account = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.status).to.deep.equal({ resolved: {} });
+      });
+    });
+
+    describe("creation deposit", () => {
+      const deposit = LAMPORTS_PER_SOL / 10;
+
+      const setCreationDeposit = (amount: anchor.BN) =>
+        marketProgram.methods
+          .setCreationDeposit(amount)
+          .accounts({
+            marketState: marketStatePDA,
+            authority: authority.publicKey,
+          })
+          .rpc();
+
+      before(async () => {
+        await setCreationDeposit(new anchor.BN(deposit));
+      });
+
+      after(async () => {
+        await setCreationDeposit(new anchor.BN(0));
+      });
+
+      it("Rejects creating a market without enough for the deposit", async () => {
+        await setCreationDeposit(new anchor.BN("18000000000000000000"));
+
+        try {
+          await expectError(createMarket("Can a creator skip the deposit?"), "InsufficientDeposit");
+        } finally {
+          await setCreationDeposit(new anchor.BN(deposit));
+        }
+      });
+
+      it("Returns the deposit once the market resolves", async () => {
+        const market = await createMarket("Will the creator get the deposit back?");
+        const { bettor, tokenAccount } = await newBettor(1_000_000);
+        await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount);
+
+        const reclaim = () =>
+          marketProgram.methods
+            .reclaimCreationDeposit(market.marketId)
+            .accounts({
+              marketAccount: market.marketPDA,
+              creator: authority.publicKey,
+            })
+            .rpc();
+
+        await expectError(reclaim(), "NotResolved");
+
+        await answerYes(market);
+        await waitForResolutionTime();
+        await resolveMarket(market);
+
+        const lamportsBefore = await provider.connection.getBalance(market.marketPDA);
+        expect((await marketProgram.account.marketAccount.fetch(market.marketPDA)).creationDeposit.toNumber()).to.equal(deposit);
+
+        await reclaim();
+
+        expect(await provider.connection.getBalance(market.marketPDA)).to.equal(lamportsBefore - deposit);
+        expect((await marketProgram.account.marketAccount.fetch(market.marketPDA)).creationDeposit.toNumber()).to.equal(0);
+      });
+
+      it("Returns the deposit once the market is canceled", async () => {
+        // One bettor short of the participant minimum, so resolution cancels the market
+        const market = await createMarket("Will a canceled market return the deposit?", 5, 50, { general: {} }, { flat: {} }, 0, 0, { flat: {} }, 2);
+        const { bettor, tokenAccount } = await newBettor(1_000_000);
+        await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount);
+        await waitForResolutionTime();
+        await resolveMarket(market);
+        expect((await marketProgram.account.marketAccount.fetch(market.marketPDA)).status).to.deep.equal({ canceled: {} });
+
+        const lamportsBefore = await provider.connection.getBalance(market.marketPDA);
+        await marketProgram.methods
+          .reclaimCreationDeposit(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            creator: authority.publicKey,
+          })
+          .rpc();
+
+        expect(await provider.connection.getBalance(market.marketPDA)).to.equal(lamportsBefore - deposit);
+        expect((await marketProgram.account.marketAccount.fetch(market.marketPDA)).creationDeposit.toNumber()).to.equal(0);
+      });
+
+      it("Keeps the deposit when the authority deletes a spam market", async () => {
+        const market = await createMarket("Is this market spam?");
+        const treasury = Keypair.generate();
+
+        const setTreasury = (key: PublicKey) =>
+          marketProgram.methods
+            .setTreasury(key)
+            .accounts({
+              marketState: marketStatePDA,
+              authority: authority.publicKey,
+            })
+            .rpc();
+
+        await setTreasury(treasury.publicKey);
+        try {
+          await marketProgram.methods
+            .deleteSpamMarket(market.marketId)
+            .accounts({
+              marketAccount: market.marketPDA,
+              questionBinding: market.questionBindingPDA,
+              marketState: marketStatePDA,
+              authority: authority.publicKey,
+              creator: authority.publicKey,
+              treasury: treasury.publicKey,
+            })
+            .rpc();
+        } finally {
+          await setTreasury(authority.publicKey);
+        }
+
+        expect(await provider.connection.getBalance(treasury.publicKey)).to.equal(deposit);
+        expect(await provider.connection.getAccountInfo(market.marketPDA)).to.equal(null);
+      });
+
+      it("Sends the deposit to the treasury when the creator deletes an empty market", async () => {
+        const market = await createMarket("Can a creator delete a market to dodge the deposit?");
+        const account = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        const rent = (await provider.connection.getBalance(market.marketPDA)) - deposit;
+        const treasury = Keypair.generate();
+
+        const setTreasury = (key: PublicKey) =>
+          marketProgram.methods
+            .setTreasury(key)
+            .accounts({
+              marketState: marketStatePDA,
+              authority: authority.publicKey,
+            })
+            .rpc();
+
+        await setTreasury(treasury.publicKey);
+        let sig: string;
+        try {
+          sig = await marketProgram.methods
+            .deleteEmptyMarket(market.marketId)
+            .accounts({
+              marketAccount: market.marketPDA,
+              questionBinding: PublicKey.findProgramAddressSync(
+                [Buffer.from("question_binding"), account.oracleQuestionId.toArrayLike(Buffer, "le", 8)],
+                marketProgram.programId
+              )[0],
+              marketState: marketStatePDA,
+              creator: authority.publicKey,
+              treasury: treasury.publicKey,
+            })
+            .rpc({ commitment: "confirmed" });
+        } finally {
+          await setTreasury(authority.publicKey);
+        }
+
+        // The creator, paying the transaction fee, gets only the rent back
+        const tx = await provider.connection.getTransaction(sig, { commitment: "confirmed", maxSupportedTransactionVersion: 0 });
+        expect(tx.meta.postBalances[0] - tx.meta.preBalances[0]).to.equal(rent - tx.meta.fee);
+        expect(await provider.connection.getBalance(treasury.publicKey)).to.equal(deposit);
+        expect(await provider.connection.getAccountInfo(market.marketPDA)).to.equal(null);
+      });
+    });
+
+    describe("total claimable", () => {
+      const stake = 1_000_000;
+
+      it("Sums only claimable positions across markets in mixed states", async () => {
+        const claimed = await createMarket("Will the already-claimed market be skipped?");
+        const unclaimed = await createMarket("Will the unclaimed market be counted?");
+        const open = await createMarket("Will the unresolved market be skipped?");
+        const { bettor, tokenAccount } = await newBettor(stake);
+
+        for (const market of [claimed, unclaimed, open]) {
+          await mintTo(provider.connection, payer, stakeMint, tokenAccount, payer, stake);
+          await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount);
+        }
+        // The first bet staked the initial balance too
+        const claimedStake = netOf(2 * stake);
+
+        await answerYes(claimed);
+        await answerYes(unclaimed);
+        await waitForResolutionTime();
+        await resolveMarket(claimed);
+        await resolveMarket(unclaimed);
+
+        await marketProgram.methods
+          .claimWinnings(claimed.marketId)
+          .accounts({
+            marketAccount: claimed.marketPDA,
+            position: findPositionPDA(claimed.marketId, bettor.publicKey),
+            positionOwner: bettor.publicKey,
+            marketState: marketStatePDA,
+            winner: bettor.publicKey,
+            stakeVault,
+            winnerTokenAccount: tokenAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([bettor])
+          .rpc();
+        expect(await tokenBalance(tokenAccount)).to.equal(claimedStake);
+
+        const total = await marketProgram.methods
+          .totalClaimable()
+          .accounts({ owner: bettor.publicKey })
+          .remainingAccounts(
+            [claimed, unclaimed, open].flatMap(market => [
+              { pubkey: market.marketPDA, isSigner: false, isWritable: false },
+              { pubkey: findPositionPDA(market.marketId, bettor.publicKey), isSigner: false, isWritable: false },
+            ])
+          )
+          .signers([bettor])
+          .view();
+
+        // Only the resolved, unclaimed market counts; the sole bettor is owed its whole net pool
+        expect(total.toNumber()).to.equal(netOf(stake));
+      });
+
+      it("Rejects a position that does not belong to the signer", async () => {
+        const market = await createMarket("Will a borrowed position be rejected?");
+        const owner = await newBettor(stake);
+        const other = await newBettor(stake);
+        await takeSide(true, market.marketId, market.marketPDA, owner.bettor, owner.tokenAccount);
+
+        try {
+          await marketProgram.methods
+            .totalClaimable()
+            .accounts({ owner: other.bettor.publicKey })
+            .remainingAccounts([
+              { pubkey: market.marketPDA, isSigner: false, isWritable: false },
+              { pubkey: findPositionPDA(market.marketId, owner.bettor.publicKey), isSigner: false, isWritable: false },
+            ])
+            .signers([other.bettor])
+            .view();
+          expect.fail("should have rejected another owner's position");
+        } catch (error) {
+          expect(error.toString()).to.match(/InvalidBatch/);
+        }
+      });
+    });
+
+    describe("resolution event", () => {
+      it("Carries the oracle answer the outcome was read from", async () => {
+        const market = await createMarket("Will the resolution event carry the oracle answer?");
+        await answerYes(market);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+
+        const signature = await marketProgram.methods
+          .resolveMarket(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            marketState: marketStatePDA,
+            oracleQuestion: market.questionPDA,
+            oracleAnswer: market.answerPDA,
+          })
+          .rpc({ commitment: "confirmed" });
+
+        const tx = await provider.connection.getTransaction(signature, {
+          commitment: "confirmed",
+          maxSupportedTransactionVersion: 0,
+        });
+        const eventParser = new anchor.EventParser(marketProgram.programId, marketProgram.coder);
+        const resolved = [...eventParser.parseLogs(tx.meta.logMessages)].find(event => event.name === "marketResolved");
+
+        const answer = await program.account.answerAccount.fetch(market.answerPDA);
+        expect(resolved.data.outcome).to.deep.equal({ yes: {} });
+        expect(resolved.data.numericAnswer.toNumber()).to.equal(answer.numericAnswer.toNumber());
+        expect(resolved.data.boolAnswer).to.equal(answer.boolAnswer);
+        expect(resolved.data.confidenceScore).to.equal(answer.confidenceScore);
+        expect(resolved.data.confidenceScore).to.equal(90);
+      });
+    });
+
+    describe("claim record", () => {
+      it("Reports the winning side's cost basis and the payout for a multi-bet position", async () => {
+        const [first, second, hedge] = [400_000, 600_000, 500_000];
+        const { bettor, tokenAccount } = await newBettor(first);
+        const market = await createMarket("Will the claim record carry the cost basis?");
+
+        // Two YES bets and a NO hedge; takeSide stakes the whole token balance each time
+        await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount);
+        await mintTo(provider.connection, payer, stakeMint, tokenAccount, payer, second);
+        await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount);
+        await mintTo(provider.connection, payer, stakeMint, tokenAccount, payer, hedge);
+        await takeSide(false, market.marketId, market.marketPDA, bettor, tokenAccount);
+
+        await answerYes(market);
+        await waitForResolutionTime();
+        await resolveMarket(market);
+
+        const signature = await marketProgram.methods
+          .claimWinnings(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            position: findPositionPDA(market.marketId, bettor.publicKey),
+            positionOwner: bettor.publicKey,
+            marketState: marketStatePDA,
+            winner: bettor.publicKey,
+            stakeVault,
+            winnerTokenAccount: tokenAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([bettor])
+          .rpc({ commitment: "confirmed" });
+
+        const tx = await provider.connection.getTransaction(signature, {
+          commitment: "confirmed",
+          maxSupportedTransactionVersion: 0,
+        });
+        const eventParser = new anchor.EventParser(marketProgram.programId, marketProgram.coder);
+        const record = [...eventParser.parseLogs(tx.meta.logMessages)].find(event => event.name === "claimRecord");
+
+        // The hedge is not part of the cost basis, but as the only YES stake the position takes the whole pool
+        expect(record.data.marketId.toString()).to.equal(market.marketId.toString());
+        expect(record.data.user.toBase58()).to.equal(bettor.publicKey.toBase58());
+        expect(record.data.costBasis.toNumber()).to.equal(netOf(first) + netOf(second));
+        expect(record.data.payout.toNumber()).to.equal(netOf(first) + netOf(second) + netOf(hedge));
+        expect(await tokenBalance(tokenAccount)).to.equal(record.data.payout.toNumber());
+      });
+    });
+
+    describe("position receipts", () => {
+      const stake = 1_000_000;
+      // Metaplex Token Metadata, cloned into the test validator
+      const metadataProgramId = new PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+      const setPositionReceipts = (enabled: boolean) =>
+        marketProgram.methods
+          .setPositionReceipts(enabled)
+          .accounts({
+            marketState: marketStatePDA,
+            authority: authority.publicKey,
+          })
+          .rpc();
+
+      const findReceiptAccounts = (marketId: anchor.BN, bettor: PublicKey) => {
+        const [receiptMint] = PublicKey.findProgramAddressSync(
+          [Buffer.from("receipt"), marketId.toArrayLike(Buffer, "le", 8), bettor.toBuffer()],
+          marketProgram.programId
+        );
+        const [receiptMetadata] = PublicKey.findProgramAddressSync(
+          [Buffer.from("metadata"), metadataProgramId.toBuffer(), receiptMint.toBuffer()],
+          metadataProgramId
+        );
+        const receiptAccount = getAssociatedTokenAddressSync(receiptMint, bettor);
+        return { receiptMint, receiptMetadata, receiptAccount };
+      };
+
+      // Take a YES position that mints its receipt to the bettor
+      const takeYesWithReceipt = async (market: Market, bettor: Keypair, tokenAccount: PublicKey) => {
+        const { receiptMint, receiptMetadata, receiptAccount } = findReceiptAccounts(market.marketId, bettor.publicKey);
+        return marketProgram.methods
+          .takePosition(market.marketId, true, new anchor.BN(await tokenBalance(tokenAccount)), null)
+          .accounts({
+            marketAccount: market.marketPDA,
+            position: findPositionPDA(market.marketId, bettor.publicKey),
+            marketState: marketStatePDA,
+            bettor: bettor.publicKey,
+            stakeVault,
+            bettorTokenAccount: tokenAccount,
+            referralBalance: null,
+            receiptMint,
+            bettorReceiptAccount: receiptAccount,
+            receiptMetadata,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            tokenMetadataProgram: metadataProgramId,
+            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+            rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([bettor])
+          .rpc();
+      };
+
+      // Sell the bettor's receipt to a new holder, who also gets a stake mint account to be paid into
+      const transferReceipt = async (market: Market, bettor: Keypair) => {
+        const { receiptMint, receiptAccount } = findReceiptAccounts(market.marketId, bettor.publicKey);
+        const holder = await newBettor(0);
+        const holderReceiptAccount = (
+          await getOrCreateAssociatedTokenAccount(provider.connection, payer, receiptMint, holder.bettor.publicKey)
+        ).address;
+        await transfer(provider.connection, payer, receiptAccount, holderReceiptAccount, bettor, 1);
+        return { ...holder, receiptMint, holderReceiptAccount, bettorReceiptAccount: receiptAccount };
+      };
+
+      before(async () => {
+        await setPositionReceipts(true);
+      });
+
+      after(async () => {
+        await setPositionReceipts(false);
+      });
+
+      it("Mints a receipt on the first bet and burns it on claim", async () => {
+        const market = await createMarket("Will the position receipt be burned on claim?");
+        const { bettor, tokenAccount } = await newBettor(stake);
+        const { receiptMint, receiptMetadata, receiptAccount } = findReceiptAccounts(market.marketId, bettor.publicKey);
+
+        await takeYesWithReceipt(market, bettor, tokenAccount);
+
+        const position = await marketProgram.account.position.fetch(findPositionPDA(market.marketId, bettor.publicKey));
+        expect(position.receiptMint.toBase58()).to.equal(receiptMint.toBase58());
+        expect(Number((await getMint(provider.connection, receiptMint)).supply)).to.equal(1);
+        expect(await tokenBalance(receiptAccount)).to.equal(1);
+        expect(await provider.connection.getAccountInfo(receiptMetadata)).to.not.equal(null);
+
+        await answerYes(market);
+        await waitForResolutionTime();
+        await resolveMarket(market);
+
+        const claim = (withReceipt: boolean) =>
+          marketProgram.methods
+            .claimWinnings(market.marketId)
+            .accounts({
+              marketAccount: market.marketPDA,
+              position: findPositionPDA(market.marketId, bettor.publicKey),
+              positionOwner: bettor.publicKey,
+              marketState: marketStatePDA,
+              winner: bettor.publicKey,
+              stakeVault,
+              winnerTokenAccount: tokenAccount,
+              receiptMint: withReceipt ? receiptMint : null,
+              winnerReceiptAccount: withReceipt ? receiptAccount : null,
+              tokenProgram: TOKEN_PROGRAM_ID,
+            })
+            .signers([bettor])
+            .rpc();
+
+        await expectError(claim(false), "MissingReceiptAccounts");
+
+        await claim(true);
+        expect(await tokenBalance(tokenAccount)).to.equal(netOf(stake));
+        expect(await tokenBalance(receiptAccount)).to.equal(0);
+        expect(Number((await getMint(provider.connection, receiptMint)).supply)).to.equal(0);
+      });
+
+      it("Pays the winnings to whoever holds the receipt", async () => {
+        const market = await createMarket("Will a sold receipt carry the claim?");
+        const { bettor, tokenAccount } = await newBettor(stake);
+        await takeYesWithReceipt(market, bettor, tokenAccount);
+        const holder = await transferReceipt(market, bettor);
+
+        await answerYes(market);
+        await waitForResolutionTime();
+        await resolveMarket(market);
+
+        const claim = (winner: Keypair, winnerTokenAccount: PublicKey, winnerReceiptAccount: PublicKey) =>
+          marketProgram.methods
+            .claimWinnings(market.marketId)
+            .accounts({
+              marketAccount: market.marketPDA,
+              position: findPositionPDA(market.marketId, bettor.publicKey),
+              positionOwner: bettor.publicKey,
+              marketState: marketStatePDA,
+              winner: winner.publicKey,
+              stakeVault,
+              winnerTokenAccount,
+              receiptMint: holder.receiptMint,
+              winnerReceiptAccount,
+              tokenProgram: TOKEN_PROGRAM_ID,
+            })
+            .signers([winner])
+            .rpc();
+
+        // The bettor sold the receipt, and the claim with it
+        await expectError(claim(bettor, tokenAccount, holder.bettorReceiptAccount), "ReceiptNotHeld");
+
+        await claim(holder.bettor, holder.tokenAccount, holder.holderReceiptAccount);
+        expect(await tokenBalance(holder.tokenAccount)).to.equal(netOf(stake));
+        expect(await tokenBalance(tokenAccount)).to.equal(0);
+        expect(Number((await getMint(provider.connection, holder.receiptMint)).supply)).to.equal(0);
+      });
+
+      it("Refunds the receipt holder, and leaves receipted positions out of batch refunds", async () => {
+        // One bettor short of the participant minimum, so resolution cancels the market
+        const market = await createMarket("Will a sold receipt carry the refund?", 5, 50, { general: {} }, { flat: {} }, 0, 0, { flat: {} }, 2);
+        const { bettor, tokenAccount } = await newBettor(stake);
+        await takeYesWithReceipt(market, bettor, tokenAccount);
+        const holder = await transferReceipt(market, bettor);
+
+        await waitForResolutionTime();
+        await resolveMarket(market);
+
+        // The batch pays position owners, so it leaves the receipted position alone
+        await marketProgram.methods
+          .batchRefund(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            marketState: marketStatePDA,
+            stakeVault,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .remainingAccounts([
+            { pubkey: findPositionPDA(market.marketId, bettor.publicKey), isSigner: false, isWritable: true },
+            { pubkey: tokenAccount, isSigner: false, isWritable: true },
+          ])
+          .rpc();
+        expect((await marketProgram.account.position.fetch(findPositionPDA(market.marketId, bettor.publicKey))).claimed).to.equal(false);
+
+        await marketProgram.methods
+          .claimRefund(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            position: findPositionPDA(market.marketId, bettor.publicKey),
+            positionOwner: bettor.publicKey,
+            marketState: marketStatePDA,
+            refundee: holder.bettor.publicKey,
+            stakeVault,
+            refundeeTokenAccount: holder.tokenAccount,
+            receiptMint: holder.receiptMint,
+            refundeeReceiptAccount: holder.holderReceiptAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([holder.bettor])
+          .rpc();
+
+        expect(await tokenBalance(holder.tokenAccount)).to.equal(stake);
+        expect(await tokenBalance(tokenAccount)).to.equal(0);
+        expect(Number((await getMint(provider.connection, holder.receiptMint)).supply)).to.equal(0);
+      });
+
+      it("Rejects a first bet without the receipt accounts", async () => {
+        const market = await createMarket("Can a receipt deployment skip the receipt?");
+        const { bettor, tokenAccount } = await newBettor(stake);
+
+        await expectError(takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount), "MissingReceiptAccounts");
+      });
+    });
+
+    describe("oracle account validation", () => {
+      it("Rejects an uninitialized oracle question account at resolution", async () => {
+        const market = await createMarket("Will an uninitialized question resolve anything?");
+        await answerYes(market);
+        await waitForResolutionTime();
+
+        // Nothing lives at a fresh address, so the question account fails the owner check
+        await expectError(resolveMarket({ ...market, questionPDA: Keypair.generate().publicKey }), "OracleAccountInvalid");
+
+        const account = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.status).to.deep.equal({ open: {} });
+      });
+
+      it("Rejects an answer account passed as the question", async () => {
+        const market = await createMarket("Will an answer pass for its question?");
+        await answerYes(market);
+        await waitForResolutionTime();
+
+        // Both are oracle-owned and start with the question id, but the discriminators differ
+        await expectError(resolveMarket({ ...market, questionPDA: market.answerPDA }), "OracleAccountInvalid");
+
+        const account = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.status).to.deep.equal({ open: {} });
+      });
+
+      it("Rejects resolution while the oracle question is still pending", async () => {
+        // The question is never answered, so the market outlives its deadline with nothing to read
+        const market = await createMarket("Will an unanswered question resolve anything?");
+        await waitForResolutionTime();
+
+        await expectError(resolveMarket(market), "OracleQuestionNotFinal");
+
+        const account = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.status).to.deep.equal({ open: {} });
+      });
+    });
+
+    describe("keeper resolution", () => {
+      it("Resolves a market from its derived oracle PDAs", async () => {
+        const market = await createMarket("Will a keeper resolve this market?");
+        await answerYes(market);
+        await waitForResolutionTime();
+
+        await marketProgram.methods
+          .resolveMarketFromPda(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            marketState: marketStatePDA,
+            oracleQuestion: market.questionPDA,
+            oracleAnswer: market.answerPDA,
+          })
+          .rpc();
+
+        const account = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.status).to.deep.equal({ resolved: {} });
+        expect(account.outcome).to.deep.equal({ yes: {} });
+      });
+
+      it("Rejects an answer account at an unexpected address", async () => {
+        const market = await createMarket("Will a keeper swap in another answer?");
+        const other = await createMarket("Is this the other answered question?");
+        await answerYes(other);
+        await waitForResolutionTime();
+
+        await expectError(
+          marketProgram.methods
+            .resolveMarketFromPda(market.marketId)
+            .accounts({
+              marketAccount: market.marketPDA,
+              marketState: marketStatePDA,
+              oracleQuestion: market.questionPDA,
+              oracleAnswer: other.answerPDA,
+            })
+            .rpc(),
+          "ConstraintSeeds"
+        );
+
+        const account = await marketProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.status).to.deep.equal({ open: {} });
+      });
+    });
+
+    describe("market top-ups", () => {
+      const topUp = (market: Market, donor: Keypair, amount: number) =>
+        marketProgram.methods
+          .topUpMarket(market.marketId, new anchor.BN(amount))
+          .accounts({
+            marketAccount: market.marketPDA,
+            donor: donor.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([donor])
+          .rpc({ commitment: "confirmed" });
+
+      it("Tops up a market at its rent floor, which keeps operating", async () => {
+        const stake = 1_000_000;
+        const donation = 5_000_000;
+        const keeper = Keypair.generate();
+        await provider.connection.requestAirdrop(keeper.publicKey, LAMPORTS_PER_SOL);
+        const { bettor, tokenAccount } = await newBettor(stake);
+        const market = await createMarket("Will a keeper keep this market funded?");
+
+        // Stakes live in the token vault, so the market account holds nothing above its rent floor
+        const info = await provider.connection.getAccountInfo(market.marketPDA);
+        const rentFloor = await provider.connection.getMinimumBalanceForRentExemption(info.data.length);
+        expect(info.lamports).to.equal(rentFloor);
+
+        const signature = await topUp(market, keeper, donation);
+
+        expect(await provider.connection.getBalance(market.marketPDA)).to.equal(rentFloor + donation);
+        const tx = await provider.connection.getTransaction(signature, {
+          commitment: "confirmed",
+          maxSupportedTransactionVersion: 0,
+        });
+        const eventParser = new anchor.EventParser(marketProgram.programId, marketProgram.coder);
+        const toppedUp = [...eventParser.parseLogs(tx.meta.logMessages)].find(event => event.name === "marketToppedUp");
+        expect(toppedUp.data.marketId.toString()).to.equal(market.marketId.toString());
+        expect(toppedUp.data.amount.toNumber()).to.equal(donation);
+
+        await takeSide(true, market.marketId, market.marketPDA, bettor, tokenAccount);
+        await answerYes(market);
+        await waitForResolutionTime();
+        await resolveMarket(market);
+
+        await marketProgram.methods
+          .claimWinnings(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            position: findPositionPDA(market.marketId, bettor.publicKey),
+            positionOwner: bettor.publicKey,
+            marketState: marketStatePDA,
+            winner: bettor.publicKey,
+            stakeVault,
+            winnerTokenAccount: tokenAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([bettor])
+          .rpc();
+        expect(await tokenBalance(tokenAccount)).to.equal(netOf(stake));
+      });
+
+      it("Rejects an empty top-up", async () => {
+        const market = await createMarket("Can a keeper donate nothing?");
+
+        await expectError(topUp(market, payer, 0), "InvalidTopUpAmount");
+      });
+    });
+  });
+
+  describe("multi-outcome market", () => {
+    const multiProgram = anchor.workspace.MultiOutcomeMarket as Program<MultiOutcomeMarket>;
+    // The state-level oracle program owns no answer accounts on this cluster
+    const pinnedOracle = Keypair.generate().publicKey;
+
+    let marketStatePDA: PublicKey;
+
+    // Multi-outcome market on a freshly answered trace9 question, resolving a few seconds from now.
+    // The answer picks outcome index 1 unless told otherwise
+    const createMultiMarket = (
+      question: string,
+      oracleProgramOverride: PublicKey | null,
+      outcomeLabels = ["Low", "High"],
+      numericAnswer = new anchor.BN(1),
+      resolver: PublicKey | null = null
+    ) =>
+      createAnsweredMarket(
+        multiProgram,
+        marketStatePDA,
+        question,
+        questionId => [
+          question,
+          outcomeLabels,
+          new anchor.BN(Math.floor(Date.now() / 1000) + 5),
+          new anchor.BN(0),
+          50,
+          questionId,
+          false,
+          new anchor.BN(0),
+          oracleProgramOverride,
+          resolver,
+        ],
+        numericAnswer
+      );
+
+    // Resolves as the provider wallet unless a resolver keypair signs instead
+    const resolve = (
+      market: { marketId: anchor.BN; marketPDA: PublicKey; questionPDA: PublicKey; answerPDA: PublicKey },
+      resolver?: Keypair
+    ) =>
+      multiProgram.methods
+        .resolveMarket(market.marketId)
+        .accounts({
+          marketAccount: market.marketPDA,
+          marketState: marketStatePDA,
+          oracleQuestion: market.questionPDA,
+          oracleAnswer: market.answerPDA,
+          resolver: resolver ? resolver.publicKey : authority.publicKey,
+        })
+        .signers(resolver ? [resolver] : [])
+        .rpc();
+
+    const findPositionPDA = (marketId: anchor.BN, bettor: PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), marketId.toArrayLike(Buffer, "le", 8), bettor.toBuffer()],
+        multiProgram.programId
+      )[0];
+
+    const claim = (market: { marketId: anchor.BN; marketPDA: PublicKey }, winner: Keypair) =>
+      multiProgram.methods
+        .claimWinnings(market.marketId)
+        .accounts({
+          marketAccount: market.marketPDA,
+          position: findPositionPDA(market.marketId, winner.publicKey),
+          winner: winner.publicKey,
+        })
+        .signers([winner])
+        .rpc();
+
+    before(async () => {
+      [marketStatePDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("market_state")],
+        multiProgram.programId
+      );
+
+      await multiProgram.methods
+        .initialize(pinnedOracle, 200, new anchor.BN(1), 500, 2, 63)
+        .accounts({
+          marketState: marketStatePDA,
+          authority: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    });
+
+    describe("oracle override", () => {
+      it("Resolves a market against its overridden oracle program", async () => {
+        const market = await createMultiMarket("Will the override oracle settle this?", program.programId);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+
+        await resolve(market);
+
+        const account = await multiProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.oracleProgramOverride.toBase58()).to.equal(program.programId.toBase58());
+        expect(account.status).to.deep.equal({ resolved: {} });
+        expect(account.winningOutcomes).to.deep.equal([1]);
+      });
+
+      it("Rejects an answer from outside the state-level oracle program without an override", async () => {
+        const market = await createMultiMarket("Will the pinned oracle settle this?", null);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+
+        // The answer PDA is derived under the pinned program, so trace9's answer is at the wrong address
+        await expectError(resolve(market), "ConstraintSeeds");
+      });
+    });
+
+    describe("account sizing", () => {
+      it("Allocates a 2-outcome market less space than a 10-outcome one, and both settle", async () => {
+        const tenLabels = Array.from({ length: 10 }, (_, i) => `Outcome ${i}`);
+        // Bet on each market right after creating it, well before its five second close
+        const small = await createMultiMarket("Which of two outcomes wins?", program.programId);
+        const smallWinner = await bet(multiProgram, marketStatePDA, small, 1);
+        const large = await createMultiMarket("Which of ten outcomes wins?", program.programId, tenLabels);
+        const largeWinner = await bet(multiProgram, marketStatePDA, large, 1);
+
+        const smallInfo = await provider.connection.getAccountInfo(small.marketPDA);
+        const largeInfo = await provider.connection.getAccountInfo(large.marketPDA);
+        expect(smallInfo.data.length).to.be.lessThan(largeInfo.data.length);
+        expect(smallInfo.lamports).to.be.lessThan(largeInfo.lamports);
+
+        await new Promise(resolve => setTimeout(resolve, 6000));
+
+        // Both resolve to outcome 1 and pay its sole backer the whole pool
+        for (const [market, winner] of [[small, smallWinner], [large, largeWinner]] as [typeof small, Keypair][]) {
+          await resolve(market);
+
+          const account = await multiProgram.account.marketAccount.fetch(market.marketPDA);
+          expect(account.status).to.deep.equal({ resolved: {} });
+          expect(account.winningOutcomes).to.deep.equal([1]);
+
+          const before = await provider.connection.getBalance(winner.publicKey);
+          await claim(market, winner);
+          expect(await provider.connection.getBalance(winner.publicKey)).to.equal(before + account.totalPool.toNumber());
+        }
+      });
+    });
+
+    describe("fee custody", () => {
+      it("Moves entry fees out of the market account into market state on resolution", async () => {
+        const market = await createMultiMarket("Where do multi-outcome fees end up?", program.programId);
+        await bet(multiProgram, marketStatePDA, market, 0);
+        const winner = await bet(multiProgram, marketStatePDA, market, 1);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+
+        const fees = (await multiProgram.account.marketAccount.fetch(market.marketPDA)).totalFees.toNumber();
+        expect(fees).to.be.greaterThan(0);
+        const stateBefore = await multiProgram.account.marketState.fetch(marketStatePDA);
+        const stateLamportsBefore = await provider.connection.getBalance(marketStatePDA);
+        const marketLamportsBefore = await provider.connection.getBalance(market.marketPDA);
+
+        await resolve(market);
+
+        const stateAfter = await multiProgram.account.marketState.fetch(marketStatePDA);
+        expect(stateAfter.accumulatedFees.toNumber()).to.equal(stateBefore.accumulatedFees.toNumber() + fees);
+        expect(await provider.connection.getBalance(marketStatePDA)).to.equal(stateLamportsBefore + fees);
+        expect(await provider.connection.getBalance(market.marketPDA)).to.equal(marketLamportsBefore - fees);
+
+        // Only the net pool stays behind for the winner, so nothing fee-shaped is left to sweep
+        const account = await multiProgram.account.marketAccount.fetch(market.marketPDA);
+        const before = await provider.connection.getBalance(winner.publicKey);
+        await claim(market, winner);
+        expect(await provider.connection.getBalance(winner.publicKey)).to.equal(before + account.totalPool.toNumber());
+        const rent = await provider.connection.getMinimumBalanceForRentExemption(
+          (await provider.connection.getAccountInfo(market.marketPDA)).data.length
+        );
+        expect(await provider.connection.getBalance(market.marketPDA)).to.equal(rent);
+      });
+    });
+
+    describe("fee treasury", () => {
+      const setTreasury = (treasury: PublicKey, signer: Keypair | null = null) =>
+        multiProgram.methods
+          .setTreasury(treasury)
+          .accounts({ marketState: marketStatePDA, authority: signer ? signer.publicKey : authority.publicKey })
+          .signers(signer ? [signer] : [])
+          .rpc();
+
+      const withdrawTo = (treasury: PublicKey) =>
+        multiProgram.methods
+          .withdrawFees()
+          .accounts({ marketState: marketStatePDA, authority: authority.publicKey, treasury })
+          .rpc();
+
+      it("Withdraws accumulated fees only to the configured treasury", async () => {
+        const market = await createMultiMarket("Where do withdrawn multi-outcome fees go?", program.programId);
+        await bet(multiProgram, marketStatePDA, market, 0);
+        await bet(multiProgram, marketStatePDA, market, 1);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+        await resolve(market);
+
+        const fees = (await multiProgram.account.marketState.fetch(marketStatePDA)).accumulatedFees.toNumber();
+        expect(fees).to.be.greaterThan(0);
+
+        await expectError(setTreasury(requester.publicKey, requester), "Unauthorized");
+
+        await setTreasury(requester.publicKey);
+        try {
+          await expectError(withdrawTo(authority.publicKey), "InvalidTreasury");
+
+          const treasuryBefore = await provider.connection.getBalance(requester.publicKey);
+          await withdrawTo(requester.publicKey);
+          expect(await provider.connection.getBalance(requester.publicKey)).to.equal(treasuryBefore + fees);
+          expect((await multiProgram.account.marketState.fetch(marketStatePDA)).accumulatedFees.toNumber()).to.equal(0);
+        } finally {
+          await setTreasury(authority.publicKey);
+        }
+      });
+    });
+
+    describe("outcome count bounds", () => {
+      // The state allows 2 to 63 outcomes; short labels keep a 64-label create inside one transaction
+      const labels = (count: number) => Array.from({ length: count }, (_, i) => `O${i}`);
+
+      it("Creates and settles a market at the configured maximum", async () => {
+        const market = await createMultiMarket(
+          "Which of sixty-three outcomes wins?",
+          program.programId,
+          labels(63),
+          new anchor.BN(62)
+        );
+        const winner = await bet(multiProgram, marketStatePDA, market, 62);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+        await resolve(market);
+
+        const account = await multiProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.numOutcomes).to.equal(63);
+        expect(account.winningOutcomes).to.deep.equal([62]);
+
+        const before = await provider.connection.getBalance(winner.publicKey);
+        await claim(market, winner);
+        expect(await provider.connection.getBalance(winner.publicKey)).to.equal(before + account.totalPool.toNumber());
+      });
+
+      for (const count of [1, 64]) {
+        it(`Rejects a market with ${count} outcome${count === 1 ? "" : "s"}`, async () => {
+          await expectError(
+            createMultiMarket(`Can a market have ${count} outcomes?`, program.programId, labels(count)),
+            "InvalidOutcomeCount"
+          );
+        });
+      }
+    });
+
+    describe("ties", () => {
+      // The tie flag is the top bit of the numeric answer; the low bits mark the tied outcomes
+      const tieAnswer = (...outcomes: number[]) =>
+        outcomes.reduce((answer, outcome) => answer.or(new anchor.BN(1).shln(outcome)), new anchor.BN(1).shln(63));
+
+      it("Splits the pool between two tied outcomes in proportion to their stakes", async () => {
+        const market = await createMultiMarket(
+          "Which team wins the league?",
+          program.programId,
+          ["Reds", "Blues", "Greens"],
+          tieAnswer(0, 1)
+        );
+        const reds = await bet(multiProgram, marketStatePDA, market, 0);
+        const blues = await bet(multiProgram, marketStatePDA, market, 1);
+        const greens = await bet(multiProgram, marketStatePDA, market, 2);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+
+        await resolve(market);
+
+        const account = await multiProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.status).to.deep.equal({ resolved: {} });
+        expect(account.winningOutcomes).to.deep.equal([0, 1]);
+
+        const totalPool = account.totalPool;
+        const tiedPool = account.outcomePools[0].add(account.outcomePools[1]);
+        for (const [winner, outcome] of [[reds, 0], [blues, 1]] as [Keypair, number][]) {
+          const stake = account.outcomePools[outcome];
+          const before = await provider.connection.getBalance(winner.publicKey);
+
+          await claim(market, winner);
+
+          const expected = stake.mul(totalPool).div(tiedPool).toNumber();
+          expect(await provider.connection.getBalance(winner.publicKey)).to.equal(before + expected);
+        }
+
+        await expectError(claim(market, greens), "BetOnLosingSide");
+      });
+
+      it("Rejects a tie naming fewer than two outcomes", async () => {
+        const market = await createMultiMarket("Can one outcome tie with itself?", program.programId, ["Yes", "No"], tieAnswer(1));
+        await new Promise(resolve => setTimeout(resolve, 6000));
+
+        await expectError(resolve(market), "InvalidOutcome");
+      });
+    });
+
+    describe("answer index range", () => {
+      it("Rejects an answer past the last outcome rather than truncating it to an index", async () => {
+        // 257 would wrap to index 1 as a u8
+        const market = await createMultiMarket("Will the wrapped index be refused?", program.programId, ["Low", "High"], new anchor.BN(257));
+        await new Promise(resolve => setTimeout(resolve, 6000));
+
+        try {
+          await resolve(market);
+          expect.fail("should have rejected an out-of-range outcome index");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("InvalidOutcome");
+        }
+
+        const account = await multiProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.status).to.not.deep.equal({ resolved: {} });
+      });
+    });
+
+    describe("outcome labels", () => {
+      it("Rejects a market whose labels repeat", async () => {
+        try {
+          await createMultiMarket("Which twin wins?", program.programId, ["Twin", "Other", "Twin"]);
+          expect.fail("should have rejected a duplicate label");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("DuplicateOutcomeLabel");
+        }
+      });
+
+      it("Reads a label back by outcome index", async () => {
+        const market = await createMultiMarket("Which colour wins?", program.programId, ["Red", "Green", "Blue"]);
+        const label = (index: number) =>
+          multiProgram.methods
+            .getOutcomeLabel(market.marketId, index)
+            .accounts({ marketAccount: market.marketPDA })
+            .view();
+
+        expect(await label(0)).to.equal("Red");
+        expect(await label(2)).to.equal("Blue");
+
+        try {
+          await label(3);
+          expect.fail("should have rejected an index past the last outcome");
+        } catch (error) {
+          expect(error.toString()).to.match(/InvalidOutcome/);
+        }
+      });
+
+      it("Creates no market when a label in the middle is invalid", async () => {
+        const counterBefore = (await multiProgram.account.marketState.fetch(marketStatePDA)).marketCounter;
+        const [marketPDA] = PublicKey.findProgramAddressSync(
+          [Buffer.from("market"), counterBefore.toArrayLike(Buffer, "le", 8)],
+          multiProgram.programId
+        );
+
+        try {
+          await createMultiMarket("Which label is missing?", program.programId, ["First", "", "Third"]);
+          expect.fail("should have rejected an empty label");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("InvalidOutcomeLabel");
+        }
+
+        const counterAfter = (await multiProgram.account.marketState.fetch(marketStatePDA)).marketCounter;
+        expect(counterAfter.toNumber()).to.equal(counterBefore.toNumber());
+        expect(await provider.connection.getAccountInfo(marketPDA)).to.equal(null);
+      });
+    });
+
+    describe("claimed stake", () => {
+      it("Zeroes the winning stake on claim and keeps the losing stake as a record", async () => {
+        // One bettor on both sides: each stake takes the whole balance, so the second needs a fresh airdrop
+        const hedger = Keypair.generate();
+        await provider.connection.requestAirdrop(hedger.publicKey, LAMPORTS_PER_SOL);
+        await new Promise(resolve => setTimeout(resolve, 1000));
+
+        const market = await createMultiMarket("Which side did the hedger win on?", program.programId);
+        const positionPDA = findPositionPDA(market.marketId, hedger.publicKey);
+        const stake = (outcome: number) =>
+          multiProgram.methods
+            .takePosition(market.marketId, outcome)
+            .accounts({
+              marketAccount: market.marketPDA,
+              position: positionPDA,
+              marketState: marketStatePDA,
+              bettor: hedger.publicKey,
+              systemProgram: SystemProgram.programId,
+            })
+            .signers([hedger])
+            .rpc();
+
+        await stake(0);
+        await provider.connection.requestAirdrop(hedger.publicKey, LAMPORTS_PER_SOL);
+        await new Promise(resolve => setTimeout(resolve, 1000));
+        await stake(1);
+        await new Promise(resolve => setTimeout(resolve, 5000));
+
+        await resolve(market);
+        const before = await multiProgram.account.position.fetch(positionPDA);
+        expect(before.amounts[0].toNumber()).to.be.greaterThan(0);
+        expect(before.amounts[1].toNumber()).to.be.greaterThan(0);
+
+        await claim(market, hedger);
+
+        const after = await multiProgram.account.position.fetch(positionPDA);
+        expect(after.claimed).to.equal(true);
+        expect(after.amounts[1].toNumber()).to.equal(0);
+        expect(after.amounts[0].toNumber()).to.equal(before.amounts[0].toNumber());
+      });
+    });
+
+    describe("emergency cancel", () => {
+      // The 30 day grace never elapses on a test validator, so these cover every guard ahead of it
+      const emergencyCancel = (market: { marketId: anchor.BN; marketPDA: PublicKey }, signer: Keypair | null = null) =>
+        multiProgram.methods
+          .emergencyCancel(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            marketState: marketStatePDA,
+            authority: signer ? signer.publicKey : authority.publicKey,
+          })
+          .signers(signer ? [signer] : [])
+          .rpc();
+
+      it("Rejects a cancel from anyone but the authority", async () => {
+        const market = await createMultiMarket("Will the stuck multi-outcome market cancel?", program.programId);
+        await expectError(emergencyCancel(market, requester), "Unauthorized");
+      });
+
+      it("Rejects a cancel before the grace period after resolution time has passed", async () => {
+        const market = await createMultiMarket("Will the stuck multi-outcome market cancel?", program.programId);
+        await expectError(emergencyCancel(market), "TooEarlyToCancel");
+      });
+
+      it("Rejects a cancel once the market has settled", async () => {
+        const market = await createMultiMarket("Will the stuck multi-outcome market cancel?", program.programId);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+        await resolve(market);
+        await expectError(emergencyCancel(market), "InvalidStatusTransition");
+      });
+    });
+
+    describe("claim market id", () => {
+      it("Rejects a claim whose market id does not match the market account", async () => {
+        const market = await createMultiMarket("Will the mismatched claim pay out?", program.programId);
+        await bet(multiProgram, marketStatePDA, market, 0);
+        const winner = await bet(multiProgram, marketStatePDA, market, 1);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+        await resolve(market);
+
+        try {
+          await multiProgram.methods
+            .claimWinnings(market.marketId.addn(1))
+            .accounts({
+              marketAccount: market.marketPDA,
+              position: findPositionPDA(market.marketId, winner.publicKey),
+              winner: winner.publicKey,
+            })
+            .signers([winner])
+            .rpc();
+          expect.fail("should have rejected a claim for another market id");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.be.oneOf(["ConstraintSeeds", "MarketMismatch"]);
+        }
+        expect(await provider.connection.getBalance(winner.publicKey)).to.equal(0);
+
+        await claim(market, winner);
+        expect(await provider.connection.getBalance(winner.publicKey)).to.be.greaterThan(0);
+      });
+    });
+
+    describe("empty market deletion", () => {
+      const deleteMarket = (market: { marketId: anchor.BN; marketPDA: PublicKey; questionBindingPDA: PublicKey }) =>
+        multiProgram.methods
+          .deleteEmptyMarket(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            questionBinding: market.questionBindingPDA,
+            creator: authority.publicKey,
+          })
+          .rpc();
+
+      it("Closes a market nobody bet on and frees its oracle question", async () => {
+        const market = await createMultiMarket("Will anyone bet on the deleted market?", program.programId);
+        await deleteMarket(market);
+
+        expect(await provider.connection.getAccountInfo(market.marketPDA)).to.equal(null);
+        const binding = await multiProgram.account.questionBinding.fetch(market.questionBindingPDA);
+        expect(binding.marketCount).to.equal(0);
+      });
+
+      it("Refuses to delete a market holding a bet", async () => {
+        const market = await createMultiMarket("Will the funded market survive deletion?", program.programId);
+        await bet(multiProgram, marketStatePDA, market, 0);
+        await expectError(deleteMarket(market), "MarketNotEmpty");
+      });
+    });
+
+    describe("unclaimed sweeps", () => {
+      it("Holds a resolved market's funds for winners until the claim deadline", async () => {
+        const market = await createMultiMarket("Will the sweep wait for the winner?", program.programId);
+        await bet(multiProgram, marketStatePDA, market, 0);
+        const winner = await bet(multiProgram, marketStatePDA, market, 1);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+        await resolve(market);
+
+        const account = await multiProgram.account.marketAccount.fetch(market.marketPDA);
+        const claimPeriod = 180 * 24 * 60 * 60;
+        expect(account.claimDeadline.toNumber()).to.be.within(
+          Math.floor(Date.now() / 1000) + claimPeriod - 60,
+          Math.floor(Date.now() / 1000) + claimPeriod + 60
+        );
+
+        await expectError(
+          multiProgram.methods
+            .sweepUnclaimed(market.marketId)
+            .accounts({
+              marketAccount: market.marketPDA,
+              marketState: marketStatePDA,
+              authority: authority.publicKey,
+              treasury: authority.publicKey,
+            })
+            .rpc(),
+          "ClaimDeadlineNotPassed"
+        );
+
+        // The sole bettor on the winning outcome takes the whole pool
+        await claim(market, winner);
+        expect(await provider.connection.getBalance(winner.publicKey)).to.equal(account.totalPool.toNumber());
+      });
+    });
+
+    describe("rounding drift", () => {
+      it("Pays every winner in full down to the last claim despite rounding dust", async () => {
+        const market = await createMultiMarket("Will uneven stakes leave dust?", program.programId);
+        // Uneven winning stakes make each pro-rata payout round down
+        const winners = [
+          await bet(multiProgram, marketStatePDA, market, 1, LAMPORTS_PER_SOL),
+          await bet(multiProgram, marketStatePDA, market, 1, (LAMPORTS_PER_SOL * 7) / 10),
+          await bet(multiProgram, marketStatePDA, market, 1, (LAMPORTS_PER_SOL * 3) / 10),
+        ];
+        await bet(multiProgram, marketStatePDA, market, 0, (LAMPORTS_PER_SOL * 9) / 10);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+
+        await resolve(market);
+
+        for (const winner of winners) {
+          const quote = await multiProgram.methods
+            .quotePayout(market.marketId)
+            .accounts({
+              marketAccount: market.marketPDA,
+              position: findPositionPDA(market.marketId, winner.publicKey),
+              owner: winner.publicKey,
+            })
+            .signers([winner])
+            .view();
+          const before = await provider.connection.getBalance(winner.publicKey);
+
+          await claim(market, winner);
+
+          expect(await provider.connection.getBalance(winner.publicKey)).to.equal(before + quote.toNumber());
+        }
+
+        // The market stays rent exempt with the dust left behind
+        const marketInfo = await provider.connection.getAccountInfo(market.marketPDA);
+        const rentExempt = await provider.connection.getMinimumBalanceForRentExemption(marketInfo.data.length);
+        expect(marketInfo.lamports).to.be.at.least(rentExempt);
+      });
+    });
+
+    describe("resolver", () => {
+      it("Lets only the configured resolver resolve the market", async () => {
+        const keeper = Keypair.generate();
+        const market = await createMultiMarket(
+          "Will the keeper settle this?",
+          program.programId,
+          ["Low", "High"],
+          new anchor.BN(1),
+          keeper.publicKey
+        );
+        await new Promise(resolve => setTimeout(resolve, 6000));
+
+        await expectError(resolve(market), "NotResolver");
+
+        await resolve(market, keeper);
+        const account = await multiProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.resolver.toBase58()).to.equal(keeper.publicKey.toBase58());
+        expect(account.status).to.deep.equal({ resolved: {} });
+      });
+
+      it("Leaves resolution open when no resolver is set", async () => {
+        const market = await createMultiMarket("Can anyone settle this?", program.programId);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+
+        await resolve(market, Keypair.generate());
+        const account = await multiProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.resolver).to.equal(null);
+        expect(account.status).to.deep.equal({ resolved: {} });
+      });
+    });
+
+    describe("resolution event", () => {
+      it("Carries the oracle answer the winners were read from", async () => {
+        const market = await createMultiMarket("Will the multi-outcome event carry the oracle answer?", program.programId);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+
+        const signature = await multiProgram.methods
+          .resolveMarket(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            marketState: marketStatePDA,
+            oracleQuestion: market.questionPDA,
+            oracleAnswer: market.answerPDA,
+            resolver: authority.publicKey,
+          })
+          .rpc({ commitment: "confirmed" });
+
+        const tx = await provider.connection.getTransaction(signature, {
+          commitment: "confirmed",
+          maxSupportedTransactionVersion: 0,
+        });
+        const eventParser = new anchor.EventParser(multiProgram.programId, multiProgram.coder);
+        const resolved = [...eventParser.parseLogs(tx.meta.logMessages)].find(
+          event => event.name === "multiOutcomeMarketResolved"
+        );
+
+        expect(resolved.data.winningOutcomes).to.deep.equal([1]);
+        expect(resolved.data.numericAnswer.toNumber()).to.equal(1);
+        expect(resolved.data.boolAnswer).to.equal(false);
+        expect(resolved.data.confidenceScore).to.equal(80);
+      });
+    });
+  });
+
+  describe("range market", () => {
+    const rangeProgram = anchor.workspace.RangeMarket as Program<RangeMarket>;
+
+    let marketStatePDA: PublicKey;
+
+    // Range market over [lowerBound, upperBound] on a freshly answered trace9 question, resolving
+    // `resolveIn` seconds from now. The oracle answers 100 with confidence 80, flagged inconclusive if asked
+    const createRangeMarket = (
+      question: string,
+      lowerBound: number,
+      upperBound: number,
+      resolveIn = 5,
+      minConfidence = 50,
+      questionType: object = { numeric: {} },
+      inconclusive = false
+    ) =>
+      createAnsweredMarket(
+        rangeProgram,
+        marketStatePDA,
+        question,
+        questionId => [
+          question,
+          new anchor.BN(lowerBound),
+          new anchor.BN(upperBound),
+          new anchor.BN(Math.floor(Date.now() / 1000) + resolveIn),
+          minConfidence,
+          questionId,
+          questionType,
+          false,
+          null,
+        ],
+        100,
+        inconclusive
+      );
+
+    const resolve = (market: { marketId: anchor.BN; marketPDA: PublicKey; questionPDA: PublicKey; answerPDA: PublicKey }) =>
+      rangeProgram.methods
+        .resolveMarket(market.marketId)
+        .accounts({
+          marketAccount: market.marketPDA,
+          marketState: marketStatePDA,
+          oracleQuestion: market.questionPDA,
+          oracleAnswer: market.answerPDA,
+          resolver: authority.publicKey,
+        })
+        .rpc();
+
+    const findPositionPDA = (marketId: anchor.BN, owner: PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), marketId.toArrayLike(Buffer, "le", 8), owner.toBuffer()],
+        rangeProgram.programId
+      )[0];
+
+    const claimRefund = (market: { marketId: anchor.BN; marketPDA: PublicKey }, refundee: Keypair) =>
+      rangeProgram.methods
+        .claimRefund(market.marketId)
+        .accounts({
+          marketAccount: market.marketPDA,
+          position: findPositionPDA(market.marketId, refundee.publicKey),
+          refundee: refundee.publicKey,
+        })
+        .signers([refundee])
+        .rpc();
+
+    before(async () => {
+      [marketStatePDA] = PublicKey.findProgramAddressSync([Buffer.from("market_state")], rangeProgram.programId);
+
+      await rangeProgram.methods
+        .initialize(program.programId, 200, new anchor.BN(1), 500)
+        .accounts({
+          marketState: marketStatePDA,
+          authority: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    });
+
+    describe("minimum bet", () => {
+      const setMinBet = (minBet: number, signer: Keypair | null = null) =>
+        rangeProgram.methods
+          .setMinBet(new anchor.BN(minBet))
+          .accounts({ marketState: marketStatePDA, authority: signer ? signer.publicKey : authority.publicKey })
+          .signers(signer ? [signer] : [])
+          .rpc();
+
+      it("Rejects bets under the authority's minimum and takes one right at it", async () => {
+        const market = await createRangeMarket("Will the small bet be refused?", 100, 200, 60);
+        // Bettors bring one SOL and pay the position rent out of it before staking the rest
+        const stake =
+          LAMPORTS_PER_SOL - (await provider.connection.getMinimumBalanceForRentExemption(rangeProgram.account.position.size));
+
+        try {
+          await setMinBet(stake + 1);
+          await expectError(bet(rangeProgram, marketStatePDA, market, true), "BetTooSmall");
+
+          await setMinBet(stake);
+          const bettor = await bet(rangeProgram, marketStatePDA, market, true);
+          const position = await rangeProgram.account.position.fetch(findPositionPDA(market.marketId, bettor.publicKey));
+          // The 2% entry fee comes off the top
+          expect(position.inRangeAmount.toNumber()).to.equal(stake - Math.floor((stake * 200) / 10_000));
+        } finally {
+          await setMinBet(1);
+        }
+      });
+
+      it("Rejects a minimum set by anyone but the authority", async () => {
+        await expectError(setMinBet(2 * LAMPORTS_PER_SOL, requester), "Unauthorized");
+      });
+    });
+
+    describe("settlement", () => {
+      it("Pays the in-range side the whole pool when the answer lands in range", async () => {
+        const market = await createRangeMarket("Will the reading land in range?", 50, 150);
+        const winner = await bet(rangeProgram, marketStatePDA, market, true);
+        const loser = await bet(rangeProgram, marketStatePDA, market, false);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+        await resolve(market);
+
+        const account = await rangeProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.resolved).to.equal(true);
+        expect(account.inRange).to.equal(true);
+
+        await rangeProgram.methods
+          .claimWinnings(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            position: findPositionPDA(market.marketId, winner.publicKey),
+            winner: winner.publicKey,
+          })
+          .signers([winner])
+          .rpc();
+        expect(await provider.connection.getBalance(winner.publicKey)).to.equal(
+          account.inRangePool.toNumber() + account.outRangePool.toNumber()
+        );
+
+        await expectError(
+          rangeProgram.methods
+            .claimWinnings(market.marketId)
+            .accounts({
+              marketAccount: market.marketPDA,
+              position: findPositionPDA(market.marketId, loser.publicKey),
+              winner: loser.publicKey,
+            })
+            .signers([loser])
+            .rpc(),
+          "BetOnLosingSide"
+        );
+      });
+
+      it("Settles out of range when the answer misses the bounds", async () => {
+        const market = await createRangeMarket("Will the reading miss the range?", 200, 300);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+        await resolve(market);
+
+        const account = await rangeProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.resolved).to.equal(true);
+        expect(account.inRange).to.equal(false);
+      });
+    });
+
+    describe("minimum confidence", () => {
+      it("Cancels and refunds instead of resolving off an answer below the market's minimum", async () => {
+        const market = await createRangeMarket("Will the shaky reading settle it?", 50, 150, 5, 90);
+        const bettor = await bet(rangeProgram, marketStatePDA, market, true);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+
+        await expectError(resolve(market), "ConfidenceTooLow");
+        const account = await rangeProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.minConfidence).to.equal(90);
+        expect(account.resolved).to.equal(false);
+
+        // A low-confidence answer cancels right away, without waiting out the refund period
+        await rangeProgram.methods
+          .cancelMarket(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            marketState: marketStatePDA,
+            oracleQuestion: market.questionPDA,
+            oracleAnswer: market.answerPDA,
+          })
+          .rpc();
+        await claimRefund(market, bettor);
+        // The refund is the gross stake, entry fee included, i.e. all the bettor brought less the position rent
+        const positionRent = await provider.connection.getMinimumBalanceForRentExemption(rangeProgram.account.position.size);
+        expect(await provider.connection.getBalance(bettor.publicKey)).to.equal(LAMPORTS_PER_SOL - positionRent);
+      });
+
+      it("Rejects a minimum confidence outside 1-100", async () => {
+        for (const minConfidence of [0, 101]) {
+          await expectError(
+            createRangeMarket(`Will confidence ${minConfidence} be accepted?`, 100, 200, 5, minConfidence),
+            "InvalidMinConfidence"
+          );
+        }
+      });
+    });
+
+    describe("question type", () => {
+      it("Refuses to resolve off a question of another type than the market expects", async () => {
+        // askAndAnswer asks a numeric question, while this market expects a price one
+        const market = await createRangeMarket("Will the numeric question settle a price market?", 50, 150, 5, 50, { price: {} });
+        await new Promise(resolve => setTimeout(resolve, 6000));
+
+        await expectError(resolve(market), "QuestionTypeMismatch");
+      });
+
+      it("Rejects a question type that yields no number", async () => {
+        for (const questionType of [{ general: {} }, { yesNo: {} }]) {
+          await expectError(
+            createRangeMarket("Will a yes/no question settle a range market?", 50, 150, 5, 50, questionType),
+            "IncompatibleQuestionType"
+          );
+        }
+      });
+    });
+
+    describe("inconclusive answers", () => {
+      it("Cancels instead of resolving on an inconclusive answer, refunding every stake", async () => {
+        // The value sits inside the range, but the inconclusive flag means it settles nothing
+        const market = await createRangeMarket("Will the disputed reading land in range?", 50, 150, 5, 50, { numeric: {} }, true);
+        const inside = await bet(rangeProgram, marketStatePDA, market, true);
+        const outside = await bet(rangeProgram, marketStatePDA, market, false);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+
+        await resolve(market);
+
+        const account = await rangeProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.canceled).to.equal(true);
+        expect(account.resolved).to.equal(false);
+
+        // Both bettors staked the same gross amount, and each gets exactly that back
+        const positionRent = await provider.connection.getMinimumBalanceForRentExemption(8 + 17);
+        for (const bettor of [inside, outside]) {
+          await claimRefund(market, bettor);
+          expect(await provider.connection.getBalance(bettor.publicKey)).to.equal(LAMPORTS_PER_SOL - positionRent);
+        }
+      });
+    });
+
+    describe("account sizing", () => {
+      it("Reserves question space for the configured cap rather than MAX_QUESTION_LEN", async () => {
+        const market = await createRangeMarket("Is the account sized to the cap?", 100, 200);
+        const { maxQuestionLen } = await rangeProgram.account.marketState.fetch(marketStatePDA);
+        expect(maxQuestionLen).to.be.lessThan(1000);
+
+        // Discriminator, market id, length-prefixed question, then 118 bytes of fixed fields
+        const info = await provider.connection.getAccountInfo(market.marketPDA);
+        expect(info.data.length).to.equal(8 + 8 + 4 + maxQuestionLen + 118);
+      });
+    });
+
+    describe("emergency cancel", () => {
+      // The 30 day grace never elapses on a test validator, so these cover every guard ahead of it
+      const emergencyCancel = (market: { marketId: anchor.BN; marketPDA: PublicKey }, signer: Keypair | null = null) =>
+        rangeProgram.methods
+          .emergencyCancel(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            marketState: marketStatePDA,
+            authority: signer ? signer.publicKey : authority.publicKey,
+          })
+          .signers(signer ? [signer] : [])
+          .rpc();
+
+      it("Rejects a cancel from anyone but the authority", async () => {
+        const market = await createRangeMarket("Will the stuck reading land in range?", 100, 200);
+        await expectError(emergencyCancel(market, requester), "Unauthorized");
+      });
+
+      it("Rejects a cancel before the grace period after the deadline has passed", async () => {
+        const market = await createRangeMarket("Will the stuck reading land in range?", 100, 200);
+        await expectError(emergencyCancel(market), "TooEarlyToCancel");
+      });
+
+      it("Rejects a cancel once the market has settled", async () => {
+        const market = await createRangeMarket("Will the stuck reading land in range?", 100, 200);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+        await resolve(market);
+        await expectError(emergencyCancel(market), "AlreadyResolved");
+      });
+    });
+
+    describe("returned amounts", () => {
+      const returnedAmount = async (signature: string) => {
+        const tx = await provider.connection.getTransaction(signature, {
+          commitment: "confirmed",
+          maxSupportedTransactionVersion: 0,
+        });
+        const [returnData] = tx.meta.returnData.data;
+        return Number(Buffer.from(returnData, "base64").readBigUInt64LE(0));
+      };
+
+      it("Returns the payout from a winning claim", async () => {
+        const market = await createRangeMarket("Will the claim report its payout?", 100, 200);
+        const inside = await bet(rangeProgram, marketStatePDA, market, true);
+        await bet(rangeProgram, marketStatePDA, market, false);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+        await resolve(market);
+
+        const signature = await rangeProgram.methods
+          .claimWinnings(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            position: findPositionPDA(market.marketId, inside.publicKey),
+            winner: inside.publicKey,
+          })
+          .signers([inside])
+          .rpc({ commitment: "confirmed" });
+
+        // The bettor staked their whole balance, so their balance is exactly the payout
+        expect(await returnedAmount(signature)).to.equal(await provider.connection.getBalance(inside.publicKey));
+      });
+
+      it("Returns the refund from a canceled market", async () => {
+        // The oracle answers at 80% confidence, short of this market's 90% minimum
+        const market = await createRangeMarket("Will the refund report its amount?", 100, 200, 5, 90);
+        const bettor = await bet(rangeProgram, marketStatePDA, market, true);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+
+        await rangeProgram.methods
+          .cancelMarket(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            marketState: marketStatePDA,
+            oracleQuestion: market.questionPDA,
+            oracleAnswer: market.answerPDA,
+          })
+          .rpc();
+
+        const signature = await rangeProgram.methods
+          .claimRefund(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            position: findPositionPDA(market.marketId, bettor.publicKey),
+            refundee: bettor.publicKey,
+          })
+          .signers([bettor])
+          .rpc({ commitment: "confirmed" });
+
+        expect(await returnedAmount(signature)).to.equal(await provider.connection.getBalance(bettor.publicKey));
+      });
+    });
+
+    describe("question binding", () => {
+      const findBindingPDA = (questionId: number) =>
+        PublicKey.findProgramAddressSync(
+          [Buffer.from("question_binding"), new anchor.BN(questionId).toArrayLike(Buffer, "le", 8)],
+          rangeProgram.programId
+        )[0];
+
+      const createOn = async (questionId: number, shared: boolean) => {
+        const marketId = (await rangeProgram.account.marketState.fetch(marketStatePDA)).marketCounter;
+        await rangeProgram.methods
+          .createMarket(
+            "Will the bound reading land in range?",
+            new anchor.BN(100),
+            new anchor.BN(200),
+            new anchor.BN(Math.floor(Date.now() / 1000) + 5),
+            50,
+            new anchor.BN(questionId),
+            { numeric: {} },
+            shared,
+            null
+          )
+          .accounts({
+            marketAccount: PublicKey.findProgramAddressSync(
+              [Buffer.from("market"), marketId.toArrayLike(Buffer, "le", 8)],
+              rangeProgram.programId
+            )[0],
+            marketState: marketStatePDA,
+            questionBinding: findBindingPDA(questionId),
+            creator: authority.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+      };
+
+      it("Keeps an exclusively bound question to its one market", async () => {
+        const { questionId } = await askAndAnswer("Will the exclusive reading land in range?", 150);
+        await createOn(questionId, false);
+
+        for (const shared of [false, true]) {
+          await expectError(createOn(questionId, shared), "QuestionAlreadyBound");
+        }
+      });
+
+      it("Lets shared markets bind the same question, but not an exclusive one", async () => {
+        const { questionId } = await askAndAnswer("Will the shared reading land in range?", 150);
+        await createOn(questionId, true);
+        await createOn(questionId, true);
+
+        const binding = await rangeProgram.account.questionBinding.fetch(findBindingPDA(questionId));
+        expect(binding.shared).to.equal(true);
+        expect(binding.marketCount).to.equal(2);
+
+        await expectError(createOn(questionId, false), "QuestionAlreadyBound");
+      });
+    });
+
+    describe("fee sweeping", () => {
+      it("Sweeps entry fees to market state on resolution for the authority to withdraw", async () => {
+        const market = await createRangeMarket("Will the fee test reading land in range?", 100, 200);
+        await bet(rangeProgram, marketStatePDA, market, true);
+        await bet(rangeProgram, marketStatePDA, market, false);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+
+        const fees = (await rangeProgram.account.marketAccount.fetch(market.marketPDA)).totalFees.toNumber();
+        expect(fees).to.be.greaterThan(0);
+        const sweptBefore = (await rangeProgram.account.marketState.fetch(marketStatePDA)).accumulatedFees.toNumber();
+
+        await resolve(market);
+
+        const swept = (await rangeProgram.account.marketState.fetch(marketStatePDA)).accumulatedFees.toNumber();
+        expect(swept).to.equal(sweptBefore + fees);
+
+        const withdraw = () =>
+          rangeProgram.methods
+            .withdrawFees()
+            .accounts({ marketState: marketStatePDA, authority: authority.publicKey, treasury: authority.publicKey })
+            .rpc();
+        const lamportsBefore = await provider.connection.getBalance(marketStatePDA);
+        await withdraw();
+        expect(await provider.connection.getBalance(marketStatePDA)).to.equal(lamportsBefore - swept);
+        expect((await rangeProgram.account.marketState.fetch(marketStatePDA)).accumulatedFees.toNumber()).to.equal(0);
+
+        await expectError(withdraw(), "NoFees");
+      });
+    });
+
+    describe("fee treasury", () => {
+      it("Withdraws fees swept on resolution only to the configured treasury", async () => {
+        const market = await createRangeMarket("Will the fee test reading land in range?", 100, 200);
+        await bet(rangeProgram, marketStatePDA, market, true);
+        await bet(rangeProgram, marketStatePDA, market, false);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+
+        await rangeProgram.methods
+          .setTreasury(requester.publicKey)
+          .accounts({ marketState: marketStatePDA, authority: authority.publicKey })
+          .rpc();
+        const withdrawTo = (treasury: PublicKey) =>
+          rangeProgram.methods
+            .withdrawFees()
+            .accounts({ marketState: marketStatePDA, authority: authority.publicKey, treasury })
+            .rpc();
+
+        try {
+          await resolve(market);
+
+          const fees = (await rangeProgram.account.marketState.fetch(marketStatePDA)).accumulatedFees.toNumber();
+          expect(fees).to.be.greaterThan(0);
+
+          await expectError(withdrawTo(authority.publicKey), "InvalidTreasury");
+
+          const treasuryBefore = await provider.connection.getBalance(requester.publicKey);
+          await withdrawTo(requester.publicKey);
+          expect(await provider.connection.getBalance(requester.publicKey)).to.equal(treasuryBefore + fees);
+          expect((await rangeProgram.account.marketState.fetch(marketStatePDA)).accumulatedFees.toNumber()).to.equal(0);
+        } finally {
+          await rangeProgram.methods
+            .setTreasury(authority.publicKey)
+            .accounts({ marketState: marketStatePDA, authority: authority.publicKey })
+            .rpc();
+        }
+      });
+    });
+  });
+
+  describe("time series market", () => {
+    const seriesProgram = anchor.workspace.TimeSeriesMarket as Program<TimeSeriesMarket>;
+
+    let marketStatePDA: PublicKey;
+
+    type SeriesTestMarket = {
+      marketId: anchor.BN;
+      marketPDA: PublicKey;
+      periods: { questionPDA: PublicKey; answerPDA: PublicKey }[];
+    };
+
+    // Series with one freshly answered trace9 question per entry of `answers`; period deadlines
+    // fall a second apart, the first five seconds out
+    const createAnsweredSeries = async (
+      question: string,
+      answers: { value: number; inconclusive?: boolean }[],
+      ordered = false,
+      closeTime = 0
+    ): Promise<SeriesTestMarket> => {
+      const periods = [];
+      const questionIds = [];
+      for (const [i, answer] of answers.entries()) {
+        const { questionId, questionPDA, answerPDA } = await askAndAnswer(`${question} (period ${i})`, answer.value, answer.inconclusive ?? false);
+        questionIds.push(new anchor.BN(questionId));
+        periods.push({ questionPDA, answerPDA });
+      }
+
+      const marketId = (await seriesProgram.account.marketState.fetch(marketStatePDA)).marketCounter;
+      const [marketPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("market"), marketId.toArrayLike(Buffer, "le", 8)],
+        seriesProgram.programId
+      );
+      const firstDeadline = Math.floor(Date.now() / 1000) + 5;
+
+      await seriesProgram.methods
+        .createMarket(
+          question,
+          answers.map((_, i) => new anchor.BN(firstDeadline + i)),
+          new anchor.BN(closeTime),
+          questionIds,
+          50,
+          { numeric: {} },
+          ordered,
+          null
+        )
+        .accounts({
+          marketAccount: marketPDA,
+          marketState: marketStatePDA,
+          creator: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      return { marketId, marketPDA, periods };
+    };
+
+    const findPositionPDA = (marketId: anchor.BN, owner: PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), marketId.toArrayLike(Buffer, "le", 8), owner.toBuffer()],
+        seriesProgram.programId
+      )[0];
+
+    const resolvePeriod = (market: SeriesTestMarket, periodIndex: number) =>
+      seriesProgram.methods
+        .resolvePeriod(market.marketId, periodIndex)
+        .accounts({
+          marketAccount: market.marketPDA,
+          marketState: marketStatePDA,
+          oracleQuestion: market.periods[periodIndex].questionPDA,
+          oracleAnswer: market.periods[periodIndex].answerPDA,
+          resolver: authority.publicKey,
+        })
+        .rpc();
+
+    const claimRefund = (market: SeriesTestMarket, refundee: Keypair) =>
+      seriesProgram.methods
+        .claimRefund(market.marketId)
+        .accounts({
+          marketAccount: market.marketPDA,
+          position: findPositionPDA(market.marketId, refundee.publicKey),
+          refundee: refundee.publicKey,
+        })
+        .signers([refundee])
+        .rpc();
+
+    before(async () => {
+      [marketStatePDA] = PublicKey.findProgramAddressSync([Buffer.from("market_state")], seriesProgram.programId);
+
+      const initialize = (fee: number) =>
+        seriesProgram.methods
+          .initialize(program.programId, fee, new anchor.BN(1), 500)
+          .accounts({
+            marketState: marketStatePDA,
+            authority: authority.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+
+      // Market state can only be initialized once, so the fee cap on initialize is checked here
+      await expectError(initialize(2_001), "FeeTooHigh");
+      await initialize(200);
+
+      // Test periods sit a second apart
+      await seriesProgram.methods
+        .setMinPeriodGap(new anchor.BN(0))
+        .accounts({
+          marketState: marketStatePDA,
+          authority: authority.publicKey,
+        })
+        .rpc();
+    });
+
+    describe("inconclusive answers", () => {
+      it("Cancels the series when a period's answer is inconclusive, refunding every stake", async () => {
+        const market = await createAnsweredSeries("Will the index rise each day?", [{ value: 5, inconclusive: true }, { value: 7 }]);
+        const success = await bet(seriesProgram, marketStatePDA, market, true);
+        const failure = await bet(seriesProgram, marketStatePDA, market, false);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+
+        await resolvePeriod(market, 0);
+
+        const account = await seriesProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.canceled).to.equal(true);
+        expect(account.periods[0].resolved).to.equal(false);
+        expect(account.allResolved).to.equal(false);
+
+        // Both bettors staked the same gross amount, and each gets exactly that back
+        const positionRent = await provider.connection.getMinimumBalanceForRentExemption(8 + 17);
+        for (const bettor of [success, failure]) {
+          await claimRefund(market, bettor);
+          expect(await provider.connection.getBalance(bettor.publicKey)).to.equal(LAMPORTS_PER_SOL - positionRent);
+        }
+      });
+
+      it("Refunds a position once and rejects the second attempt", async () => {
+        const market = await createAnsweredSeries("Will the refund be paid twice?", [{ value: 5, inconclusive: true }, { value: 7 }]);
+        const bettor = await bet(seriesProgram, marketStatePDA, market, true);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+        await resolvePeriod(market, 0);
+
+        await claimRefund(market, bettor);
+        const refunded = await provider.connection.getBalance(bettor.publicKey);
+        const position = await seriesProgram.account.position.fetch(findPositionPDA(market.marketId, bettor.publicKey));
+        expect(position.claimed).to.equal(true);
+
+        await expectError(claimRefund(market, bettor), "AlreadyClaimed");
+        expect(await provider.connection.getBalance(bettor.publicKey)).to.equal(refunded);
+      });
+    });
+
+    describe("betting cutoff", () => {
+      it("Rejects bets once the first period's deadline has passed", async () => {
+        const market = await createAnsweredSeries("Will bettors be shut out after the first period?", [{ value: 3 }, { value: 4 }]);
+        await bet(seriesProgram, marketStatePDA, market, true);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+
+        await expectError(bet(seriesProgram, marketStatePDA, market, false), "MarketExpired");
+
+        // Resolution still goes ahead once betting has stopped
+        await resolvePeriod(market, 0);
+        const account = await seriesProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.periods[0].resolved).to.equal(true);
+      });
+
+      it("Rejects a close time after the first period's deadline", async () => {
+        await expectError(
+          createAnsweredSeries("Will the late cutoff be refused?", [{ value: 3 }, { value: 4 }], false, Math.floor(Date.now() / 1000) + 60),
+          "InvalidCloseTime"
+        );
+      });
+    });
+
+    describe("resolution order", () => {
+      it("Rejects a later period ahead of an earlier one in an ordered series", async () => {
+        const market = await createAnsweredSeries("Will the ordered index rise?", [{ value: 3 }, { value: 4 }], true);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+
+        try {
+          await resolvePeriod(market, 1);
+          expect.fail("should have rejected resolving period 1 before period 0");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("OutOfOrderResolution");
+        }
+
+        await resolvePeriod(market, 0);
+        await resolvePeriod(market, 1);
+
+        const account = await seriesProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.allResolved).to.equal(true);
+        expect(account.allSuccess).to.equal(true);
+      });
+
+      it("Resolves periods in any order in an unordered series", async () => {
+        const market = await createAnsweredSeries("Will the unordered index rise?", [{ value: 3 }, { value: 4 }]);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+
+        await resolvePeriod(market, 1);
+        let account = await seriesProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.periods[1].resolved).to.equal(true);
+        expect(account.allResolved).to.equal(false);
+
+        await resolvePeriod(market, 0);
+        account = await seriesProgram.account.marketAccount.fetch(market.marketPDA);
+        expect(account.allResolved).to.equal(true);
+      });
+    });
+
+    describe("emergency cancel", () => {
+      // The 30 day grace never elapses on a test validator, so these cover every guard ahead of it
+      const emergencyCancel = (market: { marketId: anchor.BN; marketPDA: PublicKey }, signer: Keypair | null = null) =>
+        seriesProgram.methods
+          .emergencyCancel(market.marketId)
+          .accounts({
+            marketAccount: market.marketPDA,
+            marketState: marketStatePDA,
+            authority: signer ? signer.publicKey : authority.publicKey,
+          })
+          .signers(signer ? [signer] : [])
+          .rpc();
+
+      it("Rejects a cancel from anyone but the authority", async () => {
+        const market = await createAnsweredSeries("Will the stuck index rise?", [{ value: 3 }, { value: 4 }]);
+        await expectError(emergencyCancel(market, requester), "Unauthorized");
+      });
+
+      it("Rejects a cancel before the grace period after the last period has passed", async () => {
+        const market = await createAnsweredSeries("Will the stuck index rise?", [{ value: 3 }, { value: 4 }]);
+        await expectError(emergencyCancel(market), "TooEarlyToCancel");
+      });
+
+      it("Rejects a cancel once the market has settled", async () => {
+        const market = await createAnsweredSeries("Will the stuck index rise?", [{ value: 3 }, { value: 4 }]);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+        await resolvePeriod(market, 0);
+        await resolvePeriod(market, 1);
+        await expectError(emergencyCancel(market), "MarketResolved");
+      });
+    });
+
+    describe("fee cap", () => {
+      const setFee = (fee: number, signer: Keypair | null = null) =>
+        seriesProgram.methods
+          .setFeePercentage(fee)
+          .accounts({ marketState: marketStatePDA, authority: signer ? signer.publicKey : authority.publicKey })
+          .signers(signer ? [signer] : [])
+          .rpc();
+
+      it("Accepts a fee up to MAX_FEE_BPS and rejects anything above it", async () => {
+        await expectError(setFee(2_001), "FeeTooHigh");
+
+        await setFee(2_000);
+        try {
+          expect((await seriesProgram.account.marketState.fetch(marketStatePDA)).feePercentage).to.equal(2_000);
+        } finally {
+          await setFee(200);
+        }
+      });
+
+      it("Rejects a fee change from anyone but the authority", async () => {
+        await expectError(setFee(100, requester), "Unauthorized");
+      });
+    });
+
+    describe("period schedule", () => {
+      // Creation checks the schedule before it looks at the questions, so any ids will do
+      const createWith = async (deadlines: number[]) => {
+        const marketId = (await seriesProgram.account.marketState.fetch(marketStatePDA)).marketCounter;
+        await seriesProgram.methods
+          .createMarket(
+            "Is the period schedule sound?",
+            deadlines.map(deadline => new anchor.BN(deadline)),
+            new anchor.BN(0),
+            deadlines.map((_, i) => new anchor.BN(i)),
+            50,
+            { numeric: {} },
+            false,
+            null
+          )
+          .accounts({
+            marketAccount: PublicKey.findProgramAddressSync(
+              [Buffer.from("market"), marketId.toArrayLike(Buffer, "le", 8)],
+              seriesProgram.programId
+            )[0],
+            marketState: marketStatePDA,
+            creator: authority.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+      };
+
+      const setMinPeriodGap = (seconds: number) =>
+        seriesProgram.methods
+          .setMinPeriodGap(new anchor.BN(seconds))
+          .accounts({ marketState: marketStatePDA, authority: authority.publicKey })
+          .rpc();
+
+      it("Rejects a first period whose deadline has already passed", async () => {
+        const now = Math.floor(Date.now() / 1000);
+        await expectError(createWith([now - 60, now + 60]), "PeriodInPast");
+      });
+
+      it("Holds periods at least the configured gap apart", async () => {
+        const now = Math.floor(Date.now() / 1000);
+        await setMinPeriodGap(60);
+        try {
+          await expectError(createWith([now + 60, now + 90]), "PeriodGapTooSmall");
+          await createWith([now + 60, now + 120]);
+        } finally {
+          await setMinPeriodGap(0);
+        }
+      });
+    });
+
+    describe("fee sweeping", () => {
+      it("Sweeps entry fees to market state once the last period settles", async () => {
+        const market = await createAnsweredSeries("Will the fee test index rise?", [{ value: 7 }, { value: 9 }]);
+        await bet(seriesProgram, marketStatePDA, market, true);
+        await bet(seriesProgram, marketStatePDA, market, false);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+
+        const fees = (await seriesProgram.account.marketAccount.fetch(market.marketPDA)).totalFees.toNumber();
+        expect(fees).to.be.greaterThan(0);
+        const sweptBefore = (await seriesProgram.account.marketState.fetch(marketStatePDA)).accumulatedFees.toNumber();
+
+        await resolvePeriod(market, 0);
+        expect((await seriesProgram.account.marketState.fetch(marketStatePDA)).accumulatedFees.toNumber()).to.equal(sweptBefore);
+        await resolvePeriod(market, 1);
+
+        const swept = (await seriesProgram.account.marketState.fetch(marketStatePDA)).accumulatedFees.toNumber();
+        expect(swept).to.equal(sweptBefore + fees);
+
+        const withdraw = () =>
+          seriesProgram.methods
+            .withdrawFees()
+            .accounts({ marketState: marketStatePDA, authority: authority.publicKey, treasury: authority.publicKey })
+            .rpc();
+        const lamportsBefore = await provider.connection.getBalance(marketStatePDA);
+        await withdraw();
+        expect(await provider.connection.getBalance(marketStatePDA)).to.equal(lamportsBefore - swept);
+        expect((await seriesProgram.account.marketState.fetch(marketStatePDA)).accumulatedFees.toNumber()).to.equal(0);
+
+        await expectError(withdraw(), "NoFees");
+      });
+    });
+
+    describe("fee treasury", () => {
+      it("Withdraws fees swept on resolution only to the configured treasury", async () => {
+        const market = await createAnsweredSeries("Will the fee test index rise?", [{ value: 7 }, { value: 9 }]);
+        await bet(seriesProgram, marketStatePDA, market, true);
+        await bet(seriesProgram, marketStatePDA, market, false);
+        await new Promise(resolve => setTimeout(resolve, 6000));
+
+        await seriesProgram.methods
+          .setTreasury(requester.publicKey)
+          .accounts({ marketState: marketStatePDA, authority: authority.publicKey })
+          .rpc();
+        const withdrawTo = (treasury: PublicKey) =>
+          seriesProgram.methods
+            .withdrawFees()
+            .accounts({ marketState: marketStatePDA, authority: authority.publicKey, treasury })
+            .rpc();
+
+        try {
+          // Fees only sweep once the last period settles
+          await resolvePeriod(market, 0);
+          await resolvePeriod(market, 1);
+
+          const fees = (await seriesProgram.account.marketState.fetch(marketStatePDA)).accumulatedFees.toNumber();
+          expect(fees).to.be.greaterThan(0);
+
+          await expectError(withdrawTo(authority.publicKey), "InvalidTreasury");
+
+          const treasuryBefore = await provider.connection.getBalance(requester.publicKey);
+          await withdrawTo(requester.publicKey);
+          expect(await provider.connection.getBalance(requester.publicKey)).to.equal(treasuryBefore + fees);
+          expect((await seriesProgram.account.marketState.fetch(marketStatePDA)).accumulatedFees.toNumber()).to.equal(0);
+        } finally {
+          await seriesProgram.methods
+            .setTreasury(authority.publicKey)
+            .accounts({ marketState: marketStatePDA, authority: authority.publicKey })
+            .rpc();
+        }
+      });
+    });
+  });
+
+  describe("conditional market", () => {
+    const marketProgram = anchor.workspace.ConditionalMarket as Program<ConditionalMarket>;
+    const parentProgram = anchor.workspace.SimplePredictionMarket as Program<SimplePredictionMarket>;
+    const [parentStatePDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("market_state")],
+      parentProgram.programId
+    );
+
+    let marketStatePDA: PublicKey;
+    let parentPDA: PublicKey;
+
+    // Simple market parent on a fresh oracle question, resolved YES or NO five seconds out
+    // unless `resolve` is false
+    const createResolvedParent = async (question: string, outcome: boolean, resolve = true) => {
+      const oracleState = await program.account.oracleState.fetch(oracleStatePDA);
+      const questionId = oracleState.questionCounter.toNumber();
+      const { questionPDA, answerPDA } = await findQuestionPDAs(questionId);
+
+      await program.methods
+        .askQuestion({ general: {} }, question, "general", new anchor.BN(Math.floor(Date.now() / 1000) + 86400), new anchor.BN(0), null)
+        .accounts({
+          questionAccount: questionPDA,
+          oracleState: oracleStatePDA,
+          globalStats: globalStatsPDA,
+          requester: requester.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([requester])
+        .rpc();
+
+      const parentId = (await parentProgram.account.marketState.fetch(parentStatePDA)).marketCounter;
+      const [parentPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("market"), parentId.toArrayLike(Buffer, "le", 8)],
+        parentProgram.programId
+      );
+      const [questionBindingPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("question_binding"), new anchor.BN(questionId).toArrayLike(Buffer, "le", 8)],
+        parentProgram.programId
+      );
+
+      await parentProgram.methods
+        .createMarket(question, new anchor.BN(Math.floor(Date.now() / 1000) + 5), new anchor.BN(0), { parimutuel: {} }, new anchor.BN(0), 50, new anchor.BN(questionId), { general: {} }, { flat: {} }, 0, 0, false, { flat: {} }, 0, new anchor.BN(0), new anchor.BN(0), new anchor.BN(0), false, new anchor.BN(0))
+        .accounts({
+          marketAccount: parentPDA,
+          marketState: parentStatePDA,
+          questionBinding: questionBindingPDA,
+          creator: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      if (!resolve) {
+        return parentPDA;
+      }
+
+      await program.methods
+        .provideAnswer(outcome ? "Yes" : "No", new anchor.BN(outcome ? 1 : 0), outcome, 90, "Parent test", null, false)
+        .accounts({
+          questionAccount: questionPDA,
+          answerAccount: answerPDA,
+          oracleState: oracleStatePDA,
+          globalStats: globalStatsPDA,
+          providerBond: providerBondPDA,
+          oracleProvider: oracleProvider.publicKey,
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([oracleProvider])
+        .rpc();
+
+      await new Promise(resolve => setTimeout(resolve, 6000));
+      await parentProgram.methods
+        .resolveMarket(parentId)
+        .accounts({
+          marketAccount: parentPDA,
+          marketState: parentStatePDA,
+          oracleQuestion: questionPDA,
+          oracleAnswer: answerPDA,
+        })
+        .rpc();
+
+      return parentPDA;
+    };
+
+    // Conditional market on the given parents, returning its id and PDA
+    const createChild = async (
+      question: string,
+      conditions: { parentMarket: PublicKey; requiredOutcome: number }[],
+      parlay = false
+    ) => {
+      const childId = (await marketProgram.account.marketState.fetch(marketStatePDA)).marketCounter;
+      const [childPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("market"), childId.toArrayLike(Buffer, "le", 8)],
+        marketProgram.programId
+      );
+
+      await marketProgram.methods
+        .createMarket(question, conditions, parlay)
+        .accounts({
+          marketAccount: childPDA,
+          marketState: marketStatePDA,
+          creator: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      return { childId, childPDA };
+    };
+
+    const checkParents = (childId: anchor.BN, childPDA: PublicKey, parents: PublicKey[]) =>
+      marketProgram.methods
+        .checkParentMarket(childId)
+        .accounts({ marketAccount: childPDA })
+        .remainingAccounts(parents.map(pubkey => ({ pubkey, isWritable: false, isSigner: false })))
+        .rpc();
+
+    const findPositionPDA = (childId: anchor.BN, owner: PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("position"), childId.toArrayLike(Buffer, "le", 8), owner.toBuffer()],
+        marketProgram.programId
+      )[0];
+
+    before(async () => {
+      [marketStatePDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("market_state")],
+        marketProgram.programId
+      );
+
+      // A deployment for short questions
+      await marketProgram.methods
+        .initialize(200, new anchor.BN(1), 140)
+        .accounts({
+          marketState: marketStatePDA,
+          authority: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      parentPDA = await createResolvedParent("Will the parent ship?", true);
+    });
+
+    describe("unmet conditions", () => {
+      it("Refunds the gross stake, entry fee included, when the parent condition fails", async () => {
+        // The parent resolved YES, so a market on a NO parent never runs
+        const { childId, childPDA } = await createChild(
+          "Will the fallback ship if the parent resolves NO?",
+          [{ parentMarket: parentPDA, requiredOutcome: 0 }]
+        );
+        const bettors = [await bet(marketProgram, marketStatePDA, { marketId: childId, marketPDA: childPDA }, true), await bet(marketProgram, marketStatePDA, { marketId: childId, marketPDA: childPDA }, false)];
+        const positions = [];
+        for (const bettor of bettors) {
+          const position = await marketProgram.account.position.fetch(findPositionPDA(childId, bettor.publicKey));
+          expect(position.feesPaid.toNumber()).to.be.greaterThan(0);
+          positions.push(position);
+        }
+
+        await checkParents(childId, childPDA, [parentPDA]);
+        expect((await marketProgram.account.marketAccount.fetch(childPDA)).status).to.deep.equal({ conditionNotMet: {} });
+
+        for (const [i, bettor] of bettors.entries()) {
+          await marketProgram.methods
+            .getRefund(childId)
+            .accounts({
+              marketAccount: childPDA,
+              position: findPositionPDA(childId, bettor.publicKey),
+              refundee: bettor.publicKey,
+            })
+            .signers([bettor])
+            .rpc();
+
+          // Each bettor staked their whole balance, so it now holds exactly the refund
+          expect(await provider.connection.getBalance(bettor.publicKey)).to.equal(
+            positions[i].yesAmount.toNumber() + positions[i].noAmount.toNumber() + positions[i].feesPaid.toNumber()
+          );
+        }
+        expect((await marketProgram.account.marketAccount.fetch(childPDA)).totalFees.toNumber()).to.equal(0);
+      });
+    });
+
+    describe("parent state", () => {
+      it("Waits for an unresolved simple market parent", async () => {
+        const pending = await createResolvedParent("Will the pending parent ever settle?", true, false);
+        const { childId, childPDA } = await createChild(
+          "Will the child wait on its pending parent?",
+          [{ parentMarket: pending, requiredOutcome: 1 }]
+        );
+
+        try {
+          await checkParents(childId, childPDA, [pending]);
+          expect.fail("should have rejected a parent that has not resolved");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("ParentNotResolved");
+        }
+        expect((await marketProgram.account.marketAccount.fetch(childPDA)).status).to.deep.equal({ active: {} });
+      });
+
+      it("Rejects a parent that is not a simple market account", async () => {
+        // A conditional market lives in this program, not simple_prediction_market
+        const other = await createChild(
+          "Will a conditional market stand in as a parent?",
+          [{ parentMarket: parentPDA, requiredOutcome: 1 }]
+        );
+        const { childId, childPDA } = await createChild(
+          "Will a conditional parent be refused?",
+          [{ parentMarket: other.childPDA, requiredOutcome: 1 }]
+        );
+
+        try {
+          await checkParents(childId, childPDA, [other.childPDA]);
+          expect.fail("should have rejected a parent owned by another program");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("InvalidParentMarket");
+        }
+      });
+
+      it("Rejects a parent other than the one the market was created on", async () => {
+        const { childId, childPDA } = await createChild(
+          "Will the child read only its own parent?",
+          [{ parentMarket: parentPDA, requiredOutcome: 1 }]
+        );
+        const stranger = await createResolvedParent("Will an unrelated parent ship?", true);
+
+        try {
+          await checkParents(childId, childPDA, [stranger]);
+          expect.fail("should have rejected a parent the market was not created on");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("InvalidParentMarket");
+        }
+      });
+    });
+
+    describe("multiple parents", () => {
+      let noParentPDA: PublicKey;
+
+      before(async () => {
+        noParentPDA = await createResolvedParent("Will the second parent ship?", false);
+      });
+
+      it("Meets a combined condition only when every parent matches", async () => {
+        const met = await createChild("Will the bundle ship if the first parent ships and the second does not?", [
+          { parentMarket: parentPDA, requiredOutcome: 1 },
+          { parentMarket: noParentPDA, requiredOutcome: 0 },
+        ]);
+        await checkParents(met.childId, met.childPDA, [parentPDA, noParentPDA]);
+        expect((await marketProgram.account.marketAccount.fetch(met.childPDA)).status).to.deep.equal({ parentUnresolved: {} });
+
+        const unmet = await createChild("Will the bundle ship if both parents ship?", [
+          { parentMarket: parentPDA, requiredOutcome: 1 },
+          { parentMarket: noParentPDA, requiredOutcome: 1 },
+        ]);
+        await checkParents(unmet.childId, unmet.childPDA, [parentPDA, noParentPDA]);
+        expect((await marketProgram.account.marketAccount.fetch(unmet.childPDA)).status).to.deep.equal({ conditionNotMet: {} });
+      });
+
+      it("Requires every parent, in condition order", async () => {
+        const { childId, childPDA } = await createChild("Will the ordered bundle ship?", [
+          { parentMarket: parentPDA, requiredOutcome: 1 },
+          { parentMarket: noParentPDA, requiredOutcome: 0 },
+        ]);
+
+        for (const parents of [[parentPDA], [noParentPDA, parentPDA]]) {
+          try {
+            await checkParents(childId, childPDA, parents);
+            expect.fail("should have rejected a missing or misordered parent");
+          } catch (error) {
+            expect(error.error.errorCode.code).to.equal("InvalidParentMarket");
+          }
+        }
+      });
+
+      it("Rejects no conditions, too many, or the same parent twice", async () => {
+        const cases: [{ parentMarket: PublicKey; requiredOutcome: number }[], string][] = [
+          [[], "InvalidConditionCount"],
+          [
+            Array.from({ length: 5 }, () => ({ parentMarket: Keypair.generate().publicKey, requiredOutcome: 1 })),
+            "InvalidConditionCount",
+          ],
+          [
+            [
+              { parentMarket: parentPDA, requiredOutcome: 1 },
+              { parentMarket: parentPDA, requiredOutcome: 0 },
+            ],
+            "InvalidParentMarket",
+          ],
+        ];
+
+        for (const [conditions, code] of cases) {
+          try {
+            await createChild("Will the malformed bundle be refused?", conditions);
+            expect.fail(`should have failed with ${code}`);
+          } catch (error) {
+            expect(error.error.errorCode.code).to.equal(code);
+          }
+        }
+      });
+    });
+
+    describe("minimum bet", () => {
+      const setMinBet = (minBet: number, signer: Keypair | null = null) =>
+        marketProgram.methods
+          .setMinBet(new anchor.BN(minBet))
+          .accounts({ marketState: marketStatePDA, authority: signer ? signer.publicKey : authority.publicKey })
+          .signers(signer ? [signer] : [])
+          .rpc();
+
+      it("Rejects bets under the authority's minimum and takes one right at it", async () => {
+        const { childId, childPDA } = await createChild(
+          "Will the small bet be refused?",
+          [{ parentMarket: parentPDA, requiredOutcome: 1 }]
+        );
+        // Bettors bring one SOL and pay the position rent out of it before staking the rest
+        const stake =
+          LAMPORTS_PER_SOL - (await provider.connection.getMinimumBalanceForRentExemption(marketProgram.account.position.size));
+
+        try {
+          await setMinBet(stake + 1);
+          try {
+            await bet(marketProgram, marketStatePDA, { marketId: childId, marketPDA: childPDA }, true);
+            expect.fail("should have rejected a bet under the minimum");
+          } catch (error) {
+            expect(error.error.errorCode.code).to.equal("BetTooSmall");
+          }
+
+          await setMinBet(stake);
+          const bettor = await bet(marketProgram, marketStatePDA, { marketId: childId, marketPDA: childPDA }, true);
+          const position = await marketProgram.account.position.fetch(findPositionPDA(childId, bettor.publicKey));
+          expect(position.yesAmount.toNumber() + position.feesPaid.toNumber()).to.equal(stake);
+        } finally {
+          await setMinBet(1);
+        }
+      });
+
+      it("Rejects a minimum set by anyone but the authority", async () => {
+        try {
+          await setMinBet(2 * LAMPORTS_PER_SOL, requester);
+          expect.fail("should have rejected a non-authority min bet change");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("Unauthorized");
+        }
+      });
+    });
+
+    describe("fee sweeping", () => {
+      let childId: anchor.BN;
+      let childPDA: PublicKey;
+
+      before(async () => {
+        ({ childId, childPDA } = await createChild(
+          "Will the follow-up ship if the parent resolves YES?",
+          [{ parentMarket: parentPDA, requiredOutcome: 1 }]
+        ));
+        await bet(marketProgram, marketStatePDA, { marketId: childId, marketPDA: childPDA }, true);
+      });
+
+      it("Sweeps entry fees to market state on resolution", async () => {
+        const fees = (await marketProgram.account.marketAccount.fetch(childPDA)).totalFees.toNumber();
+        expect(fees).to.be.greaterThan(0);
+
+        const sweptBefore = (await marketProgram.account.marketState.fetch(marketStatePDA)).accumulatedFees.toNumber();
+        const lamportsBefore = await provider.connection.getBalance(marketStatePDA);
+
+        await checkParents(childId, childPDA, [parentPDA]);
+        await marketProgram.methods
+          .resolveMarket(childId, true)
+          .accounts({
+            marketAccount: childPDA,
+            marketState: marketStatePDA,
+            authority: authority.publicKey,
+          })
+          .rpc();
+
+        const state = await marketProgram.account.marketState.fetch(marketStatePDA);
+        expect(state.accumulatedFees.toNumber()).to.equal(sweptBefore + fees);
+        expect(await provider.connection.getBalance(marketStatePDA)).to.equal(lamportsBefore + fees);
+      });
+
+      it("Lets the authority withdraw swept fees once, to the configured treasury", async () => {
+        const fees = (await marketProgram.account.marketState.fetch(marketStatePDA)).accumulatedFees.toNumber();
+        const lamportsBefore = await provider.connection.getBalance(marketStatePDA);
+        const setTreasury = (key: PublicKey) =>
+          marketProgram.methods
+            .setTreasury(key)
+            .accounts({ marketState: marketStatePDA, authority: authority.publicKey })
+            .rpc();
+        const withdrawTo = (treasury: PublicKey) =>
+          marketProgram.methods
+            .withdrawFees()
+            .accounts({
+              marketState: marketStatePDA,
+              authority: authority.publicKey,
+              treasury,
+            })
+            .rpc();
+
+        // Route fees away from the authority wallet for the duration of the test
+        await setTreasury(requester.publicKey);
+        try {
+          await expectError(withdrawTo(authority.publicKey), "InvalidTreasury");
+
+          const treasuryBefore = await provider.connection.getBalance(requester.publicKey);
+          await withdrawTo(requester.publicKey);
+
+          const state = await marketProgram.account.marketState.fetch(marketStatePDA);
+          expect(state.accumulatedFees.toNumber()).to.equal(0);
+          expect(await provider.connection.getBalance(marketStatePDA)).to.equal(lamportsBefore - fees);
+          expect(await provider.connection.getBalance(requester.publicKey)).to.equal(treasuryBefore + fees);
+
+          await expectError(withdrawTo(requester.publicKey), "NoFees");
+        } finally {
+          await setTreasury(authority.publicKey);
+        }
+      });
+    });
+
+    describe("parlay markets", () => {
+      it("Creates a parlay market that starts with nothing inherited", async () => {
+        const { childPDA } = await createChild(
+          "Will the parlay leg land if the parent resolves NO?",
+          [{ parentMarket: Keypair.generate().publicKey, requiredOutcome: 0 }],
+          true
+        );
+
+        const parlay = await marketProgram.account.marketAccount.fetch(childPDA);
+        expect(parlay.parlay).to.equal(true);
+        expect(parlay.inheritedPool.toNumber()).to.equal(0);
+      });
+    });
+
+    describe("question length", () => {
+      it("Enforces the deployment's question length cap", async () => {
+        const state = await marketProgram.account.marketState.fetch(marketStatePDA);
+        expect(state.maxQuestionLen).to.equal(140);
+
+        await expectError(
+          createChild("Q".repeat(141), [{ parentMarket: Keypair.generate().publicKey, requiredOutcome: 1 }]),
+          "InvalidQuestion"
+        );
+
+        const { childPDA } = await createChild("Q".repeat(140), [{ parentMarket: Keypair.generate().publicKey, requiredOutcome: 1 }]);
+        const market = await marketProgram.account.marketAccount.fetch(childPDA);
+        expect(market.question.length).to.equal(140);
+      });
+    });
+
+    describe("parent pool release", () => {
+      it("Hands a parent's parlay share back only once the parlay can no longer inherit it", async () => {
+        const releaseParentPool = (childId: anchor.BN, childPDA: PublicKey) =>
+          marketProgram.methods
+            .releaseParentPool(childId)
+            .accounts({
+              marketAccount: childPDA,
+              marketState: marketStatePDA,
+              parentMarket: parentPDA,
+              parentMarketState: parentStatePDA,
+              authority: authority.publicKey,
+              parentProgram: parentProgram.programId,
+            })
+            .rpc();
+
+        const plain = await createChild("Does a plain market hold a parlay share?", [{ parentMarket: parentPDA, requiredOutcome: 0 }]);
+        await expectError(releaseParentPool(plain.childId, plain.childPDA), "NotParlay");
+
+        // An active parlay can still meet its condition and inherit
+        const parlay = await createChild("Will the parlay leg land if the parent resolves NO?", [{ parentMarket: parentPDA, requiredOutcome: 0 }], true);
+        await expectError(releaseParentPool(parlay.childId, parlay.childPDA), "InvalidStatusTransition");
+
+        // Once its condition fails the release reaches the parent, which routes no share here
+        await checkParents(parlay.childId, parlay.childPDA, [parentPDA]);
+        expect((await marketProgram.account.marketAccount.fetch(parlay.childPDA)).status).to.deep.equal({ conditionNotMet: {} });
+        await expectError(releaseParentPool(parlay.childId, parlay.childPDA), "InvalidParentMarket");
+      });
+    });
+
+    describe("two-level payouts", () => {
+      const resolveChild = (childId: anchor.BN, childPDA: PublicKey, resolver: Keypair | null = null) =>
+        marketProgram.methods
+          .resolveMarket(childId, true)
+          .accounts({
+            marketAccount: childPDA,
+            marketState: marketStatePDA,
+            authority: resolver ? resolver.publicKey : authority.publicKey,
+          })
+          .signers(resolver ? [resolver] : [])
+          .rpc();
+
+      const claim = (childId: anchor.BN, childPDA: PublicKey, winner: Keypair) =>
+        marketProgram.methods
+          .claimWinnings(childId)
+          .accounts({
+            marketAccount: childPDA,
+            position: findPositionPDA(childId, winner.publicKey),
+            winner: winner.publicKey,
+          })
+          .signers([winner])
+          .rpc();
+
+      it("Resolves a child only after its parent condition is met, by the authority, and pays its winners", async () => {
+        const { childId, childPDA } = await createChild(
+          "Will the sequel ship if the parent ships?",
+          [{ parentMarket: parentPDA, requiredOutcome: 1 }]
+        );
+        const yesBettor = await bet(marketProgram, marketStatePDA, { marketId: childId, marketPDA: childPDA }, true);
+        const noBettor = await bet(marketProgram, marketStatePDA, { marketId: childId, marketPDA: childPDA }, false);
+
+        // The parent has resolved, but the child has not checked it yet
+        await expectError(resolveChild(childId, childPDA), "InvalidStatusTransition");
+
+        await checkParents(childId, childPDA, [parentPDA]);
+        expect((await marketProgram.account.marketAccount.fetch(childPDA)).status).to.deep.equal({ parentUnresolved: {} });
+
+        await expectError(resolveChild(childId, childPDA, Keypair.generate()), "Unauthorized");
+
+        await resolveChild(childId, childPDA);
+
+        // The YES side takes both net pools, the entry fees having moved to the market state
+        const child = await marketProgram.account.marketAccount.fetch(childPDA);
+        await claim(childId, childPDA, yesBettor);
+        expect(await provider.connection.getBalance(yesBettor.publicKey)).to.equal(
+          child.yesPool.toNumber() + child.noPool.toNumber()
+        );
+
+        await expectError(claim(childId, childPDA, noBettor), "BetOnLosingSide");
+      });
+    });
+  });
+
+  describe("payment facilitator", () => {
+    const paymentProgram = anchor.workspace.PaymentFacilitator as Program<PaymentFacilitator>;
+    const payer = Keypair.generate();
+    const recipient = Keypair.generate();
+
+    let facilitatorPDA: PublicKey;
+
+    const findPaymentRecordPDA = (paymentId: Buffer) =>
+      PublicKey.findProgramAddressSync([Buffer.from("payment"), paymentId], paymentProgram.programId)[0];
+
+    // `expiresAt` of zero settles with no expiry
+    const settlePayment = (paymentId: Buffer, expiresAt = 0, memo = "") =>
+      paymentProgram.methods
+        .settlePayment(new anchor.BN(1_000_000), [...paymentId], memo, new anchor.BN(expiresAt))
+        .accounts({
+          facilitator: facilitatorPDA,
+          paymentRecord: findPaymentRecordPDA(paymentId),
+          payer: payer.publicKey,
+          recipient: recipient.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([payer])
+        .rpc({ commitment: "confirmed" });
+
+    before(async () => {
+      [facilitatorPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("payment_facilitator")],
+        paymentProgram.programId
+      );
+
+      await provider.connection.requestAirdrop(payer.publicKey, LAMPORTS_PER_SOL);
+      await provider.connection.requestAirdrop(recipient.publicKey, LAMPORTS_PER_SOL);
+      await new Promise(resolve => setTimeout(resolve, 1000));
+
+      await paymentProgram.methods
+        .initialize(100)
+        .accounts({
+          facilitator: facilitatorPDA,
+          authority: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    });
+
+    describe("settlement expiry", () => {
+      it("Rejects settling a payment past its expiry", async () => {
+        const paymentId = Keypair.generate().publicKey.toBuffer();
+
+        await expectError(settlePayment(paymentId, Math.floor(Date.now() / 1000) - 60), "PaymentExpired");
+      });
+
+      it("Settles a payment still inside its validity window", async () => {
+        const paymentId = Keypair.generate().publicKey.toBuffer();
+        const expiresAt = Math.floor(Date.now() / 1000) + 3600;
+
+        await settlePayment(paymentId, expiresAt);
+
+        const record = await paymentProgram.account.paymentRecord.fetch(findPaymentRecordPDA(paymentId));
+        expect(record.expiresAt.toNumber()).to.equal(expiresAt);
+      });
+
+      it("Treats a zero expiry as never expiring", async () => {
+        const paymentId = Keypair.generate().publicKey.toBuffer();
+
+        await settlePayment(paymentId);
+
+        const record = await paymentProgram.account.paymentRecord.fetch(findPaymentRecordPDA(paymentId));
+        expect(record.expiresAt.toNumber()).to.equal(0);
+      });
+    });
+
+    describe("memo", () => {
+      it("Carries the invoice reference on the settlement event", async () => {
+        const signature = await settlePayment(Keypair.generate().publicKey.toBuffer(), 0, "INV-2026-0042");
+
+        const tx = await provider.connection.getTransaction(signature, {
+          commitment: "confirmed",
+          maxSupportedTransactionVersion: 0,
+        });
+        const eventParser = new anchor.EventParser(paymentProgram.programId, paymentProgram.coder);
+        const settled = [...eventParser.parseLogs(tx.meta.logMessages)].find(event => event.name === "paymentSettled");
+        expect(settled.data.memo).to.equal("INV-2026-0042");
+      });
+
+      it("Rejects a memo that is too long or holds control characters", async () => {
+        for (const memo of ["x".repeat(65), "INV-1\nINV-2"]) {
+          try {
+            await settlePayment(Keypair.generate().publicKey.toBuffer(), 0, memo);
+            expect.fail("should have rejected an invalid memo");
+          } catch (error) {
+            expect(error.error.errorCode.code).to.equal("InvalidMemo");
+          }
+        }
+      });
+    });
+
+    describe("subscriptions", () => {
+      it("Pays one installment per interval out of the deposit and refunds the rest on cancel", async () => {
+        const installment = 100_000;
+        const fee = (installment * 100) / 10000;
+        const [subscriptionPDA] = PublicKey.findProgramAddressSync(
+          [Buffer.from("subscription"), payer.publicKey.toBuffer(), recipient.publicKey.toBuffer()],
+          paymentProgram.programId
+        );
+
+        await paymentProgram.methods
+          .createSubscription(new anchor.BN(installment), new anchor.BN(3600), new anchor.BN(0), new anchor.BN(2.5 * installment))
+          .accounts({
+            subscription: subscriptionPDA,
+            payer: payer.publicKey,
+            recipient: recipient.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([payer])
+          .rpc();
+
+        const execute = () =>
+          paymentProgram.methods
+            .executeSubscription()
+            .accounts({
+              subscription: subscriptionPDA,
+              facilitator: facilitatorPDA,
+              recipient: recipient.publicKey,
+            })
+            .rpc();
+
+        // The first installment is due at creation
+        const created = await paymentProgram.account.subscription.fetch(subscriptionPDA);
+        const recipientBefore = await provider.connection.getBalance(recipient.publicKey);
+        await execute();
+        expect(await provider.connection.getBalance(recipient.publicKey)).to.equal(recipientBefore + installment - fee);
+
+        const subscription = await paymentProgram.account.subscription.fetch(subscriptionPDA);
+        expect(subscription.balance.toNumber()).to.equal(1.5 * installment);
+        expect(subscription.nextDue.toNumber()).to.equal(created.nextDue.toNumber() + 3600);
+
+        try {
+          await execute();
+          expect.fail("should have rejected an installment before it is due");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("SubscriptionNotDue");
+        }
+
+        // Canceling closes the account, returning the unspent deposit along with its rent
+        const subscriptionLamports = await provider.connection.getBalance(subscriptionPDA);
+        const payerBefore = await provider.connection.getBalance(payer.publicKey);
+        await paymentProgram.methods
+          .cancelSubscription()
+          .accounts({ subscription: subscriptionPDA, payer: payer.publicKey })
+          .signers([payer])
+          .rpc();
+
+        expect(await provider.connection.getBalance(payer.publicKey)).to.equal(payerBefore + subscriptionLamports);
+        expect(await provider.connection.getAccountInfo(subscriptionPDA)).to.equal(null);
+      });
+    });
+
+    describe("split payments", () => {
+      const splitRecipients = () => [recipient.publicKey, requester.publicKey, oracleProvider.publicKey];
+
+      const settleSplit = (amount: number, shares: number[]) =>
+        paymentProgram.methods
+          .settleSplitPayment(new anchor.BN(amount), [...Keypair.generate().publicKey.toBuffer()], shares)
+          .accounts({
+            facilitator: facilitatorPDA,
+            payer: payer.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .remainingAccounts(splitRecipients().map(pubkey => ({ pubkey, isSigner: false, isWritable: true })))
+          .signers([payer])
+          .rpc();
+
+      it("Splits the net amount by share, giving the rounding remainder to the first recipient", async () => {
+        const before = await Promise.all(splitRecipients().map(key => provider.connection.getBalance(key)));
+
+        // 1_000_001 less the 1% fee leaves 990_001, and the thirds round down by one lamport in total
+        await settleSplit(1_000_001, [3333, 3333, 3334]);
+
+        const after = await Promise.all(splitRecipients().map(key => provider.connection.getBalance(key)));
+        expect(after.map((balance, i) => balance - before[i])).to.deep.equal([329_968, 329_967, 330_066]);
+      });
+
+      it("Rejects shares that do not add up to 10000", async () => {
+        try {
+          await settleSplit(1_000_000, [3333, 3333, 3333]);
+          expect.fail("should have rejected shares summing to 9999");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("InvalidShares");
+        }
+      });
+    });
+
+    describe("refunds", () => {
+      const refund = (paymentId: Buffer, refunder: Keypair) =>
+        paymentProgram.methods
+          .refundPayment([...paymentId])
+          .accounts({
+            facilitator: facilitatorPDA,
+            paymentRecord: findPaymentRecordPDA(paymentId),
+            refunder: refunder.publicKey,
+            payer: payer.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([refunder])
+          .rpc();
+
+      const setGracePeriod = (seconds: number) =>
+        paymentProgram.methods
+          .setRefundGracePeriod(new anchor.BN(seconds))
+          .accounts({ facilitator: facilitatorPDA, authority: authority.publicKey })
+          .rpc();
+
+      it("Returns the gross amount, fee included, when the recipient refunds inside the grace period", async () => {
+        const paymentId = Keypair.generate().publicKey.toBuffer();
+        await settlePayment(paymentId);
+
+        const feesBefore = (await paymentProgram.account.paymentFacilitator.fetch(facilitatorPDA)).accumulatedFees.toNumber();
+        const payerBefore = await provider.connection.getBalance(payer.publicKey);
+
+        try {
+          await refund(paymentId, requester);
+          expect.fail("should have rejected a refund from outside the payment");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("Unauthorized");
+        }
+
+        await refund(paymentId, recipient);
+
+        expect(await provider.connection.getBalance(payer.publicKey)).to.equal(payerBefore + 1_000_000);
+        const facilitator = await paymentProgram.account.paymentFacilitator.fetch(facilitatorPDA);
+        expect(facilitator.accumulatedFees.toNumber()).to.equal(feesBefore - 10_000);
+        expect((await paymentProgram.account.paymentRecord.fetch(findPaymentRecordPDA(paymentId))).refunded).to.equal(true);
+
+        try {
+          await refund(paymentId, recipient);
+          expect.fail("should have rejected a second refund");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("AlreadyRefunded");
+        }
+      });
+
+      it("Refuses a refund once the grace period has passed", async () => {
+        const paymentId = Keypair.generate().publicKey.toBuffer();
+        await setGracePeriod(0);
+        try {
+          await settlePayment(paymentId);
+          await new Promise(resolve => setTimeout(resolve, 2000));
+
+          try {
+            await refund(paymentId, recipient);
+            expect.fail("should have rejected a refund after the grace period");
+          } catch (error) {
+            expect(error.error.errorCode.code).to.equal("RefundWindowClosed");
+          }
+        } finally {
+          await setGracePeriod(24 * 60 * 60);
+        }
+      });
+    });
+
+    describe("fee tiers", () => {
+      const setFeeSchedule = (tiers: [number, number][]) =>
+        paymentProgram.methods
+          .setFeeSchedule(tiers.map(([threshold, bps]) => ({ threshold: new anchor.BN(threshold), bps })))
+          .accounts({ facilitator: facilitatorPDA, authority: authority.publicKey })
+          .rpc();
+
+      it("Charges the highest tier a payment reaches", async () => {
+        const paymentId = Keypair.generate().publicKey.toBuffer();
+        await setFeeSchedule([[500_000, 50], [1_000_000, 20]]);
+        try {
+          await settlePayment(paymentId);
+        } finally {
+          await setFeeSchedule([]);
+        }
+
+        // 1_000_000 reaches the 0.2% tier rather than the 1% base fee or the 0.5% tier
+        const record = await paymentProgram.account.paymentRecord.fetch(findPaymentRecordPDA(paymentId));
+        expect(record.fee.toNumber()).to.equal(2_000);
+      });
+
+      it("Rejects tiers out of threshold order", async () => {
+        try {
+          await setFeeSchedule([[1_000_000, 20], [500_000, 50]]);
+          expect.fail("should have rejected an unsorted fee schedule");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("InvalidFeeSchedule");
+        }
+      });
+    });
+
+    describe("fee treasury", () => {
+      const setTreasury = (treasury: PublicKey) =>
+        paymentProgram.methods
+          .setTreasury(treasury)
+          .accounts({ facilitator: facilitatorPDA, authority: authority.publicKey })
+          .rpc();
+
+      it("Sends withdrawn fees to the configured treasury and nowhere else", async () => {
+        const fees = (await paymentProgram.account.paymentFacilitator.fetch(facilitatorPDA)).accumulatedFees.toNumber();
+        expect(fees).to.be.greaterThan(0);
+
+        await setTreasury(requester.publicKey);
+        try {
+          const withdrawTo = (treasury: PublicKey) =>
+            paymentProgram.methods
+              .withdrawFees()
+              .accounts({ facilitator: facilitatorPDA, authority: authority.publicKey, treasury })
+              .rpc();
+
+          try {
+            await withdrawTo(authority.publicKey);
+            expect.fail("should have rejected a treasury other than the configured one");
+          } catch (error) {
+            expect(error.error.errorCode.code).to.equal("InvalidTreasury");
+          }
+
+          const treasuryBefore = await provider.connection.getBalance(requester.publicKey);
+          await withdrawTo(requester.publicKey);
+          expect(await provider.connection.getBalance(requester.publicKey)).to.equal(treasuryBefore + fees);
+        } finally {
+          await setTreasury(authority.publicKey);
+        }
+      });
+    });
+
+    describe("batch funding", () => {
+      // Both checks run before any recipient is paid, so the single parsed recipient account never matters
+      const batchSettle = (amounts: anchor.BN[]) =>
+        paymentProgram.methods
+          .batchSettlePayments(
+            amounts,
+            amounts.map(() => recipient.publicKey),
+            amounts.map(() => [...Keypair.generate().publicKey.toBuffer()])
+          )
+          .accounts({
+            facilitator: facilitatorPDA,
+            payer: payer.publicKey,
+            recipients: recipient.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([payer])
+          .rpc();
+
+      it("Rejects a batch the payer cannot cover before paying anyone", async () => {
+        const recipientBefore = await provider.connection.getBalance(recipient.publicKey);
+
+        await expectError(batchSettle([new anchor.BN(1_000_000), new anchor.BN(1000 * LAMPORTS_PER_SOL)]), "InsufficientFunds");
+        expect(await provider.connection.getBalance(recipient.publicKey)).to.equal(recipientBefore);
+      });
+
+      it("Rejects a batch whose total overflows", async () => {
+        await expectError(batchSettle([new anchor.BN("18446744073709551615"), new anchor.BN(1)]), "Overflow");
+      });
+    });
+
+    describe("claimable deposits", () => {
+      it("Pools deposits for a recipient and pays them out less the fee on claim", async () => {
+        const claimant = Keypair.generate();
+        await provider.connection.requestAirdrop(claimant.publicKey, LAMPORTS_PER_SOL);
+        await new Promise(resolve => setTimeout(resolve, 1000));
+        const [claimablePDA] = PublicKey.findProgramAddressSync(
+          [Buffer.from("claimable"), claimant.publicKey.toBuffer()],
+          paymentProgram.programId
+        );
+
+        for (let i = 0; i < 2; i++) {
+          await paymentProgram.methods
+            .depositFor(claimant.publicKey, new anchor.BN(1_000_000), [...Keypair.generate().publicKey.toBuffer()])
+            .accounts({
+              facilitator: facilitatorPDA,
+              claimableBalance: claimablePDA,
+              payer: payer.publicKey,
+              systemProgram: SystemProgram.programId,
+            })
+            .signers([payer])
+            .rpc();
+        }
+        expect((await paymentProgram.account.claimableBalance.fetch(claimablePDA)).balance.toNumber()).to.equal(2_000_000);
+
+        const claim = () =>
+          paymentProgram.methods
+            .claimPayment()
+            .accounts({
+              facilitator: facilitatorPDA,
+              claimableBalance: claimablePDA,
+              recipient: claimant.publicKey,
+            })
+            .signers([claimant])
+            .rpc();
+
+        const claimantBefore = await provider.connection.getBalance(claimant.publicKey);
+        await claim();
+
+        // The 1% fee is taken once, on the pooled amount
+        expect(await provider.connection.getBalance(claimant.publicKey)).to.equal(claimantBefore + 1_980_000);
+        expect((await paymentProgram.account.claimableBalance.fetch(claimablePDA)).balance.toNumber()).to.equal(0);
+
+        await expectError(claim(), "NothingToClaim");
+      });
+    });
+
+    describe("recipient checks", () => {
+      const settleTo = (to: PublicKey) => {
+        const paymentId = Keypair.generate().publicKey.toBuffer();
+        return paymentProgram.methods
+          .settlePayment(new anchor.BN(1_000_000), [...paymentId], "", new anchor.BN(0))
+          .accounts({
+            facilitator: facilitatorPDA,
+            paymentRecord: findPaymentRecordPDA(paymentId),
+            payer: payer.publicKey,
+            recipient: to,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([payer])
+          .rpc();
+      };
+
+      it("Rejects a payment to the payer", async () => {
+        await expectError(settleTo(payer.publicKey), "RecipientIsPayer");
+      });
+
+      it("Rejects a payment to the facilitator account", async () => {
+        await expectError(settleTo(facilitatorPDA), "RecipientIsFacilitator");
+      });
+    });
+
+    describe("SPL settlement", () => {
+      const amount = 1_000_000; // 1 USDC at 6 decimals
+      const fee = (amount * 100) / 10000;
+
+      let usdcMint: PublicKey;
+      let payerUsdc: PublicKey;
+      let recipientUsdc: PublicKey;
+      let feeVault: PublicKey;
+      let tokenFeeBalancePDA: PublicKey;
+
+      const settlePaymentSpl = (paymentId: Buffer, expiresAt = 0, memo = "") =>
+        paymentProgram.methods
+          .settlePaymentSpl(new anchor.BN(amount), [...paymentId], usdcMint, memo, new anchor.BN(expiresAt))
+          .accounts({
+            facilitator: facilitatorPDA,
+            paymentRecord: findPaymentRecordPDA(paymentId),
+            payer: payer.publicKey,
+            payerTokenAccount: payerUsdc,
+            recipientTokenAccount: recipientUsdc,
+            feeVault,
+            tokenFeeBalance: tokenFeeBalancePDA,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([payer])
+          .rpc();
+
+      const tokenFeesAccrued = async () => {
+        const balance = await paymentProgram.account.tokenFeeBalance.fetchNullable(tokenFeeBalancePDA);
+        return balance ? balance.accumulatedFees.toNumber() : 0;
+      };
+
+      const usdcBalance = async (account: PublicKey) =>
+        Number((await getAccount(provider.connection, account)).amount);
+
+      before(async () => {
+        usdcMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+        payerUsdc = (
+          await getOrCreateAssociatedTokenAccount(provider.connection, payer, usdcMint, payer.publicKey)
+        ).address;
+        recipientUsdc = (
+          await getOrCreateAssociatedTokenAccount(provider.connection, payer, usdcMint, recipient.publicKey)
+        ).address;
+        // Token fees accrue in the mint's associated token account of the facilitator PDA
+        feeVault = (
+          await getOrCreateAssociatedTokenAccount(provider.connection, payer, usdcMint, facilitatorPDA, true)
+        ).address;
+        [tokenFeeBalancePDA] = PublicKey.findProgramAddressSync(
+          [Buffer.from("token_fees"), usdcMint.toBuffer()],
+          paymentProgram.programId
+        );
+
+        await mintTo(provider.connection, payer, usdcMint, payerUsdc, payer, 10 * amount);
+      });
+
+      it("Settles a USDC payment and accrues the fee in the vault", async () => {
+        const paymentId = Keypair.generate().publicKey.toBuffer();
+        const payerBefore = await usdcBalance(payerUsdc);
+        const recipientBefore = await usdcBalance(recipientUsdc);
+        const vaultBefore = await usdcBalance(feeVault);
+        const accruedBefore = await tokenFeesAccrued();
+        const lamportFeesBefore = (await paymentProgram.account.paymentFacilitator.fetch(facilitatorPDA))
+          .accumulatedFees.toNumber();
+
+        await settlePaymentSpl(paymentId);
+
+        expect(await usdcBalance(payerUsdc)).to.equal(payerBefore - amount);
+        expect(await usdcBalance(recipientUsdc)).to.equal(recipientBefore + amount - fee);
+        expect(await usdcBalance(feeVault)).to.equal(vaultBefore + fee);
+        expect(await tokenFeesAccrued()).to.equal(accruedBefore + fee);
+
+        // Lamport fee accounting is untouched by token settlements
+        const facilitator = await paymentProgram.account.paymentFacilitator.fetch(facilitatorPDA);
+        expect(facilitator.accumulatedFees.toNumber()).to.equal(lamportFeesBefore);
+
+        const record = await paymentProgram.account.paymentRecord.fetch(findPaymentRecordPDA(paymentId));
+        expect(record.mint.toBase58()).to.equal(usdcMint.toBase58());
+        expect(record.recipient.toBase58()).to.equal(recipient.publicKey.toBase58());
+        expect(record.amount.toNumber()).to.equal(amount);
+        expect(record.fee.toNumber()).to.equal(fee);
+        expect(record.expiresAt.toNumber()).to.equal(0);
+      });
+
+      it("Applies the same expiry and memo checks as SOL settlement", async () => {
+        try {
+          await settlePaymentSpl(Keypair.generate().publicKey.toBuffer(), Math.floor(Date.now() / 1000) - 60);
+          expect.fail("should have rejected an expired payment");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("PaymentExpired");
+        }
+
+        try {
+          await settlePaymentSpl(Keypair.generate().publicKey.toBuffer(), 0, "line one\nline two");
+          expect.fail("should have rejected a memo with control characters");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("InvalidMemo");
+        }
+
+        const paymentId = Keypair.generate().publicKey.toBuffer();
+        const expiresAt = Math.floor(Date.now() / 1000) + 3600;
+        await settlePaymentSpl(paymentId, expiresAt, "invoice-42");
+        const record = await paymentProgram.account.paymentRecord.fetch(findPaymentRecordPDA(paymentId));
+        expect(record.expiresAt.toNumber()).to.equal(expiresAt);
+      });
+
+      it("Rejects replaying a settled payment ID", async () => {
+        const paymentId = Keypair.generate().publicKey.toBuffer();
+        await settlePaymentSpl(paymentId);
+
+        const payerBefore = await usdcBalance(payerUsdc);
+        try {
+          await settlePaymentSpl(paymentId);
+          expect.fail("should have rejected a replayed payment ID");
+        } catch (error) {
+          expect(error).to.exist;
+        }
+        expect(await usdcBalance(payerUsdc)).to.equal(payerBefore);
+      });
+
+      it("Refuses to refund a token payment through the SOL refund path", async () => {
+        const paymentId = Keypair.generate().publicKey.toBuffer();
+        await settlePaymentSpl(paymentId);
+
+        try {
+          await paymentProgram.methods
+            .refundPayment([...paymentId])
+            .accounts({
+              facilitator: facilitatorPDA,
+              paymentRecord: findPaymentRecordPDA(paymentId),
+              refunder: recipient.publicKey,
+              payer: payer.publicKey,
+              systemProgram: SystemProgram.programId,
+            })
+            .signers([recipient])
+            .rpc();
+          expect.fail("should have rejected refunding a token payment");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("TokenPayment");
+        }
+      });
+
+      it("Withdraws accrued token fees from the vault to the treasury's token account", async () => {
+        await settlePaymentSpl(Keypair.generate().publicKey.toBuffer());
+        const fees = await tokenFeesAccrued();
+        expect(fees).to.be.greaterThan(0);
+
+        const treasuryUsdc = (
+          await getOrCreateAssociatedTokenAccount(provider.connection, payer, usdcMint, authority.publicKey)
+        ).address;
+        const withdrawTokenFees = (treasury: PublicKey, treasuryTokenAccount: PublicKey) =>
+          paymentProgram.methods
+            .withdrawTokenFees(usdcMint)
+            .accounts({
+              facilitator: facilitatorPDA,
+              authority: authority.publicKey,
+              tokenFeeBalance: tokenFeeBalancePDA,
+              feeVault,
+              treasury,
+              treasuryTokenAccount,
+              tokenProgram: TOKEN_PROGRAM_ID,
+            })
+            .rpc();
+
+        try {
+          await withdrawTokenFees(recipient.publicKey, recipientUsdc);
+          expect.fail("should have rejected a treasury other than the configured one");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("InvalidTreasury");
+        }
+
+        const vaultBefore = await usdcBalance(feeVault);
+        const treasuryBefore = await usdcBalance(treasuryUsdc);
+        await withdrawTokenFees(authority.publicKey, treasuryUsdc);
+
+        expect(await usdcBalance(feeVault)).to.equal(vaultBefore - fees);
+        expect(await usdcBalance(treasuryUsdc)).to.equal(treasuryBefore + fees);
+        expect(await tokenFeesAccrued()).to.equal(0);
+
+        try {
+          await withdrawTokenFees(authority.publicKey, treasuryUsdc);
+          expect.fail("should have rejected withdrawing with nothing accrued");
+        } catch (error) {
+          expect(error.error.errorCode.code).to.equal("NoFees");
+        }
+      });
+    });
+  });
 });
 